@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS outbox (
+    id UUID PRIMARY KEY,
+    exchange VARCHAR NOT NULL,
+    routing_key VARCHAR NOT NULL,
+    payload JSONB NOT NULL,
+    headers JSONB NOT NULL,
+    sent_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS outbox_pending_idx ON outbox(created_at) WHERE sent_at IS NULL;
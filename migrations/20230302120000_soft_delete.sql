@@ -0,0 +1,6 @@
+-- Add migration script here
+ALTER TABLE project ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE workflow ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE job ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE project DROP CONSTRAINT IF EXISTS project_name_key;
+CREATE UNIQUE INDEX IF NOT EXISTS project_name_key ON project(name) WHERE deleted_at IS NULL;
@@ -0,0 +1,7 @@
+-- Add migration script here
+ALTER TABLE workflow ADD COLUMN IF NOT EXISTS schedule VARCHAR;
+ALTER TABLE job ADD COLUMN IF NOT EXISTS depends_on VARCHAR[] NOT NULL DEFAULT '{}';
+ALTER TABLE workflow DROP CONSTRAINT workflow_project_id_name_id_key;
+CREATE UNIQUE INDEX IF NOT EXISTS workflow_project_id_name_key ON workflow(project_id, name) INCLUDE (id) WHERE deleted_at IS NULL;
+ALTER TABLE job DROP CONSTRAINT job_workflow_id_name_id_key;
+CREATE UNIQUE INDEX IF NOT EXISTS job_workflow_id_name_key ON job(workflow_id, name) INCLUDE (id) WHERE deleted_at IS NULL;
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE project ADD COLUMN IF NOT EXISTS config_schema JSONB;
+ALTER TABLE job ADD COLUMN IF NOT EXISTS config JSONB;
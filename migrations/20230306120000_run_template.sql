@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE run ADD COLUMN IF NOT EXISTS attempt INT NOT NULL DEFAULT 1;
+ALTER TABLE run ADD COLUMN IF NOT EXISTS args VARCHAR[];
+ALTER TABLE run ADD COLUMN IF NOT EXISTS envs VARCHAR[];
@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS run_log (
+    run_id UUID NOT NULL REFERENCES run(id),
+    seq BIGINT NOT NULL,
+    stream VARCHAR NOT NULL,
+    line TEXT NOT NULL,
+    emitted_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP,
+    PRIMARY KEY(run_id, seq)
+);
@@ -0,0 +1,13 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS stash (
+    id UUID PRIMARY KEY,
+    run_id UUID NOT NULL REFERENCES run(id),
+    name VARCHAR NOT NULL,
+    content_type VARCHAR NOT NULL,
+    size BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    UNIQUE(run_id, name)
+);
+
+CREATE INDEX IF NOT EXISTS stash_expires_at_idx ON stash(expires_at);
@@ -0,0 +1,16 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS workflow_run (
+    id UUID PRIMARY KEY,
+    workflow_id UUID NOT NULL REFERENCES workflow(id),
+    priority VARCHAR NOT NULL,
+    triggered_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS workflow_run_workflow_id_idx ON workflow_run(workflow_id, triggered_at);
+
+ALTER TABLE run ADD COLUMN IF NOT EXISTS execution_id UUID REFERENCES workflow_run(id);
+ALTER TABLE run ADD COLUMN IF NOT EXISTS dispatched_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS run_execution_id_idx ON run(execution_id);
@@ -0,0 +1,26 @@
+-- Add migration script here
+ALTER TABLE project ADD COLUMN IF NOT EXISTS retention_days INT;
+ALTER TABLE project ADD COLUMN IF NOT EXISTS retention_runs INT;
+
+CREATE INDEX IF NOT EXISTS run_job_id_finished_at_idx ON run(job_id, finished_at);
+CREATE INDEX IF NOT EXISTS run_state_idx ON run(state);
+
+CREATE TABLE IF NOT EXISTS run_archive (
+    id UUID PRIMARY KEY,
+    state VARCHAR,
+    priority VARCHAR NOT NULL,
+    job_id UUID NOT NULL,
+    execution_id UUID,
+    triggered_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    started_at TIMESTAMP WITH TIME ZONE,
+    finished_at TIMESTAMP WITH TIME ZONE,
+    attempt INT NOT NULL,
+    args VARCHAR[],
+    envs VARCHAR[],
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    archived_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS run_archive_job_id_finished_at_idx ON run_archive(job_id, finished_at);
+CREATE INDEX IF NOT EXISTS run_archive_execution_id_idx ON run_archive(execution_id);
@@ -0,0 +1,14 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS controller (
+    id UUID PRIMARY KEY,
+    addr VARCHAR NOT NULL,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP,
+    heartbeat_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS lease (
+    name VARCHAR PRIMARY KEY,
+    holder UUID NOT NULL,
+    acquired_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
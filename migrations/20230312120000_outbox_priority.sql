@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE outbox ADD COLUMN IF NOT EXISTS priority INT;
@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE project ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE workflow ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE job ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 1;
@@ -0,0 +1,30 @@
+-- Add migration script here
+CREATE OR REPLACE FUNCTION notify_run_update() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'UPDATE' AND OLD.state IS NOT DISTINCT FROM NEW.state THEN
+        RETURN NEW;
+    END IF;
+    PERFORM pg_notify(
+        'kotosiro_run_updates',
+        json_build_object(
+            'type', 'run',
+            'id', NEW.id,
+            'job_id', NEW.job_id,
+            'workflow_id', workflow.id,
+            'project_id', workflow.project_id,
+            'execution_id', NEW.execution_id,
+            'state', NEW.state
+        )::text
+    )
+    FROM job
+    JOIN workflow ON workflow.id = job.workflow_id
+    WHERE job.id = NEW.job_id;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS run_notify_update ON run;
+
+CREATE TRIGGER run_notify_update
+    AFTER INSERT OR UPDATE OF state ON run
+    FOR EACH ROW EXECUTE FUNCTION notify_run_update();
@@ -0,0 +1,46 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS notification_rule (
+    id UUID PRIMARY KEY,
+    project_id UUID NOT NULL REFERENCES project(id),
+    name VARCHAR NOT NULL,
+    events VARCHAR[] NOT NULL,
+    workflow_id UUID,
+    url VARCHAR NOT NULL,
+    secret BYTEA NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP,
+    UNIQUE(project_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS notification_delivery (
+    id UUID PRIMARY KEY,
+    rule_id UUID NOT NULL REFERENCES notification_rule(id),
+    event VARCHAR NOT NULL,
+    subject_id UUID NOT NULL,
+    payload JSONB NOT NULL,
+    state VARCHAR NOT NULL DEFAULT 'pending',
+    attempts INT NOT NULL DEFAULT 0,
+    status_code INT,
+    last_error TEXT,
+    next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP,
+    delivered_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP,
+    UNIQUE(rule_id, event, subject_id)
+);
+
+CREATE INDEX IF NOT EXISTS notification_delivery_pending_idx ON notification_delivery(next_attempt_at) WHERE state = 'pending';
+CREATE INDEX IF NOT EXISTS notification_delivery_rule_id_idx ON notification_delivery(rule_id, created_at);
+
+CREATE TABLE IF NOT EXISTS notification_dead_letter (
+    id UUID PRIMARY KEY REFERENCES notification_delivery(id),
+    rule_id UUID NOT NULL REFERENCES notification_rule(id),
+    event VARCHAR NOT NULL,
+    payload JSONB NOT NULL,
+    attempts INT NOT NULL,
+    status_code INT,
+    last_error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS notification_dead_letter_rule_id_idx ON notification_dead_letter(rule_id, created_at);
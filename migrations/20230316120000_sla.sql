@@ -0,0 +1,44 @@
+-- Add migration script here
+ALTER TABLE workflow ADD COLUMN IF NOT EXISTS sla_within_secs BIGINT;
+ALTER TABLE workflow ADD COLUMN IF NOT EXISTS sla_by VARCHAR;
+ALTER TABLE workflow ADD COLUMN IF NOT EXISTS sla_timezone VARCHAR;
+ALTER TABLE workflow ADD COLUMN IF NOT EXISTS sla_since TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP;
+
+CREATE TABLE IF NOT EXISTS sla_miss (
+    id UUID PRIMARY KEY,
+    workflow_id UUID NOT NULL REFERENCES workflow(id),
+    execution_id UUID,
+    kind VARCHAR NOT NULL,
+    deadline TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL default CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS sla_miss_execution_id_idx ON sla_miss(execution_id) WHERE execution_id IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS sla_miss_workflow_id_deadline_idx ON sla_miss(workflow_id, deadline) WHERE execution_id IS NULL;
+CREATE INDEX IF NOT EXISTS sla_miss_workflow_id_created_at_idx ON sla_miss(workflow_id, created_at);
+
+CREATE OR REPLACE FUNCTION notify_sla_miss() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify(
+        'kotosiro_run_updates',
+        json_build_object(
+            'type', 'sla_miss',
+            'id', NEW.id,
+            'workflow_id', NEW.workflow_id,
+            'project_id', workflow.project_id,
+            'execution_id', NEW.execution_id,
+            'kind', NEW.kind,
+            'deadline', NEW.deadline
+        )::text
+    )
+    FROM workflow
+    WHERE workflow.id = NEW.workflow_id;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS sla_miss_notify ON sla_miss;
+
+CREATE TRIGGER sla_miss_notify
+    AFTER INSERT ON sla_miss
+    FOR EACH ROW EXECUTE FUNCTION notify_sla_miss();
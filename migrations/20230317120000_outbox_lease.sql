@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE outbox ADD COLUMN IF NOT EXISTS leased_until TIMESTAMP WITH TIME ZONE;
+CREATE INDEX IF NOT EXISTS outbox_sent_at_idx ON outbox(sent_at) WHERE sent_at IS NOT NULL;
@@ -0,0 +1,1067 @@
+mod render;
+use crate::config::Config;
+use crate::messages::log::LogLine;
+use crate::messages::token::TokenState;
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use chrono::DateTime;
+use chrono::NaiveDate;
+use chrono::TimeZone;
+use chrono::Utc;
+use clap::Arg;
+use clap::ArgAction;
+use clap::ArgMatches;
+use clap::Command;
+use reqwest::Method;
+use reqwest::RequestBuilder;
+use reqwest::Url;
+use serde_json::json;
+use serde_json::Value;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::EnumString)]
+pub enum Output {
+    #[strum(ascii_case_insensitive)]
+    Table,
+    #[strum(ascii_case_insensitive)]
+    Json,
+}
+
+pub struct Client {
+    addr: Url,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl Client {
+    pub fn new(addr: &str, token: Option<String>) -> Result<Self> {
+        let addr = if addr.contains("://") {
+            addr.to_owned()
+        } else {
+            format!("http://{}", addr)
+        };
+        let addr = Url::parse(&addr).context(format!(r#"failed to parse "{}" to URL"#, addr))?;
+        Ok(Self {
+            addr,
+            token,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    fn url(&self, path: &str) -> Result<Url> {
+        self.addr.join(path).context(format!(
+            r#"failed to join "{}" to controller address"#,
+            path
+        ))
+    }
+
+    fn request(&self, method: Method, path: &str) -> Result<RequestBuilder> {
+        let request = self.http.request(method, self.url(path)?);
+        match &self.token {
+            Some(token) => Ok(request.bearer_auth(token)),
+            None => Ok(request),
+        }
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Option<Value>> {
+        let res = request
+            .send()
+            .await
+            .context("failed to send request to controller")?;
+        let status = res.status();
+        let body = res
+            .bytes()
+            .await
+            .context("failed to read response from controller")?;
+        let body: Option<Value> = serde_json::from_slice(&body).ok();
+        if status.is_success() {
+            return Ok(body);
+        }
+        Err(anyhow!(
+            "controller responded {}: {}",
+            status.as_u16(),
+            describe_error(status, body.as_ref())
+        ))
+    }
+
+    pub async fn get_cluster(&self) -> Result<Value> {
+        let request = self.request(Method::GET, "api/cluster")?;
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn list_projects(&self, page: &[(&str, String)]) -> Result<Value> {
+        let request = self.request(Method::GET, "api/project")?.query(page);
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn get_project(&self, name: &str) -> Result<Value> {
+        let request = self
+            .request(Method::GET, "api/project")?
+            .query(&[("name", name)]);
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn list_workflows(&self, project_id: &str, page: &[(&str, String)]) -> Result<Value> {
+        let path = format!("api/project/{}/workflow", project_id);
+        let request = self.request(Method::GET, &path)?.query(page);
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn apply_project(&self, document: String, dry_run: bool) -> Result<Value> {
+        let request = self
+            .request(Method::POST, "api/project/apply")?
+            .query(&[("dry_run", dry_run)])
+            .body(document);
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn delete_project(&self, id: &str, cascade: bool) -> Result<()> {
+        let path = format!("api/project/{}", id);
+        let request = self
+            .request(Method::DELETE, &path)?
+            .query(&[("cascade", cascade)]);
+        self.send(request).await?;
+        Ok(())
+    }
+
+    pub async fn set_workflow_paused(&self, id: &str, paused: bool) -> Result<()> {
+        let action = if paused { "pause" } else { "resume" };
+        let path = format!("api/workflow/{}/{}", id, action);
+        let request = self.request(Method::PUT, &path)?;
+        self.send(request).await?;
+        Ok(())
+    }
+
+    pub async fn list_jobs(&self, workflow_id: &str, page: &[(&str, String)]) -> Result<Value> {
+        let path = format!("api/workflow/{}/job", workflow_id);
+        let request = self.request(Method::GET, &path)?.query(page);
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn run_workflow(&self, id: &str, priority: Option<&String>) -> Result<Value> {
+        let path = format!("api/workflow/{}/run", id);
+        let request = self
+            .request(Method::POST, &path)?
+            .json(&json!({ "priority": priority }));
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn backfill_workflow(
+        &self,
+        id: &str,
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+        priority: Option<&String>,
+    ) -> Result<Value> {
+        let path = format!("api/workflow/{}/backfill", id);
+        let request = self
+            .request(Method::POST, &path)?
+            .json(&json!({ "from": from, "to": to, "priority": priority }));
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn list_executions(&self, workflow_id: &str, limit: Option<&i64>) -> Result<Value> {
+        let path = format!("api/workflow/{}/execution", workflow_id);
+        let mut request = self.request(Method::GET, &path)?;
+        if let Some(limit) = limit {
+            request = request.query(&[("limit", limit)]);
+        }
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn get_execution(&self, id: &str) -> Result<Value> {
+        let path = format!("api/execution/{}", id);
+        let request = self.request(Method::GET, &path)?;
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn cancel_execution(&self, id: &str) -> Result<()> {
+        let path = format!("api/execution/{}/cancel", id);
+        let request = self.request(Method::POST, &path)?;
+        self.send(request).await?;
+        Ok(())
+    }
+
+    pub async fn rerun_execution(&self, id: &str, failed_only: bool) -> Result<Value> {
+        let path = format!("api/execution/{}/rerun", id);
+        let request = self
+            .request(Method::POST, &path)?
+            .query(&[("failed_only", failed_only)]);
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn run_job(&self, id: &str, priority: Option<&String>) -> Result<Value> {
+        let path = format!("api/job/{}/run", id);
+        let request = self
+            .request(Method::POST, &path)?
+            .json(&json!({ "priority": priority }));
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn list_runs(&self, job_id: &str, page: &[(&str, String)]) -> Result<Value> {
+        let request = self
+            .request(Method::GET, "api/run")?
+            .query(&[("job", job_id)])
+            .query(page);
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn cancel_run(&self, id: &str) -> Result<()> {
+        let path = format!("api/run/{}/cancel", id);
+        let request = self.request(Method::POST, &path)?;
+        self.send(request).await?;
+        Ok(())
+    }
+
+    pub async fn get_run_config(&self, id: &str, token: &str) -> Result<Value> {
+        let path = format!("internal/api/run/{}/config", id);
+        // NOTE: Run configs are authorized by the run-bound token instead of the API token.
+        let request = self.http.get(self.url(&path)?).bearer_auth(token);
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn update_run_state(&self, id: &str, state: &TokenState) -> Result<()> {
+        let path = format!("internal/api/run/{}/state", id);
+        let request = self
+            .request(Method::PUT, &path)?
+            .json(&json!({ "state": state }));
+        self.send(request).await?;
+        Ok(())
+    }
+
+    pub async fn append_logs(&self, run_id: &str, lines: &[LogLine]) -> Result<()> {
+        let path = format!("internal/api/run/{}/logs", run_id);
+        let request = self.request(Method::POST, &path)?.json(lines);
+        self.send(request).await?;
+        Ok(())
+    }
+
+    fn logs_request(
+        &self,
+        run_id: &str,
+        tail: Option<&i64>,
+        stream: Option<&String>,
+        follow: bool,
+    ) -> Result<RequestBuilder> {
+        let path = format!("api/run/{}/logs", run_id);
+        let mut request = self
+            .request(Method::GET, &path)?
+            .query(&[("follow", follow)]);
+        if let Some(tail) = tail {
+            request = request.query(&[("tail", tail)]);
+        }
+        if let Some(stream) = stream {
+            request = request.query(&[("stream", stream)]);
+        }
+        Ok(request)
+    }
+
+    pub async fn logs(
+        &self,
+        run_id: &str,
+        tail: Option<&i64>,
+        stream: Option<&String>,
+    ) -> Result<Value> {
+        let request = self.logs_request(run_id, tail, stream, false)?;
+        Ok(self.send(request).await?.unwrap_or_default())
+    }
+
+    pub async fn follow_logs(
+        &self,
+        run_id: &str,
+        tail: Option<&i64>,
+        stream: Option<&String>,
+        mut on_line: impl FnMut(Value),
+    ) -> Result<()> {
+        let request = self.logs_request(run_id, tail, stream, true)?;
+        let mut res = request
+            .send()
+            .await
+            .context("failed to send request to controller")?;
+        if !res.status().is_success() {
+            return Err(anyhow!("controller responded {}", res.status().as_u16()));
+        }
+        let mut buffer = String::new();
+        while let Some(chunk) = res
+            .chunk()
+            .await
+            .context("failed to read response from controller")?
+        {
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+            for line in drain_events(&mut buffer) {
+                on_line(line);
+            }
+        }
+        Ok(())
+    }
+}
+
+// NOTE: Older controllers only return an "error" field, so it is kept as a fallback of "message".
+fn describe_error(status: reqwest::StatusCode, body: Option<&Value>) -> String {
+    let field = |name: &str| body.and_then(|body| body.get(name)).and_then(Value::as_str);
+    let mut message = field("message")
+        .or_else(|| field("error"))
+        .or_else(|| status.canonical_reason())
+        .unwrap_or("unknown error")
+        .to_owned();
+    if let Some(id) = field("request_id") {
+        message.push_str(&format!(" (request id: {})", id));
+    }
+    let violations = body
+        .and_then(|body| body.get("violations"))
+        .and_then(Value::as_array);
+    for violation in violations.into_iter().flatten() {
+        let path = violation.get("path").and_then(Value::as_str).unwrap_or("");
+        let text = violation
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("");
+        if path.is_empty() {
+            message.push_str(&format!("\n  {}", text));
+        } else {
+            message.push_str(&format!("\n  {}: {}", path, text));
+        }
+    }
+    message
+}
+
+fn drain_events(buffer: &mut String) -> Vec<Value> {
+    let mut events = Vec::new();
+    while let Some(end) = buffer.find("\n\n") {
+        let event: String = buffer.drain(..end + 2).collect();
+        let data: Vec<&str> = event
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(|data| data.strip_prefix(' ').unwrap_or(data))
+            .collect();
+        if data.is_empty() {
+            continue;
+        }
+        if let Ok(value) = serde_json::from_str(&data.join("\n")) {
+            events.push(value);
+        }
+    }
+    events
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default()));
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| String::from("expected YYYY-MM-DD or RFC 3339 time"))
+}
+
+fn id_arg(help: &'static str) -> Arg {
+    Arg::new("id").required(true).help(help)
+}
+
+fn page_args(command: Command, named: bool) -> Command {
+    let sorts = if named {
+        vec!["name", "created_at", "updated_at"]
+    } else {
+        vec!["created_at", "updated_at"]
+    };
+    let command = command
+        .arg(
+            Arg::new("limit")
+                .long("limit")
+                .value_parser(clap::value_parser!(i64))
+                .help("Maximum number of items per page"),
+        )
+        .arg(
+            Arg::new("page-token")
+                .long("page-token")
+                .help("Token of the page to fetch, as returned by the previous page"),
+        )
+        .arg(
+            Arg::new("sort")
+                .long("sort")
+                .value_parser(sorts)
+                .help("Sort key"),
+        )
+        .arg(
+            Arg::new("order")
+                .long("order")
+                .value_parser(["asc", "desc"])
+                .help("Sort order"),
+        );
+    if named {
+        command.arg(
+            Arg::new("prefix")
+                .long("prefix")
+                .help("Show only items whose name starts with the prefix"),
+        )
+    } else {
+        command
+    }
+}
+
+fn page_query(args: &ArgMatches) -> Vec<(&'static str, String)> {
+    let mut query = Vec::new();
+    if let Some(limit) = args.get_one::<i64>("limit") {
+        query.push(("limit", limit.to_string()));
+    }
+    for (arg, key) in [
+        ("page-token", "page_token"),
+        ("sort", "sort"),
+        ("order", "order"),
+        ("prefix", "prefix"),
+    ] {
+        if let Ok(Some(value)) = args.try_get_one::<String>(arg) {
+            query.push((key, value.to_owned()));
+        }
+    }
+    query
+}
+
+pub fn commands() -> Vec<Command> {
+    vec![
+        Command::new("project")
+            .about("Manage projects on the controller")
+            .subcommand_required(true)
+            .subcommand(page_args(Command::new("list").about("List projects"), true))
+            .subcommand(
+                Command::new("get")
+                    .about("Show a project by name")
+                    .arg(Arg::new("name").required(true).help("Project name")),
+            )
+            .subcommand(page_args(
+                Command::new("workflows")
+                    .about("List workflows of a project")
+                    .arg(id_arg("Project id")),
+                true,
+            ))
+            .subcommand(
+                Command::new("apply")
+                    .about("Apply a project document written in YAML or JSON")
+                    .arg(
+                        Arg::new("file")
+                            .long("file")
+                            .short('f')
+                            .required(true)
+                            .help("Path to the project document"),
+                    )
+                    .arg(
+                        Arg::new("dry-run")
+                            .long("dry-run")
+                            .action(ArgAction::SetTrue)
+                            .help("Show the plan without applying it"),
+                    ),
+            )
+            .subcommand(
+                Command::new("delete")
+                    .about("Delete a project")
+                    .arg(id_arg("Project id"))
+                    .arg(
+                        Arg::new("cascade")
+                            .long("cascade")
+                            .action(ArgAction::SetTrue)
+                            .help("Remove the project with its workflows, jobs and runs"),
+                    ),
+            ),
+        Command::new("cluster")
+            .about("Inspect the controller cluster")
+            .subcommand_required(true)
+            .subcommand(Command::new("members").about("List controllers and the current leader")),
+        Command::new("workflow")
+            .about("Manage workflows on the controller")
+            .subcommand_required(true)
+            .subcommand(
+                Command::new("pause")
+                    .about("Pause a workflow")
+                    .arg(id_arg("Workflow id")),
+            )
+            .subcommand(
+                Command::new("resume")
+                    .about("Resume a workflow")
+                    .arg(id_arg("Workflow id")),
+            )
+            .subcommand(page_args(
+                Command::new("jobs")
+                    .about("List jobs of a workflow")
+                    .arg(id_arg("Workflow id")),
+                true,
+            ))
+            .subcommand(
+                Command::new("run")
+                    .about("Trigger an execution of all jobs in a workflow")
+                    .arg(id_arg("Workflow id"))
+                    .arg(
+                        Arg::new("priority")
+                            .long("priority")
+                            .value_parser(["backfill", "low", "normal", "high"])
+                            .help("Run priority"),
+                    ),
+            )
+            .subcommand(
+                Command::new("executions")
+                    .about("List executions of a workflow")
+                    .arg(id_arg("Workflow id"))
+                    .arg(
+                        Arg::new("limit")
+                            .long("limit")
+                            .value_parser(clap::value_parser!(i64))
+                            .help("Maximum number of executions"),
+                    ),
+            ),
+        Command::new("backfill")
+            .about("Trigger executions of a workflow for its scheduled times in a range")
+            .arg(
+                Arg::new("workflow")
+                    .long("workflow")
+                    .required(true)
+                    .help("Workflow id"),
+            )
+            .arg(
+                Arg::new("from")
+                    .long("from")
+                    .required(true)
+                    .value_parser(parse_time)
+                    .help("Start of the range, inclusive"),
+            )
+            .arg(
+                Arg::new("to")
+                    .long("to")
+                    .required(true)
+                    .value_parser(parse_time)
+                    .help("End of the range, inclusive"),
+            )
+            .arg(
+                Arg::new("priority")
+                    .long("priority")
+                    .value_parser(["backfill", "low", "normal", "high"])
+                    .help("Run priority, backfill by default"),
+            ),
+        Command::new("execution")
+            .about("Manage workflow executions on the controller")
+            .subcommand_required(true)
+            .subcommand(
+                Command::new("get")
+                    .about("Show runs of an execution")
+                    .arg(id_arg("Execution id")),
+            )
+            .subcommand(
+                Command::new("cancel")
+                    .about("Cancel all unfinished runs of an execution")
+                    .arg(id_arg("Execution id")),
+            )
+            .subcommand(
+                Command::new("rerun")
+                    .about("Rerun a finished execution")
+                    .arg(id_arg("Execution id"))
+                    .arg(
+                        Arg::new("failed-only")
+                            .long("failed-only")
+                            .action(ArgAction::SetTrue)
+                            .help("Rerun only the jobs which did not succeed"),
+                    ),
+            ),
+        Command::new("job")
+            .about("Manage jobs on the controller")
+            .subcommand_required(true)
+            .subcommand(
+                Command::new("run")
+                    .about("Trigger a run of a job")
+                    .arg(id_arg("Job id"))
+                    .arg(
+                        Arg::new("priority")
+                            .long("priority")
+                            .value_parser(["backfill", "low", "normal", "high"])
+                            .help("Run priority"),
+                    ),
+            ),
+        Command::new("run")
+            .about("Manage runs on the controller")
+            .subcommand_required(true)
+            .subcommand(page_args(
+                Command::new("list")
+                    .about("List runs of a job")
+                    .arg(Arg::new("job").long("job").required(true).help("Job id")),
+                false,
+            ))
+            .subcommand(
+                Command::new("cancel")
+                    .about("Cancel a run")
+                    .arg(id_arg("Run id")),
+            )
+            .subcommand(
+                Command::new("logs")
+                    .about("Show logs of a run")
+                    .arg(id_arg("Run id"))
+                    .arg(
+                        Arg::new("tail")
+                            .long("tail")
+                            .value_parser(clap::value_parser!(i64))
+                            .help("Number of lines to show from the end"),
+                    )
+                    .arg(
+                        Arg::new("stream")
+                            .long("stream")
+                            .value_parser(["stdout", "stderr"])
+                            .help("Show only the given stream"),
+                    )
+                    .arg(
+                        Arg::new("follow")
+                            .long("follow")
+                            .short('f')
+                            .action(ArgAction::SetTrue)
+                            .help("Keep streaming new lines until the run finishes"),
+                    ),
+            ),
+    ]
+}
+
+fn print(output: Output, columns: &[&str], value: &Value) -> Result<()> {
+    match output {
+        Output::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        Output::Table => match value {
+            Value::Array(rows) => print!("{}", render::table(columns, rows)),
+            row => print!("{}", render::table(columns, std::slice::from_ref(row))),
+        },
+    }
+    Ok(())
+}
+
+fn print_page(output: Output, columns: &[&str], page: &Value) -> Result<()> {
+    match output {
+        Output::Json => print(output, columns, page),
+        Output::Table => {
+            print(output, columns, &page["items"])?;
+            if let Some(token) = page["next_page_token"].as_str() {
+                eprintln!("next page token: {}", token);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn print_log(output: Output, row: &Value) {
+    match output {
+        Output::Json => println!("{}", row),
+        Output::Table => println!("{}", row["line"].as_str().unwrap_or_default()),
+    }
+}
+
+fn done(output: Output, message: String) {
+    match output {
+        Output::Json => println!("{}", json!({ "status": "ok", "message": message })),
+        Output::Table => println!("{}", message),
+    }
+}
+
+fn plan_rows(plan: &Value) -> Vec<Value> {
+    let mut rows = vec![json!({
+        "kind": "project",
+        "name": plan["project"]["name"],
+        "action": plan["project"]["action"],
+        "id": plan["project"]["id"],
+    })];
+    for workflow in plan["workflows"].as_array().into_iter().flatten() {
+        rows.push(json!({
+            "kind": "workflow",
+            "name": workflow["name"],
+            "action": workflow["action"],
+            "id": workflow["id"],
+        }));
+    }
+    for job in plan["jobs"].as_array().into_iter().flatten() {
+        rows.push(json!({
+            "kind": "job",
+            "name": format!(
+                "{}/{}",
+                job["workflow"].as_str().unwrap_or_default(),
+                job["name"].as_str().unwrap_or_default()
+            ),
+            "action": job["action"],
+            "id": job["id"],
+        }));
+    }
+    rows
+}
+
+fn required<'a>(args: &'a ArgMatches, name: &str) -> &'a String {
+    args.get_one::<String>(name)
+        .expect("clap should have already checked the required arguments")
+}
+
+pub async fn execute(
+    config: &Config,
+    token: Option<&String>,
+    output: Output,
+    command: (&str, &ArgMatches),
+) -> Result<()> {
+    let token = token.or(config.api_token.as_ref()).cloned();
+    let client = Client::new(&config.controller_addr, token)?;
+    let (resource, args) = command;
+    if resource == "backfill" {
+        let executions = client
+            .backfill_workflow(
+                required(args, "workflow"),
+                args.get_one::<DateTime<Utc>>("from")
+                    .expect("clap should have already checked the required arguments"),
+                args.get_one::<DateTime<Utc>>("to")
+                    .expect("clap should have already checked the required arguments"),
+                args.get_one::<String>("priority"),
+            )
+            .await?;
+        return print(output, &["id", "priority", "triggered_at"], &executions);
+    }
+    let (action, args) = args
+        .subcommand()
+        .expect("clap should have already checked the subcommands");
+    match (resource, action) {
+        ("project", "list") => {
+            let projects = client.list_projects(&page_query(args)).await?;
+            print_page(
+                output,
+                &["id", "name", "description", "updated_at"],
+                &projects,
+            )
+        }
+        ("project", "get") => {
+            let project = client.get_project(required(args, "name")).await?;
+            print(output, &["id", "name", "description", "config"], &project)
+        }
+        ("project", "workflows") => {
+            let workflows = client
+                .list_workflows(required(args, "id"), &page_query(args))
+                .await?;
+            let columns = [
+                "id", "name", "paused", "success", "running", "failure", "waiting", "error",
+            ];
+            print_page(output, &columns, &workflows)
+        }
+        ("project", "apply") => {
+            let path = required(args, "file");
+            let document = std::fs::read_to_string(Path::new(path))
+                .context(format!(r#"failed to read "{}""#, path))?;
+            let plan = client
+                .apply_project(document, args.get_flag("dry-run"))
+                .await?;
+            match output {
+                Output::Json => print(output, &[], &plan),
+                Output::Table => print(
+                    output,
+                    &["kind", "name", "action", "id"],
+                    &Value::Array(plan_rows(&plan)),
+                ),
+            }
+        }
+        ("project", "delete") => {
+            let id = required(args, "id");
+            client.delete_project(id, args.get_flag("cascade")).await?;
+            done(output, format!(r#"deleted project "{}""#, id));
+            Ok(())
+        }
+        ("cluster", "members") => {
+            let cluster = client.get_cluster().await?;
+            match output {
+                Output::Json => print(output, &[], &cluster),
+                Output::Table => print(
+                    output,
+                    &["id", "addr", "leader", "started_at", "heartbeat_at"],
+                    &cluster["members"],
+                ),
+            }
+        }
+        ("workflow", "pause") | ("workflow", "resume") => {
+            let id = required(args, "id");
+            client.set_workflow_paused(id, action == "pause").await?;
+            done(output, format!(r#"{}d workflow "{}""#, action, id));
+            Ok(())
+        }
+        ("workflow", "run") => {
+            let execution = client
+                .run_workflow(required(args, "id"), args.get_one::<String>("priority"))
+                .await?;
+            match output {
+                Output::Json => print(output, &[], &execution),
+                Output::Table => print(
+                    output,
+                    &["id", "state", "priority", "job_id", "triggered_at"],
+                    &execution["runs"],
+                ),
+            }
+        }
+        ("workflow", "jobs") => {
+            let jobs = client
+                .list_jobs(required(args, "id"), &page_query(args))
+                .await?;
+            print_page(output, &["id", "name", "image", "updated_at"], &jobs)
+        }
+        ("workflow", "executions") => {
+            let executions = client
+                .list_executions(required(args, "id"), args.get_one::<i64>("limit"))
+                .await?;
+            print(
+                output,
+                &["id", "state", "priority", "triggered_at"],
+                &executions,
+            )
+        }
+        ("execution", "get") => {
+            let execution = client.get_execution(required(args, "id")).await?;
+            match output {
+                Output::Json => print(output, &[], &execution),
+                Output::Table => print(
+                    output,
+                    &[
+                        "job_name",
+                        "state",
+                        "attempt",
+                        "run_id",
+                        "started_at",
+                        "finished_at",
+                    ],
+                    &execution["nodes"],
+                ),
+            }
+        }
+        ("execution", "cancel") => {
+            let id = required(args, "id");
+            client.cancel_execution(id).await?;
+            done(output, format!(r#"cancelled execution "{}""#, id));
+            Ok(())
+        }
+        ("execution", "rerun") => {
+            let runs = client
+                .rerun_execution(required(args, "id"), args.get_flag("failed-only"))
+                .await?;
+            print(
+                output,
+                &["id", "state", "priority", "job_id", "attempt"],
+                &runs,
+            )
+        }
+        ("job", "run") => {
+            let run = client
+                .run_job(required(args, "id"), args.get_one::<String>("priority"))
+                .await?;
+            print(
+                output,
+                &["id", "state", "priority", "job_id", "triggered_at"],
+                &run,
+            )
+        }
+        ("run", "list") => {
+            let runs = client
+                .list_runs(required(args, "job"), &page_query(args))
+                .await?;
+            let columns = [
+                "id",
+                "state",
+                "priority",
+                "triggered_at",
+                "started_at",
+                "finished_at",
+            ];
+            print_page(output, &columns, &runs)
+        }
+        ("run", "cancel") => {
+            let id = required(args, "id");
+            client.cancel_run(id).await?;
+            done(output, format!(r#"cancelled run "{}""#, id));
+            Ok(())
+        }
+        ("run", "logs") => {
+            let id = required(args, "id");
+            let tail = args.get_one::<i64>("tail");
+            let stream = args.get_one::<String>("stream");
+            if args.get_flag("follow") {
+                return client
+                    .follow_logs(id, tail, stream, |row| print_log(output, &row))
+                    .await;
+            }
+            let rows = client.logs(id, tail, stream).await?;
+            for row in rows.as_array().into_iter().flatten() {
+                print_log(output, row);
+            }
+            Ok(())
+        }
+        _ => unreachable!("clap should have already checked the subcommands"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Query;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use axum::Json;
+    use axum::Router;
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::net::TcpListener;
+
+    fn serve(app: Router) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("port should be bound");
+        let addr = listener.local_addr().expect("address should be assigned");
+        let server = axum::Server::from_tcp(listener)
+            .expect("server should be created")
+            .serve(app.into_make_service());
+        tokio::spawn(server);
+        addr
+    }
+
+    #[test]
+    fn test_new_without_scheme() {
+        let client = Client::new("127.0.0.1:8080", None).expect("client should be created");
+        assert_eq!(client.addr.as_str(), "http://127.0.0.1:8080/");
+    }
+
+    #[tokio::test]
+    async fn test_list_projects() {
+        let app = Router::new().route(
+            "/api/project",
+            get(|Query(query): Query<HashMap<String, String>>| async move {
+                Json(json!({
+                    "items": [{ "name": "example" }],
+                    "next_page_token": query.get("page_token"),
+                }))
+            }),
+        );
+        let addr = serve(app);
+        let client = Client::new(&addr.to_string(), None).expect("client should be created");
+        let projects = client
+            .list_projects(&[("page_token", "next".to_owned())])
+            .await
+            .expect("projects should be listed");
+        assert_eq!(projects["items"][0]["name"], "example");
+        assert_eq!(projects["next_page_token"], "next");
+    }
+
+    #[tokio::test]
+    async fn test_error_response() {
+        let app = Router::new().route(
+            "/api/project",
+            get(|| async {
+                (
+                    StatusCode::UNAUTHORIZED,
+                    Json(json!({ "error": "Unauthorized" })),
+                )
+            }),
+        );
+        let addr = serve(app);
+        let client = Client::new(&addr.to_string(), Some(testutils::rand::string(10)))
+            .expect("client should be created");
+        let error = client
+            .list_projects(&[])
+            .await
+            .expect_err("error response should be returned");
+        assert_eq!(error.to_string(), "controller responded 401: Unauthorized");
+    }
+
+    #[tokio::test]
+    async fn test_error_response_with_violations() {
+        let app = Router::new().route(
+            "/api/project",
+            get(|| async {
+                (
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    Json(json!({
+                        "code": "validation_failed",
+                        "message": "Validation errors",
+                        "request_id": "abc",
+                        "violations": [
+                            { "path": "limit", "message": "failed range validation (max: 1000.0, min: 1.0)" },
+                        ],
+                    })),
+                )
+            }),
+        );
+        let addr = serve(app);
+        let client = Client::new(&addr.to_string(), None).expect("client should be created");
+        let error = client
+            .list_projects(&[])
+            .await
+            .expect_err("error response should be returned");
+        assert_eq!(
+            error.to_string(),
+            "controller responded 422: Validation errors (request id: abc)\n  limit: failed range validation (max: 1000.0, min: 1.0)"
+        );
+    }
+
+    #[test]
+    fn test_drain_events() {
+        let mut buffer = String::from(
+            "event: log\nid: 0\ndata: {\"seq\":0,\"line\":\"hello\"}\n\n:\n\nevent: log\nid: 1\ndata: {\"seq\"",
+        );
+        let events = drain_events(&mut buffer);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["line"], "hello");
+        assert_eq!(buffer, "event: log\nid: 1\ndata: {\"seq\"");
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(
+            parse_time("2023-03-01"),
+            Ok(Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_time("2023-03-01T09:00:00+09:00"),
+            Ok(Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap())
+        );
+        assert!(parse_time("yesterday").is_err());
+        let backfill = commands()
+            .into_iter()
+            .find(|command| command.get_name() == "backfill")
+            .expect("backfill command should exist");
+        let matches = backfill
+            .try_get_matches_from([
+                "backfill",
+                "--workflow",
+                "w",
+                "--from",
+                "2023-03-01",
+                "--to",
+                "2023-03-07",
+            ])
+            .expect("arguments should be parsed");
+        assert_eq!(
+            matches.get_one::<DateTime<Utc>>("to"),
+            Some(&Utc.with_ymd_and_hms(2023, 3, 7, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_page_query() {
+        let run = commands()
+            .into_iter()
+            .find(|command| command.get_name() == "run")
+            .expect("run command should exist");
+        let matches = run
+            .try_get_matches_from([
+                "run",
+                "list",
+                "--job",
+                "j",
+                "--limit",
+                "5",
+                "--page-token",
+                "t",
+            ])
+            .expect("arguments should be parsed");
+        let (_, args) = matches.subcommand().expect("subcommand should be parsed");
+        assert_eq!(
+            page_query(args),
+            vec![("limit", "5".to_owned()), ("page_token", "t".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_plan_rows() {
+        let plan = json!({
+            "project": { "id": "p", "name": "example", "action": "create" },
+            "workflows": [{ "id": "w", "name": "daily", "action": "create" }],
+            "jobs": [{ "id": "j", "workflow": "daily", "name": "load", "action": "create" }],
+        });
+        let rows = plan_rows(&plan);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[2]["name"], "daily/load");
+    }
+}
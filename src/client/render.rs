@@ -0,0 +1,58 @@
+use serde_json::Value;
+
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::from("-"),
+        Some(Value::String(value)) => value.to_owned(),
+        Some(value) => value.to_string(),
+    }
+}
+
+pub fn table(columns: &[&str], rows: &[Value]) -> String {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| columns.iter().map(|column| cell(row.get(column))).collect())
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain(std::iter::once(column.len()))
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let line = |values: Vec<String>| {
+        let line = values
+            .iter()
+            .zip(widths.iter())
+            .map(|(value, width)| format!("{:<width$}", value, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        format!("{}\n", line.trim_end())
+    };
+    let mut rendered = line(columns.iter().map(|c| c.to_uppercase()).collect());
+    for row in cells {
+        rendered.push_str(&line(row));
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_table() {
+        let rows = vec![
+            json!({ "name": "example", "paused": false }),
+            json!({ "name": "x", "paused": null }),
+        ];
+        let rendered = table(&["name", "paused"], &rows);
+        assert_eq!(rendered, "NAME     PAUSED\nexample  false\nx        -\n");
+    }
+}
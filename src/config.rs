@@ -1,18 +1,36 @@
 pub mod builder;
+use crate::infra::broker::MEMORY_SCHEME;
 use anyhow::Context;
 use anyhow::Result;
 use std::path::Path;
+use uuid::Uuid;
 
 #[derive(serde::Deserialize, Clone, Debug)]
 pub struct Config {
     pub db_url: String,
     pub controller_addr: String,
     pub controller_bind: String,
+    pub runner_bind: String,
     pub cluster_gossip_bind: String,
     pub cluster_gossip_addr: String,
     pub mq_addr: String,
     pub opa_addr: Option<String>,
+    pub otlp_addr: Option<String>,
+    pub api_token: Option<String>,
+    pub secret_key: Option<String>,
+    pub jwt_secret: Option<String>,
+    pub stash_dir: String,
+    pub stash_max_bytes: usize,
+    pub stash_retention_secs: u64,
+    pub retention_interval_secs: u64,
+    pub retention_batch_size: i64,
+    pub notification_max_attempts: i32,
+    pub notification_timeout_secs: u64,
+    pub sla_check_interval_secs: u64,
+    pub runner_engine: String,
+    pub runner_prefetch: u16,
     pub no_auth: bool,
+    pub docs_ui: bool,
     pub use_json_log: bool,
     pub log_filter: String,
 }
@@ -26,4 +44,13 @@ impl Config {
             .context("mandatory configuration value not set")?;
         Ok(config)
     }
+
+    pub fn standalone(mut self) -> Self {
+        self.mq_addr = format!("{}://", MEMORY_SCHEME);
+        // NOTE: Run credentials never leave the process, so an ephemeral secret is enough.
+        if self.jwt_secret.is_none() {
+            self.jwt_secret = Some(Uuid::new_v4().simple().to_string());
+        }
+        self
+    }
 }
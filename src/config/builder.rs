@@ -34,6 +34,7 @@ mod tests {
         let db_url: String = testutils::rand::url();
         let controller_addr: String = testutils::rand::ip();
         let controller_bind: String = testutils::rand::ip();
+        let runner_bind: String = testutils::rand::ip();
         let cluster_gossip_addr: String = testutils::rand::ip();
         let cluster_gossip_bind: String = testutils::rand::ip();
         let mq_addr: String = testutils::rand::ip();
@@ -45,6 +46,7 @@ mod tests {
             db_url = &db_url,
             controller_addr = &controller_addr,
             controller_bind = &controller_bind,
+            runner_bind = &runner_bind,
             cluster_gossip_addr = &cluster_gossip_addr,
             cluster_gossip_bind = &cluster_gossip_bind,
             mq_addr = &mq_addr,
@@ -62,11 +64,27 @@ mod tests {
         assert_eq!(&db_url, &config.db_url);
         assert_eq!(&controller_addr, &config.controller_addr);
         assert_eq!(&controller_bind, &config.controller_bind);
+        assert_eq!(&runner_bind, &config.runner_bind);
         assert_eq!(&cluster_gossip_addr, &config.cluster_gossip_addr);
         assert_eq!(&cluster_gossip_bind, &config.cluster_gossip_bind);
         assert_eq!(&mq_addr, &config.mq_addr);
         assert_eq!(&None, &config.opa_addr);
+        assert_eq!(&None, &config.otlp_addr);
+        assert_eq!(&None, &config.api_token);
+        assert_eq!(&None, &config.secret_key);
+        assert_eq!(&None, &config.jwt_secret);
+        assert_eq!("./stash", &config.stash_dir);
+        assert_eq!(1048576, config.stash_max_bytes);
+        assert_eq!(604800, config.stash_retention_secs);
+        assert_eq!(3600, config.retention_interval_secs);
+        assert_eq!(1000, config.retention_batch_size);
+        assert_eq!(8, config.notification_max_attempts);
+        assert_eq!(10, config.notification_timeout_secs);
+        assert_eq!(60, config.sla_check_interval_secs);
+        assert_eq!("docker", &config.runner_engine);
+        assert_eq!(1, config.runner_prefetch);
         assert_eq!(&no_auth, &config.no_auth);
+        assert!(!config.docs_ui);
         assert_eq!(&use_json_log, &config.use_json_log);
         assert_eq!(&log_filter, &config.log_filter);
         testutils::io::remove(&path).expect("temporary confiiguration file should be removed");
@@ -78,6 +96,7 @@ mod tests {
         let db_url: String = testutils::rand::url();
         let controller_addr: String = testutils::rand::ip();
         let controller_bind: String = testutils::rand::ip();
+        let runner_bind: String = testutils::rand::ip();
         let cluster_gossip_addr: String = testutils::rand::ip();
         let cluster_gossip_bind: String = testutils::rand::ip();
         let mq_addr: String = testutils::rand::ip();
@@ -87,6 +106,7 @@ mod tests {
         env::set_var("KOTOSIRO_DB_URL", &db_url);
         env::set_var("KOTOSIRO_CONTROLLER_ADDR", &controller_addr);
         env::set_var("KOTOSIRO_CONTROLLER_BIND", &controller_bind);
+        env::set_var("KOTOSIRO_RUNNER_BIND", &runner_bind);
         env::set_var("KOTOSIRO_CLUSTER_GOSSIP_ADDR", &cluster_gossip_addr);
         env::set_var("KOTOSIRO_CLUSTER_GOSSIP_BIND", &cluster_gossip_bind);
         env::set_var("KOTOSIRO_MQ_ADDR", &mq_addr);
@@ -101,16 +121,33 @@ mod tests {
         assert_eq!(&db_url, &config.db_url);
         assert_eq!(&controller_addr, &config.controller_addr);
         assert_eq!(&controller_bind, &config.controller_bind);
+        assert_eq!(&runner_bind, &config.runner_bind);
         assert_eq!(&cluster_gossip_addr, &config.cluster_gossip_addr);
         assert_eq!(&cluster_gossip_bind, &config.cluster_gossip_bind);
         assert_eq!(&mq_addr, &config.mq_addr);
         assert_eq!(&None, &config.opa_addr);
+        assert_eq!(&None, &config.otlp_addr);
+        assert_eq!(&None, &config.api_token);
+        assert_eq!(&None, &config.secret_key);
+        assert_eq!(&None, &config.jwt_secret);
+        assert_eq!("./stash", &config.stash_dir);
+        assert_eq!(1048576, config.stash_max_bytes);
+        assert_eq!(604800, config.stash_retention_secs);
+        assert_eq!(3600, config.retention_interval_secs);
+        assert_eq!(1000, config.retention_batch_size);
+        assert_eq!(8, config.notification_max_attempts);
+        assert_eq!(10, config.notification_timeout_secs);
+        assert_eq!(60, config.sla_check_interval_secs);
+        assert_eq!("docker", &config.runner_engine);
+        assert_eq!(1, config.runner_prefetch);
         assert_eq!(&no_auth, &config.no_auth);
+        assert!(!config.docs_ui);
         assert_eq!(&use_json_log, &config.use_json_log);
         assert_eq!(&log_filter, &config.log_filter);
         env::remove_var("KOTOSIRO_DB_URL");
         env::remove_var("KOTOSIRO_CONTROLLER_ADDR");
         env::remove_var("KOTOSIRO_CONTROLLER_BIND");
+        env::remove_var("KOTOSIRO_RUNNER_BIND");
         env::remove_var("KOTOSIRO_CLUSTER_GOSSIP_ADDR");
         env::remove_var("KOTOSIRO_CLUSTER_GOSSIP_BIND");
         env::remove_var("KOTOSIRO_MQ_ADDR");
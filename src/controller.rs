@@ -3,34 +3,60 @@ mod interactors;
 mod repositories;
 mod services;
 use crate::config::Config;
+use crate::controller::services::update::Update;
 use crate::infra;
+use crate::infra::broker;
+use crate::infra::broker::Broker;
+use crate::infra::crypto::Cipher;
+use crate::infra::jwt::Keys;
+use crate::infra::stash::Backend;
+use crate::messages;
 use anyhow::Context;
 use anyhow::Result;
-use lapin::Connection;
 use sqlx::PgPool;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::watch;
+use tracing::info;
 use tracing::warn;
 use uuid::Uuid;
 
 pub struct Controller {
     pub id: Uuid,
     pub db_pool: PgPool,
-    pub mq_conn: Connection,
+    pub broker: Arc<dyn Broker>,
+    pub cipher: Option<Cipher>,
+    pub keys: Option<Keys>,
+    pub stash: Arc<dyn Backend>,
+    pub updates: broadcast::Sender<Update>,
+    pub draining: watch::Sender<bool>,
     pub config: Config,
 }
 
 impl Controller {
-    pub async fn new(config: Config) -> Result<Arc<Self>> {
+    pub async fn new(config: Config, broker: Arc<dyn Broker>) -> Result<Arc<Self>> {
         let db_pool = infra::new_pg_pool(&config)
             .await
             .context("failed to create postgres connection pool")?;
-        let mq_conn = infra::new_rmq_connection(&config)
-            .await
-            .context("failed to create rabbitmq connection")?;
+        Self::with_pool(config, db_pool, broker)
+    }
+
+    pub fn with_pool(
+        config: Config,
+        db_pool: PgPool,
+        broker: Arc<dyn Broker>,
+    ) -> Result<Arc<Self>> {
+        let cipher = infra::new_cipher(&config).context("failed to create secret cipher")?;
         Ok(Arc::new(Controller {
             id: Uuid::new_v4(),
             db_pool,
-            mq_conn,
+            broker,
+            cipher,
+            keys: infra::new_jwt_keys(&config),
+            stash: infra::new_stash_backend(&config),
+            updates: broadcast::channel(services::update::CAPACITY).0,
+            draining: watch::channel(false).0,
             config,
         }))
     }
@@ -39,9 +65,88 @@ impl Controller {
         if self.config.no_auth {
             warn!("authorization is disabled, this is not recommended in production");
         }
-        interactors::bind(self)
+        if self.cipher.is_none() {
+            warn!("secret key is not configured, secrets are disabled");
+        }
+        if self.keys.is_none() {
+            warn!("jwt secret is not configured, stash and runs are disabled");
+        }
+        if broker::is_memory(&self.config.mq_addr) {
+            warn!("in-memory message broker is used, messages never leave this process");
+        }
+        messages::setup(self.broker.as_ref())
+            .await
+            .context("failed to setup message broker exchanges")?;
+        let (shutdown, watcher) = watch::channel(false);
+        let (leader, follower) = watch::channel(false);
+        let elector = tokio::spawn(services::cluster::elect(
+            self.db_pool.clone(),
+            self.id,
+            self.config.controller_addr.clone(),
+            leader,
+            watcher.clone(),
+        ));
+        let relay = tokio::spawn(services::outbox::relay(
+            self.db_pool.clone(),
+            self.broker.clone(),
+            self.keys.clone(),
+            watcher.clone(),
+        ));
+        let listener = tokio::spawn(services::update::listen(
+            self.db_pool.clone(),
+            self.broker.clone(),
+            self.updates.clone(),
+            watcher.clone(),
+        ));
+        let deliverer = tokio::spawn(services::notification::deliver(
+            services::notification::Deliverer {
+                pool: self.db_pool.clone(),
+                cipher: self.cipher.clone(),
+                max_attempts: self.config.notification_max_attempts,
+                timeout: Duration::from_secs(self.config.notification_timeout_secs),
+            },
+            watcher.clone(),
+        ));
+        let reaper = tokio::spawn(services::stash::reap(
+            self.db_pool.clone(),
+            self.stash.clone(),
+            follower.clone(),
+            watcher.clone(),
+        ));
+        let monitor = tokio::spawn(services::sla::monitor(
+            self.db_pool.clone(),
+            Duration::from_secs(self.config.sla_check_interval_secs),
+            follower.clone(),
+            watcher.clone(),
+        ));
+        let compactor = tokio::spawn(services::retention::compact(
+            self.db_pool.clone(),
+            Duration::from_secs(self.config.retention_interval_secs),
+            self.config.retention_batch_size,
+            follower,
+            watcher,
+        ));
+        let draining = self.clone();
+        let signal = async move {
+            crate::shutdown::signal().await;
+            draining.draining.send_replace(true);
+        };
+        let served = interactors::bind(self, signal)
+            .await
+            .context("failed to start API server");
+        info!("draining background tasks");
+        let _ = shutdown.send(true);
+        relay.await.context("failed to join outbox relay")?;
+        listener.await.context("failed to join update listener")?;
+        deliverer
+            .await
+            .context("failed to join notification deliverer")?;
+        reaper.await.context("failed to join stash reaper")?;
+        monitor.await.context("failed to join sla monitor")?;
+        compactor
             .await
-            .context("failed to start API server")?;
-        Ok(())
+            .context("failed to join retention compactor")?;
+        elector.await.context("failed to join leader elector")?;
+        served
     }
 }
@@ -1,7 +1,15 @@
+pub mod execution;
 pub mod job;
+pub mod notification;
+pub mod outbox;
+pub mod page;
 pub mod project;
 pub mod run;
+pub mod secret;
+pub mod sla;
+pub mod stash;
 pub mod token;
+pub mod version;
 pub mod workflow;
 
 #[macro_export]
@@ -409,3 +417,62 @@ macro_rules! impl_json_property {
         }
     };
 }
+
+// NOTE: Property newtypes validate a single "value" field, so their errors are renamed after the entity field they populate.
+pub fn field<T>(name: &'static str, result: anyhow::Result<T>) -> anyhow::Result<T> {
+    result.map_err(|e| {
+        let mut errors = validator::ValidationErrors::new();
+        match e.downcast_ref::<validator::ValidationErrors>() {
+            Some(found) => {
+                for (key, violations) in found.field_errors() {
+                    let key = if key == "value" { name } else { key };
+                    for violation in violations {
+                        errors.add(key, violation.clone());
+                    }
+                }
+            }
+            None => {
+                let mut violation = validator::ValidationError::new("invalid");
+                violation.message = Some(e.to_string().into());
+                errors.add(name, violation);
+            }
+        }
+        anyhow::Error::new(errors)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[derive(Validate)]
+    struct Name {
+        #[validate(length(min = 1))]
+        value: String,
+    }
+
+    #[test]
+    fn test_field() {
+        let result = Name {
+            value: String::new(),
+        }
+        .validate()
+        .map_err(anyhow::Error::from);
+        let e = field("name", result).expect_err("field should fail");
+        let errors = e
+            .downcast_ref::<validator::ValidationErrors>()
+            .expect("validation errors should be found");
+        assert!(errors.field_errors().contains_key("name"));
+        assert!(!errors.field_errors().contains_key("value"));
+        let e =
+            field::<()>("id", Err(anyhow::anyhow!("not a uuid"))).expect_err("field should fail");
+        let errors = e
+            .downcast_ref::<validator::ValidationErrors>()
+            .expect("validation errors should be found");
+        assert_eq!(
+            errors.field_errors()["id"][0].message.as_deref(),
+            Some("not a uuid")
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use super::workflow::WorkflowId;
+use crate::impl_uuid_property;
+use crate::messages::run::RunPriority;
+use anyhow::Result;
+use chrono::DateTime;
+use chrono::Utc;
+use getset::Getters;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionId {
+    value: Uuid,
+}
+
+impl_uuid_property!(ExecutionId);
+
+#[derive(Debug, Clone, PartialEq, Eq, Getters, serde::Serialize)]
+pub struct Execution {
+    #[getset(get = "pub")]
+    id: ExecutionId,
+    #[getset(get = "pub")]
+    workflow_id: WorkflowId,
+    #[getset(get = "pub")]
+    priority: RunPriority,
+    #[getset(get = "pub")]
+    triggered_at: DateTime<Utc>,
+}
+
+impl Execution {
+    pub fn new(
+        id: String,
+        workflow_id: String,
+        priority: RunPriority,
+        triggered_at: impl Into<DateTime<Utc>>,
+    ) -> Result<Self> {
+        Ok(Self {
+            id: ExecutionId::try_from(id)?,
+            workflow_id: WorkflowId::try_from(workflow_id)?,
+            priority,
+            triggered_at: triggered_at.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_execution_id() {
+        assert!(ExecutionId::try_from(testutils::rand::uuid()).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_execution_id() {
+        assert!(ExecutionId::try_from(testutils::rand::string(10)).is_err());
+    }
+}
@@ -1,12 +1,15 @@
 use super::workflow::WorkflowId;
 use crate::impl_i32_property;
+use crate::impl_json_property;
 use crate::impl_string_property;
 use crate::impl_uuid_property;
 use anyhow::Result;
 use getset::Getters;
 use getset::Setters;
+use serde_json::Value as Json;
 use uuid::Uuid;
 use validator::Validate;
+use validator::ValidationError;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct JobId {
@@ -39,9 +42,13 @@ pub struct JobImage {
 
 impl_string_property!(JobImage);
 
+fn validate_template(value: &str) -> Result<(), ValidationError> {
+    crate::template::check(value).map_err(|_| ValidationError::new("template"))
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Validate)]
 pub struct JobArg {
-    #[validate(length(min = 0))]
+    #[validate(length(min = 0), custom = "validate_template")]
     value: String,
 }
 
@@ -49,12 +56,19 @@ impl_string_property!(JobArg);
 
 #[derive(Debug, Clone, PartialEq, Eq, Validate)]
 pub struct JobEnv {
-    #[validate(length(min = 0))]
+    #[validate(length(min = 0), custom = "validate_template")]
     value: String,
 }
 
 impl_string_property!(JobEnv);
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobConfig {
+    value: Json,
+}
+
+impl_json_property!(JobConfig);
+
 #[derive(Debug, Clone, PartialEq, Eq, Getters, Setters, serde::Serialize)]
 pub struct Job {
     #[getset(get = "pub")]
@@ -71,6 +85,10 @@ pub struct Job {
     args: Vec<JobArg>,
     #[getset(get = "pub", set = "pub")]
     envs: Vec<JobEnv>,
+    #[getset(get = "pub", set = "pub")]
+    depends_on: Vec<JobName>,
+    #[getset(get = "pub", set = "pub")]
+    config: Option<JobConfig>,
 }
 
 impl Job {
@@ -89,8 +107,16 @@ impl Job {
             workflow_id: WorkflowId::try_from(workflow_id)?,
             threshold: JobThreshold::new(threshold)?,
             image: JobImage::new(image)?,
-            args: args.into_iter().map(|a| JobArg::new(a)).flatten().collect(),
-            envs: envs.into_iter().map(|e| JobEnv::new(e)).flatten().collect(),
+            args: args
+                .into_iter()
+                .map(JobArg::new)
+                .collect::<Result<Vec<_>>>()?,
+            envs: envs
+                .into_iter()
+                .map(JobEnv::new)
+                .collect::<Result<Vec<_>>>()?,
+            depends_on: Vec::new(),
+            config: None,
         })
     }
 }
@@ -159,4 +185,33 @@ mod tests {
         assert!(matches!(JobEnv::new(testutils::rand::string(255)), Ok(_)));
         assert!(matches!(JobEnv::new(""), Ok(_)));
     }
+
+    #[test]
+    fn test_invalid_job_template() {
+        assert!(JobArg::new("--date={{ run.triggered_at | date(\"%Y\") }}").is_ok());
+        assert!(JobArg::new("--date={{ run.triggered_at ").is_err());
+        assert!(JobEnv::new("DATE={{ run.triggered_at | unknown }}").is_err());
+    }
+
+    #[test]
+    fn test_invalid_job() {
+        let job = |args: Vec<String>, envs: Vec<String>| {
+            Job::new(
+                testutils::rand::uuid(),
+                testutils::rand::string(10),
+                testutils::rand::uuid(),
+                0,
+                String::from("busybox"),
+                args,
+                envs,
+            )
+        };
+        assert!(job(
+            vec![String::from("--date={{ run.triggered_at ")],
+            Vec::new()
+        )
+        .is_err());
+        assert!(job(Vec::new(), vec![String::from("DATE={{ run | unknown }}")]).is_err());
+        assert!(job(vec![String::from("--verbose")], vec![String::from("A=1")]).is_ok());
+    }
 }
@@ -0,0 +1,200 @@
+use super::project::ProjectId;
+use super::secret::SecretValue;
+use super::workflow::WorkflowId;
+use crate::controller::entities::field;
+use crate::impl_string_property;
+use crate::impl_uuid_property;
+use anyhow::anyhow;
+use anyhow::Result;
+use getset::Getters;
+use std::str::FromStr;
+use uuid::Uuid;
+use validator::Validate;
+use validator::ValidationError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotificationRuleId {
+    value: Uuid,
+}
+
+impl_uuid_property!(NotificationRuleId);
+
+fn validate_name(value: &str) -> Result<(), ValidationError> {
+    if value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        Ok(())
+    } else {
+        Err(ValidationError::new("name"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Validate)]
+pub struct NotificationRuleName {
+    #[validate(length(min = 1, max = 64), custom = "validate_name")]
+    value: String,
+}
+
+impl_string_property!(NotificationRuleName);
+
+fn validate_url(value: &str) -> Result<(), ValidationError> {
+    match url::Url::parse(value) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => Ok(()),
+        _ => Err(ValidationError::new("url")),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Validate)]
+pub struct NotificationUrl {
+    #[validate(custom = "validate_url")]
+    value: String,
+}
+
+impl_string_property!(NotificationUrl);
+
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum_macros::EnumString,
+    utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    #[strum(serialize = "run_failure")]
+    RunFailure,
+    #[strum(serialize = "run_error")]
+    RunError,
+    #[strum(serialize = "execution_success")]
+    ExecutionSuccess,
+    #[strum(serialize = "sla_miss")]
+    SlaMiss,
+}
+
+impl AsRef<str> for NotificationEvent {
+    fn as_ref(&self) -> &str {
+        match self {
+            NotificationEvent::RunFailure => "run_failure",
+            NotificationEvent::RunError => "run_error",
+            NotificationEvent::ExecutionSuccess => "execution_success",
+            NotificationEvent::SlaMiss => "sla_miss",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+pub struct NotificationRule {
+    #[getset(get = "pub")]
+    id: NotificationRuleId,
+    #[getset(get = "pub")]
+    project_id: ProjectId,
+    #[getset(get = "pub")]
+    name: NotificationRuleName,
+    #[getset(get = "pub")]
+    events: Vec<NotificationEvent>,
+    #[getset(get = "pub")]
+    workflow_id: Option<WorkflowId>,
+    #[getset(get = "pub")]
+    url: NotificationUrl,
+    #[getset(get = "pub")]
+    secret: SecretValue,
+}
+
+impl NotificationRule {
+    pub fn new(
+        id: String,
+        project_id: String,
+        name: String,
+        events: Vec<String>,
+        workflow_id: Option<String>,
+        url: String,
+        secret: String,
+    ) -> Result<Self> {
+        let events = field(
+            "events",
+            events
+                .iter()
+                .map(|event| {
+                    NotificationEvent::from_str(event)
+                        .map_err(|_| anyhow!(r#"unknown notification event "{}""#, event))
+                })
+                .collect::<Result<Vec<_>>>()
+                .and_then(|events| {
+                    if events.is_empty() {
+                        Err(anyhow!("at least one notification event is required"))
+                    } else {
+                        Ok(events)
+                    }
+                }),
+        )?;
+        Ok(Self {
+            id: field("id", NotificationRuleId::try_from(id))?,
+            project_id: field("project_id", ProjectId::try_from(project_id))?,
+            name: field("name", NotificationRuleName::new(name))?,
+            events,
+            workflow_id: field(
+                "workflow_id",
+                workflow_id.map(WorkflowId::try_from).transpose(),
+            )?,
+            url: field("url", NotificationUrl::new(url))?,
+            secret: field("secret", SecretValue::new(secret))?,
+        })
+    }
+
+    pub fn aad(&self) -> Vec<u8> {
+        aad(&self.project_id, &self.name)
+    }
+}
+
+pub fn aad(project_id: &ProjectId, name: &NotificationRuleName) -> Vec<u8> {
+    format!("{}/notification/{}", project_id.as_uuid(), name.as_str()).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(events: &[&str], url: &str) -> Result<NotificationRule> {
+        NotificationRule::new(
+            testutils::rand::uuid(),
+            testutils::rand::uuid(),
+            String::from("on-call"),
+            events.iter().map(|event| event.to_string()).collect(),
+            None,
+            url.to_owned(),
+            testutils::rand::string(10),
+        )
+    }
+
+    #[test]
+    fn test_valid_notification_rule() {
+        let rule = rule(
+            &["run_failure", "execution_success", "sla_miss"],
+            "https://example.com/hook",
+        )
+        .expect("rule should be created");
+        assert_eq!(
+            rule.events(),
+            &vec![
+                NotificationEvent::RunFailure,
+                NotificationEvent::ExecutionSuccess,
+                NotificationEvent::SlaMiss
+            ]
+        );
+        assert!(!format!("{:?}", rule).contains(rule.secret().as_str()));
+    }
+
+    #[test]
+    fn test_invalid_notification_rule() {
+        assert!(rule(&[], "https://example.com/hook").is_err());
+        assert!(rule(&["run_success"], "https://example.com/hook").is_err());
+        assert!(rule(&["run_error"], "ftp://example.com/hook").is_err());
+        assert!(rule(&["run_error"], "example.com").is_err());
+        assert!(NotificationRuleName::new("on call").is_err());
+    }
+}
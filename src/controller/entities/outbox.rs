@@ -0,0 +1,137 @@
+use crate::impl_i32_property;
+use crate::impl_json_property;
+use crate::impl_string_property;
+use crate::impl_uuid_property;
+use crate::logging::propagation;
+use crate::messages::Message;
+use anyhow::Context;
+use anyhow::Result;
+use getset::Getters;
+use serde_json::Value as Json;
+use std::collections::HashMap;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxId {
+    value: Uuid,
+}
+
+impl_uuid_property!(OutboxId);
+
+#[derive(Debug, Clone, PartialEq, Eq, Validate)]
+pub struct OutboxExchange {
+    #[validate(length(min = 1))]
+    value: String,
+}
+
+impl_string_property!(OutboxExchange);
+
+#[derive(Debug, Clone, PartialEq, Eq, Validate)]
+pub struct OutboxRoutingKey {
+    #[validate(length(min = 0))]
+    value: String,
+}
+
+impl_string_property!(OutboxRoutingKey);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxPayload {
+    value: Json,
+}
+
+impl_json_property!(OutboxPayload);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxHeaders {
+    value: Json,
+}
+
+impl_json_property!(OutboxHeaders);
+
+#[derive(Debug, Clone, PartialEq, Eq, Validate)]
+pub struct OutboxPriority {
+    #[validate(range(min = 0, max = 255))]
+    value: i32,
+}
+
+impl_i32_property!(OutboxPriority);
+
+#[derive(Debug, Clone, PartialEq, Eq, Getters, serde::Serialize)]
+pub struct Outbox {
+    #[getset(get = "pub")]
+    id: OutboxId,
+    #[getset(get = "pub")]
+    exchange: OutboxExchange,
+    #[getset(get = "pub")]
+    routing_key: OutboxRoutingKey,
+    #[getset(get = "pub")]
+    payload: OutboxPayload,
+    #[getset(get = "pub")]
+    headers: OutboxHeaders,
+    #[getset(get = "pub")]
+    priority: Option<OutboxPriority>,
+}
+
+impl Outbox {
+    pub fn new(message: &impl Message) -> Result<Self> {
+        let payload = serde_json::to_value(message).context("failed to serialize message")?;
+        let mut headers = HashMap::new();
+        propagation::inject_map(&mut headers);
+        let headers = serde_json::to_value(headers).context("failed to serialize headers")?;
+        Ok(Self {
+            id: OutboxId::new(Uuid::new_v4()),
+            exchange: OutboxExchange::new(message.exchange())?,
+            routing_key: OutboxRoutingKey::new(message.routing_key())?,
+            payload: OutboxPayload::new(payload),
+            headers: OutboxHeaders::new(headers),
+            priority: message.priority().map(OutboxPriority::new).transpose()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::config::ConfigUpdate;
+    use crate::messages::config::CONFIG_UPDATES_EXCHANGE;
+    use crate::messages::run::RunDispatch;
+    use crate::messages::run::RunPriority;
+
+    #[test]
+    fn test_valid_outbox_id() {
+        assert!(OutboxId::try_from(testutils::rand::uuid()).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_outbox_id() {
+        assert!(OutboxId::try_from(testutils::rand::string(255)).is_err());
+    }
+
+    #[test]
+    fn test_new_outbox() {
+        let uuid = Uuid::new_v4();
+        let outbox = Outbox::new(&ConfigUpdate::Project(uuid)).expect("outbox should be created");
+        assert_eq!(outbox.exchange().as_str(), CONFIG_UPDATES_EXCHANGE);
+        assert_eq!(outbox.routing_key().as_str(), "");
+        let update: ConfigUpdate = serde_json::from_value(outbox.payload().to_json())
+            .expect("payload should be deserialized");
+        assert!(matches!(update, ConfigUpdate::Project(id) if id == uuid));
+        assert!(outbox.priority().is_none());
+    }
+
+    #[test]
+    fn test_new_outbox_with_priority() {
+        let dispatch = RunDispatch {
+            run_id: Uuid::new_v4(),
+            job_id: Uuid::new_v4(),
+            priority: RunPriority::High,
+            config_token: None,
+        };
+        let outbox = Outbox::new(&dispatch).expect("outbox should be created");
+        assert_eq!(
+            outbox.priority().as_ref().map(OutboxPriority::to_i32),
+            Some(RunPriority::High as i32)
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use crate::controller::repositories::job::JobRow;
+use crate::controller::repositories::project::ProjectRow;
+use crate::controller::repositories::project::WorkflowSummaryRow;
+use crate::controller::repositories::run::RunRow;
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::DateTime;
+use chrono::SecondsFormat;
+use chrono::Utc;
+use getset::CopyGetters;
+use getset::Getters;
+use uuid::Uuid;
+use validator::Validate;
+
+pub const DEFAULT_LIMIT: i64 = 100;
+
+pub const MAX_LIMIT: i64 = 1000;
+
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum PageSort {
+    Name,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl PageSort {
+    pub fn column(&self) -> &'static str {
+        match self {
+            PageSort::Name => "name",
+            PageSort::CreatedAt => "created_at",
+            PageSort::UpdatedAt => "updated_at",
+        }
+    }
+
+    fn sql_type(&self) -> &'static str {
+        match self {
+            PageSort::Name => "VARCHAR",
+            PageSort::CreatedAt | PageSort::UpdatedAt => "TIMESTAMPTZ",
+        }
+    }
+
+    fn default_order(&self) -> PageOrder {
+        match self {
+            PageSort::Name => PageOrder::Asc,
+            PageSort::CreatedAt | PageSort::UpdatedAt => PageOrder::Desc,
+        }
+    }
+}
+
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PageOrder {
+    Asc,
+    Desc,
+}
+
+impl PageOrder {
+    fn keyword(&self) -> &'static str {
+        match self {
+            PageOrder::Asc => "ASC",
+            PageOrder::Desc => "DESC",
+        }
+    }
+
+    fn comparator(&self) -> &'static str {
+        match self {
+            PageOrder::Asc => ">",
+            PageOrder::Desc => "<",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Cursor {
+    sort: PageSort,
+    order: PageOrder,
+    prefix: Option<String>,
+    value: String,
+    id: Uuid,
+}
+
+impl Cursor {
+    fn encode(&self) -> Result<String> {
+        let json = serde_json::to_vec(self).context("failed to serialize page cursor")?;
+        Ok(URL_SAFE_NO_PAD.encode(json))
+    }
+
+    fn decode(token: &str) -> Result<Self> {
+        let json = URL_SAFE_NO_PAD
+            .decode(token)
+            .context("page token must be base64 encoded")?;
+        serde_json::from_slice(&json).context("failed to deserialize page token")
+    }
+}
+
+pub trait Keyset {
+    fn id(&self) -> Uuid;
+
+    fn name(&self) -> Option<&str>;
+
+    fn created_at(&self) -> DateTime<Utc>;
+
+    fn updated_at(&self) -> DateTime<Utc>;
+
+    fn key(&self, sort: PageSort) -> String {
+        match sort {
+            PageSort::Name => self.name().unwrap_or_default().to_owned(),
+            PageSort::CreatedAt => self
+                .created_at()
+                .to_rfc3339_opts(SecondsFormat::Micros, true),
+            PageSort::UpdatedAt => self
+                .updated_at()
+                .to_rfc3339_opts(SecondsFormat::Micros, true),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Getters, CopyGetters, Validate)]
+pub struct Pagination {
+    #[getset(get_copy = "pub")]
+    #[validate(range(min = 1, max = 1000))]
+    limit: i64,
+    #[getset(get_copy = "pub")]
+    sort: PageSort,
+    #[getset(get_copy = "pub")]
+    order: PageOrder,
+    #[getset(get = "pub")]
+    prefix: Option<String>,
+    cursor: Option<Cursor>,
+}
+
+impl Pagination {
+    // NOTE: The first of the sorts is the default, and name prefixes are only accepted when names are sortable.
+    pub fn new(
+        limit: Option<i64>,
+        sort: Option<PageSort>,
+        order: Option<PageOrder>,
+        prefix: Option<String>,
+        token: Option<&str>,
+        sorts: &[PageSort],
+    ) -> Result<Self> {
+        let cursor = token.map(Cursor::decode).transpose()?;
+        if let Some(cursor) = &cursor {
+            if sort.map_or(false, |sort| sort != cursor.sort)
+                || order.map_or(false, |order| order != cursor.order)
+                || prefix
+                    .as_ref()
+                    .map_or(false, |p| Some(p) != cursor.prefix.as_ref())
+            {
+                return Err(anyhow!("page token does not match the query"));
+            }
+        }
+        let sort = cursor
+            .as_ref()
+            .map(|cursor| cursor.sort)
+            .or(sort)
+            .or_else(|| sorts.first().copied())
+            .ok_or_else(|| anyhow!("no sort key is available"))?;
+        if !sorts.contains(&sort) {
+            return Err(anyhow!(r#"cannot sort by "{}""#, sort.column()));
+        }
+        let order = cursor
+            .as_ref()
+            .map(|cursor| cursor.order)
+            .or(order)
+            .unwrap_or_else(|| sort.default_order());
+        let prefix = match &cursor {
+            Some(cursor) => cursor.prefix.clone(),
+            None => prefix.filter(|prefix| !prefix.is_empty()),
+        };
+        if prefix.is_some() && !sorts.contains(&PageSort::Name) {
+            return Err(anyhow!("name prefix is not supported"));
+        }
+        let object = Self {
+            limit: limit.unwrap_or(DEFAULT_LIMIT),
+            sort,
+            order,
+            prefix,
+            cursor,
+        };
+        object.validate()?;
+        Ok(object)
+    }
+
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit + 1
+    }
+
+    pub fn order_by(&self, table: &str) -> String {
+        format!(
+            "ORDER BY {table}.{column} {order}, {table}.id {order}",
+            table = table,
+            column = self.sort.column(),
+            order = self.order.keyword(),
+        )
+    }
+
+    // NOTE: Binds the cursor key to ${key} and the cursor id to ${key} + 1.
+    pub fn seek(&self, table: &str, key: usize) -> String {
+        format!(
+            "(${key}::VARCHAR IS NULL OR ({table}.{column}, {table}.id) {comparator} (${key}::VARCHAR::{sql_type}, ${id}::UUID))",
+            key = key,
+            id = key + 1,
+            table = table,
+            column = self.sort.column(),
+            comparator = self.order.comparator(),
+            sql_type = self.sort.sql_type(),
+        )
+    }
+
+    pub fn pattern(&self) -> Option<String> {
+        self.prefix.as_ref().map(|prefix| {
+            let escaped = prefix
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("{}%", escaped)
+        })
+    }
+
+    pub fn after_key(&self) -> Option<&str> {
+        self.cursor.as_ref().map(|cursor| cursor.value.as_str())
+    }
+
+    pub fn after_id(&self) -> Option<Uuid> {
+        self.cursor.as_ref().map(|cursor| cursor.id)
+    }
+
+    pub fn next_page_token(&self, last: &impl Keyset) -> Result<String> {
+        Cursor {
+            sort: self.sort,
+            order: self.order,
+            prefix: self.prefix.clone(),
+            value: last.key(self.sort),
+            id: last.id(),
+        }
+        .encode()
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+#[aliases(
+    ProjectPage = Page<ProjectRow>,
+    WorkflowSummaryPage = Page<WorkflowSummaryRow>,
+    JobPage = Page<JobRow>,
+    RunPage = Page<RunRow>
+)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_page_token: Option<String>,
+}
+
+impl<T: Keyset> Page<T> {
+    // NOTE: Rows must be fetched with one extra row beyond the limit to detect the next page.
+    pub fn new(mut rows: Vec<T>, pagination: &Pagination) -> Result<Self> {
+        let limit = usize::try_from(pagination.limit()).unwrap_or(usize::MAX);
+        let next_page_token = if rows.len() > limit {
+            rows.truncate(limit);
+            rows.last()
+                .map(|last| pagination.next_page_token(last))
+                .transpose()?
+        } else {
+            None
+        };
+        Ok(Self {
+            items: rows,
+            next_page_token,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Row {
+        id: Uuid,
+        name: String,
+        created_at: DateTime<Utc>,
+    }
+
+    impl Keyset for Row {
+        fn id(&self) -> Uuid {
+            self.id
+        }
+
+        fn name(&self) -> Option<&str> {
+            Some(&self.name)
+        }
+
+        fn created_at(&self) -> DateTime<Utc> {
+            self.created_at
+        }
+
+        fn updated_at(&self) -> DateTime<Utc> {
+            self.created_at
+        }
+    }
+
+    fn rows(count: usize) -> Vec<Row> {
+        (0..count)
+            .map(|_| Row {
+                id: Uuid::new_v4(),
+                name: testutils::rand::string(10),
+                created_at: Utc::now(),
+            })
+            .collect()
+    }
+
+    const SORTS: [PageSort; 3] = [PageSort::Name, PageSort::CreatedAt, PageSort::UpdatedAt];
+
+    #[test]
+    fn test_default_pagination() {
+        let pagination = Pagination::new(None, None, None, None, None, &SORTS)
+            .expect("pagination should be created");
+        assert_eq!(pagination.limit(), DEFAULT_LIMIT);
+        assert_eq!(pagination.sort(), PageSort::Name);
+        assert_eq!(pagination.order(), PageOrder::Asc);
+        let pagination = Pagination::new(None, Some(PageSort::CreatedAt), None, None, None, &SORTS)
+            .expect("pagination should be created");
+        assert_eq!(pagination.order(), PageOrder::Desc);
+    }
+
+    #[test]
+    fn test_invalid_limit() {
+        assert!(Pagination::new(Some(0), None, None, None, None, &SORTS).is_err());
+        assert!(Pagination::new(Some(MAX_LIMIT + 1), None, None, None, None, &SORTS).is_err());
+        assert!(Pagination::new(Some(MAX_LIMIT), None, None, None, None, &SORTS).is_ok());
+    }
+
+    #[test]
+    fn test_unsupported_sort_and_prefix() {
+        let sorts = [PageSort::CreatedAt, PageSort::UpdatedAt];
+        assert!(Pagination::new(None, Some(PageSort::Name), None, None, None, &sorts).is_err());
+        assert!(Pagination::new(None, None, None, Some("a".into()), None, &sorts).is_err());
+    }
+
+    #[test]
+    fn test_sql_fragments() {
+        let pagination = Pagination::new(None, Some(PageSort::UpdatedAt), None, None, None, &SORTS)
+            .expect("pagination should be created");
+        assert_eq!(
+            pagination.order_by("project"),
+            "ORDER BY project.updated_at DESC, project.id DESC"
+        );
+        assert_eq!(
+            pagination.seek("project", 2),
+            "($2::VARCHAR IS NULL OR (project.updated_at, project.id) < ($2::VARCHAR::TIMESTAMPTZ, $3::UUID))"
+        );
+    }
+
+    #[test]
+    fn test_pattern() {
+        let pagination = Pagination::new(None, None, None, Some("a_b%".into()), None, &SORTS)
+            .expect("pagination should be created");
+        assert_eq!(pagination.pattern().as_deref(), Some("a\\_b\\%%"));
+    }
+
+    #[test]
+    fn test_page_and_token() {
+        let pagination = Pagination::new(
+            Some(2),
+            Some(PageSort::CreatedAt),
+            Some(PageOrder::Asc),
+            Some("x".into()),
+            None,
+            &SORTS,
+        )
+        .expect("pagination should be created");
+        let page = Page::new(rows(3), &pagination).expect("page should be created");
+        assert_eq!(page.items.len(), 2);
+        let token = page.next_page_token.expect("next page token should exist");
+        let next = Pagination::new(Some(2), None, None, None, Some(&token), &SORTS)
+            .expect("pagination should be restored");
+        assert_eq!(next.sort(), PageSort::CreatedAt);
+        assert_eq!(next.order(), PageOrder::Asc);
+        assert_eq!(next.prefix().as_deref(), Some("x"));
+        assert_eq!(next.after_id(), Some(page.items[1].id));
+        assert_eq!(
+            next.after_key(),
+            Some(page.items[1].key(PageSort::CreatedAt).as_str())
+        );
+        assert!(
+            Pagination::new(None, Some(PageSort::Name), None, None, Some(&token), &SORTS).is_err()
+        );
+        let page = Page::new(rows(2), &pagination).expect("page should be created");
+        assert!(page.next_page_token.is_none());
+    }
+
+    #[test]
+    fn test_invalid_token() {
+        let token = testutils::rand::string(20);
+        assert!(Pagination::new(None, None, None, None, Some(&token), &SORTS).is_err());
+    }
+}
@@ -1,7 +1,9 @@
+use crate::controller::entities::field;
 use crate::impl_json_property;
 use crate::impl_string_property;
 use crate::impl_uuid_property;
 use anyhow::Result;
+use getset::CopyGetters;
 use getset::Getters;
 use getset::Setters;
 use serde_json::Value as Json;
@@ -38,16 +40,61 @@ pub struct ProjectConfig {
 
 impl_json_property!(ProjectConfig);
 
-#[derive(Debug, Clone, PartialEq, Eq, Getters, Setters, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectConfigSchema {
+    value: Json,
+}
+
+impl_json_property!(ProjectConfigSchema);
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    CopyGetters,
+    Validate,
+    serde::Serialize,
+    serde::Deserialize,
+    utoipa::ToSchema,
+)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectRetention {
+    #[getset(get_copy = "pub")]
+    #[validate(range(min = 1))]
+    days: Option<i32>,
+    #[getset(get_copy = "pub")]
+    #[validate(range(min = 1))]
+    runs: Option<i32>,
+}
+
+impl ProjectRetention {
+    pub fn new(days: Option<i32>, runs: Option<i32>) -> Result<Self> {
+        let object = Self { days, runs };
+        object.validate()?;
+        Ok(object)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Getters, Setters, serde::Serialize, utoipa::ToSchema)]
 pub struct Project {
     #[getset(get = "pub")]
+    #[schema(value_type = Uuid)]
     id: ProjectId,
     #[getset(get = "pub", set = "pub")]
+    #[schema(value_type = String)]
     name: ProjectName,
     #[getset(get = "pub", set = "pub")]
+    #[schema(value_type = String)]
     description: ProjectDescription,
     #[getset(get = "pub", set = "pub")]
+    #[schema(value_type = Option<Object>)]
     config: Option<ProjectConfig>,
+    #[getset(get = "pub", set = "pub")]
+    #[schema(value_type = Option<Object>)]
+    config_schema: Option<ProjectConfigSchema>,
+    #[getset(get = "pub", set = "pub")]
+    retention: Option<ProjectRetention>,
 }
 
 impl Project {
@@ -58,10 +105,12 @@ impl Project {
         config: impl Into<Option<Json>>,
     ) -> Result<Self> {
         Ok(Self {
-            id: ProjectId::try_from(id)?,
-            name: ProjectName::new(name)?,
-            description: ProjectDescription::new(description)?,
-            config: config.into().map(|json| ProjectConfig::new(json)),
+            id: field("id", ProjectId::try_from(id))?,
+            name: field("name", ProjectName::new(name))?,
+            description: field("description", ProjectDescription::new(description))?,
+            config: config.into().map(ProjectConfig::new),
+            config_schema: None,
+            retention: None,
         })
     }
 }
@@ -107,4 +156,17 @@ mod tests {
         ));
         assert!(matches!(ProjectDescription::new(""), Ok(_)));
     }
+
+    #[test]
+    fn test_valid_project_retention() {
+        assert!(ProjectRetention::new(Some(testutils::rand::i32(1, 365)), None).is_ok());
+        assert!(ProjectRetention::new(None, Some(testutils::rand::i32(1, 100))).is_ok());
+        assert!(ProjectRetention::new(None, None).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_project_retention() {
+        assert!(ProjectRetention::new(Some(0), None).is_err());
+        assert!(ProjectRetention::new(None, Some(-1)).is_err());
+    }
 }
@@ -1,4 +1,6 @@
+use super::execution::ExecutionId;
 use super::job::JobId;
+use crate::impl_i32_property;
 use crate::impl_uuid_property;
 use crate::messages::run::RunPriority;
 use crate::messages::token::TokenState;
@@ -8,6 +10,7 @@ use chrono::Utc;
 use getset::Getters;
 use getset::Setters;
 use uuid::Uuid;
+use validator::Validate;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RunId {
@@ -16,22 +19,42 @@ pub struct RunId {
 
 impl_uuid_property!(RunId);
 
-#[derive(Debug, Clone, PartialEq, Eq, Getters, Setters, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Validate)]
+pub struct RunAttempt {
+    #[validate(range(min = 1))]
+    value: i32,
+}
+
+impl_i32_property!(RunAttempt);
+
+#[derive(Debug, Clone, PartialEq, Eq, Getters, Setters, serde::Serialize, utoipa::ToSchema)]
 pub struct Run {
     #[getset(get = "pub")]
+    #[schema(value_type = Uuid)]
     id: RunId,
     #[getset(get = "pub", set = "pub")]
     state: TokenState,
     #[getset(get = "pub", set = "pub")]
     priority: RunPriority,
     #[getset(get = "pub", set = "pub")]
+    #[schema(value_type = Uuid)]
     job_id: JobId,
     #[getset(get = "pub", set = "pub")]
     triggered_at: DateTime<Utc>,
+    #[getset(get = "pub", set = "pub")]
+    #[schema(value_type = i32)]
+    attempt: RunAttempt,
+    #[getset(get = "pub", set = "pub")]
+    args: Option<Vec<String>>,
+    #[getset(get = "pub", set = "pub")]
+    envs: Option<Vec<String>>,
+    #[getset(get = "pub", set = "pub")]
+    #[schema(value_type = Option<Uuid>)]
+    execution_id: Option<ExecutionId>,
 }
 
 impl Run {
-    pub fn new<'a>(
+    pub fn new(
         id: String,
         state: TokenState,
         priority: RunPriority,
@@ -40,10 +63,14 @@ impl Run {
     ) -> Result<Self> {
         Ok(Self {
             id: RunId::try_from(id)?,
-            state: state,
-            priority: priority,
+            state,
+            priority,
             job_id: JobId::try_from(job_id)?,
             triggered_at: triggered_at.into(),
+            attempt: RunAttempt::new(1)?,
+            args: None,
+            envs: None,
+            execution_id: None,
         })
     }
 }
@@ -64,4 +91,14 @@ mod tests {
             Err(_)
         ));
     }
+
+    #[test]
+    fn test_valid_run_attempt() {
+        assert!(RunAttempt::new(testutils::rand::i32(1, 10)).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_run_attempt() {
+        assert!(RunAttempt::new(0).is_err());
+    }
 }
@@ -0,0 +1,128 @@
+use super::project::ProjectId;
+use crate::controller::entities::field;
+use crate::impl_string_property;
+use crate::impl_uuid_property;
+use anyhow::anyhow;
+use anyhow::Result;
+use getset::Getters;
+use uuid::Uuid;
+use validator::Validate;
+use validator::ValidationError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretId {
+    value: Uuid,
+}
+
+impl_uuid_property!(SecretId);
+
+fn validate_identifier(value: &str) -> Result<(), ValidationError> {
+    let mut chars = value.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return Err(ValidationError::new("identifier")),
+    }
+    if chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Ok(())
+    } else {
+        Err(ValidationError::new("identifier"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Validate)]
+pub struct SecretName {
+    #[validate(custom = "validate_identifier")]
+    value: String,
+}
+
+impl_string_property!(SecretName);
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretValue {
+    value: String,
+}
+
+impl SecretValue {
+    pub fn new<S>(value: S) -> Result<Self>
+    where
+        S: Into<String>,
+    {
+        let value = value.into();
+        if value.is_empty() {
+            return Err(anyhow!("secret value must not be empty"));
+        }
+        Ok(Self { value })
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.value.as_str()
+    }
+}
+
+impl std::fmt::Debug for SecretValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretValue(***)")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+pub struct Secret {
+    #[getset(get = "pub")]
+    id: SecretId,
+    #[getset(get = "pub")]
+    project_id: ProjectId,
+    #[getset(get = "pub")]
+    name: SecretName,
+    #[getset(get = "pub")]
+    value: SecretValue,
+}
+
+impl Secret {
+    pub fn new(id: String, project_id: String, name: String, value: String) -> Result<Self> {
+        Ok(Self {
+            id: field("id", SecretId::try_from(id))?,
+            project_id: field("project_id", ProjectId::try_from(project_id))?,
+            name: field("name", SecretName::new(name))?,
+            value: field("value", SecretValue::new(value))?,
+        })
+    }
+
+    pub fn aad(&self) -> Vec<u8> {
+        aad(&self.project_id, &self.name)
+    }
+}
+
+pub fn aad(project_id: &ProjectId, name: &SecretName) -> Vec<u8> {
+    format!("{}/{}", project_id.as_uuid(), name.as_str()).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_secret_name() {
+        assert!(SecretName::new("DB_PASSWORD").is_ok());
+        assert!(SecretName::new("_token1").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_secret_name() {
+        assert!(SecretName::new("").is_err());
+        assert!(SecretName::new("1TOKEN").is_err());
+        assert!(SecretName::new("DB-PASSWORD").is_err());
+    }
+
+    #[test]
+    fn test_redacted_secret_value() {
+        let value = testutils::rand::string(10);
+        let secret = Secret::new(
+            testutils::rand::uuid(),
+            testutils::rand::uuid(),
+            String::from("TOKEN"),
+            value.clone(),
+        )
+        .expect("secret should be created");
+        assert!(!format!("{:?}", secret).contains(&value));
+    }
+}
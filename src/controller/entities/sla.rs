@@ -0,0 +1,120 @@
+use super::execution::ExecutionId;
+use super::workflow::WorkflowId;
+use crate::impl_uuid_property;
+use anyhow::Result;
+use chrono::DateTime;
+use chrono::Utc;
+use getset::Getters;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlaMissId {
+    value: Uuid,
+}
+
+impl_uuid_property!(SlaMissId);
+
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    strum_macros::EnumString,
+    utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SlaKind {
+    #[strum(serialize = "within")]
+    Within,
+    #[strum(serialize = "by")]
+    By,
+}
+
+impl AsRef<str> for SlaKind {
+    fn as_ref(&self) -> &str {
+        match self {
+            SlaKind::Within => "within",
+            SlaKind::By => "by",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+pub struct SlaMiss {
+    #[getset(get = "pub")]
+    id: SlaMissId,
+    #[getset(get = "pub")]
+    workflow_id: WorkflowId,
+    #[getset(get = "pub")]
+    execution_id: Option<ExecutionId>,
+    #[getset(get = "pub")]
+    kind: SlaKind,
+    #[getset(get = "pub")]
+    deadline: DateTime<Utc>,
+}
+
+impl SlaMiss {
+    pub fn new(
+        id: String,
+        workflow_id: String,
+        execution_id: Option<String>,
+        kind: SlaKind,
+        deadline: impl Into<DateTime<Utc>>,
+    ) -> Result<Self> {
+        Ok(Self {
+            id: SlaMissId::try_from(id)?,
+            workflow_id: WorkflowId::try_from(workflow_id)?,
+            execution_id: execution_id.map(ExecutionId::try_from).transpose()?,
+            kind,
+            deadline: deadline.into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_valid_sla_miss() {
+        assert!(SlaMiss::new(
+            testutils::rand::uuid(),
+            testutils::rand::uuid(),
+            Some(testutils::rand::uuid()),
+            SlaKind::Within,
+            Utc::now(),
+        )
+        .is_ok());
+        assert!(SlaMiss::new(
+            testutils::rand::uuid(),
+            testutils::rand::uuid(),
+            None,
+            SlaKind::By,
+            Utc::now(),
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_invalid_sla_miss() {
+        assert!(SlaMiss::new(
+            testutils::rand::uuid(),
+            testutils::rand::uuid(),
+            Some(testutils::rand::string(10)),
+            SlaKind::Within,
+            Utc::now(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_sla_kind() {
+        for kind in [SlaKind::Within, SlaKind::By] {
+            assert_eq!(SlaKind::from_str(kind.as_ref()).ok(), Some(kind));
+        }
+    }
+}
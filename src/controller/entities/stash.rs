@@ -0,0 +1,136 @@
+use super::run::RunId;
+use crate::controller::entities::field;
+use crate::impl_i64_property;
+use crate::impl_string_property;
+use crate::impl_uuid_property;
+use anyhow::Result;
+use chrono::DateTime;
+use chrono::Utc;
+use getset::Getters;
+use uuid::Uuid;
+use validator::Validate;
+use validator::ValidationError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StashId {
+    value: Uuid,
+}
+
+impl_uuid_property!(StashId);
+
+fn validate_key(value: &str) -> Result<(), ValidationError> {
+    if value.starts_with('.') {
+        return Err(ValidationError::new("key"));
+    }
+    if value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+    {
+        Ok(())
+    } else {
+        Err(ValidationError::new("key"))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Validate)]
+pub struct StashName {
+    #[validate(length(min = 1, max = 128), custom = "validate_key")]
+    value: String,
+}
+
+impl_string_property!(StashName);
+
+#[derive(Debug, Clone, PartialEq, Eq, Validate)]
+pub struct StashContentType {
+    #[validate(length(min = 1, max = 255))]
+    value: String,
+}
+
+impl_string_property!(StashContentType);
+
+#[derive(Debug, Clone, PartialEq, Eq, Validate)]
+pub struct StashSize {
+    #[validate(range(min = 0))]
+    value: i64,
+}
+
+impl_i64_property!(StashSize);
+
+#[derive(Debug, Clone, PartialEq, Eq, Getters)]
+pub struct Stash {
+    #[getset(get = "pub")]
+    id: StashId,
+    #[getset(get = "pub")]
+    run_id: RunId,
+    #[getset(get = "pub")]
+    name: StashName,
+    #[getset(get = "pub")]
+    content_type: StashContentType,
+    #[getset(get = "pub")]
+    size: StashSize,
+    #[getset(get = "pub")]
+    expires_at: DateTime<Utc>,
+}
+
+impl Stash {
+    pub fn new(
+        id: String,
+        run_id: String,
+        name: String,
+        content_type: String,
+        size: i64,
+        expires_at: impl Into<DateTime<Utc>>,
+    ) -> Result<Self> {
+        Ok(Self {
+            id: field("id", StashId::try_from(id))?,
+            run_id: field("run_id", RunId::try_from(run_id))?,
+            name: field("name", StashName::new(name))?,
+            content_type: field("content_type", StashContentType::new(content_type))?,
+            size: field("size", StashSize::new(size))?,
+            expires_at: expires_at.into(),
+        })
+    }
+
+    pub fn key(&self) -> String {
+        key(&self.run_id, &self.name)
+    }
+}
+
+pub fn key(run_id: &RunId, name: &StashName) -> String {
+    format!("{}/{}", run_id.as_uuid(), name.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_stash_name() {
+        assert!(StashName::new("result.json").is_ok());
+        assert!(StashName::new("model-v1_final").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_stash_name() {
+        assert!(StashName::new("").is_err());
+        assert!(StashName::new("..").is_err());
+        assert!(StashName::new("../etc/passwd").is_err());
+        assert!(StashName::new("dir/file").is_err());
+        assert!(StashName::new(testutils::rand::string(129)).is_err());
+    }
+
+    #[test]
+    fn test_stash_key() {
+        let run_id = testutils::rand::uuid();
+        let stash = Stash::new(
+            testutils::rand::uuid(),
+            run_id.clone(),
+            String::from("result.json"),
+            String::from("application/json"),
+            2,
+            Utc::now(),
+        )
+        .expect("stash should be created");
+        assert_eq!(stash.key(), format!("{}/result.json", run_id));
+    }
+}
@@ -29,7 +29,7 @@ impl Token {
         Ok(Self {
             job_id: JobId::try_from(job_id)?,
             count: TokenCount::new(count)?,
-            state: state,
+            state,
         })
     }
 }
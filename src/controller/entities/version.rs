@@ -0,0 +1,106 @@
+use anyhow::Result;
+
+pub fn etag(version: i64) -> String {
+    format!(r#""{}""#, version)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Precondition {
+    #[default]
+    None,
+    Any,
+    Versions(Vec<i64>),
+}
+
+impl Precondition {
+    // NOTE: Weak and malformed entity tags are kept out of the list so that they never match.
+    pub fn parse(header: &str) -> Self {
+        if header.trim() == "*" {
+            return Precondition::Any;
+        }
+        Precondition::Versions(
+            header
+                .split(',')
+                .filter_map(|tag| {
+                    tag.trim()
+                        .strip_prefix('"')
+                        .and_then(|tag| tag.strip_suffix('"'))
+                        .and_then(|tag| tag.parse::<i64>().ok())
+                })
+                .collect(),
+        )
+    }
+
+    pub fn check(&self, current: Option<i64>) -> Result<()> {
+        let matched = match (self, current) {
+            (Precondition::None, _) => true,
+            (_, None) => false,
+            (Precondition::Any, Some(_)) => true,
+            (Precondition::Versions(versions), Some(current)) => versions.contains(&current),
+        };
+        if matched {
+            Ok(())
+        } else {
+            Err(anyhow::Error::new(Stale { current }))
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Stale {
+    pub current: Option<i64>,
+}
+
+impl std::fmt::Display for Stale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.current {
+            Some(current) => write!(f, "current version {} does not match", current),
+            None => write!(f, "resource does not exist"),
+        }
+    }
+}
+
+impl std::error::Error for Stale {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Precondition::Any, Precondition::parse("*"));
+        assert_eq!(
+            Precondition::Versions(vec![3]),
+            Precondition::parse(r#""3""#)
+        );
+        assert_eq!(
+            Precondition::Versions(vec![1, 4]),
+            Precondition::parse(r#""1", W/"2", "x", "4""#)
+        );
+        assert_eq!(Precondition::Versions(vec![]), Precondition::parse("3"));
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(Precondition::None.check(None).is_ok());
+        assert!(Precondition::None.check(Some(1)).is_ok());
+        assert!(Precondition::Any.check(Some(1)).is_ok());
+        assert!(Precondition::Versions(vec![1, 2]).check(Some(2)).is_ok());
+        let e = Precondition::Any
+            .check(None)
+            .expect_err("missing resource should not match");
+        assert_eq!(Some(&Stale { current: None }), e.downcast_ref::<Stale>());
+        let e = Precondition::Versions(vec![1])
+            .check(Some(2))
+            .expect_err("other version should not match");
+        assert_eq!(Some(&Stale { current: Some(2) }), e.downcast_ref::<Stale>());
+    }
+
+    #[test]
+    fn test_etag() {
+        assert_eq!(
+            Precondition::Versions(vec![7]),
+            Precondition::parse(&etag(7))
+        );
+    }
+}
@@ -2,11 +2,21 @@ use super::project::ProjectId;
 use crate::impl_bool_property;
 use crate::impl_string_property;
 use crate::impl_uuid_property;
+use anyhow::anyhow;
 use anyhow::Result;
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::NaiveTime;
+use chrono::TimeZone;
+use chrono::Utc;
+use chrono_tz::Tz;
+use croner::Cron;
+use getset::CopyGetters;
 use getset::Getters;
 use getset::Setters;
 use uuid::Uuid;
 use validator::Validate;
+use validator::ValidationError;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct WorkflowId {
@@ -38,6 +48,143 @@ pub struct WorkflowPaused {
 
 impl_bool_property!(WorkflowPaused);
 
+fn parse_cron(value: &str) -> Result<Cron> {
+    Cron::new(value)
+        .with_seconds_optional()
+        .parse()
+        .map_err(|e| anyhow!(r#"invalid cron expression "{}": {}"#, value, e))
+}
+
+fn validate_cron(value: &str) -> Result<(), ValidationError> {
+    match parse_cron(value) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(ValidationError::new("cron")),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Validate)]
+pub struct WorkflowSchedule {
+    #[validate(custom = "validate_cron")]
+    value: String,
+}
+
+impl_string_property!(WorkflowSchedule);
+
+impl WorkflowSchedule {
+    pub fn occurrences(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<DateTime<Utc>>> {
+        if from > to {
+            return Err(anyhow!("range must start before it ends"));
+        }
+        let cron = parse_cron(self.as_str())?;
+        let ticks: Vec<DateTime<Utc>> = cron
+            .iter_from(from)
+            .take_while(|tick| *tick <= to)
+            .take(limit + 1)
+            .collect();
+        if ticks.len() > limit {
+            return Err(anyhow!(
+                "range must not contain more than {} scheduled times",
+                limit
+            ));
+        }
+        Ok(ticks)
+    }
+}
+
+fn validate_by(value: &str) -> Result<(), ValidationError> {
+    match NaiveTime::parse_from_str(value, "%H:%M") {
+        Ok(_) => Ok(()),
+        Err(_) => Err(ValidationError::new("by")),
+    }
+}
+
+fn validate_timezone(value: &str) -> Result<(), ValidationError> {
+    match value.parse::<Tz>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(ValidationError::new("timezone")),
+    }
+}
+
+fn validate_sla(sla: &WorkflowSla) -> Result<(), ValidationError> {
+    if sla.within_secs.is_none() && sla.by.is_none() {
+        return Err(ValidationError::new("either within_secs or by is required"));
+    }
+    if sla.by.is_none() && sla.timezone.is_some() {
+        return Err(ValidationError::new("timezone requires by"));
+    }
+    Ok(())
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Getters,
+    CopyGetters,
+    Validate,
+    serde::Serialize,
+    serde::Deserialize,
+    utoipa::ToSchema,
+)]
+#[serde(deny_unknown_fields)]
+#[validate(schema(function = "validate_sla"))]
+pub struct WorkflowSla {
+    #[getset(get_copy = "pub")]
+    #[validate(range(min = 1))]
+    within_secs: Option<i64>,
+    #[getset(get = "pub")]
+    #[validate(custom = "validate_by")]
+    by: Option<String>,
+    #[getset(get = "pub")]
+    #[validate(custom = "validate_timezone")]
+    timezone: Option<String>,
+}
+
+impl WorkflowSla {
+    pub fn new(
+        within_secs: Option<i64>,
+        by: Option<String>,
+        timezone: Option<String>,
+    ) -> Result<Self> {
+        let object = Self {
+            within_secs,
+            by,
+            timezone,
+        };
+        object.validate()?;
+        Ok(object)
+    }
+
+    pub fn deadline_of(&self, triggered_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.within_secs
+            .map(|secs| triggered_at + Duration::seconds(secs))
+    }
+
+    // NOTE: Wall-clock times skipped by a DST transition fall due one hour later.
+    pub fn last_deadline(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let by = NaiveTime::parse_from_str(self.by.as_ref()?, "%H:%M").ok()?;
+        let tz: Tz = self.timezone.as_deref().unwrap_or("UTC").parse().ok()?;
+        let today = now.with_timezone(&tz).date_naive();
+        [today, today - Duration::days(1)]
+            .iter()
+            .filter_map(|date| {
+                let local = date.and_time(by);
+                tz.from_local_datetime(&local).earliest().or_else(|| {
+                    tz.from_local_datetime(&(local + Duration::hours(1)))
+                        .earliest()
+                })
+            })
+            .map(|deadline| deadline.with_timezone(&Utc))
+            .find(|deadline| *deadline <= now)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Getters, Setters, serde::Serialize)]
 pub struct Workflow {
     #[getset(get = "pub")]
@@ -50,6 +197,10 @@ pub struct Workflow {
     description: WorkflowDescription,
     #[getset(get = "pub", set = "pub")]
     paused: WorkflowPaused,
+    #[getset(get = "pub", set = "pub")]
+    schedule: Option<WorkflowSchedule>,
+    #[getset(get = "pub", set = "pub")]
+    sla: Option<WorkflowSla>,
 }
 
 impl Workflow {
@@ -66,6 +217,8 @@ impl Workflow {
             project_id: ProjectId::try_from(project_id)?,
             description: WorkflowDescription::new(description)?,
             paused: WorkflowPaused::new(paused),
+            schedule: None,
+            sla: None,
         })
     }
 }
@@ -111,4 +264,111 @@ mod tests {
         ));
         assert!(matches!(WorkflowDescription::new(""), Ok(_)));
     }
+
+    #[test]
+    fn test_valid_workflow_schedule() {
+        assert!(WorkflowSchedule::new("*/5 * * * *").is_ok());
+        assert!(WorkflowSchedule::new("0 0 12 * * MON").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_workflow_schedule() {
+        assert!(WorkflowSchedule::new("").is_err());
+        assert!(WorkflowSchedule::new(testutils::rand::string(10)).is_err());
+        assert!(WorkflowSchedule::new("a b c d e").is_err());
+        assert!(WorkflowSchedule::new("60 24 32 13 8").is_err());
+    }
+
+    #[test]
+    fn test_workflow_schedule_occurrences() {
+        let schedule = WorkflowSchedule::new("0 0 * * *").expect("schedule should be valid");
+        let from = Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2023, 3, 3, 12, 0, 0).unwrap();
+        assert_eq!(
+            schedule
+                .occurrences(from, to, 10)
+                .expect("occurrences should be listed"),
+            vec![
+                from,
+                Utc.with_ymd_and_hms(2023, 3, 2, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2023, 3, 3, 0, 0, 0).unwrap(),
+            ]
+        );
+        assert!(schedule.occurrences(from, to, 2).is_err());
+        assert!(schedule.occurrences(to, from, 10).is_err());
+        assert!(schedule
+            .occurrences(from + Duration::hours(1), from + Duration::hours(2), 10)
+            .expect("occurrences should be listed")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_valid_workflow_sla() {
+        assert!(WorkflowSla::new(Some(7200), None, None).is_ok());
+        assert!(WorkflowSla::new(None, Some(String::from("06:00")), None).is_ok());
+        assert!(WorkflowSla::new(
+            Some(7200),
+            Some(String::from("06:00")),
+            Some(String::from("Asia/Tokyo"))
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_invalid_workflow_sla() {
+        assert!(WorkflowSla::new(None, None, None).is_err());
+        assert!(WorkflowSla::new(Some(0), None, None).is_err());
+        assert!(WorkflowSla::new(None, Some(String::from("6am")), None).is_err());
+        assert!(WorkflowSla::new(None, Some(String::from("25:00")), None).is_err());
+        assert!(WorkflowSla::new(
+            None,
+            Some(String::from("06:00")),
+            Some(String::from("Mars/Olympus"))
+        )
+        .is_err());
+        assert!(WorkflowSla::new(Some(7200), None, Some(String::from("Asia/Tokyo"))).is_err());
+    }
+
+    #[test]
+    fn test_workflow_sla_deadline_of() {
+        let sla = WorkflowSla::new(Some(7200), None, None).expect("sla should be created");
+        let triggered_at = Utc.with_ymd_and_hms(2023, 3, 15, 0, 0, 0).unwrap();
+        assert_eq!(
+            sla.deadline_of(triggered_at),
+            Some(Utc.with_ymd_and_hms(2023, 3, 15, 2, 0, 0).unwrap())
+        );
+        let sla = WorkflowSla::new(None, Some(String::from("06:00")), None)
+            .expect("sla should be created");
+        assert_eq!(sla.deadline_of(triggered_at), None);
+    }
+
+    #[test]
+    fn test_workflow_sla_last_deadline() {
+        let sla = WorkflowSla::new(
+            None,
+            Some(String::from("06:00")),
+            Some(String::from("Asia/Tokyo")),
+        )
+        .expect("sla should be created");
+        assert_eq!(
+            sla.last_deadline(Utc.with_ymd_and_hms(2023, 3, 15, 22, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2023, 3, 15, 21, 0, 0).unwrap())
+        );
+        assert_eq!(
+            sla.last_deadline(Utc.with_ymd_and_hms(2023, 3, 15, 20, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2023, 3, 14, 21, 0, 0).unwrap())
+        );
+        let sla = WorkflowSla::new(
+            None,
+            Some(String::from("02:30")),
+            Some(String::from("America/New_York")),
+        )
+        .expect("sla should be created");
+        assert_eq!(
+            sla.last_deadline(Utc.with_ymd_and_hms(2023, 3, 12, 12, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2023, 3, 12, 7, 30, 0).unwrap())
+        );
+        let sla = WorkflowSla::new(Some(7200), None, None).expect("sla should be created");
+        assert_eq!(sla.last_deadline(Utc::now()), None);
+    }
 }
@@ -1,88 +1,312 @@
 pub mod api;
+pub mod health;
 pub mod internal;
-use crate::controller::services::config::ConfigService;
+pub mod metrics;
+pub mod openapi;
+pub mod stash;
+use crate::controller::entities::version::Precondition;
+use crate::controller::entities::version::Stale;
+use crate::controller::services::config::Violation;
+use crate::controller::services::opa::Denial;
+use crate::controller::services::run::Unrenderable;
 use crate::controller::Controller;
 use crate::infra::opa::Token;
+use crate::logging::propagation;
 use anyhow::Context;
 use anyhow::Result;
+use axum::async_trait;
+use axum::extract::DefaultBodyLimit;
 use axum::extract::Extension;
+use axum::extract::FromRequestParts;
+use axum::extract::MatchedPath;
+use axum::http::header::IF_MATCH;
+use axum::http::request::Parts;
+use axum::http::HeaderValue;
+use axum::http::Request;
 use axum::http::StatusCode;
 use axum::middleware::from_extractor;
+use axum::middleware::from_fn;
+use axum::middleware::Next;
 use axum::response::IntoResponse;
 use axum::response::Response;
-use axum::routing::delete;
 use axum::routing::get;
 use axum::routing::post;
 use axum::routing::put;
 use axum::Json;
 use axum::Router;
-use lapin::Channel;
-use serde_json::json;
+use serde_json::Value;
+use std::convert::Infallible;
+use std::future::Future;
 use std::sync::Arc;
 use tracing::debug;
+use tracing::error;
+use tracing::info_span;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+use validator::ValidationError;
+use validator::ValidationErrors;
 
 pub struct State {
-    mq_chan: Channel,
     controller: Arc<Controller>,
 }
 
 type SharedState = Arc<State>;
 
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+pub fn request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InternalError,
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    ValidationFailed,
+    Conflict,
+    PreconditionFailed,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, utoipa::ToSchema)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<Violation>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<Value>,
+}
+
+impl ErrorBody {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            request_id: request_id(),
+            violations: Vec::new(),
+            details: None,
+        }
+    }
+
+    pub fn with_violations(mut self, violations: Vec<Violation>) -> Self {
+        self.violations = violations;
+        self
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
 pub enum InteractorError {
     InternalServerProblem(anyhow::Error),
     BadRequest,
     Unauthorized,
-    ValidationFailed,
+    Forbidden,
+    NotFound,
+    ValidationFailed(Vec<Violation>),
     Conflict,
+    PreconditionFailed,
+}
+
+impl InteractorError {
+    // NOTE: Errors of property newtypes are reported under the given path instead of their inner "value" field.
+    pub fn invalid(path: &str, e: &anyhow::Error) -> Self {
+        let errors = match e.downcast_ref::<ValidationErrors>() {
+            Some(errors) => errors,
+            None => {
+                return InteractorError::ValidationFailed(vec![Violation {
+                    path: path.to_owned(),
+                    message: e.to_string(),
+                }])
+            }
+        };
+        let mut violations: Vec<Violation> = errors
+            .field_errors()
+            .into_iter()
+            .flat_map(|(field, errors)| {
+                let path = match field {
+                    "value" => path.to_owned(),
+                    field if path.is_empty() => field.to_owned(),
+                    field => format!("{}.{}", path, field),
+                };
+                errors.iter().map(move |error| Violation {
+                    path: path.clone(),
+                    message: describe(error),
+                })
+            })
+            .collect();
+        violations.sort_by(|a, b| a.path.cmp(&b.path));
+        InteractorError::ValidationFailed(violations)
+    }
+}
+
+fn describe(error: &ValidationError) -> String {
+    if let Some(message) = &error.message {
+        return message.to_string();
+    }
+    let mut params: Vec<String> = error
+        .params
+        .iter()
+        .filter(|(key, _)| key.as_ref() != "value")
+        .map(|(key, value)| format!("{}: {}", key, value))
+        .collect();
+    params.sort();
+    if params.is_empty() {
+        format!("failed {} validation", error.code)
+    } else {
+        format!("failed {} validation ({})", error.code, params.join(", "))
+    }
 }
 
 impl From<anyhow::Error> for InteractorError {
     fn from(e: anyhow::Error) -> Self {
-        InteractorError::InternalServerProblem(e)
+        if e.downcast_ref::<Stale>().is_some() {
+            return InteractorError::PreconditionFailed;
+        }
+        if let Some(e) = e.downcast_ref::<Unrenderable>() {
+            return InteractorError::ValidationFailed(vec![Violation {
+                path: e.path.clone(),
+                message: e.to_string(),
+            }]);
+        }
+        match e.downcast_ref::<Denial>() {
+            Some(Denial::Unauthenticated) => InteractorError::Unauthorized,
+            Some(Denial::Forbidden) => InteractorError::Forbidden,
+            None => InteractorError::InternalServerProblem(e),
+        }
     }
 }
 
 impl IntoResponse for InteractorError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
+        let (status, body) = match self {
             InteractorError::InternalServerProblem(e) => {
+                error!("internal server problem: {:#}", e);
                 debug!("stacktrace: {}", e.backtrace());
-                (StatusCode::INTERNAL_SERVER_ERROR, "Something went wrong")
-            }
-            InteractorError::BadRequest => (StatusCode::BAD_REQUEST, "Bad request"),
-            InteractorError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized"),
-            InteractorError::ValidationFailed => {
-                (StatusCode::UNPROCESSABLE_ENTITY, "Validation errors")
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ErrorBody::new(ErrorCode::InternalError, "Something went wrong"),
+                )
             }
-            InteractorError::Conflict => (StatusCode::CONFLICT, "Confliction occured"),
+            InteractorError::BadRequest => (
+                StatusCode::BAD_REQUEST,
+                ErrorBody::new(ErrorCode::BadRequest, "Bad request"),
+            ),
+            InteractorError::Unauthorized => (
+                StatusCode::UNAUTHORIZED,
+                ErrorBody::new(ErrorCode::Unauthorized, "Authentication is required"),
+            ),
+            InteractorError::Forbidden => (
+                StatusCode::FORBIDDEN,
+                ErrorBody::new(ErrorCode::Forbidden, "Permission denied"),
+            ),
+            InteractorError::NotFound => (
+                StatusCode::NOT_FOUND,
+                ErrorBody::new(ErrorCode::NotFound, "Resource not found"),
+            ),
+            InteractorError::ValidationFailed(violations) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorBody::new(ErrorCode::ValidationFailed, "Validation errors")
+                    .with_violations(violations),
+            ),
+            InteractorError::Conflict => (
+                StatusCode::CONFLICT,
+                ErrorBody::new(ErrorCode::Conflict, "Confliction occured"),
+            ),
+            InteractorError::PreconditionFailed => (
+                StatusCode::PRECONDITION_FAILED,
+                ErrorBody::new(
+                    ErrorCode::PreconditionFailed,
+                    "Resource has been modified since it was read",
+                ),
+            ),
         };
-        let body = Json(json!({
-            "error": message,
-        }));
-        (status, body).into_response()
+        (status, Json(body)).into_response()
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Precondition
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let values: Vec<&str> = parts
+            .headers
+            .get_all(IF_MATCH)
+            .iter()
+            .map(|value| value.to_str().unwrap_or_default())
+            .collect();
+        if values.is_empty() {
+            Ok(Precondition::None)
+        } else {
+            Ok(Precondition::parse(&values.join(",")))
+        }
     }
 }
 
+async fn trace<B>(request: Request<B>, next: Next<B>) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned())
+        .unwrap_or_else(|| String::from("unmatched"));
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map(ToOwned::to_owned)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let span = info_span!(
+        "request",
+        otel.name = format!("{} {}", method, route),
+        http.method = method,
+        http.route = route,
+        request_id = request_id.as_str(),
+    );
+    span.set_parent(propagation::extract_http(request.headers()));
+    let mut response = REQUEST_ID
+        .scope(request_id.clone(), next.run(request).instrument(span))
+        .await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
 async fn route(controller: Arc<Controller>) -> Result<Router> {
-    let mq_chan = controller
-        .mq_conn
-        .create_channel()
-        .await
-        .context("failed to create rabbitmq channel")?;
-    let state = Arc::new(State {
-        mq_chan,
-        controller,
-    });
-    ConfigService::setup(&state.mq_chan)
-        .await
-        .context("failed to setup config service")?;
+    let stash_limit = controller.config.stash_max_bytes;
+    let state = Arc::new(State { controller });
     let app = Router::new()
+        .route("/api/cluster", get(self::api::cluster::get))
         .route(
             "/api/project",
             get(self::api::project::get_by_name)
                 .post(self::api::project::create)
                 .put(self::api::project::create),
         )
+        .route("/api/project/apply", post(self::api::project::apply))
         .route(
             "/api/project/:id",
             get(self::api::project::get_summary_by_id).delete(self::api::project::delete),
@@ -91,12 +315,95 @@ async fn route(controller: Arc<Controller>) -> Result<Router> {
             "/api/project/:id/workflow",
             get(self::api::project::list_workflows_by_id),
         )
-        .layer(Extension(state))
-        .layer(from_extractor::<Token>());
+        .route(
+            "/api/project/:id/config",
+            get(self::api::project::get_config_by_id).patch(self::api::project::patch_config),
+        )
+        .route(
+            "/api/project/:id/config/schema",
+            get(self::api::project::get_config_schema_by_id)
+                .put(self::api::project::put_config_schema),
+        )
+        .route("/api/project/:id/secret", get(self::api::secret::list))
+        .route(
+            "/api/project/:id/secret/:name",
+            put(self::api::secret::put).delete(self::api::secret::delete),
+        )
+        .route(
+            "/api/project/:id/notification",
+            get(self::api::notification::list),
+        )
+        .route(
+            "/api/project/:id/notification/:name",
+            put(self::api::notification::put).delete(self::api::notification::delete),
+        )
+        .route(
+            "/api/project/:id/notification/:name/delivery",
+            get(self::api::notification::list_deliveries),
+        )
+        .route("/api/workflow/:id/pause", put(self::api::workflow::pause))
+        .route("/api/workflow/:id/resume", put(self::api::workflow::resume))
+        .route("/api/workflow/:id/job", get(self::api::workflow::list_jobs))
+        .route("/api/workflow/:id/run", post(self::api::workflow::run))
+        .route(
+            "/api/workflow/:id/backfill",
+            post(self::api::workflow::backfill),
+        )
+        .route(
+            "/api/workflow/:id/execution",
+            get(self::api::workflow::list_executions),
+        )
+        .route("/api/execution/:id", get(self::api::execution::get))
+        .route(
+            "/api/execution/:id/cancel",
+            post(self::api::execution::cancel),
+        )
+        .route(
+            "/api/execution/:id/rerun",
+            post(self::api::execution::rerun),
+        )
+        .route(
+            "/api/job/:id/config",
+            get(self::api::job::get_config).patch(self::api::job::patch_config),
+        )
+        .route("/api/job/:id/run", post(self::api::job::run))
+        .route("/api/run", get(self::api::run::list))
+        .route("/api/run/:id/logs", get(self::api::run::get_logs))
+        .route("/api/run/:id/cancel", post(self::api::run::cancel))
+        .route("/api/events", get(self::api::event::stream))
+        .route(
+            "/internal/api/run/:id/logs",
+            post(self::internal::api::run::append_logs),
+        )
+        .route(
+            "/internal/api/run/:id/state",
+            put(self::internal::api::run::update_state),
+        )
+        .route(
+            "/internal/api/run/:id/config",
+            get(self::internal::api::run::get_config),
+        )
+        .route("/stash/run/:id", get(self::stash::list))
+        .route(
+            "/stash/run/:id/:name",
+            get(self::stash::get)
+                .put(self::stash::put)
+                .layer(DefaultBodyLimit::max(stash_limit)),
+        )
+        .layer(from_extractor::<Token>())
+        .route("/api/openapi.json", get(self::openapi::get))
+        .route("/api/docs", get(self::openapi::docs))
+        .route("/metrics", get(self::metrics::get))
+        .route("/healthz", get(self::health::healthz))
+        .route("/readyz", get(self::health::readyz))
+        .route("/version", get(self::health::version))
+        .layer(from_fn(self::metrics::track))
+        .layer(from_fn(trace))
+        .layer(Extension(state));
     Ok(app)
 }
 
-pub async fn bind(controller: Arc<Controller>) -> Result<()> {
+pub async fn bind(controller: Arc<Controller>, signal: impl Future<Output = ()>) -> Result<()> {
     let app = route(controller.clone())
         .await
         .context("failed to create axum router")?;
@@ -112,6 +419,7 @@ pub async fn bind(controller: Arc<Controller>) -> Result<()> {
     debug!("kotosiro controller listening on {}", addr);
     axum::Server::bind(&addr)
         .serve(app.into_make_service())
+        .with_graceful_shutdown(signal)
         .await
         .context(format!(
             r#"failed to bind "{}" to hyper::Server"#,
@@ -119,3 +427,190 @@ pub async fn bind(controller: Arc<Controller>) -> Result<()> {
         ))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use serde_json::json;
+    use tower::ServiceExt;
+    use validator::Validate;
+
+    #[derive(Validate)]
+    struct Limit {
+        #[validate(range(min = 1, max = 10))]
+        value: i64,
+    }
+
+    #[derive(Validate)]
+    struct Spec {
+        #[validate(length(min = 1))]
+        name: String,
+        #[validate(range(min = 1))]
+        days: i32,
+    }
+
+    fn violations(e: InteractorError) -> Vec<Violation> {
+        match e {
+            InteractorError::ValidationFailed(violations) => violations,
+            _ => panic!("validation failure should be returned"),
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let e = Limit { value: 0 }
+            .validate()
+            .map_err(anyhow::Error::from)
+            .expect_err("validation should fail");
+        assert_eq!(
+            violations(InteractorError::invalid("limit", &e)),
+            vec![Violation {
+                path: String::from("limit"),
+                message: String::from("failed range validation (max: 10.0, min: 1.0)"),
+            }]
+        );
+        let e = Spec {
+            name: String::new(),
+            days: 0,
+        }
+        .validate()
+        .map_err(anyhow::Error::from)
+        .expect_err("validation should fail");
+        let paths: Vec<String> = violations(InteractorError::invalid("retention", &e))
+            .into_iter()
+            .map(|violation| violation.path)
+            .collect();
+        assert_eq!(paths, vec!["retention.days", "retention.name"]);
+        let e = anyhow::anyhow!("page token does not match the query");
+        assert_eq!(
+            violations(InteractorError::invalid("", &e)),
+            vec![Violation {
+                path: String::new(),
+                message: String::from("page token does not match the query"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_denial() {
+        let e = anyhow::Error::new(Denial::Forbidden).context("failed to authorize event");
+        assert!(matches!(
+            InteractorError::from(e),
+            InteractorError::Forbidden
+        ));
+        let e = anyhow::Error::new(Denial::Unauthenticated);
+        assert!(matches!(
+            InteractorError::from(e),
+            InteractorError::Unauthorized
+        ));
+        let e = anyhow::anyhow!("connection refused");
+        assert!(matches!(
+            InteractorError::from(e),
+            InteractorError::InternalServerProblem(_)
+        ));
+        let e = anyhow::Error::new(Stale { current: Some(2) }).context("failed to update project");
+        assert!(matches!(
+            InteractorError::from(e),
+            InteractorError::PreconditionFailed
+        ));
+    }
+
+    #[test]
+    fn test_unrenderable() {
+        let e = anyhow::Error::new(Unrenderable {
+            path: String::from("args[1]"),
+            job: String::from("load"),
+            message: String::from("undefined value"),
+        })
+        .context("failed to trigger job");
+        assert_eq!(
+            violations(InteractorError::from(e)),
+            vec![Violation {
+                path: String::from("args[1]"),
+                message: String::from(r#"job "load": undefined value"#),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_error_body() {
+        let body = ErrorBody::new(ErrorCode::Conflict, "Project has active runs")
+            .with_details(json!({ "active_runs": [] }));
+        assert_eq!(
+            serde_json::to_value(body).expect("error body should be serialized"),
+            json!({
+                "code": "conflict",
+                "message": "Project has active runs",
+                "active_runs": [],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_id() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async { Err::<Response, _>(InteractorError::NotFound) }),
+            )
+            .layer(from_fn(trace));
+        let request = Request::builder()
+            .uri("/")
+            .header(REQUEST_ID_HEADER, "abc")
+            .body(Body::empty())
+            .expect("request should be built");
+        let response = app
+            .oneshot(request)
+            .await
+            .expect("response should be returned");
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "abc");
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .expect("body should be read");
+        let body: Value = serde_json::from_slice(&body).expect("body should be JSON");
+        assert_eq!(
+            body,
+            json!({
+                "code": "not_found",
+                "message": "Resource not found",
+                "request_id": "abc",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_precondition() {
+        let app = Router::new().route(
+            "/",
+            put(|precondition: Precondition| async move {
+                match precondition.check(Some(3)) {
+                    Ok(_) => Ok(StatusCode::NO_CONTENT),
+                    Err(e) => Err(InteractorError::from(e)),
+                }
+            }),
+        );
+        for (value, status) in [
+            (None, StatusCode::NO_CONTENT),
+            (Some("*"), StatusCode::NO_CONTENT),
+            (Some(r#""2", "3""#), StatusCode::NO_CONTENT),
+            (Some(r#""2""#), StatusCode::PRECONDITION_FAILED),
+            (Some(r#"W/"3""#), StatusCode::PRECONDITION_FAILED),
+        ] {
+            let mut request = Request::builder().method("PUT").uri("/");
+            if let Some(value) = value {
+                request = request.header(IF_MATCH, value);
+            }
+            let request = request
+                .body(Body::empty())
+                .expect("request should be built");
+            let response = app
+                .clone()
+                .oneshot(request)
+                .await
+                .expect("response should be returned");
+            assert_eq!(response.status(), status, "If-Match: {:?}", value);
+        }
+    }
+}
@@ -1 +1,47 @@
+pub mod cluster;
+pub mod event;
+pub mod execution;
+pub mod job;
+pub mod notification;
 pub mod project;
+pub mod run;
+pub mod secret;
+pub mod workflow;
+use crate::controller::entities::page::PageOrder;
+use crate::controller::entities::page::PageSort;
+use crate::controller::entities::page::Pagination;
+use crate::controller::interactors::InteractorError;
+use tracing::error;
+
+pub const NAMED_SORTS: [PageSort; 3] = [PageSort::Name, PageSort::CreatedAt, PageSort::UpdatedAt];
+
+pub const TIMED_SORTS: [PageSort; 2] = [PageSort::CreatedAt, PageSort::UpdatedAt];
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageQuery {
+    limit: Option<i64>,
+    page_token: Option<String>,
+    sort: Option<PageSort>,
+    order: Option<PageOrder>,
+    prefix: Option<String>,
+}
+
+impl PageQuery {
+    pub fn paginate(&self, sorts: &[PageSort]) -> Result<Pagination, InteractorError> {
+        match Pagination::new(
+            self.limit,
+            self.sort,
+            self.order,
+            self.prefix.clone(),
+            self.page_token.as_deref(),
+            sorts,
+        ) {
+            Ok(pagination) => Ok(pagination),
+            Err(e) => {
+                error!("invalid pagination found: {}", e);
+                Err(InteractorError::invalid("", &e))
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::controller::interactors::InteractorError;
+use crate::controller::interactors::SharedState;
+use crate::controller::services::cluster::ClusterService;
+use crate::controller::services::opa::Event;
+use crate::controller::services::opa::OPAService;
+use crate::infra::opa::Token;
+use axum::extract::Extension;
+use axum::extract::Json;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use tracing::warn;
+
+#[utoipa::path(
+    get,
+    path = "/api/cluster",
+    operation_id = "get_cluster",
+    tag = "cluster",
+    responses(
+        (status = 200, description = "Controllers of the cluster and the current leader", body = ClusterView),
+    ),
+)]
+pub async fn get(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+) -> Result<Response, InteractorError> {
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::get().of_kind("cluster").with_token(token),
+    )
+    .await
+    {
+        warn!("failed to get cluster: {}", e);
+        return Err(e.into());
+    }
+    let view = ClusterService::view(&state.controller.db_pool, &state.controller.id).await?;
+    Ok((StatusCode::OK, Json(view)).into_response())
+}
@@ -0,0 +1,147 @@
+use crate::controller::entities::project::ProjectId;
+use crate::controller::entities::workflow::WorkflowId;
+use crate::controller::interactors::InteractorError;
+use crate::controller::interactors::SharedState;
+use crate::controller::services::opa::Event;
+use crate::controller::services::opa::OPAService;
+use crate::controller::services::project::ProjectService;
+use crate::controller::services::update;
+use crate::controller::services::update::Filter;
+use crate::controller::services::update::Update;
+use crate::controller::services::workflow::WorkflowService;
+use crate::infra::opa::Token;
+use axum::extract::ws::Message;
+use axum::extract::ws::WebSocket;
+use axum::extract::ws::WebSocketUpgrade;
+use axum::extract::Extension;
+use axum::extract::Query;
+use axum::response::sse::Event as SseEvent;
+use axum::response::sse::KeepAlive;
+use axum::response::sse::Sse;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use futures::Stream;
+use futures::StreamExt;
+use std::time::Duration;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EventsQuery {
+    project: String,
+    workflow: Option<String>,
+}
+
+async fn relay(mut socket: WebSocket, updates: impl Stream<Item = Update>) {
+    let mut updates = Box::pin(updates);
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    loop {
+        let message = tokio::select! {
+            update = updates.next() => match update {
+                Some(update) => match serde_json::to_string(&update) {
+                    Ok(text) => Message::Text(text),
+                    Err(e) => {
+                        error!("failed to serialize update: {}", e);
+                        continue;
+                    }
+                },
+                None => Message::Close(None),
+            },
+            received = socket.recv() => match received {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            _ = ping.tick() => Message::Ping(Vec::new()),
+        };
+        let closing = matches!(message, Message::Close(_));
+        if socket.send(message).await.is_err() || closing {
+            break;
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/events",
+    operation_id = "stream_events",
+    tag = "event",
+    params(EventsQuery),
+    responses(
+        (status = 101, description = "WebSocket of updates as JSON text messages when upgrading"),
+        (status = 200, description = "Server-sent updates named after their type", content(
+            ("text/event-stream" = Update),
+        )),
+        (status = 404, description = "Project or workflow was not found", body = ErrorBody),
+    ),
+)]
+pub async fn stream(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    query: Query<EventsQuery>,
+    upgrade: Option<WebSocketUpgrade>,
+) -> Result<Response, InteractorError> {
+    let project_id = if let Ok(id) = ProjectId::try_from(query.project.as_str()) {
+        id
+    } else {
+        error!("project id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    let workflow_id = match query.workflow.as_deref().map(WorkflowId::try_from) {
+        None => None,
+        Some(Ok(id)) => Some(id),
+        Some(Err(_)) => {
+            error!("workflow id must be uuid v4");
+            return Err(InteractorError::BadRequest);
+        }
+    };
+    if ProjectService::get_by_id(&state.controller.db_pool, &project_id)
+        .await?
+        .is_none()
+    {
+        return Err(InteractorError::NotFound);
+    }
+    if let Some(id) = workflow_id.as_ref() {
+        match WorkflowService::get_by_id(&state.controller.db_pool, id).await? {
+            Some(workflow) if &workflow.project_id == project_id.as_uuid() => {}
+            _ => return Err(InteractorError::NotFound),
+        }
+    }
+    let event = match workflow_id.as_ref() {
+        Some(id) => Event::list().on_workflow(id.to_uuid(), project_id.to_uuid()),
+        None => Event::list().on_project(project_id.to_uuid()),
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        event.of_kind("event").with_token(token),
+    )
+    .await
+    {
+        warn!("failed to subscribe events: {}", e);
+        return Err(e.into());
+    }
+    let updates = update::subscribe(
+        &state.controller.updates,
+        Filter {
+            project_id: project_id.to_uuid(),
+            workflow_id: workflow_id.map(|id| id.to_uuid()),
+        },
+        state.controller.draining.subscribe(),
+    );
+    info!(
+        r#"subscribed events of project id: "{}""#,
+        project_id.as_uuid()
+    );
+    if let Some(upgrade) = upgrade {
+        return Ok(upgrade.on_upgrade(move |socket| relay(socket, updates)));
+    }
+    let events = updates.map(|update| SseEvent::default().event(update.kind()).json_data(update));
+    Ok(Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
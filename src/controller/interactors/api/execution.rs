@@ -0,0 +1,165 @@
+use crate::controller::entities::execution::ExecutionId;
+use crate::controller::interactors::InteractorError;
+use crate::controller::interactors::SharedState;
+use crate::controller::services::execution::ExecutionService;
+use crate::controller::services::execution::ExecutionSummary;
+use crate::controller::services::execution::Rerun;
+use crate::controller::services::opa::Event;
+use crate::controller::services::opa::OPAService;
+use crate::infra::opa::Token;
+use axum::extract::Extension;
+use axum::extract::Json;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RerunQuery {
+    #[serde(default)]
+    failed_only: bool,
+}
+
+async fn authorize(
+    token: Token,
+    state: &SharedState,
+    id: String,
+    event: Event,
+) -> Result<Option<(ExecutionId, ExecutionSummary)>, InteractorError> {
+    let id = if let Ok(id) = ExecutionId::try_from(id) {
+        id
+    } else {
+        error!("execution id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    let summary = match ExecutionService::get_by_id(&state.controller.db_pool, &id).await? {
+        Some(summary) => summary,
+        None => return Ok(None),
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        event
+            .on_workflow(summary.execution.workflow_id, None)
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to access execution: {}", e);
+        return Err(e.into());
+    }
+    Ok(Some((id, summary)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/execution/{id}",
+    operation_id = "get_execution",
+    tag = "execution",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    responses(
+        (status = 200, description = "Execution with its run graph", body = ExecutionGraph),
+        (status = 404, description = "Execution was not found", body = ErrorBody),
+    ),
+)]
+pub async fn get(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Response, InteractorError> {
+    let id = match authorize(token, &state, id, Event::get()).await? {
+        Some((id, _)) => id,
+        None => return Err(InteractorError::NotFound),
+    };
+    match ExecutionService::get_graph_by_id(&state.controller.db_pool, &id).await? {
+        Some(graph) => Ok((StatusCode::OK, Json(graph)).into_response()),
+        None => Err(InteractorError::NotFound),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/execution/{id}/cancel",
+    operation_id = "cancel_execution",
+    tag = "execution",
+    params(("id" = Uuid, Path, description = "Execution id")),
+    responses(
+        (status = 204, description = "Unfinished runs of the execution were cancelled"),
+        (status = 404, description = "Execution was not found", body = ErrorBody),
+        (status = 409, description = "Execution has already finished", body = ErrorBody),
+    ),
+)]
+pub async fn cancel(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Response, InteractorError> {
+    let (id, summary) = match authorize(token, &state, id, Event::update()).await? {
+        Some(found) => found,
+        None => return Err(InteractorError::NotFound),
+    };
+    if summary.state.is_done() {
+        warn!(
+            r#"execution id: "{}" has already finished as "{}""#,
+            id.as_uuid(),
+            summary.state.as_ref()
+        );
+        return Err(InteractorError::Conflict);
+    }
+    let done = ExecutionService::cancel(&state.controller.db_pool, &id).await?;
+    info!(
+        r#"cancelled {} run(s) of execution id: "{}""#,
+        done.rows_affected(),
+        id.as_uuid()
+    );
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/execution/{id}/rerun",
+    operation_id = "rerun_execution",
+    tag = "execution",
+    params(("id" = Uuid, Path, description = "Execution id"), RerunQuery),
+    responses(
+        (status = 201, description = "Runs were triggered again", body = [Run]),
+        (status = 404, description = "Execution was not found", body = ErrorBody),
+        (status = 409, description = "Execution has not finished yet", body = ErrorBody),
+        (status = 422, description = "Templates cannot be rendered", body = ErrorBody),
+    ),
+)]
+pub async fn rerun(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+    query: Query<RerunQuery>,
+) -> Result<Response, InteractorError> {
+    let (id, _) = match authorize(token, &state, id, Event::update()).await? {
+        Some(found) => found,
+        None => return Err(InteractorError::NotFound),
+    };
+    let runs =
+        match ExecutionService::rerun(&state.controller.db_pool, &id, query.failed_only).await? {
+            Rerun::Done(runs) => runs,
+            Rerun::Blocked(nodes) => {
+                warn!(
+                    r#"execution id: "{}" still has {} unfinished job(s)"#,
+                    id.as_uuid(),
+                    nodes.len()
+                );
+                return Err(InteractorError::Conflict);
+            }
+        };
+    info!(
+        r#"rerun {} job(s) of execution id: "{}""#,
+        runs.len(),
+        id.as_uuid()
+    );
+    Ok((StatusCode::CREATED, Json(runs)).into_response())
+}
@@ -0,0 +1,215 @@
+use crate::controller::entities::execution::Execution;
+use crate::controller::entities::job::JobId;
+use crate::controller::entities::version::etag;
+use crate::controller::entities::version::Precondition;
+use crate::controller::interactors::InteractorError;
+use crate::controller::interactors::SharedState;
+use crate::controller::services::config::ConfigService;
+use crate::controller::services::config::Validated;
+use crate::controller::services::execution::ExecutionService;
+use crate::controller::services::job::JobService;
+use crate::controller::services::opa::Event;
+use crate::controller::services::opa::OPAService;
+use crate::infra::opa::Token;
+use crate::messages::run::RunPriority;
+use anyhow::anyhow;
+use axum::extract::Extension;
+use axum::extract::Json;
+use axum::extract::Path;
+use axum::http::header::ETAG;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use chrono::Utc;
+use serde_json::Value;
+use std::str::FromStr;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+#[derive(serde::Deserialize, Default, utoipa::ToSchema)]
+pub struct RunJson {
+    priority: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/job/{id}/run",
+    operation_id = "run_job",
+    tag = "job",
+    params(("id" = Uuid, Path, description = "Job id")),
+    request_body(content = Option<RunJson>, description = "Priority of the triggered run"),
+    responses(
+        (status = 201, description = "Run was triggered", body = Run),
+        (status = 404, description = "Job was not found", body = ErrorBody),
+        (status = 422, description = "Priority is invalid or templates cannot be rendered", body = ErrorBody),
+    ),
+)]
+pub async fn run(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+    payload: Option<Json<RunJson>>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = JobId::try_from(id) {
+        id
+    } else {
+        error!("job id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    let Json(payload) = payload.unwrap_or_default();
+    let priority = match payload.priority.as_deref().map(RunPriority::from_str) {
+        None => RunPriority::default(),
+        Some(Ok(priority)) => priority,
+        Some(Err(e)) => {
+            error!("invalid run priority found: {}", e);
+            return Err(InteractorError::invalid("priority", &e.into()));
+        }
+    };
+    let job = match JobService::get_by_id(&state.controller.db_pool, &id).await? {
+        Some(job) => job,
+        None => return Err(InteractorError::NotFound),
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::update()
+            .on_workflow(job.workflow_id, None)
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to run job: {}", e);
+        return Err(e.into());
+    }
+    let execution = Execution::new(
+        uuid::Uuid::new_v4().to_string(),
+        job.workflow_id.to_string(),
+        priority,
+        Utc::now(),
+    )?;
+    let run = match ExecutionService::trigger(
+        &state.controller.db_pool,
+        &execution,
+        std::slice::from_ref(&id),
+    )
+    .await?
+    .pop()
+    {
+        Some(run) => run,
+        None => return Err(anyhow!("execution was triggered without any run").into()),
+    };
+    info!(
+        r#"triggered run id: "{}" of job id: "{}""#,
+        run.id().as_uuid(),
+        id.as_uuid()
+    );
+    Ok((StatusCode::CREATED, Json(run)).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/job/{id}/config",
+    operation_id = "get_job_config",
+    tag = "job",
+    params(("id" = Uuid, Path, description = "Job id")),
+    responses(
+        (status = 200, description = "Job config merged over the project config", body = JobConfig, headers(("ETag" = String))),
+        (status = 404, description = "Job was not found", body = ErrorBody),
+    ),
+)]
+pub async fn get_config(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = JobId::try_from(id) {
+        id
+    } else {
+        error!("job id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    let config = match ConfigService::get_job_config(&state.controller.db_pool, &id).await? {
+        Some(config) => config,
+        None => return Err(InteractorError::NotFound),
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::get().on_project(config.project_id).with_token(token),
+    )
+    .await
+    {
+        warn!("failed to get job config: {}", e);
+        return Err(e.into());
+    }
+    Ok((StatusCode::OK, [(ETAG, etag(config.version))], Json(config)).into_response())
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/job/{id}/config",
+    operation_id = "patch_job_config",
+    tag = "job",
+    params(
+        ("id" = Uuid, Path, description = "Job id"),
+        ("If-Match" = Option<String>, Header, description = "ETag the job is expected to have"),
+    ),
+    request_body(content = Object, description = "JSON merge patch applied to the job config"),
+    responses(
+        (status = 200, description = "Patched job config", body = JobConfig, headers(("ETag" = String))),
+        (status = 404, description = "Job was not found", body = ErrorBody),
+        (status = 412, description = "Job does not have the expected ETag", body = ErrorBody),
+        (status = 422, description = "Patched config does not conform to the schema", body = ErrorBody),
+    ),
+)]
+pub async fn patch_config(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+    precondition: Precondition,
+    Json(patch): Json<Value>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = JobId::try_from(id) {
+        id
+    } else {
+        error!("job id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    let job = match JobService::get_by_id(&state.controller.db_pool, &id).await? {
+        Some(job) => job,
+        None => return Err(InteractorError::NotFound),
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::update()
+            .on_workflow(job.workflow_id, None)
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to update job config: {}", e);
+        return Err(e.into());
+    }
+    match ConfigService::patch_job_config(&state.controller.db_pool, &id, &patch, &precondition)
+        .await?
+    {
+        None => Err(InteractorError::NotFound),
+        Some(Validated::Rejected(violations)) => {
+            warn!(
+                r#"rejected config of job id: "{}" with {} violation(s)"#,
+                id.as_uuid(),
+                violations.len()
+            );
+            Err(InteractorError::ValidationFailed(violations))
+        }
+        Some(Validated::Accepted(config)) => {
+            info!(r#"updated config of job id: "{}""#, id.as_uuid());
+            Ok((StatusCode::OK, [(ETAG, etag(config.version))], Json(config)).into_response())
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use crate::controller::entities::notification::NotificationRule;
+use crate::controller::entities::notification::NotificationRuleId;
+use crate::controller::entities::notification::NotificationRuleName;
+use crate::controller::entities::project::ProjectId;
+use crate::controller::interactors::InteractorError;
+use crate::controller::interactors::SharedState;
+use crate::controller::services::notification::NotificationService;
+use crate::controller::services::opa::Event;
+use crate::controller::services::opa::OPAService;
+use crate::controller::services::project::ProjectService;
+use crate::infra::opa::Token;
+use anyhow::anyhow;
+use axum::extract::Extension;
+use axum::extract::Json;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+const MAX_DELIVERIES: i64 = 1000;
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct NotificationJson {
+    events: Vec<String>,
+    workflow: Option<String>,
+    url: String,
+    secret: String,
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeliveryQuery {
+    limit: Option<i64>,
+}
+
+fn parse(id: String, name: String) -> Result<(ProjectId, NotificationRuleName), InteractorError> {
+    let id = if let Ok(id) = ProjectId::try_from(id) {
+        id
+    } else {
+        error!("project id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    match NotificationRuleName::new(name) {
+        Ok(name) => Ok((id, name)),
+        Err(e) => {
+            error!("invalid notification rule name found: {}", e);
+            Err(InteractorError::invalid("name", &e))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/project/{id}/notification",
+    operation_id = "list_project_notifications",
+    tag = "notification",
+    params(("id" = Uuid, Path, description = "Project id")),
+    responses(
+        (status = 200, description = "Notification rules of the project without their secrets", body = [NotificationRuleRow]),
+    ),
+)]
+pub async fn list(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = ProjectId::try_from(id) {
+        id
+    } else {
+        error!("project id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::list().on_project(id.to_uuid()).with_token(token),
+    )
+    .await
+    {
+        warn!("failed to list project notifications: {}", e);
+        return Err(e.into());
+    }
+    let rows = NotificationService::list_by_project_id(&state.controller.db_pool, &id).await?;
+    Ok((StatusCode::OK, Json(rows)).into_response())
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/project/{id}/notification/{name}",
+    operation_id = "put_project_notification",
+    tag = "notification",
+    params(("id" = Uuid, Path, description = "Project id"), ("name" = String, Path, description = "Notification rule name")),
+    request_body = NotificationJson,
+    responses(
+        (status = 204, description = "Notification rule was stored"),
+        (status = 404, description = "Project was not found", body = ErrorBody),
+        (status = 422, description = "Notification rule is invalid", body = ErrorBody),
+    ),
+)]
+pub async fn put(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path((id, name)): Path<(String, String)>,
+    Json(payload): Json<NotificationJson>,
+) -> Result<Response, InteractorError> {
+    let rule = match NotificationRule::new(
+        uuid::Uuid::new_v4().to_string(),
+        id,
+        name,
+        payload.events,
+        payload.workflow,
+        payload.url,
+        payload.secret,
+    ) {
+        Ok(rule) => rule,
+        Err(e) => {
+            error!("invalid notification rule specification found: {}", e);
+            return Err(InteractorError::invalid("", &e));
+        }
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::update()
+            .on_project(rule.project_id().to_uuid())
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to update project notification: {}", e);
+        return Err(e.into());
+    }
+    let cipher = match state.controller.cipher.as_ref() {
+        Some(cipher) => cipher,
+        None => {
+            return Err(InteractorError::InternalServerProblem(anyhow!(
+                "secret key is not configured"
+            )))
+        }
+    };
+    if ProjectService::get_by_id(&state.controller.db_pool, rule.project_id())
+        .await?
+        .is_none()
+    {
+        return Err(InteractorError::NotFound);
+    }
+    NotificationService::put(&state.controller.db_pool, &rule, cipher).await?;
+    info!(
+        r#"updated notification name: "{}" of project id: "{}""#,
+        rule.name().as_str(),
+        rule.project_id().as_uuid()
+    );
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/project/{id}/notification/{name}",
+    operation_id = "delete_project_notification",
+    tag = "notification",
+    params(("id" = Uuid, Path, description = "Project id"), ("name" = String, Path, description = "Notification rule name")),
+    responses(
+        (status = 204, description = "Notification rule and its delivery log were deleted"),
+        (status = 404, description = "Notification rule was not found", body = ErrorBody),
+        (status = 422, description = "Notification rule name is invalid", body = ErrorBody),
+    ),
+)]
+pub async fn delete(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path((id, name)): Path<(String, String)>,
+) -> Result<Response, InteractorError> {
+    let (id, name) = parse(id, name)?;
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::delete().on_project(id.to_uuid()).with_token(token),
+    )
+    .await
+    {
+        warn!("failed to delete project notification: {}", e);
+        return Err(e.into());
+    }
+    let done = NotificationService::delete(&state.controller.db_pool, &id, &name).await?;
+    if done.rows_affected() == 1 {
+        info!(
+            r#"deleted notification name: "{}" of project id: "{}""#,
+            name.as_str(),
+            id.as_uuid()
+        );
+        Ok(StatusCode::NO_CONTENT.into_response())
+    } else {
+        Err(InteractorError::NotFound)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/project/{id}/notification/{name}/delivery",
+    operation_id = "list_project_notification_deliveries",
+    tag = "notification",
+    params(("id" = Uuid, Path, description = "Project id"), ("name" = String, Path, description = "Notification rule name"), DeliveryQuery),
+    responses(
+        (status = 200, description = "Most recent deliveries of the notification rule", body = [NotificationDeliveryRow]),
+        (status = 404, description = "Notification rule was not found", body = ErrorBody),
+        (status = 422, description = "Notification rule name or limit is invalid", body = ErrorBody),
+    ),
+)]
+pub async fn list_deliveries(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path((id, name)): Path<(String, String)>,
+    Query(query): Query<DeliveryQuery>,
+) -> Result<Response, InteractorError> {
+    let (id, name) = parse(id, name)?;
+    if let Some(limit) = query.limit {
+        if !(1..=MAX_DELIVERIES).contains(&limit) {
+            error!("delivery limit must be between 1 and {}", MAX_DELIVERIES);
+            return Err(InteractorError::invalid(
+                "limit",
+                &anyhow!("limit must be between 1 and {}", MAX_DELIVERIES),
+            ));
+        }
+    }
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::list().on_project(id.to_uuid()).with_token(token),
+    )
+    .await
+    {
+        warn!("failed to list project notification deliveries: {}", e);
+        return Err(e.into());
+    }
+    let rule = match NotificationService::get_by_name(&state.controller.db_pool, &id, &name).await?
+    {
+        Some(rule) => rule,
+        None => return Err(InteractorError::NotFound),
+    };
+    let rows = NotificationService::list_deliveries(
+        &state.controller.db_pool,
+        &NotificationRuleId::new(rule.id),
+        Some(&query.limit.unwrap_or(100)),
+    )
+    .await?;
+    Ok((StatusCode::OK, Json(rows)).into_response())
+}
@@ -1,64 +1,113 @@
 use crate::controller::entities::project::Project;
+use crate::controller::entities::project::ProjectConfigSchema;
 use crate::controller::entities::project::ProjectId;
 use crate::controller::entities::project::ProjectName;
-use crate::controller::entities::workflow::WorkflowName;
+use crate::controller::entities::project::ProjectRetention;
+use crate::controller::entities::version::etag;
+use crate::controller::entities::version::Precondition;
+use crate::controller::interactors::api::PageQuery;
+use crate::controller::interactors::api::NAMED_SORTS;
+use crate::controller::interactors::ErrorBody;
+use crate::controller::interactors::ErrorCode;
 use crate::controller::interactors::InteractorError;
 use crate::controller::interactors::SharedState;
+use crate::controller::services::config;
 use crate::controller::services::config::ConfigService;
+use crate::controller::services::config::Validated;
+use crate::controller::services::config::Violation;
 use crate::controller::services::opa::Event;
 use crate::controller::services::opa::OPAService;
+use crate::controller::services::project::Deletion;
 use crate::controller::services::project::ProjectService;
+use crate::controller::services::spec::ProjectSpec;
+use crate::controller::services::spec::SpecService;
 use crate::infra::opa::Token;
 use crate::infra::postgres::has_conflict;
 use crate::infra::postgres::pg_error;
-use crate::messages::config::ConfigUpdate;
 use anyhow::anyhow;
 use axum::extract::Extension;
 use axum::extract::Json;
 use axum::extract::Path;
 use axum::extract::Query;
+use axum::http::header::ETAG;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum::response::Response;
+use serde_json::json;
 use serde_json::Value;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct GetByNameQuery {
     name: Option<String>,
 }
 
-#[derive(serde::Deserialize)]
-pub struct ListWorkflowsByIdQuery {
-    name: Option<String>,
-    after: Option<String>,
-    limit: Option<i64>,
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ApplyQuery {
+    dry_run: Option<bool>,
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteQuery {
+    cascade: Option<bool>,
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, utoipa::ToSchema)]
 pub struct CreateJson {
     id: Option<String>,
     name: String,
     description: String,
     config: Option<Value>,
+    config_schema: Option<Value>,
+    retention: Option<ProjectRetention>,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/project",
+    operation_id = "create_project",
+    tag = "project",
+    params(("If-Match" = Option<String>, Header, description = "ETag the project is expected to have")),
+    request_body = CreateJson,
+    responses(
+        (status = 201, description = "Project was created or updated", body = Project, headers(("ETag" = String))),
+        (status = 409, description = "Project name is already taken", body = ErrorBody),
+        (status = 412, description = "Project does not have the expected ETag", body = ErrorBody),
+        (status = 422, description = "Project or its config is invalid", body = ErrorBody),
+    ),
+)]
 pub async fn create(
     token: Token,
     Extension(state): Extension<SharedState>,
+    precondition: Precondition,
     Json(payload): Json<CreateJson>,
 ) -> Result<Response, InteractorError> {
     let id = payload.id.unwrap_or(uuid::Uuid::new_v4().to_string());
-    let project =
-        if let Ok(project) = Project::new(id, payload.name, payload.description, payload.config) {
-            project
-        } else {
-            error!("invalid project specification found");
-            return Err(InteractorError::ValidationFailed);
-        };
-    if let Err(_) = OPAService::authorize(
+    let mut project = match Project::new(id, payload.name, payload.description, payload.config) {
+        Ok(project) => project,
+        Err(e) => {
+            error!("invalid project specification found: {}", e);
+            return Err(InteractorError::invalid("", &e));
+        }
+    };
+    project.set_config_schema(payload.config_schema.map(ProjectConfigSchema::new));
+    match payload
+        .retention
+        .map(|retention| ProjectRetention::new(retention.days(), retention.runs()))
+        .transpose()
+    {
+        Ok(retention) => project.set_retention(retention),
+        Err(e) => {
+            error!("invalid project retention found: {}", e);
+            return Err(InteractorError::invalid("retention", &e));
+        }
+    };
+    if let Err(e) = OPAService::authorize(
         &state.controller.db_pool,
         &state.controller.config.no_auth,
         state.controller.config.opa_addr.as_ref(),
@@ -68,25 +117,42 @@ pub async fn create(
     )
     .await
     {
-        warn!("failed to update project");
-        return Err(InteractorError::Unauthorized);
+        warn!("failed to update project: {}", e);
+        return Err(e.into());
+    }
+    let current = ProjectService::get_by_id(&state.controller.db_pool, project.id()).await?;
+    let schema = project
+        .config_schema()
+        .as_ref()
+        .map(ProjectConfigSchema::to_json)
+        .or_else(|| current.as_ref().and_then(|row| row.config_schema.clone()));
+    let config = project
+        .config()
+        .as_ref()
+        .map(|config| config.to_json())
+        .or_else(|| current.and_then(|row| row.config))
+        .unwrap_or_else(|| json!({}));
+    let violations = config::validate(schema.as_ref(), &config);
+    if !violations.is_empty() {
+        error!("project config does not conform to its schema");
+        return Err(InteractorError::ValidationFailed(violations));
     }
-    match pg_error(ProjectService::create(&state.controller.db_pool, &project).await)? {
-        Ok(_) => {
+    match pg_error(
+        ProjectService::create(&state.controller.db_pool, &project, &precondition).await,
+    )? {
+        Ok(row) => {
             info!(
-                r#"updated project id: "{}" name: "{}""#,
+                r#"updated project id: "{}" name: "{}" version: {}"#,
                 project.id().as_uuid(),
-                project.name().as_str()
+                project.name().as_str(),
+                row.version
             );
-            if let Err(_) = ConfigService::publish(
-                &state.mq_chan,
-                ConfigUpdate::Project(project.id().to_uuid()),
+            Ok((
+                StatusCode::CREATED,
+                [(ETAG, etag(row.version))],
+                Json(project),
             )
-            .await
-            {
-                warn!("failed to publish project config update");
-            }
-            Ok((StatusCode::CREATED, Json(project)).into_response())
+                .into_response())
         }
         Err(e) if has_conflict(&e) => {
             warn!("failed to update project: {}", e);
@@ -98,22 +164,132 @@ pub async fn create(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/project/apply",
+    operation_id = "apply_project",
+    tag = "project",
+    params(
+        ApplyQuery,
+        ("If-Match" = Option<String>, Header, description = "ETag the project is expected to have"),
+    ),
+    request_body(content = String, description = "Project document in YAML", content_type = "application/yaml"),
+    responses(
+        (status = 200, description = "Changes planned or applied to the project", body = Plan, headers(("ETag" = String))),
+        (status = 400, description = "Project document is malformed", body = ErrorBody),
+        (status = 409, description = "Project name is already taken", body = ErrorBody),
+        (status = 412, description = "Project does not have the expected ETag", body = ErrorBody),
+        (status = 422, description = "Project document is invalid", body = ErrorBody),
+    ),
+)]
+pub async fn apply(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    query: Query<ApplyQuery>,
+    precondition: Precondition,
+    body: String,
+) -> Result<Response, InteractorError> {
+    let spec: ProjectSpec = match serde_yaml::from_str(&body) {
+        Ok(spec) => spec,
+        Err(e) => {
+            error!("invalid project document found: {}", e);
+            return Err(InteractorError::BadRequest);
+        }
+    };
+    let name = match spec.check().and_then(|_| ProjectName::new(&spec.name)) {
+        Ok(name) => name,
+        Err(e) => {
+            error!("invalid project specification found: {:#}", e);
+            return Err(InteractorError::ValidationFailed(vec![Violation {
+                path: String::from("spec"),
+                message: format!("{:#}", e),
+            }]));
+        }
+    };
+    let current = ProjectService::get_by_name(&state.controller.db_pool, &name).await?;
+    if let (Some(row), Some(config)) = (&current, &spec.config) {
+        let violations = config::validate(row.config_schema.as_ref(), config);
+        if !violations.is_empty() {
+            error!("project config does not conform to its schema");
+            return Err(InteractorError::ValidationFailed(violations));
+        }
+    }
+    let id = match current {
+        Some(row) => ProjectId::new(row.id),
+        None => ProjectId::new(uuid::Uuid::new_v4()),
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::update().on_project(id.to_uuid()).with_token(token),
+    )
+    .await
+    {
+        warn!("failed to apply project: {}", e);
+        return Err(e.into());
+    }
+    let dry_run = query.dry_run.unwrap_or(false);
+    match pg_error(
+        SpecService::apply(
+            &state.controller.db_pool,
+            &id,
+            &spec,
+            dry_run,
+            &precondition,
+        )
+        .await,
+    )? {
+        Ok(plan) => {
+            info!(
+                r#"applied project id: "{}" name: "{}" dry_run: {}"#,
+                id.as_uuid(),
+                name.as_str(),
+                dry_run
+            );
+            let tag = plan.version.map(|version| [(ETAG, etag(version))]);
+            Ok((StatusCode::OK, tag, Json(plan)).into_response())
+        }
+        Err(e) if has_conflict(&e) => {
+            warn!("failed to apply project: {}", e);
+            Err(InteractorError::Conflict)
+        }
+        _ => Err(InteractorError::InternalServerProblem(anyhow!(
+            "Internal server error"
+        ))),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/project",
+    operation_id = "list_projects",
+    tag = "project",
+    params(GetByNameQuery, PageQuery),
+    responses(
+        (status = 200, description = "Page of projects, or the project row when a name is given", body = ProjectPage, headers(("ETag" = String, description = "Set only when a name is given"))),
+        (status = 404, description = "No project has the given name", body = ErrorBody),
+        (status = 422, description = "Query is invalid", body = ErrorBody),
+    ),
+)]
 pub async fn get_by_name(
     token: Token,
     Extension(state): Extension<SharedState>,
     query: Query<GetByNameQuery>,
+    page: Query<PageQuery>,
 ) -> Result<Response, InteractorError> {
     if let Some(name) = &query.name {
-        let name = if let Ok(name) = ProjectName::new(name) {
-            name
-        } else {
-            error!("invalid project name found");
-            return Err(InteractorError::ValidationFailed);
+        let name = match ProjectName::new(name) {
+            Ok(name) => name,
+            Err(e) => {
+                error!("invalid project name found: {}", e);
+                return Err(InteractorError::invalid("name", &e));
+            }
         };
         match ProjectService::get_by_name(&state.controller.db_pool, &name).await? {
-            None => Ok(StatusCode::NOT_FOUND.into_response()),
+            None => Err(InteractorError::NotFound),
             Some(row) => {
-                if let Err(_) = OPAService::authorize(
+                if let Err(e) = OPAService::authorize(
                     &state.controller.db_pool,
                     &state.controller.config.no_auth,
                     state.controller.config.opa_addr.as_ref(),
@@ -121,14 +297,15 @@ pub async fn get_by_name(
                 )
                 .await
                 {
-                    warn!("failed to get project");
-                    return Err(InteractorError::Unauthorized);
+                    warn!("failed to get project: {}", e);
+                    return Err(e.into());
                 }
-                Ok((StatusCode::OK, Json(row)).into_response())
+                Ok((StatusCode::OK, [(ETAG, etag(row.version))], Json(row)).into_response())
             }
         }
     } else {
-        if let Err(_) = OPAService::authorize(
+        let pagination = page.paginate(&NAMED_SORTS)?;
+        if let Err(e) = OPAService::authorize(
             &state.controller.db_pool,
             &state.controller.config.no_auth,
             state.controller.config.opa_addr.as_ref(),
@@ -136,14 +313,25 @@ pub async fn get_by_name(
         )
         .await
         {
-            warn!("failed to list project");
-            return Err(InteractorError::Unauthorized);
+            warn!("failed to list project: {}", e);
+            return Err(e.into());
         }
-        let rows = ProjectService::list(&state.controller.db_pool, None).await?;
-        Ok((StatusCode::OK, Json(rows)).into_response())
+        let page = ProjectService::list(&state.controller.db_pool, &pagination).await?;
+        Ok((StatusCode::OK, Json(page)).into_response())
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/project/{id}",
+    operation_id = "get_project",
+    tag = "project",
+    params(("id" = Uuid, Path, description = "Project id")),
+    responses(
+        (status = 200, description = "Project with job statistics", body = ProjectSummaryRow, headers(("ETag" = String))),
+        (status = 404, description = "Project was not found", body = ErrorBody),
+    ),
+)]
 pub async fn get_summary_by_id(
     token: Token,
     Extension(state): Extension<SharedState>,
@@ -156,9 +344,9 @@ pub async fn get_summary_by_id(
         return Err(InteractorError::BadRequest);
     };
     match ProjectService::get_summary_by_id(&state.controller.db_pool, &id).await? {
-        None => Ok(StatusCode::NOT_FOUND.into_response()),
+        None => Err(InteractorError::NotFound),
         Some(row) => {
-            if let Err(_) = OPAService::authorize(
+            if let Err(e) = OPAService::authorize(
                 &state.controller.db_pool,
                 &state.controller.config.no_auth,
                 state.controller.config.opa_addr.as_ref(),
@@ -166,18 +354,37 @@ pub async fn get_summary_by_id(
             )
             .await
             {
-                warn!("failed to get project");
-                return Err(InteractorError::Unauthorized);
+                warn!("failed to get project: {}", e);
+                return Err(e.into());
             }
-            Ok((StatusCode::OK, Json(row)).into_response())
+            Ok((StatusCode::OK, [(ETAG, etag(row.version))], Json(row)).into_response())
         }
     }
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/project/{id}",
+    operation_id = "delete_project",
+    tag = "project",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+        DeleteQuery,
+        ("If-Match" = Option<String>, Header, description = "ETag the project is expected to have"),
+    ),
+    responses(
+        (status = 204, description = "Project was deleted"),
+        (status = 404, description = "Project was not found", body = ErrorBody),
+        (status = 409, description = "Project still has active runs", body = ErrorBody),
+        (status = 412, description = "Project does not have the expected ETag", body = ErrorBody),
+    ),
+)]
 pub async fn delete(
     token: Token,
     Extension(state): Extension<SharedState>,
     Path(id): Path<String>,
+    query: Query<DeleteQuery>,
+    precondition: Precondition,
 ) -> Result<Response, InteractorError> {
     let id = if let Ok(id) = ProjectId::try_from(id) {
         id
@@ -185,7 +392,7 @@ pub async fn delete(
         error!("project id must be uuid v4");
         return Err(InteractorError::BadRequest);
     };
-    if let Err(_) = OPAService::authorize(
+    if let Err(e) = OPAService::authorize(
         &state.controller.db_pool,
         &state.controller.config.no_auth,
         state.controller.config.opa_addr.as_ref(),
@@ -193,17 +400,43 @@ pub async fn delete(
     )
     .await
     {
-        warn!("failed to delete project");
-        return Err(InteractorError::Unauthorized);
+        warn!("failed to delete project: {}", e);
+        return Err(e.into());
     }
-    match pg_error(ProjectService::delete(&state.controller.db_pool, &id).await)? {
+    let deleted = if query.cascade.unwrap_or(false) {
+        match pg_error(
+            ProjectService::delete_cascade(
+                &state.controller.db_pool,
+                &id,
+                &precondition,
+                state.controller.stash.as_ref(),
+            )
+            .await,
+        )? {
+            Ok(Deletion::Blocked(runs)) => {
+                warn!(
+                    r#"failed to delete project id: "{}" with {} active run(s)"#,
+                    id.as_uuid(),
+                    runs.len()
+                );
+                let body = ErrorBody::new(ErrorCode::Conflict, "Project has active runs")
+                    .with_details(json!({ "active_runs": runs }));
+                return Ok((StatusCode::CONFLICT, Json(body)).into_response());
+            }
+            Ok(Deletion::Done(done)) => Ok(done),
+            Err(e) => Err(e),
+        }
+    } else {
+        pg_error(ProjectService::delete(&state.controller.db_pool, &id, &precondition).await)?
+    };
+    match deleted {
         Ok(done) => {
             if done.rows_affected() == 1 {
                 info!(r#"deleted project id: "{}""#, id.as_uuid());
                 Ok(StatusCode::NO_CONTENT.into_response())
             } else {
                 info!(r#"no project was found with id: "{}""#, id.as_uuid());
-                Ok(StatusCode::NOT_FOUND.into_response())
+                Err(InteractorError::NotFound)
             }
         }
         Err(e) => {
@@ -215,11 +448,22 @@ pub async fn delete(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/project/{id}/workflow",
+    operation_id = "list_project_workflows",
+    tag = "project",
+    params(("id" = Uuid, Path, description = "Project id"), PageQuery),
+    responses(
+        (status = 200, description = "Page of workflows with run statistics", body = WorkflowSummaryPage),
+        (status = 422, description = "Query is invalid", body = ErrorBody),
+    ),
+)]
 pub async fn list_workflows_by_id(
     token: Token,
     Extension(state): Extension<SharedState>,
     Path(id): Path<String>,
-    query: Query<ListWorkflowsByIdQuery>,
+    page: Query<PageQuery>,
 ) -> Result<Response, InteractorError> {
     let id = if let Ok(id) = ProjectId::try_from(id) {
         id
@@ -227,20 +471,8 @@ pub async fn list_workflows_by_id(
         error!("project id must be uuid v4");
         return Err(InteractorError::BadRequest);
     };
-    let name = query
-        .name
-        .as_ref()
-        .map(WorkflowName::new)
-        .transpose()
-        .unwrap_or(None);
-    let after = query
-        .after
-        .as_ref()
-        .map(WorkflowName::new)
-        .transpose()
-        .unwrap_or(None);
-    let limit = query.limit;
-    if let Err(_) = OPAService::authorize(
+    let pagination = page.paginate(&NAMED_SORTS)?;
+    if let Err(e) = OPAService::authorize(
         &state.controller.db_pool,
         &state.controller.config.no_auth,
         state.controller.config.opa_addr.as_ref(),
@@ -248,16 +480,234 @@ pub async fn list_workflows_by_id(
     )
     .await
     {
-        warn!("failed to list project workflows");
-        return Err(InteractorError::Unauthorized);
+        warn!("failed to list project workflows: {}", e);
+        return Err(e.into());
+    }
+    let page =
+        ProjectService::list_workflows_by_id(&state.controller.db_pool, &id, &pagination).await?;
+    Ok((StatusCode::OK, Json(page)).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/project/{id}/config",
+    operation_id = "get_project_config",
+    tag = "project",
+    params(("id" = Uuid, Path, description = "Project id")),
+    responses(
+        (status = 200, description = "Project config", body = Object, headers(("ETag" = String))),
+        (status = 404, description = "Project was not found", body = ErrorBody),
+    ),
+)]
+pub async fn get_config_by_id(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = ProjectId::try_from(id) {
+        id
+    } else {
+        error!("project id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::get().on_project(id.to_uuid()).with_token(token),
+    )
+    .await
+    {
+        warn!("failed to get project config: {}", e);
+        return Err(e.into());
+    }
+    match ProjectService::get_by_id(&state.controller.db_pool, &id).await? {
+        None => Err(InteractorError::NotFound),
+        Some(row) => Ok((
+            StatusCode::OK,
+            [(ETAG, etag(row.version))],
+            Json(row.config.unwrap_or_else(|| json!({}))),
+        )
+            .into_response()),
     }
-    let rows = ProjectService::list_workflows_by_id(
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/project/{id}/config",
+    operation_id = "patch_project_config",
+    tag = "project",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+        ("If-Match" = Option<String>, Header, description = "ETag the project is expected to have"),
+    ),
+    request_body(content = Object, description = "JSON merge patch applied to the project config"),
+    responses(
+        (status = 200, description = "Patched project config", body = Object, headers(("ETag" = String))),
+        (status = 404, description = "Project was not found", body = ErrorBody),
+        (status = 412, description = "Project does not have the expected ETag", body = ErrorBody),
+        (status = 422, description = "Patched config does not conform to the schema", body = ErrorBody),
+    ),
+)]
+pub async fn patch_config(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+    precondition: Precondition,
+    Json(patch): Json<Value>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = ProjectId::try_from(id) {
+        id
+    } else {
+        error!("project id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::update().on_project(id.to_uuid()).with_token(token),
+    )
+    .await
+    {
+        warn!("failed to update project config: {}", e);
+        return Err(e.into());
+    }
+    match ConfigService::patch_project_config(&state.controller.db_pool, &id, &patch, &precondition)
+        .await?
+    {
+        None => Err(InteractorError::NotFound),
+        Some(Validated::Rejected(violations)) => {
+            warn!(
+                r#"rejected config of project id: "{}" with {} violation(s)"#,
+                id.as_uuid(),
+                violations.len()
+            );
+            Err(InteractorError::ValidationFailed(violations))
+        }
+        Some(Validated::Accepted(row)) => {
+            info!(r#"updated config of project id: "{}""#, id.as_uuid());
+            Ok((
+                StatusCode::OK,
+                [(ETAG, etag(row.version))],
+                Json(row.config.unwrap_or_else(|| json!({}))),
+            )
+                .into_response())
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/project/{id}/config/schema",
+    operation_id = "get_project_config_schema",
+    tag = "project",
+    params(("id" = Uuid, Path, description = "Project id")),
+    responses(
+        (status = 200, description = "JSON schema of the project config, or null", body = Object, headers(("ETag" = String))),
+        (status = 404, description = "Project was not found", body = ErrorBody),
+    ),
+)]
+pub async fn get_config_schema_by_id(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = ProjectId::try_from(id) {
+        id
+    } else {
+        error!("project id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::get().on_project(id.to_uuid()).with_token(token),
+    )
+    .await
+    {
+        warn!("failed to get project config schema: {}", e);
+        return Err(e.into());
+    }
+    match ProjectService::get_by_id(&state.controller.db_pool, &id).await? {
+        None => Err(InteractorError::NotFound),
+        Some(row) => Ok((
+            StatusCode::OK,
+            [(ETAG, etag(row.version))],
+            Json(row.config_schema),
+        )
+            .into_response()),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/project/{id}/config/schema",
+    operation_id = "put_project_config_schema",
+    tag = "project",
+    params(
+        ("id" = Uuid, Path, description = "Project id"),
+        ("If-Match" = Option<String>, Header, description = "ETag the project is expected to have"),
+    ),
+    request_body(content = Object, description = "JSON schema of the project config, or null to remove it"),
+    responses(
+        (status = 200, description = "Stored JSON schema", body = Object, headers(("ETag" = String))),
+        (status = 404, description = "Project was not found", body = ErrorBody),
+        (status = 412, description = "Project does not have the expected ETag", body = ErrorBody),
+        (status = 422, description = "Schema is invalid or rejects the current config", body = ErrorBody),
+    ),
+)]
+pub async fn put_config_schema(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+    precondition: Precondition,
+    Json(schema): Json<Value>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = ProjectId::try_from(id) {
+        id
+    } else {
+        error!("project id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::update().on_project(id.to_uuid()).with_token(token),
+    )
+    .await
+    {
+        warn!("failed to update project config schema: {}", e);
+        return Err(e.into());
+    }
+    let schema = if schema.is_null() { None } else { Some(schema) };
+    match ConfigService::set_schema(
         &state.controller.db_pool,
         &id,
-        name.as_ref(),
-        after.as_ref(),
-        limit.as_ref(),
+        schema.as_ref(),
+        &precondition,
     )
-    .await?;
-    Ok((StatusCode::OK, Json(rows)).into_response())
+    .await?
+    {
+        None => Err(InteractorError::NotFound),
+        Some(Validated::Rejected(violations)) => {
+            warn!(
+                r#"rejected config schema of project id: "{}" with {} violation(s)"#,
+                id.as_uuid(),
+                violations.len()
+            );
+            Err(InteractorError::ValidationFailed(violations))
+        }
+        Some(Validated::Accepted(row)) => {
+            info!(r#"updated config schema of project id: "{}""#, id.as_uuid());
+            Ok((
+                StatusCode::OK,
+                [(ETAG, etag(row.version))],
+                Json(row.config_schema),
+            )
+                .into_response())
+        }
+    }
 }
@@ -0,0 +1,251 @@
+use crate::controller::entities::job::JobId;
+use crate::controller::entities::run::RunId;
+use crate::controller::interactors::api::PageQuery;
+use crate::controller::interactors::api::TIMED_SORTS;
+use crate::controller::interactors::InteractorError;
+use crate::controller::interactors::SharedState;
+use crate::controller::services::job::JobService;
+use crate::controller::services::log;
+use crate::controller::services::log::LogQuery;
+use crate::controller::services::log::LogService;
+use crate::controller::services::opa::Event;
+use crate::controller::services::opa::OPAService;
+use crate::controller::services::run::RunService;
+use crate::infra::opa::Token;
+use crate::messages::log::LogStream;
+use axum::extract::Extension;
+use axum::extract::Json;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::http::HeaderMap;
+use axum::http::StatusCode;
+use axum::response::sse::Event as SseEvent;
+use axum::response::sse::KeepAlive;
+use axum::response::sse::Sse;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use futures::StreamExt;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    job: String,
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LogsQuery {
+    stream: Option<LogStream>,
+    from: Option<i64>,
+    tail: Option<i64>,
+    limit: Option<i64>,
+    #[serde(default)]
+    follow: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/run",
+    operation_id = "list_runs",
+    tag = "run",
+    params(ListQuery, PageQuery),
+    responses(
+        (status = 200, description = "Page of runs of the job", body = RunPage),
+        (status = 404, description = "Job was not found", body = ErrorBody),
+        (status = 422, description = "Query is invalid", body = ErrorBody),
+    ),
+)]
+pub async fn list(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    query: Query<ListQuery>,
+    page: Query<PageQuery>,
+) -> Result<Response, InteractorError> {
+    let job_id = if let Ok(id) = JobId::try_from(query.job.as_str()) {
+        id
+    } else {
+        error!("job id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    let pagination = page.paginate(&TIMED_SORTS)?;
+    let job = match JobService::get_by_id(&state.controller.db_pool, &job_id).await? {
+        Some(job) => job,
+        None => return Err(InteractorError::NotFound),
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::list()
+            .on_workflow(job.workflow_id, None)
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to list runs: {}", e);
+        return Err(e.into());
+    }
+    let page = RunService::list_by_job_id(&state.controller.db_pool, &job_id, &pagination).await?;
+    Ok((StatusCode::OK, Json(page)).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/run/{id}/cancel",
+    operation_id = "cancel_run",
+    tag = "run",
+    params(("id" = Uuid, Path, description = "Run id")),
+    responses(
+        (status = 204, description = "Run was cancelled"),
+        (status = 404, description = "Run was not found", body = ErrorBody),
+        (status = 409, description = "Run has already finished", body = ErrorBody),
+    ),
+)]
+pub async fn cancel(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = RunId::try_from(id) {
+        id
+    } else {
+        error!("run id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    let run = match RunService::get_by_id(&state.controller.db_pool, &id).await? {
+        Some(run) => run,
+        None => return Err(InteractorError::NotFound),
+    };
+    let job = JobService::get_by_id(&state.controller.db_pool, &JobId::new(run.job_id)).await?;
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::update()
+            .on_workflow(job.map(|job| job.workflow_id), None)
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to cancel run: {}", e);
+        return Err(e.into());
+    }
+    let done = RunService::cancel(&state.controller.db_pool, &id).await?;
+    if done.rows_affected() == 1 {
+        info!(r#"cancelled run id: "{}""#, id.as_uuid());
+        Ok(StatusCode::NO_CONTENT.into_response())
+    } else {
+        warn!(
+            r#"run id: "{}" has already finished as "{}""#,
+            id.as_uuid(),
+            run.state
+        );
+        Err(InteractorError::Conflict)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/run/{id}/logs",
+    operation_id = "get_run_logs",
+    tag = "run",
+    params(("id" = Uuid, Path, description = "Run id"), LogsQuery),
+    responses(
+        (status = 200, description = "Log lines, or server-sent log events when following", content(
+            ("application/json" = [LogRow]),
+            ("text/event-stream" = String),
+        )),
+        (status = 404, description = "Run was not found", body = ErrorBody),
+    ),
+)]
+pub async fn get_logs(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+    query: Query<LogsQuery>,
+    headers: HeaderMap,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = RunId::try_from(id) {
+        id
+    } else {
+        error!("run id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    let run = match RunService::get_by_id(&state.controller.db_pool, &id).await? {
+        Some(run) => run,
+        None => return Err(InteractorError::NotFound),
+    };
+    let job = JobService::get_by_id(&state.controller.db_pool, &JobId::new(run.job_id)).await?;
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::get()
+            .on_workflow(job.map(|job| job.workflow_id), None)
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to get run logs: {}", e);
+        return Err(e.into());
+    }
+    if !query.follow {
+        let query = LogQuery {
+            stream: query.stream,
+            from: query.from,
+            tail: query.tail,
+            limit: query.limit,
+        };
+        let rows = LogService::list(&state.controller.db_pool, &id, &query).await?;
+        return Ok((StatusCode::OK, Json(rows)).into_response());
+    }
+    let resumed = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i64>().ok())
+        .map(|seq| seq + 1);
+    let from = match (resumed, query.from, query.tail) {
+        (Some(from), _, _) => from,
+        (None, Some(from), _) => from,
+        (None, None, Some(tail)) => {
+            let query = LogQuery {
+                stream: query.stream,
+                tail: Some(tail),
+                ..LogQuery::default()
+            };
+            let rows = LogService::list(&state.controller.db_pool, &id, &query).await?;
+            rows.first().map(|row| row.seq).unwrap_or(0)
+        }
+        (None, None, None) => 0,
+    };
+    info!(r#"following logs of run id: "{}""#, id.as_uuid());
+    let events = log::follow(
+        state.controller.db_pool.clone(),
+        id,
+        query.stream,
+        from,
+        state.controller.draining.subscribe(),
+    )
+    .map(|rows| match rows {
+        Ok(rows) => rows
+            .into_iter()
+            .map(|row| {
+                SseEvent::default()
+                    .event("log")
+                    .id(row.seq.to_string())
+                    .json_data(row)
+            })
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            error!("failed to follow run logs: {}", e);
+            Vec::new()
+        }
+    })
+    .flat_map(futures::stream::iter);
+    Ok(Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
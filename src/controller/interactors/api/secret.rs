@@ -0,0 +1,177 @@
+use crate::controller::entities::project::ProjectId;
+use crate::controller::entities::secret::Secret;
+use crate::controller::entities::secret::SecretName;
+use crate::controller::interactors::InteractorError;
+use crate::controller::interactors::SharedState;
+use crate::controller::services::opa::Event;
+use crate::controller::services::opa::OPAService;
+use crate::controller::services::project::ProjectService;
+use crate::controller::services::secret::SecretService;
+use crate::infra::opa::Token;
+use anyhow::anyhow;
+use axum::extract::Extension;
+use axum::extract::Json;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct PutJson {
+    value: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/project/{id}/secret",
+    operation_id = "list_project_secrets",
+    tag = "secret",
+    params(("id" = Uuid, Path, description = "Project id")),
+    responses(
+        (status = 200, description = "Secrets of the project without their values", body = [SecretRow]),
+    ),
+)]
+pub async fn list(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = ProjectId::try_from(id) {
+        id
+    } else {
+        error!("project id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::list().on_project(id.to_uuid()).with_token(token),
+    )
+    .await
+    {
+        warn!("failed to list project secrets: {}", e);
+        return Err(e.into());
+    }
+    let rows = SecretService::list_by_project_id(&state.controller.db_pool, &id).await?;
+    Ok((StatusCode::OK, Json(rows)).into_response())
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/project/{id}/secret/{name}",
+    operation_id = "put_project_secret",
+    tag = "secret",
+    params(("id" = Uuid, Path, description = "Project id"), ("name" = String, Path, description = "Secret name")),
+    request_body = PutJson,
+    responses(
+        (status = 204, description = "Secret was stored"),
+        (status = 404, description = "Project was not found", body = ErrorBody),
+        (status = 422, description = "Secret is invalid", body = ErrorBody),
+    ),
+)]
+pub async fn put(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path((id, name)): Path<(String, String)>,
+    Json(payload): Json<PutJson>,
+) -> Result<Response, InteractorError> {
+    let secret = match Secret::new(uuid::Uuid::new_v4().to_string(), id, name, payload.value) {
+        Ok(secret) => secret,
+        Err(e) => {
+            error!("invalid secret specification found: {}", e);
+            return Err(InteractorError::invalid("", &e));
+        }
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::update()
+            .on_project(secret.project_id().to_uuid())
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to update project secret: {}", e);
+        return Err(e.into());
+    }
+    let cipher = match state.controller.cipher.as_ref() {
+        Some(cipher) => cipher,
+        None => {
+            return Err(InteractorError::InternalServerProblem(anyhow!(
+                "secret key is not configured"
+            )))
+        }
+    };
+    if ProjectService::get_by_id(&state.controller.db_pool, secret.project_id())
+        .await?
+        .is_none()
+    {
+        return Err(InteractorError::NotFound);
+    }
+    SecretService::put(&state.controller.db_pool, &secret, cipher).await?;
+    info!(
+        r#"updated secret name: "{}" of project id: "{}""#,
+        secret.name().as_str(),
+        secret.project_id().as_uuid()
+    );
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/project/{id}/secret/{name}",
+    operation_id = "delete_project_secret",
+    tag = "secret",
+    params(("id" = Uuid, Path, description = "Project id"), ("name" = String, Path, description = "Secret name")),
+    responses(
+        (status = 204, description = "Secret was deleted"),
+        (status = 404, description = "Secret was not found", body = ErrorBody),
+        (status = 422, description = "Secret name is invalid", body = ErrorBody),
+    ),
+)]
+pub async fn delete(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path((id, name)): Path<(String, String)>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = ProjectId::try_from(id) {
+        id
+    } else {
+        error!("project id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    let name = match SecretName::new(name) {
+        Ok(name) => name,
+        Err(e) => {
+            error!("invalid secret name found: {}", e);
+            return Err(InteractorError::invalid("name", &e));
+        }
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::delete().on_project(id.to_uuid()).with_token(token),
+    )
+    .await
+    {
+        warn!("failed to delete project secret: {}", e);
+        return Err(e.into());
+    }
+    let done = SecretService::delete(&state.controller.db_pool, &id, &name).await?;
+    if done.rows_affected() == 1 {
+        info!(
+            r#"deleted secret name: "{}" of project id: "{}""#,
+            name.as_str(),
+            id.as_uuid()
+        );
+        Ok(StatusCode::NO_CONTENT.into_response())
+    } else {
+        Err(InteractorError::NotFound)
+    }
+}
@@ -0,0 +1,441 @@
+use crate::controller::entities::execution::Execution;
+use crate::controller::entities::job::JobId;
+use crate::controller::entities::run::Run;
+use crate::controller::entities::version::etag;
+use crate::controller::entities::version::Precondition;
+use crate::controller::entities::workflow::WorkflowId;
+use crate::controller::entities::workflow::WorkflowSchedule;
+use crate::controller::interactors::api::PageQuery;
+use crate::controller::interactors::api::NAMED_SORTS;
+use crate::controller::interactors::InteractorError;
+use crate::controller::interactors::SharedState;
+use crate::controller::services::config::Violation;
+use crate::controller::services::execution::ExecutionService;
+use crate::controller::services::job::JobService;
+use crate::controller::services::opa::Event;
+use crate::controller::services::opa::OPAService;
+use crate::controller::services::workflow::WorkflowService;
+use crate::infra::opa::Token;
+use crate::messages::run::RunPriority;
+use axum::extract::Extension;
+use axum::extract::Json;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::http::header::ETAG;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use chrono::DateTime;
+use chrono::Utc;
+use std::str::FromStr;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+use uuid::Uuid;
+
+const BACKFILL_LIMIT: usize = 1000;
+
+#[derive(serde::Deserialize, Default, utoipa::ToSchema)]
+pub struct RunJson {
+    priority: Option<String>,
+}
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct BackfillJson {
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    priority: Option<String>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct ExecutionJson {
+    id: Uuid,
+    workflow_id: Uuid,
+    priority: RunPriority,
+    triggered_at: DateTime<Utc>,
+    runs: Vec<Run>,
+}
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListExecutionsQuery {
+    limit: Option<i64>,
+}
+
+async fn set_paused(
+    token: Token,
+    state: SharedState,
+    id: String,
+    paused: bool,
+    precondition: Precondition,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = WorkflowId::try_from(id) {
+        id
+    } else {
+        error!("workflow id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::update()
+            .on_workflow(id.to_uuid(), None)
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to update workflow: {}", e);
+        return Err(e.into());
+    }
+    match WorkflowService::set_paused(&state.controller.db_pool, &id, paused, &precondition).await?
+    {
+        Some(row) => {
+            info!(
+                r#"updated workflow id: "{}" paused: {}"#,
+                id.as_uuid(),
+                paused
+            );
+            Ok((StatusCode::NO_CONTENT, [(ETAG, etag(row.version))]).into_response())
+        }
+        None => {
+            info!(r#"no workflow was found with id: "{}""#, id.as_uuid());
+            Err(InteractorError::NotFound)
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/workflow/{id}/pause",
+    operation_id = "pause_workflow",
+    tag = "workflow",
+    params(
+        ("id" = Uuid, Path, description = "Workflow id"),
+        ("If-Match" = Option<String>, Header, description = "ETag the workflow is expected to have"),
+    ),
+    responses(
+        (status = 204, description = "Workflow was paused", headers(("ETag" = String))),
+        (status = 404, description = "Workflow was not found", body = ErrorBody),
+        (status = 412, description = "Workflow does not have the expected ETag", body = ErrorBody),
+    ),
+)]
+pub async fn pause(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+    precondition: Precondition,
+) -> Result<Response, InteractorError> {
+    set_paused(token, state, id, true, precondition).await
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/workflow/{id}/resume",
+    operation_id = "resume_workflow",
+    tag = "workflow",
+    params(
+        ("id" = Uuid, Path, description = "Workflow id"),
+        ("If-Match" = Option<String>, Header, description = "ETag the workflow is expected to have"),
+    ),
+    responses(
+        (status = 204, description = "Workflow was resumed", headers(("ETag" = String))),
+        (status = 404, description = "Workflow was not found", body = ErrorBody),
+        (status = 412, description = "Workflow does not have the expected ETag", body = ErrorBody),
+    ),
+)]
+pub async fn resume(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+    precondition: Precondition,
+) -> Result<Response, InteractorError> {
+    set_paused(token, state, id, false, precondition).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/workflow/{id}/run",
+    operation_id = "run_workflow",
+    tag = "workflow",
+    params(("id" = Uuid, Path, description = "Workflow id")),
+    request_body(content = Option<RunJson>, description = "Priority of the triggered runs"),
+    responses(
+        (status = 201, description = "Execution was triggered", body = ExecutionJson),
+        (status = 404, description = "Workflow was not found", body = ErrorBody),
+        (status = 422, description = "Priority is invalid, the workflow has no jobs or templates cannot be rendered", body = ErrorBody),
+    ),
+)]
+pub async fn run(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+    payload: Option<Json<RunJson>>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = WorkflowId::try_from(id) {
+        id
+    } else {
+        error!("workflow id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    let Json(payload) = payload.unwrap_or_default();
+    let priority = match payload.priority.as_deref().map(RunPriority::from_str) {
+        None => RunPriority::default(),
+        Some(Ok(priority)) => priority,
+        Some(Err(e)) => {
+            error!("invalid run priority found: {}", e);
+            return Err(InteractorError::invalid("priority", &e.into()));
+        }
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::update()
+            .on_workflow(id.to_uuid(), None)
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to run workflow: {}", e);
+        return Err(e.into());
+    }
+    if WorkflowService::get_by_id(&state.controller.db_pool, &id)
+        .await?
+        .is_none()
+    {
+        return Err(InteractorError::NotFound);
+    }
+    let jobs: Vec<JobId> = JobService::list_by_workflow_id(&state.controller.db_pool, &id)
+        .await?
+        .into_iter()
+        .map(|job| JobId::new(job.id))
+        .collect();
+    if jobs.is_empty() {
+        error!(r#"workflow id: "{}" has no jobs to run"#, id.as_uuid());
+        return Err(InteractorError::ValidationFailed(vec![Violation {
+            path: String::from("jobs"),
+            message: String::from("workflow has no jobs to run"),
+        }]));
+    }
+    let execution = Execution::new(
+        uuid::Uuid::new_v4().to_string(),
+        id.as_uuid().to_string(),
+        priority,
+        Utc::now(),
+    )?;
+    let runs = ExecutionService::trigger(&state.controller.db_pool, &execution, &jobs).await?;
+    info!(
+        r#"triggered execution id: "{}" of workflow id: "{}" with {} run(s)"#,
+        execution.id().as_uuid(),
+        id.as_uuid(),
+        runs.len()
+    );
+    let body = ExecutionJson {
+        id: execution.id().to_uuid(),
+        workflow_id: execution.workflow_id().to_uuid(),
+        priority: *execution.priority(),
+        triggered_at: *execution.triggered_at(),
+        runs,
+    };
+    Ok((StatusCode::CREATED, Json(body)).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/workflow/{id}/backfill",
+    operation_id = "backfill_workflow",
+    tag = "workflow",
+    params(("id" = Uuid, Path, description = "Workflow id")),
+    request_body(content = BackfillJson, description = "Range of scheduled times to trigger executions for"),
+    responses(
+        (status = 201, description = "Executions were triggered for the scheduled times in the range", body = [ExecutionJson]),
+        (status = 404, description = "Workflow was not found", body = ErrorBody),
+        (status = 422, description = "Range or priority is invalid, the workflow has no schedule or jobs, or templates cannot be rendered", body = ErrorBody),
+    ),
+)]
+pub async fn backfill(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+    Json(payload): Json<BackfillJson>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = WorkflowId::try_from(id) {
+        id
+    } else {
+        error!("workflow id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    let priority = match payload.priority.as_deref().map(RunPriority::from_str) {
+        None => RunPriority::BackFill,
+        Some(Ok(priority)) => priority,
+        Some(Err(e)) => {
+            error!("invalid run priority found: {}", e);
+            return Err(InteractorError::invalid("priority", &e.into()));
+        }
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::update()
+            .on_workflow(id.to_uuid(), None)
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to backfill workflow: {}", e);
+        return Err(e.into());
+    }
+    let workflow = match WorkflowService::get_by_id(&state.controller.db_pool, &id).await? {
+        Some(workflow) => workflow,
+        None => return Err(InteractorError::NotFound),
+    };
+    let schedule = match workflow.schedule.map(WorkflowSchedule::new).transpose() {
+        Ok(Some(schedule)) => schedule,
+        Ok(None) => {
+            error!(
+                r#"workflow id: "{}" has no schedule to backfill"#,
+                id.as_uuid()
+            );
+            return Err(InteractorError::ValidationFailed(vec![Violation {
+                path: String::from("schedule"),
+                message: String::from("workflow has no schedule to backfill"),
+            }]));
+        }
+        Err(e) => {
+            error!("invalid workflow schedule found: {}", e);
+            return Err(InteractorError::invalid("schedule", &e));
+        }
+    };
+    let ticks = match schedule.occurrences(payload.from, payload.to, BACKFILL_LIMIT) {
+        Ok(ticks) => ticks,
+        Err(e) => {
+            error!("invalid backfill range found: {}", e);
+            return Err(InteractorError::invalid("to", &e));
+        }
+    };
+    let jobs: Vec<JobId> = JobService::list_by_workflow_id(&state.controller.db_pool, &id)
+        .await?
+        .into_iter()
+        .map(|job| JobId::new(job.id))
+        .collect();
+    if jobs.is_empty() {
+        error!(r#"workflow id: "{}" has no jobs to run"#, id.as_uuid());
+        return Err(InteractorError::ValidationFailed(vec![Violation {
+            path: String::from("jobs"),
+            message: String::from("workflow has no jobs to run"),
+        }]));
+    }
+    let executions = ticks
+        .into_iter()
+        .map(|tick| {
+            Execution::new(
+                uuid::Uuid::new_v4().to_string(),
+                id.as_uuid().to_string(),
+                priority,
+                tick,
+            )
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let runs = ExecutionService::backfill(&state.controller.db_pool, &executions, &jobs).await?;
+    info!(
+        r#"backfilled {} execution(s) of workflow id: "{}""#,
+        executions.len(),
+        id.as_uuid()
+    );
+    let body: Vec<ExecutionJson> = executions
+        .into_iter()
+        .zip(runs)
+        .map(|(execution, runs)| ExecutionJson {
+            id: execution.id().to_uuid(),
+            workflow_id: execution.workflow_id().to_uuid(),
+            priority: *execution.priority(),
+            triggered_at: *execution.triggered_at(),
+            runs,
+        })
+        .collect();
+    Ok((StatusCode::CREATED, Json(body)).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/workflow/{id}/execution",
+    operation_id = "list_workflow_executions",
+    tag = "workflow",
+    params(("id" = Uuid, Path, description = "Workflow id"), ListExecutionsQuery),
+    responses(
+        (status = 200, description = "Latest executions of the workflow", body = [ExecutionSummary]),
+    ),
+)]
+pub async fn list_executions(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+    query: Query<ListExecutionsQuery>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = WorkflowId::try_from(id) {
+        id
+    } else {
+        error!("workflow id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::list()
+            .on_workflow(id.to_uuid(), None)
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to list executions: {}", e);
+        return Err(e.into());
+    }
+    let executions =
+        ExecutionService::list_by_workflow_id(&state.controller.db_pool, &id, query.limit.as_ref())
+            .await?;
+    Ok((StatusCode::OK, Json(executions)).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/workflow/{id}/job",
+    operation_id = "list_workflow_jobs",
+    tag = "workflow",
+    params(("id" = Uuid, Path, description = "Workflow id"), PageQuery),
+    responses(
+        (status = 200, description = "Page of jobs of the workflow", body = JobPage),
+        (status = 422, description = "Query is invalid", body = ErrorBody),
+    ),
+)]
+pub async fn list_jobs(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+    page: Query<PageQuery>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = WorkflowId::try_from(id) {
+        id
+    } else {
+        error!("workflow id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    let pagination = page.paginate(&NAMED_SORTS)?;
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::list()
+            .on_workflow(id.to_uuid(), None)
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to list jobs: {}", e);
+        return Err(e.into());
+    }
+    let page = JobService::page_by_workflow_id(&state.controller.db_pool, &id, &pagination).await?;
+    Ok((StatusCode::OK, Json(page)).into_response())
+}
@@ -0,0 +1,103 @@
+use crate::controller::interactors::SharedState;
+use crate::controller::services::health::HealthService;
+use crate::controller::services::opa::OPAService;
+use axum::extract::Extension;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::Json;
+use serde_json::json;
+use tracing::warn;
+
+const OK: &str = "ok";
+
+const UNAVAILABLE: &str = "unavailable";
+
+const SKIPPED: &str = "skipped";
+
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    operation_id = "healthz",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Controller is alive", body = Object),
+    ),
+)]
+pub async fn healthz() -> Response {
+    (StatusCode::OK, Json(json!({ "status": OK }))).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    operation_id = "readyz",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Dependencies are reachable", body = Object),
+        (status = 503, description = "Some dependency is unavailable", body = Object),
+    ),
+)]
+pub async fn readyz(Extension(state): Extension<SharedState>) -> Response {
+    let postgres = match HealthService::check(&state.controller.db_pool).await {
+        Ok(_) => OK,
+        Err(e) => {
+            warn!("postgres is not ready: {}", e);
+            UNAVAILABLE
+        }
+    };
+    let broker = match HealthService::check(state.controller.broker.as_ref()).await {
+        Ok(_) => OK,
+        Err(e) => {
+            warn!("message broker is not ready: {}", e);
+            UNAVAILABLE
+        }
+    };
+    let opa = if state.controller.config.no_auth {
+        SKIPPED
+    } else {
+        match OPAService::ping(
+            &state.controller.db_pool,
+            state.controller.config.opa_addr.as_ref(),
+        )
+        .await
+        {
+            Ok(_) => OK,
+            Err(e) => {
+                warn!("OPA is not ready: {}", e);
+                UNAVAILABLE
+            }
+        }
+    };
+    let status = if [postgres, broker, opa].contains(&UNAVAILABLE) {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    let body = Json(json!({
+        "postgres": postgres,
+        "broker": broker,
+        "opa": opa,
+    }));
+    (status, body).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/version",
+    operation_id = "version",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Build version of the controller", body = Object),
+    ),
+)]
+pub async fn version() -> Response {
+    let body = Json(json!({
+        "version": crate::VERSION,
+        "package": env!("CARGO_PKG_VERSION"),
+    }));
+    (StatusCode::OK, body).into_response()
+}
@@ -0,0 +1,267 @@
+use crate::controller::entities::job::JobId;
+use crate::controller::entities::project::ProjectId;
+use crate::controller::entities::run::RunId;
+use crate::controller::interactors::InteractorError;
+use crate::controller::interactors::SharedState;
+use crate::controller::services::config::ConfigService;
+use crate::controller::services::job::JobService;
+use crate::controller::services::log::LogService;
+use crate::controller::services::opa::Event;
+use crate::controller::services::opa::OPAService;
+use crate::controller::services::run::RunService;
+use crate::controller::services::secret::SecretService;
+use crate::infra::jwt;
+use crate::infra::opa::Token;
+use crate::messages::log::LogLine;
+use crate::messages::token::TokenState;
+use axum::extract::Extension;
+use axum::extract::Json;
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use serde_json::Value;
+use tracing::debug;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct StateJson {
+    state: TokenState,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct RunConfigJson {
+    run_id: Uuid,
+    attempt: i32,
+    job_id: Uuid,
+    image: String,
+    args: Vec<String>,
+    envs: Vec<String>,
+    #[schema(value_type = Object)]
+    config: Value,
+    stash_token: Option<String>,
+}
+
+fn authenticate(state: &SharedState, token: &Token, id: &RunId) -> Result<(), InteractorError> {
+    let keys = match &state.controller.keys {
+        Some(keys) => keys,
+        None => {
+            warn!("run config is disabled because jwt secret is not configured");
+            return Err(InteractorError::Unauthorized);
+        }
+    };
+    let token = match token {
+        Token::Bearer(token) => token,
+        Token::None => {
+            warn!("run config token is missing");
+            return Err(InteractorError::Unauthorized);
+        }
+    };
+    match jwt::verify_config(keys, token).map(RunId::try_from) {
+        Ok(Ok(sub)) if sub.as_uuid() == id.as_uuid() => Ok(()),
+        _ => {
+            warn!(
+                r#"run config token is invalid for run id: "{}""#,
+                id.as_uuid()
+            );
+            Err(InteractorError::Unauthorized)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/internal/api/run/{id}/logs",
+    operation_id = "append_run_logs",
+    tag = "internal",
+    params(("id" = Uuid, Path, description = "Run id")),
+    request_body = [LogLine],
+    responses(
+        (status = 204, description = "Log lines were appended"),
+        (status = 404, description = "Run was not found", body = ErrorBody),
+    ),
+)]
+pub async fn append_logs(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+    Json(lines): Json<Vec<LogLine>>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = RunId::try_from(id) {
+        id
+    } else {
+        error!("run id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    let run = match RunService::get_by_id(&state.controller.db_pool, &id).await? {
+        Some(run) => run,
+        None => return Err(InteractorError::NotFound),
+    };
+    let job = JobService::get_by_id(&state.controller.db_pool, &JobId::new(run.job_id)).await?;
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::update()
+            .on_workflow(job.map(|job| job.workflow_id), None)
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to append run logs: {}", e);
+        return Err(e.into());
+    }
+    let done = LogService::append(
+        &state.controller.db_pool,
+        &run,
+        lines,
+        state.controller.cipher.as_ref(),
+    )
+    .await?;
+    debug!(
+        r#"appended {} log lines to run id: "{}""#,
+        done.rows_affected(),
+        id.as_uuid()
+    );
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[utoipa::path(
+    put,
+    path = "/internal/api/run/{id}/state",
+    operation_id = "update_run_state",
+    tag = "internal",
+    params(("id" = Uuid, Path, description = "Run id")),
+    request_body = StateJson,
+    responses(
+        (status = 204, description = "Run state was updated"),
+        (status = 404, description = "Run was not found", body = ErrorBody),
+        (status = 409, description = "Run has already finished", body = ErrorBody),
+    ),
+)]
+pub async fn update_state(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+    Json(payload): Json<StateJson>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = RunId::try_from(id) {
+        id
+    } else {
+        error!("run id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    let run = match RunService::get_by_id(&state.controller.db_pool, &id).await? {
+        Some(run) => run,
+        None => return Err(InteractorError::NotFound),
+    };
+    let job = JobService::get_by_id(&state.controller.db_pool, &JobId::new(run.job_id)).await?;
+    if let Err(e) = OPAService::authorize(
+        &state.controller.db_pool,
+        &state.controller.config.no_auth,
+        state.controller.config.opa_addr.as_ref(),
+        Event::update()
+            .on_workflow(job.map(|job| job.workflow_id), None)
+            .with_token(token),
+    )
+    .await
+    {
+        warn!("failed to update run state: {}", e);
+        return Err(e.into());
+    }
+    let done = RunService::update_state(&state.controller.db_pool, &id, &payload.state).await?;
+    if done.rows_affected() == 0 {
+        warn!(r#"run id: "{}" has already finished"#, id.as_uuid());
+        return Err(InteractorError::Conflict);
+    }
+    info!(
+        r#"updated run id: "{}" to "{}""#,
+        id.as_uuid(),
+        payload.state.as_ref()
+    );
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/internal/api/run/{id}/config",
+    operation_id = "get_run_config",
+    tag = "internal",
+    params(("id" = Uuid, Path, description = "Run id")),
+    responses(
+        (status = 200, description = "Resolved config for the runner", body = RunConfigJson),
+        (status = 401, description = "Run config token is missing or invalid", body = ErrorBody),
+        (status = 404, description = "Run was not found", body = ErrorBody),
+        (status = 409, description = "Run has already finished", body = ErrorBody),
+        (status = 422, description = "Secrets of the run cannot be resolved", body = ErrorBody),
+    ),
+)]
+pub async fn get_config(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Response, InteractorError> {
+    let id = if let Ok(id) = RunId::try_from(id) {
+        id
+    } else {
+        error!("run id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    authenticate(&state, &token, &id)?;
+    let run = match RunService::get_by_id(&state.controller.db_pool, &id).await? {
+        Some(run) => run,
+        None => return Err(InteractorError::NotFound),
+    };
+    if run.finished_at.is_some() {
+        warn!(r#"run id: "{}" has already finished"#, id.as_uuid());
+        return Err(InteractorError::Conflict);
+    }
+    let job_id = JobId::new(run.job_id);
+    let job = match JobService::get_by_id(&state.controller.db_pool, &job_id).await? {
+        Some(job) => job,
+        None => return Err(InteractorError::NotFound),
+    };
+    let config = match ConfigService::get_job_config(&state.controller.db_pool, &job_id).await? {
+        Some(config) => config,
+        None => return Err(InteractorError::NotFound),
+    };
+    let envs = match SecretService::resolve(
+        &state.controller.db_pool,
+        &ProjectId::new(config.project_id),
+        run.envs.as_ref().unwrap_or(&job.envs),
+        state.controller.cipher.as_ref(),
+    )
+    .await
+    {
+        Ok(envs) => envs,
+        Err(e) => {
+            error!(
+                r#"failed to resolve secrets of run id: "{}": {}"#,
+                id.as_uuid(),
+                e
+            );
+            return Err(InteractorError::invalid("envs", &e));
+        }
+    };
+    let stash_token = state
+        .controller
+        .keys
+        .as_ref()
+        .map(|keys| jwt::stash(keys, &id.as_uuid().to_string()))
+        .transpose()?;
+    info!(r#"resolved config of run id: "{}""#, id.as_uuid());
+    let body = RunConfigJson {
+        run_id: run.id,
+        attempt: run.attempt,
+        job_id: job.id,
+        image: job.image,
+        args: run.args.unwrap_or(job.args),
+        envs,
+        config: config.merged,
+        stash_token,
+    };
+    Ok((StatusCode::OK, Json(body)).into_response())
+}
@@ -0,0 +1,61 @@
+use crate::controller::interactors::InteractorError;
+use crate::controller::interactors::SharedState;
+use crate::controller::services::run::RunService;
+use crate::metrics;
+use axum::extract::Extension;
+use axum::extract::MatchedPath;
+use axum::http::header;
+use axum::http::Request;
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use std::time::Instant;
+
+pub async fn track<B>(request: Request<B>, next: Next<B>) -> Response {
+    let start = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned())
+        .unwrap_or_else(|| String::from("unmatched"));
+    let response = next.run(request).await;
+    metrics::HTTP_REQUESTS_TOTAL
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .inc();
+    metrics::HTTP_REQUEST_DURATION_SECONDS
+        .with_label_values(&[&method, &route])
+        .observe(start.elapsed().as_secs_f64());
+    response
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    operation_id = "get_metrics",
+    tag = "metrics",
+    security(()),
+    responses(
+        (status = 200, description = "Prometheus metrics", body = String, content_type = "text/plain"),
+    ),
+)]
+pub async fn get(Extension(state): Extension<SharedState>) -> Result<Response, InteractorError> {
+    let pool = &state.controller.db_pool;
+    metrics::PG_POOL_CONNECTIONS.set(pool.size().into());
+    metrics::PG_POOL_IDLE_CONNECTIONS.set(pool.num_idle() as i64);
+    let rows = RunService::count_by_state(pool).await?;
+    metrics::RUNS.reset();
+    for row in rows {
+        metrics::RUNS
+            .with_label_values(&[&row.project, &row.workflow, &row.state])
+            .set(row.count);
+    }
+    let body = metrics::encode()?;
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, metrics::CONTENT_TYPE)],
+        body,
+    )
+        .into_response())
+}
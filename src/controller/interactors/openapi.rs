@@ -0,0 +1,422 @@
+use crate::controller::entities::notification::NotificationEvent;
+use crate::controller::entities::page::JobPage;
+use crate::controller::entities::page::PageOrder;
+use crate::controller::entities::page::PageSort;
+use crate::controller::entities::page::ProjectPage;
+use crate::controller::entities::page::RunPage;
+use crate::controller::entities::page::WorkflowSummaryPage;
+use crate::controller::entities::project::Project;
+use crate::controller::entities::project::ProjectRetention;
+use crate::controller::entities::run::Run;
+use crate::controller::entities::sla::SlaKind;
+use crate::controller::entities::workflow::WorkflowSla;
+use crate::controller::interactors::api::notification::NotificationJson;
+use crate::controller::interactors::api::project::CreateJson;
+use crate::controller::interactors::api::secret::PutJson;
+use crate::controller::interactors::api::workflow::BackfillJson;
+use crate::controller::interactors::api::workflow::ExecutionJson;
+use crate::controller::interactors::api::workflow::RunJson;
+use crate::controller::interactors::internal::api::run::RunConfigJson;
+use crate::controller::interactors::internal::api::run::StateJson;
+use crate::controller::interactors::ErrorBody;
+use crate::controller::interactors::ErrorCode;
+use crate::controller::interactors::InteractorError;
+use crate::controller::interactors::SharedState;
+use crate::controller::repositories::cluster::LeaseRow;
+use crate::controller::repositories::cluster::MemberRow;
+use crate::controller::repositories::execution::ExecutionNodeRow;
+use crate::controller::repositories::execution::ExecutionRow;
+use crate::controller::repositories::job::JobRow;
+use crate::controller::repositories::log::LogRow;
+use crate::controller::repositories::notification::NotificationDeliveryRow;
+use crate::controller::repositories::notification::NotificationRuleRow;
+use crate::controller::repositories::project::ProjectRow;
+use crate::controller::repositories::project::ProjectSummaryRow;
+use crate::controller::repositories::project::WorkflowSummaryRow;
+use crate::controller::repositories::run::RunRow;
+use crate::controller::repositories::secret::SecretRow;
+use crate::controller::repositories::stash::StashRow;
+use crate::controller::services::cluster::ClusterView;
+use crate::controller::services::cluster::Member;
+use crate::controller::services::config::JobConfig;
+use crate::controller::services::config::Violation;
+use crate::controller::services::execution::ExecutionEdge;
+use crate::controller::services::execution::ExecutionGraph;
+use crate::controller::services::execution::ExecutionSummary;
+use crate::controller::services::notification::Notification;
+use crate::controller::services::sla::SlaNotification;
+use crate::controller::services::spec::Action;
+use crate::controller::services::spec::Change;
+use crate::controller::services::spec::JobChange;
+use crate::controller::services::spec::Plan;
+use crate::controller::services::update::Update;
+use crate::messages::log::LogLine;
+use crate::messages::log::LogStream;
+use crate::messages::run::RunPriority;
+use crate::messages::token::TokenState;
+use axum::extract::Extension;
+use axum::http::StatusCode;
+use axum::response::Html;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::Json;
+use utoipa::openapi::security::Http;
+use utoipa::openapi::security::HttpAuthScheme;
+use utoipa::openapi::security::SecurityScheme;
+use utoipa::openapi::ContentBuilder;
+use utoipa::openapi::Ref;
+use utoipa::openapi::ResponseBuilder;
+use utoipa::Modify;
+use utoipa::OpenApi;
+
+const BEARER: &str = "bearer";
+
+const DOCS: &str = r##"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8" />
+    <title>kotosiro API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+  </head>
+  <body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+    <script>
+      window.ui = SwaggerUIBundle({ url: "/api/openapi.json", dom_id: "#swagger-ui" });
+    </script>
+  </body>
+</html>
+"##;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::api::cluster::get,
+        super::api::project::get_by_name,
+        super::api::project::create,
+        super::api::project::apply,
+        super::api::project::get_summary_by_id,
+        super::api::project::delete,
+        super::api::project::list_workflows_by_id,
+        super::api::project::get_config_by_id,
+        super::api::project::patch_config,
+        super::api::project::get_config_schema_by_id,
+        super::api::project::put_config_schema,
+        super::api::secret::list,
+        super::api::secret::put,
+        super::api::secret::delete,
+        super::api::notification::list,
+        super::api::notification::put,
+        super::api::notification::delete,
+        super::api::notification::list_deliveries,
+        super::api::workflow::pause,
+        super::api::workflow::resume,
+        super::api::workflow::list_jobs,
+        super::api::workflow::run,
+        super::api::workflow::backfill,
+        super::api::workflow::list_executions,
+        super::api::execution::get,
+        super::api::execution::cancel,
+        super::api::execution::rerun,
+        super::api::job::get_config,
+        super::api::job::patch_config,
+        super::api::job::run,
+        super::api::run::list,
+        super::api::run::get_logs,
+        super::api::run::cancel,
+        super::api::event::stream,
+        super::internal::api::run::append_logs,
+        super::internal::api::run::update_state,
+        super::internal::api::run::get_config,
+        super::stash::list,
+        super::stash::get,
+        super::stash::put,
+        super::metrics::get,
+        super::health::healthz,
+        super::health::readyz,
+        super::health::version,
+        get,
+        docs,
+    ),
+    components(schemas(
+        Action,
+        BackfillJson,
+        Change,
+        ClusterView,
+        CreateJson,
+        ErrorBody,
+        ErrorCode,
+        ExecutionEdge,
+        ExecutionGraph,
+        ExecutionJson,
+        ExecutionNodeRow,
+        ExecutionRow,
+        ExecutionSummary,
+        JobChange,
+        JobConfig,
+        JobPage,
+        JobRow,
+        LeaseRow,
+        LogLine,
+        LogRow,
+        LogStream,
+        Member,
+        MemberRow,
+        Notification,
+        NotificationDeliveryRow,
+        NotificationEvent,
+        NotificationJson,
+        NotificationRuleRow,
+        PageOrder,
+        PageSort,
+        Plan,
+        Project,
+        ProjectPage,
+        ProjectRetention,
+        ProjectRow,
+        ProjectSummaryRow,
+        PutJson,
+        Run,
+        RunConfigJson,
+        RunJson,
+        RunPage,
+        RunPriority,
+        RunRow,
+        SecretRow,
+        SlaKind,
+        SlaNotification,
+        StashRow,
+        StateJson,
+        TokenState,
+        Update,
+        Violation,
+        WorkflowSla,
+        WorkflowSummaryPage,
+        WorkflowSummaryRow,
+    )),
+    modifiers(&Security, &Errors, &Aliases),
+    security(("bearer" = [])),
+)]
+pub struct ApiDoc;
+
+struct Security;
+
+impl Modify for Security {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                BEARER,
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+        }
+    }
+}
+
+// NOTE: Every handler returning InteractorError may also respond 401, 403 and 500 with the same body.
+struct Errors;
+
+impl Modify for Errors {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let response = ResponseBuilder::new()
+            .description("Structured error with a machine-readable code")
+            .content(
+                "application/json",
+                ContentBuilder::new()
+                    .schema(Ref::from_schema_name("ErrorBody"))
+                    .build(),
+            )
+            .build();
+        for item in openapi.paths.paths.values_mut() {
+            for operation in item.operations.values_mut() {
+                let public = operation
+                    .tags
+                    .as_ref()
+                    .map_or(false, |tags| tags.iter().any(|tag| tag == "health"));
+                if !public {
+                    operation
+                        .responses
+                        .responses
+                        .insert(String::from("default"), response.clone().into());
+                }
+            }
+        }
+    }
+}
+
+// NOTE: PUT /api/project is routed to the same handler as POST /api/project.
+struct Aliases;
+
+impl Modify for Aliases {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        use utoipa::openapi::PathItemType;
+        if let Some(item) = openapi.paths.paths.get_mut("/api/project") {
+            if let Some(mut operation) = item.operations.get(&PathItemType::Post).cloned() {
+                operation.operation_id = Some(String::from("put_project"));
+                item.operations.insert(PathItemType::Put, operation);
+            }
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/openapi.json",
+    operation_id = "get_openapi",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "OpenAPI document of the controller API", body = Object),
+    ),
+)]
+pub async fn get() -> Response {
+    (StatusCode::OK, Json(ApiDoc::openapi())).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/docs",
+    operation_id = "get_docs",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "Interactive API documentation", body = String, content_type = "text/html"),
+        (status = 404, description = "Documentation UI is disabled", body = ErrorBody),
+    ),
+)]
+pub async fn docs(Extension(state): Extension<SharedState>) -> Result<Response, InteractorError> {
+    if !state.controller.config.docs_ui {
+        return Err(InteractorError::NotFound);
+    }
+    Ok((StatusCode::OK, Html(DOCS)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::collections::BTreeSet;
+
+    const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+    // NOTE: Reads the routes from the source of interactors::route since axum routers cannot be enumerated.
+    fn routes() -> BTreeSet<(String, String)> {
+        let source = include_str!("../interactors.rs");
+        let start = source
+            .find("async fn route(")
+            .expect("route should be defined");
+        let end = source
+            .find("#[cfg(test)]")
+            .expect("tests should be defined");
+        let source = &source[start..end];
+        let mut routes = BTreeSet::new();
+        for (start, _) in source.match_indices(".route(") {
+            let rest = &source[start + ".route(".len()..];
+            let mut depth = 1;
+            let end = rest
+                .char_indices()
+                .find(|(_, c)| {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0
+                })
+                .map(|(i, _)| i)
+                .expect("route should be closed");
+            let route = &rest[..end];
+            let path = route
+                .split('"')
+                .nth(1)
+                .expect("route should have a path")
+                .split('/')
+                .map(|segment| match segment.strip_prefix(':') {
+                    Some(param) => format!("{{{}}}", param),
+                    None => segment.to_owned(),
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            for method in METHODS {
+                if route.contains(&format!("{}(self::", method)) {
+                    routes.insert((method.to_owned(), path.clone()));
+                }
+            }
+        }
+        routes
+    }
+
+    fn spec() -> Value {
+        serde_json::to_value(ApiDoc::openapi()).expect("spec should be serialized")
+    }
+
+    fn refs(value: &Value, found: &mut Vec<String>) {
+        match value {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    match (key.as_str(), value) {
+                        ("$ref", Value::String(reference)) => found.push(reference.clone()),
+                        _ => refs(value, found),
+                    }
+                }
+            }
+            Value::Array(values) => values.iter().for_each(|value| refs(value, found)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_routes_are_documented() {
+        let routes = routes();
+        assert!(routes.len() > 40);
+        let spec = spec();
+        let paths = spec["paths"].as_object().expect("paths should exist");
+        let documented: BTreeSet<(String, String)> = paths
+            .iter()
+            .flat_map(|(path, item)| {
+                METHODS
+                    .iter()
+                    .filter(|method| item.get(**method).is_some())
+                    .map(|method| (method.to_string(), path.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let missing: Vec<_> = routes.difference(&documented).collect();
+        assert!(missing.is_empty(), "undocumented routes: {:?}", missing);
+        let stale: Vec<_> = documented.difference(&routes).collect();
+        assert!(stale.is_empty(), "unrouted operations: {:?}", stale);
+    }
+
+    #[test]
+    fn test_operation_ids_are_unique() {
+        let spec = spec();
+        let mut ids = BTreeSet::new();
+        for item in spec["paths"]
+            .as_object()
+            .expect("paths should exist")
+            .values()
+        {
+            for method in METHODS {
+                if let Some(id) = item[method]["operationId"].as_str() {
+                    assert!(ids.insert(id.to_owned()), "duplicated operation id: {}", id);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_refs_are_resolved() {
+        let spec = spec();
+        let schemas = spec["components"]["schemas"]
+            .as_object()
+            .expect("schemas should exist");
+        let mut found = Vec::new();
+        refs(&spec, &mut found);
+        assert!(!found.is_empty());
+        for reference in found {
+            let name = reference
+                .strip_prefix("#/components/schemas/")
+                .expect("ref should point to a schema");
+            assert!(schemas.contains_key(name), "unresolved ref: {}", reference);
+        }
+    }
+}
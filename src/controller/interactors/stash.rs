@@ -0,0 +1,205 @@
+use crate::controller::entities::run::RunId;
+use crate::controller::entities::stash::Stash;
+use crate::controller::entities::stash::StashName;
+use crate::controller::interactors::InteractorError;
+use crate::controller::interactors::SharedState;
+use crate::controller::services::run::RunService;
+use crate::controller::services::stash::StashService;
+use crate::infra::jwt;
+use crate::infra::opa::Token;
+use axum::body::Bytes;
+use axum::extract::Extension;
+use axum::extract::Json;
+use axum::extract::Path;
+use axum::http::header;
+use axum::http::HeaderMap;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use chrono::Duration;
+use chrono::Utc;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+use uuid::Uuid;
+
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+fn authenticate(state: &SharedState, token: &Token) -> Result<RunId, InteractorError> {
+    let keys = match &state.controller.keys {
+        Some(keys) => keys,
+        None => {
+            warn!("stash is disabled because jwt secret is not configured");
+            return Err(InteractorError::Unauthorized);
+        }
+    };
+    let token = match token {
+        Token::Bearer(token) => token,
+        Token::None => {
+            warn!("stash token is missing");
+            return Err(InteractorError::Unauthorized);
+        }
+    };
+    match jwt::verify_stash(keys, token).map(RunId::try_from) {
+        Ok(Ok(id)) => Ok(id),
+        _ => {
+            warn!("stash token is invalid");
+            Err(InteractorError::Unauthorized)
+        }
+    }
+}
+
+fn parse(id: String, name: Option<String>) -> Result<(RunId, Option<StashName>), InteractorError> {
+    let id = if let Ok(id) = RunId::try_from(id) {
+        id
+    } else {
+        error!("run id must be uuid v4");
+        return Err(InteractorError::BadRequest);
+    };
+    match name.map(StashName::new).transpose() {
+        Ok(name) => Ok((id, name)),
+        Err(_) => {
+            error!("stash name must consist of alphanumerics, '_', '-' and '.'");
+            Err(InteractorError::BadRequest)
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/stash/run/{id}/{name}",
+    operation_id = "put_stash",
+    tag = "stash",
+    params(("id" = Uuid, Path, description = "Run id"), ("name" = String, Path, description = "Stash name")),
+    request_body(content = Vec<u8>, description = "Value to stash", content_type = "application/octet-stream"),
+    responses(
+        (status = 204, description = "Value was stashed"),
+        (status = 404, description = "Run was not found", body = ErrorBody),
+        (status = 422, description = "Stash is invalid", body = ErrorBody),
+    ),
+)]
+pub async fn put(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path((id, name)): Path<(String, String)>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, InteractorError> {
+    let writer = authenticate(&state, &token)?;
+    let (id, name) = parse(id, Some(name))?;
+    let name = name.expect("stash name should have already been parsed");
+    if writer != id {
+        warn!(
+            r#"run id: "{}" must not write stash of run id: "{}""#,
+            writer.as_uuid(),
+            id.as_uuid()
+        );
+        return Err(InteractorError::Forbidden);
+    }
+    if RunService::get_by_id(&state.controller.db_pool, &id)
+        .await?
+        .is_none()
+    {
+        return Err(InteractorError::NotFound);
+    }
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or(DEFAULT_CONTENT_TYPE);
+    if content_type.starts_with("application/json") {
+        if let Err(e) = serde_json::from_slice::<serde_json::Value>(&body) {
+            error!("stash value must be valid JSON: {}", e);
+            return Err(InteractorError::invalid("body", &e.into()));
+        }
+    }
+    let retention = Duration::seconds(state.controller.config.stash_retention_secs as i64);
+    let stash = match Stash::new(
+        Uuid::new_v4().to_string(),
+        id.as_uuid().to_string(),
+        name.to_string(),
+        content_type.to_owned(),
+        body.len() as i64,
+        Utc::now() + retention,
+    ) {
+        Ok(stash) => stash,
+        Err(e) => {
+            error!("failed to validate stash: {}", e);
+            return Err(InteractorError::invalid("", &e));
+        }
+    };
+    StashService::put(
+        &state.controller.db_pool,
+        &stash,
+        &body,
+        state.controller.stash.as_ref(),
+    )
+    .await?;
+    info!(r#"stashed "{}""#, stash.key());
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/stash/run/{id}/{name}",
+    operation_id = "get_stash",
+    tag = "stash",
+    params(("id" = Uuid, Path, description = "Run id"), ("name" = String, Path, description = "Stash name")),
+    responses(
+        (status = 200, description = "Stashed value with its content type", body = Vec<u8>, content_type = "application/octet-stream"),
+        (status = 404, description = "Stash was not found", body = ErrorBody),
+    ),
+)]
+pub async fn get(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path((id, name)): Path<(String, String)>,
+) -> Result<Response, InteractorError> {
+    let reader = authenticate(&state, &token)?;
+    let (id, name) = parse(id, Some(name))?;
+    let name = name.expect("stash name should have already been parsed");
+    if !StashService::is_shared(&state.controller.db_pool, &id, &reader).await? {
+        warn!("failed to get stash");
+        return Err(InteractorError::Forbidden);
+    }
+    match StashService::get(
+        &state.controller.db_pool,
+        &id,
+        &name,
+        state.controller.stash.as_ref(),
+    )
+    .await?
+    {
+        Some((row, value)) => Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, row.content_type)],
+            value,
+        )
+            .into_response()),
+        None => Err(InteractorError::NotFound),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/stash/run/{id}",
+    operation_id = "list_stashes",
+    tag = "stash",
+    params(("id" = Uuid, Path, description = "Run id")),
+    responses(
+        (status = 200, description = "Stashes of the run", body = [StashRow]),
+    ),
+)]
+pub async fn list(
+    token: Token,
+    Extension(state): Extension<SharedState>,
+    Path(id): Path<String>,
+) -> Result<Response, InteractorError> {
+    let reader = authenticate(&state, &token)?;
+    let (id, _) = parse(id, None)?;
+    if !StashService::is_shared(&state.controller.db_pool, &id, &reader).await? {
+        warn!("failed to list stashes");
+        return Err(InteractorError::Forbidden);
+    }
+    let rows = StashService::list_by_run_id(&state.controller.db_pool, &id).await?;
+    Ok((StatusCode::OK, Json(rows)).into_response())
+}
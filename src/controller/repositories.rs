@@ -1,4 +1,13 @@
+pub mod cluster;
+pub mod execution;
 pub mod job;
+pub mod log;
+pub mod notification;
+pub mod outbox;
 pub mod project;
+pub mod retention;
 pub mod run;
+pub mod secret;
+pub mod sla;
+pub mod stash;
 pub mod workflow;
@@ -0,0 +1,290 @@
+use crate::infra::postgres::PgAcquire;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
+use sqlx::postgres::PgQueryResult;
+use std::time::Duration;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
+pub struct MemberRow {
+    pub id: Uuid,
+    pub addr: String,
+    pub started_at: DateTime<Utc>,
+    pub heartbeat_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
+pub struct LeaseRow {
+    pub name: String,
+    pub holder: Uuid,
+    pub acquired_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait ClusterRepository: Send + Sync + 'static {
+    async fn heartbeat(
+        &self,
+        id: &Uuid,
+        addr: &str,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn leave(
+        &self,
+        id: &Uuid,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn list_members(
+        &self,
+        ttl: &Duration,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<MemberRow>>;
+
+    async fn prune_members(
+        &self,
+        ttl: &Duration,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn acquire_lease(
+        &self,
+        name: &str,
+        holder: &Uuid,
+        ttl: &Duration,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<bool>;
+
+    async fn release_lease(
+        &self,
+        name: &str,
+        holder: &Uuid,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn get_lease(
+        &self,
+        name: &str,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<LeaseRow>>;
+}
+
+pub struct PgClusterRepository;
+
+#[async_trait]
+impl ClusterRepository for PgClusterRepository {
+    #[instrument(name = "controller.heartbeat", skip_all)]
+    async fn heartbeat(
+        &self,
+        id: &Uuid,
+        addr: &str,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "INSERT INTO controller (
+                 id,
+                 addr
+             ) VALUES ($1, $2)
+             ON CONFLICT(id)
+             DO UPDATE
+             SET addr = $2,
+                 heartbeat_at = CURRENT_TIMESTAMP",
+        )
+        .bind(id)
+        .bind(addr)
+        .execute(&mut *conn)
+        .await
+        .context(format!(r#"failed to upsert "{}" into [controller]"#, id))
+    }
+
+    #[instrument(name = "controller.leave", skip_all)]
+    async fn leave(
+        &self,
+        id: &Uuid,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "DELETE FROM controller
+             WHERE id = $1",
+        )
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .context(format!(r#"failed to delete "{}" from [controller]"#, id))
+    }
+
+    #[instrument(name = "controller.list_members", skip_all)]
+    async fn list_members(
+        &self,
+        ttl: &Duration,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<MemberRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<MemberRow> = sqlx::query_as::<_, MemberRow>(
+            "SELECT
+                 id,
+                 addr,
+                 started_at,
+                 heartbeat_at
+             FROM controller
+             WHERE heartbeat_at > CURRENT_TIMESTAMP - $1 * INTERVAL '1 second'
+             ORDER BY started_at",
+        )
+        .bind(ttl.as_secs_f64())
+        .fetch_all(&mut *conn)
+        .await
+        .context("failed to list members from [controller]")?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "controller.prune_members", skip_all)]
+    async fn prune_members(
+        &self,
+        ttl: &Duration,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "DELETE FROM controller
+             WHERE heartbeat_at < CURRENT_TIMESTAMP - $1 * INTERVAL '1 second'",
+        )
+        .bind(ttl.as_secs_f64())
+        .execute(&mut *conn)
+        .await
+        .context("failed to prune members from [controller]")
+    }
+
+    #[instrument(name = "lease.acquire", skip_all)]
+    async fn acquire_lease(
+        &self,
+        name: &str,
+        holder: &Uuid,
+        ttl: &Duration,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<bool> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let done = sqlx::query(
+            "INSERT INTO lease (
+                 name,
+                 holder,
+                 expires_at
+             ) VALUES ($1, $2, CURRENT_TIMESTAMP + $3 * INTERVAL '1 second')
+             ON CONFLICT(name)
+             DO UPDATE
+             SET holder = $2,
+                 acquired_at = CASE
+                     WHEN lease.holder = $2 THEN lease.acquired_at
+                     ELSE CURRENT_TIMESTAMP
+                 END,
+                 expires_at = CURRENT_TIMESTAMP + $3 * INTERVAL '1 second'
+             WHERE lease.holder = $2 OR lease.expires_at < CURRENT_TIMESTAMP",
+        )
+        .bind(name)
+        .bind(holder)
+        .bind(ttl.as_secs_f64())
+        .execute(&mut *conn)
+        .await
+        .context(format!(r#"failed to acquire "{}" in [lease]"#, name))?;
+        Ok(done.rows_affected() == 1)
+    }
+
+    #[instrument(name = "lease.release", skip_all)]
+    async fn release_lease(
+        &self,
+        name: &str,
+        holder: &Uuid,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "DELETE FROM lease
+             WHERE name = $1 AND holder = $2",
+        )
+        .bind(name)
+        .bind(holder)
+        .execute(&mut *conn)
+        .await
+        .context(format!(r#"failed to release "{}" in [lease]"#, name))
+    }
+
+    #[instrument(name = "lease.get", skip_all)]
+    async fn get_lease(
+        &self,
+        name: &str,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<LeaseRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let row: Option<LeaseRow> = sqlx::query_as::<_, LeaseRow>(
+            "SELECT
+                 name,
+                 holder,
+                 acquired_at,
+                 expires_at
+             FROM lease
+             WHERE name = $1 AND expires_at > CURRENT_TIMESTAMP",
+        )
+        .bind(name)
+        .fetch_optional(&mut *conn)
+        .await
+        .context(format!(r#"failed to select "{}" from [lease]"#, name))?;
+        Ok(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_acquire_lease(pool: PgPool) -> Result<()> {
+        let repo = PgClusterRepository;
+        let ttl = Duration::from_secs(10);
+        let (leader, follower) = (Uuid::new_v4(), Uuid::new_v4());
+        repo.heartbeat(&leader, "127.0.0.1:8080", &pool).await?;
+        repo.heartbeat(&follower, "127.0.0.1:8081", &pool).await?;
+        assert_eq!(repo.list_members(&ttl, &pool).await?.len(), 2);
+        assert!(repo.acquire_lease("leader", &leader, &ttl, &pool).await?);
+        assert!(!repo.acquire_lease("leader", &follower, &ttl, &pool).await?);
+        assert!(repo.acquire_lease("leader", &leader, &ttl, &pool).await?);
+        let lease = repo
+            .get_lease("leader", &pool)
+            .await?
+            .expect("lease should be found");
+        assert_eq!(lease.holder, leader);
+        repo.release_lease("leader", &leader, &pool).await?;
+        repo.leave(&leader, &pool).await?;
+        assert!(repo.acquire_lease("leader", &follower, &ttl, &pool).await?);
+        assert_eq!(repo.list_members(&ttl, &pool).await?.len(), 1);
+        Ok(())
+    }
+}
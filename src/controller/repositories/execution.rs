@@ -0,0 +1,249 @@
+use crate::controller::entities::execution::Execution;
+use crate::controller::entities::execution::ExecutionId;
+use crate::controller::entities::workflow::WorkflowId;
+use crate::infra::postgres::PgAcquire;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
+use sqlx::postgres::PgQueryResult;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
+pub struct ExecutionRow {
+    pub id: Uuid,
+    pub workflow_id: Uuid,
+    pub priority: String,
+    pub triggered_at: DateTime<Utc>,
+    #[serde(skip)]
+    pub states: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
+pub struct ExecutionNodeRow {
+    pub job_id: Uuid,
+    pub job_name: String,
+    pub depends_on: Vec<String>,
+    pub run_id: Uuid,
+    pub state: String,
+    pub attempt: i32,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[async_trait]
+pub trait ExecutionRepository: Send + Sync + 'static {
+    async fn create(
+        &self,
+        execution: &Execution,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn get_by_id(
+        &self,
+        id: &ExecutionId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<ExecutionRow>>;
+
+    async fn lock_by_id(
+        &self,
+        id: &ExecutionId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<Uuid>>;
+
+    async fn list_by_workflow_id(
+        &self,
+        workflow_id: &WorkflowId,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<ExecutionRow>>;
+
+    async fn list_nodes_by_id(
+        &self,
+        id: &ExecutionId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<ExecutionNodeRow>>;
+}
+
+pub struct PgExecutionRepository;
+
+#[async_trait]
+impl ExecutionRepository for PgExecutionRepository {
+    #[instrument(name = "workflow_run.create", skip_all)]
+    async fn create(
+        &self,
+        execution: &Execution,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "INSERT INTO workflow_run (
+                 id,
+                 workflow_id,
+                 priority,
+                 triggered_at
+             ) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(execution.id())
+        .bind(execution.workflow_id())
+        .bind(execution.priority())
+        .bind(execution.triggered_at())
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to insert "{}" into [workflow_run]"#,
+            execution.id().as_uuid()
+        ))
+    }
+
+    #[instrument(name = "workflow_run.get_by_id", skip_all)]
+    async fn get_by_id(
+        &self,
+        id: &ExecutionId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<ExecutionRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let row: Option<ExecutionRow> = sqlx::query_as::<_, ExecutionRow>(
+            "SELECT
+                 workflow_run.id,
+                 workflow_run.workflow_id,
+                 workflow_run.priority,
+                 workflow_run.triggered_at,
+                 ARRAY(
+                     SELECT DISTINCT ON (run.job_id) run.state
+                     FROM run
+                     WHERE run.execution_id = workflow_run.id
+                     ORDER BY run.job_id, run.attempt DESC, run.created_at DESC
+                 ) AS states,
+                 workflow_run.created_at,
+                 workflow_run.updated_at
+             FROM workflow_run
+             WHERE workflow_run.id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to select "{}" from [workflow_run]"#,
+            id.as_uuid()
+        ))?;
+        Ok(row)
+    }
+
+    // NOTE: Only the row is locked, run states have to be read by a later statement to see concurrent commits.
+    #[instrument(name = "workflow_run.lock_by_id", skip_all)]
+    async fn lock_by_id(
+        &self,
+        id: &ExecutionId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<Uuid>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let id: Option<Uuid> = sqlx::query_scalar(
+            "SELECT id
+             FROM workflow_run
+             WHERE id = $1
+             FOR UPDATE",
+        )
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to lock "{}" in [workflow_run]"#,
+            id.as_uuid()
+        ))?;
+        Ok(id)
+    }
+
+    #[instrument(name = "workflow_run.list_by_workflow_id", skip_all)]
+    async fn list_by_workflow_id(
+        &self,
+        workflow_id: &WorkflowId,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<ExecutionRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<ExecutionRow> = sqlx::query_as::<_, ExecutionRow>(
+            "SELECT
+                 workflow_run.id,
+                 workflow_run.workflow_id,
+                 workflow_run.priority,
+                 workflow_run.triggered_at,
+                 ARRAY(
+                     SELECT DISTINCT ON (run.job_id) run.state
+                     FROM run
+                     WHERE run.execution_id = workflow_run.id
+                     ORDER BY run.job_id, run.attempt DESC, run.created_at DESC
+                 ) AS states,
+                 workflow_run.created_at,
+                 workflow_run.updated_at
+             FROM workflow_run
+             WHERE workflow_run.workflow_id = $1
+             ORDER BY workflow_run.triggered_at DESC
+             LIMIT $2",
+        )
+        .bind(workflow_id)
+        .bind(limit.unwrap_or(&100))
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list executions of "{}" from [workflow_run]"#,
+            workflow_id.as_uuid()
+        ))?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "workflow_run.list_nodes_by_id", skip_all)]
+    async fn list_nodes_by_id(
+        &self,
+        id: &ExecutionId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<ExecutionNodeRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<ExecutionNodeRow> = sqlx::query_as::<_, ExecutionNodeRow>(
+            "SELECT *
+             FROM (
+                 SELECT DISTINCT ON (run.job_id)
+                     job.id AS job_id,
+                     job.name AS job_name,
+                     job.depends_on,
+                     run.id AS run_id,
+                     run.state,
+                     run.attempt,
+                     run.started_at,
+                     run.finished_at
+                 FROM run
+                 JOIN job ON job.id = run.job_id
+                 WHERE run.execution_id = $1
+                 ORDER BY run.job_id, run.attempt DESC, run.created_at DESC
+             ) AS latest
+             ORDER BY job_name",
+        )
+        .bind(id)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list nodes of "{}" from [workflow_run]"#,
+            id.as_uuid()
+        ))?;
+        Ok(rows)
+    }
+}
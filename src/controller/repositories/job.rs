@@ -1,15 +1,22 @@
 use crate::controller::entities::job::Job;
 use crate::controller::entities::job::JobId;
+use crate::controller::entities::page::Keyset;
+use crate::controller::entities::page::Page;
+use crate::controller::entities::page::Pagination;
+use crate::controller::entities::project::ProjectId;
+use crate::controller::entities::workflow::WorkflowId;
 use crate::infra::postgres::PgAcquire;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::DateTime;
 use chrono::Utc;
+use serde_json::Value as Json;
 use sqlx::postgres::PgQueryResult;
+use tracing::instrument;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
 pub struct JobRow {
     pub id: Uuid,
     pub name: String,
@@ -18,10 +25,69 @@ pub struct JobRow {
     pub image: String,
     pub args: Vec<String>,
     pub envs: Vec<String>,
+    pub depends_on: Vec<String>,
+    #[schema(value_type = Option<Object>)]
+    pub config: Option<Json>,
+    pub version: i64,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+impl Keyset for JobRow {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+pub struct JobConfigRow {
+    pub project_id: Uuid,
+    pub project_config: Json,
+    pub config_schema: Option<Json>,
+    pub config: Json,
+    pub version: i64,
+}
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+pub struct JobOverrideRow {
+    pub id: Uuid,
+    pub name: String,
+    pub workflow_name: String,
+    pub config: Json,
+}
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+pub struct JobOwnerRow {
+    pub workflow_id: Uuid,
+    pub project_id: Uuid,
+}
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+pub struct JobContextRow {
+    pub id: Uuid,
+    pub name: String,
+    pub args: Vec<String>,
+    pub envs: Vec<String>,
+    pub config: Json,
+    pub workflow_id: Uuid,
+    pub workflow_name: String,
+    pub project_id: Uuid,
+    pub project_name: String,
+    pub project_config: Json,
+}
+
 #[async_trait]
 pub trait JobRepository: Send + Sync + 'static {
     async fn create(
@@ -36,17 +102,68 @@ pub trait JobRepository: Send + Sync + 'static {
         executor: impl PgAcquire<'_> + 'async_trait,
     ) -> Result<PgQueryResult>;
 
+    async fn soft_delete(
+        &self,
+        id: &JobId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
     async fn get_by_id(
         &self,
         id: &JobId,
         executor: impl PgAcquire<'_> + 'async_trait,
     ) -> Result<Option<JobRow>>;
+
+    async fn list_by_workflow_id(
+        &self,
+        workflow_id: &WorkflowId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<JobRow>>;
+
+    async fn page_by_workflow_id(
+        &self,
+        workflow_id: &WorkflowId,
+        pagination: &Pagination,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Page<JobRow>>;
+
+    async fn get_config_by_id(
+        &self,
+        id: &JobId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<JobConfigRow>>;
+
+    async fn update_config(
+        &self,
+        id: &JobId,
+        config: &Json,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn list_overrides_by_project_id(
+        &self,
+        project_id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<JobOverrideRow>>;
+
+    async fn get_owner_by_id(
+        &self,
+        id: &JobId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<JobOwnerRow>>;
+
+    async fn get_context_by_id(
+        &self,
+        id: &JobId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<JobContextRow>>;
 }
 
 pub struct PgJobRepository;
 
 #[async_trait]
 impl JobRepository for PgJobRepository {
+    #[instrument(name = "job.create", skip_all)]
     async fn create(
         &self,
         job: &Job,
@@ -64,14 +181,36 @@ impl JobRepository for PgJobRepository {
                  threshold,
                  image,
                  args,
-                 envs
-             ) VALUES ($1, $2, $3, $4, $5, $6, $7)
-             ON CONFLICT(name, workflow_id)
+                 envs,
+                 depends_on,
+                 config
+             ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+             ON CONFLICT(workflow_id, name) WHERE deleted_at IS NULL
              DO UPDATE
              SET threshold = $4,
                  image = $5,
                  args = $6,
-                 envs = $7",
+                 envs = $7,
+                 depends_on = $8,
+                 config = COALESCE($9, job.config),
+                 deleted_at = NULL,
+                 version = job.version + 1,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE (
+                 job.threshold,
+                 job.image,
+                 job.args,
+                 job.envs,
+                 job.depends_on,
+                 job.config
+             ) IS DISTINCT FROM (
+                 EXCLUDED.threshold,
+                 EXCLUDED.image,
+                 EXCLUDED.args,
+                 EXCLUDED.envs,
+                 EXCLUDED.depends_on,
+                 COALESCE(EXCLUDED.config, job.config)
+             )",
         )
         .bind(job.id())
         .bind(job.name())
@@ -80,6 +219,8 @@ impl JobRepository for PgJobRepository {
         .bind(job.image())
         .bind(job.args())
         .bind(job.envs())
+        .bind(job.depends_on())
+        .bind(job.config())
         .execute(&mut *conn)
         .await
         .context(format!(
@@ -88,6 +229,7 @@ impl JobRepository for PgJobRepository {
         ))
     }
 
+    #[instrument(name = "job.delete", skip_all)]
     async fn delete(
         &self,
         id: &JobId,
@@ -107,6 +249,32 @@ impl JobRepository for PgJobRepository {
         .context(format!(r#"failed to delete "{}" from [job]"#, id.as_uuid()))
     }
 
+    #[instrument(name = "job.soft_delete", skip_all)]
+    async fn soft_delete(
+        &self,
+        id: &JobId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "UPDATE job
+             SET deleted_at = CURRENT_TIMESTAMP,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to soft delete "{}" from [job]"#,
+            id.as_uuid()
+        ))
+    }
+
+    #[instrument(name = "job.get_by_id", skip_all)]
     async fn get_by_id(
         &self,
         id: &JobId,
@@ -125,10 +293,13 @@ impl JobRepository for PgJobRepository {
                  image,
                  args,
                  envs,
+                 depends_on,
+                 config,
+                 version,
                  created_at,
                  updated_at
              FROM job
-             WHERE id = $1",
+             WHERE id = $1 AND deleted_at IS NULL",
         )
         .bind(id)
         .fetch_optional(&mut *conn)
@@ -136,11 +307,261 @@ impl JobRepository for PgJobRepository {
         .context(format!(r#"failed to select "{}" from [job]"#, id.as_uuid()))?;
         Ok(row)
     }
+
+    #[instrument(name = "job.list_by_workflow_id", skip_all)]
+    async fn list_by_workflow_id(
+        &self,
+        workflow_id: &WorkflowId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<JobRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<JobRow> = sqlx::query_as::<_, JobRow>(
+            "SELECT
+                 id,
+                 name,
+                 workflow_id,
+                 threshold,
+                 image,
+                 args,
+                 envs,
+                 depends_on,
+                 config,
+                 version,
+                 created_at,
+                 updated_at
+             FROM job
+             WHERE workflow_id = $1 AND deleted_at IS NULL
+             ORDER BY name",
+        )
+        .bind(workflow_id)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list jobs of "{}" from [job]"#,
+            workflow_id.as_uuid()
+        ))?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "job.page_by_workflow_id", skip_all)]
+    async fn page_by_workflow_id(
+        &self,
+        workflow_id: &WorkflowId,
+        pagination: &Pagination,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Page<JobRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let sql = format!(
+            "SELECT
+                 id,
+                 name,
+                 workflow_id,
+                 threshold,
+                 image,
+                 args,
+                 envs,
+                 depends_on,
+                 config,
+                 version,
+                 created_at,
+                 updated_at
+             FROM job
+             WHERE
+                 workflow_id = $1
+                 AND deleted_at IS NULL
+                 AND ($2::VARCHAR IS NULL OR name LIKE $2)
+                 AND {}
+             {}
+             LIMIT $5",
+            pagination.seek("job", 3),
+            pagination.order_by("job"),
+        );
+        let rows: Vec<JobRow> = sqlx::query_as::<_, JobRow>(&sql)
+            .bind(workflow_id)
+            .bind(pagination.pattern())
+            .bind(pagination.after_key())
+            .bind(pagination.after_id())
+            .bind(pagination.fetch_limit())
+            .fetch_all(&mut *conn)
+            .await
+            .context(format!(
+                r#"failed to list {} job(s) of "{}" from [job]"#,
+                pagination.limit(),
+                workflow_id.as_uuid()
+            ))?;
+        Page::new(rows, pagination)
+    }
+
+    #[instrument(name = "job.get_config_by_id", skip_all)]
+    async fn get_config_by_id(
+        &self,
+        id: &JobId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<JobConfigRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let row: Option<JobConfigRow> = sqlx::query_as::<_, JobConfigRow>(
+            "SELECT
+                 project.id AS project_id,
+                 COALESCE(project.config, '{}'::jsonb) AS project_config,
+                 project.config_schema,
+                 COALESCE(job.config, '{}'::jsonb) AS config,
+                 job.version
+             FROM job
+             JOIN workflow ON workflow.id = job.workflow_id
+             JOIN project ON project.id = workflow.project_id
+             WHERE job.id = $1 AND job.deleted_at IS NULL",
+        )
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to select config of "{}" from [job]"#,
+            id.as_uuid()
+        ))?;
+        Ok(row)
+    }
+
+    #[instrument(name = "job.list_overrides_by_project_id", skip_all)]
+    async fn list_overrides_by_project_id(
+        &self,
+        project_id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<JobOverrideRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<JobOverrideRow> = sqlx::query_as::<_, JobOverrideRow>(
+            "SELECT
+                 job.id,
+                 job.name,
+                 workflow.name AS workflow_name,
+                 job.config
+             FROM job
+             JOIN workflow ON workflow.id = job.workflow_id
+             WHERE workflow.project_id = $1
+             AND job.config IS NOT NULL
+             AND job.deleted_at IS NULL
+             ORDER BY workflow.name, job.name",
+        )
+        .bind(project_id)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list config overrides of project "{}" from [job]"#,
+            project_id.as_uuid()
+        ))?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "job.update_config", skip_all)]
+    async fn update_config(
+        &self,
+        id: &JobId,
+        config: &Json,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "UPDATE job
+             SET config = $2,
+                 version = version + 1,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(config)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to update config of "{}" in [job]"#,
+            id.as_uuid()
+        ))
+    }
+
+    // NOTE: Soft-deleted jobs are included so that their deletion can still be attributed to a workflow.
+    #[instrument(name = "job.get_owner_by_id", skip_all)]
+    async fn get_owner_by_id(
+        &self,
+        id: &JobId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<JobOwnerRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let row: Option<JobOwnerRow> = sqlx::query_as::<_, JobOwnerRow>(
+            "SELECT
+                 workflow.id AS workflow_id,
+                 workflow.project_id
+             FROM job
+             JOIN workflow ON workflow.id = job.workflow_id
+             WHERE job.id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to select owner of "{}" from [job]"#,
+            id.as_uuid()
+        ))?;
+        Ok(row)
+    }
+
+    #[instrument(name = "job.get_context_by_id", skip_all)]
+    async fn get_context_by_id(
+        &self,
+        id: &JobId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<JobContextRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let row: Option<JobContextRow> = sqlx::query_as::<_, JobContextRow>(
+            "SELECT
+                 job.id,
+                 job.name,
+                 job.args,
+                 job.envs,
+                 COALESCE(job.config, '{}'::jsonb) AS config,
+                 workflow.id AS workflow_id,
+                 workflow.name AS workflow_name,
+                 project.id AS project_id,
+                 project.name AS project_name,
+                 COALESCE(project.config, '{}'::jsonb) AS project_config
+             FROM job
+             JOIN workflow ON workflow.id = job.workflow_id
+             JOIN project ON project.id = workflow.project_id
+             WHERE job.id = $1 AND job.deleted_at IS NULL",
+        )
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to select context of "{}" from [job]"#,
+            id.as_uuid()
+        ))?;
+        Ok(row)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::controller::entities::job::JobImage;
     use crate::controller::entities::project::Project;
     use crate::controller::entities::project::ProjectId;
     use crate::controller::entities::workflow::Workflow;
@@ -262,4 +683,131 @@ mod tests {
             .expect("rollback should be done properly");
         Ok(())
     }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_soft_delete_and_get_owner_by_id(pool: PgPool) -> Result<()> {
+        let repo = PgJobRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let project = create_project(&mut tx)
+            .await
+            .expect("new project should be created");
+        let workflow = create_workflow(project.id(), &mut tx)
+            .await
+            .expect("new workflow should be created");
+        let job = create_job(workflow.id(), &mut tx)
+            .await
+            .expect("new job should be created");
+        repo.soft_delete(job.id(), &mut tx)
+            .await
+            .expect("job should be deleted");
+        assert!(repo.get_context_by_id(job.id(), &mut tx).await?.is_none());
+        let owner = repo
+            .get_owner_by_id(job.id(), &mut tx)
+            .await?
+            .expect("deleted job should still have an owner");
+        assert_eq!(&owner.workflow_id, workflow.id().as_uuid());
+        assert_eq!(&owner.project_id, project.id().as_uuid());
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_create_and_update_config_bump_version(pool: PgPool) -> Result<()> {
+        let repo = PgJobRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let project = create_project(&mut tx)
+            .await
+            .expect("new project should be created");
+        let workflow = create_workflow(project.id(), &mut tx)
+            .await
+            .expect("new workflow should be created");
+        let mut job = create_job(workflow.id(), &mut tx)
+            .await
+            .expect("new job should be created");
+        let done = repo
+            .create(&job, &mut tx)
+            .await
+            .expect("unchanged job should be upserted");
+        assert_eq!(0, done.rows_affected());
+        repo.update_config(job.id(), &serde_json::json!({ "retries": 1 }), &mut tx)
+            .await
+            .expect("job config should be updated");
+        let fetched = repo
+            .get_config_by_id(job.id(), &mut tx)
+            .await?
+            .expect("updated job should be found");
+        assert_eq!(2, fetched.version);
+        job.set_image(JobImage::new(testutils::rand::string(20))?);
+        repo.create(&job, &mut tx)
+            .await
+            .expect("changed job should be upserted");
+        let fetched = repo
+            .get_by_id(job.id(), &mut tx)
+            .await?
+            .expect("upserted job should be found");
+        assert_eq!(&fetched.image, job.image().as_str());
+        assert_eq!(3, fetched.version);
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_create_and_list_by_workflow_id(pool: PgPool) -> Result<()> {
+        let repo = PgJobRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let project = create_project(&mut tx)
+            .await
+            .expect("new project should be created");
+        let workflow = create_workflow(project.id(), &mut tx)
+            .await
+            .expect("new workflow should be created");
+        let upstream = create_job(workflow.id(), &mut tx)
+            .await
+            .expect("new job should be created");
+        let mut job = create_job(workflow.id(), &mut tx)
+            .await
+            .expect("new job should be created");
+        job.set_depends_on(vec![upstream.name().clone()]);
+        repo.create(&job, &mut tx)
+            .await
+            .expect("job should be updated");
+        let fetched = repo
+            .list_by_workflow_id(workflow.id(), &mut tx)
+            .await
+            .expect("inserted jobs should be listed");
+        assert_eq!(fetched.len(), 2);
+        let row = fetched
+            .iter()
+            .find(|row| &row.id == job.id().as_uuid())
+            .expect("updated job should be listed");
+        assert_eq!(row.depends_on, vec![upstream.name().to_string()]);
+        repo.soft_delete(upstream.id(), &mut tx)
+            .await
+            .expect("job should be soft deleted");
+        let fetched = repo
+            .list_by_workflow_id(workflow.id(), &mut tx)
+            .await
+            .expect("remaining jobs should be listed");
+        assert_eq!(fetched.len(), 1);
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
 }
@@ -0,0 +1,294 @@
+use crate::controller::entities::run::RunId;
+use crate::infra::postgres::PgAcquire;
+use crate::messages::log::LogLine;
+use crate::messages::log::LogStream;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
+use sqlx::postgres::PgQueryResult;
+use tracing::instrument;
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
+pub struct LogRow {
+    pub seq: i64,
+    pub stream: String,
+    pub line: String,
+    pub emitted_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait LogRepository: Send + Sync + 'static {
+    async fn create(
+        &self,
+        run_id: &RunId,
+        lines: &[LogLine],
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn list_by_run_id(
+        &self,
+        run_id: &RunId,
+        stream: Option<&LogStream>,
+        from: Option<&i64>,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<LogRow>>;
+
+    async fn tail_by_run_id(
+        &self,
+        run_id: &RunId,
+        stream: Option<&LogStream>,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<LogRow>>;
+}
+
+pub struct PgLogRepository;
+
+#[async_trait]
+impl LogRepository for PgLogRepository {
+    #[instrument(name = "run_log.create", skip_all)]
+    async fn create(
+        &self,
+        run_id: &RunId,
+        lines: &[LogLine],
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let seqs: Vec<i64> = lines.iter().map(|l| l.seq).collect();
+        let streams: Vec<&str> = lines.iter().map(|l| l.stream.as_ref()).collect();
+        let texts: Vec<&str> = lines.iter().map(|l| l.line.as_str()).collect();
+        let emitted_ats: Vec<DateTime<Utc>> = lines.iter().map(|l| l.emitted_at).collect();
+        sqlx::query(
+            "INSERT INTO run_log (
+                 run_id,
+                 seq,
+                 stream,
+                 line,
+                 emitted_at
+             )
+             SELECT $1, *
+             FROM UNNEST($2::BIGINT[], $3::VARCHAR[], $4::TEXT[], $5::TIMESTAMPTZ[])
+             ON CONFLICT(run_id, seq)
+             DO NOTHING",
+        )
+        .bind(run_id)
+        .bind(seqs)
+        .bind(streams)
+        .bind(texts)
+        .bind(emitted_ats)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to insert logs of "{}" into [run_log]"#,
+            run_id.as_uuid()
+        ))
+    }
+
+    #[instrument(name = "run_log.list_by_run_id", skip_all)]
+    async fn list_by_run_id(
+        &self,
+        run_id: &RunId,
+        stream: Option<&LogStream>,
+        from: Option<&i64>,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<LogRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<LogRow> = sqlx::query_as::<_, LogRow>(
+            "SELECT
+                 seq,
+                 stream,
+                 line,
+                 emitted_at
+             FROM run_log
+             WHERE run_id = $1
+             AND ($2::VARCHAR IS NULL OR stream = $2)
+             AND seq >= $3
+             ORDER BY seq
+             LIMIT $4",
+        )
+        .bind(run_id)
+        .bind(stream)
+        .bind(from.unwrap_or(&0))
+        .bind(limit.unwrap_or(&1000))
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list logs of "{}" from [run_log]"#,
+            run_id.as_uuid()
+        ))?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "run_log.tail_by_run_id", skip_all)]
+    async fn tail_by_run_id(
+        &self,
+        run_id: &RunId,
+        stream: Option<&LogStream>,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<LogRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<LogRow> = sqlx::query_as::<_, LogRow>(
+            "SELECT *
+             FROM (
+                 SELECT
+                     seq,
+                     stream,
+                     line,
+                     emitted_at
+                 FROM run_log
+                 WHERE run_id = $1
+                 AND ($2::VARCHAR IS NULL OR stream = $2)
+                 ORDER BY seq DESC
+                 LIMIT $3
+             ) AS tail
+             ORDER BY seq",
+        )
+        .bind(run_id)
+        .bind(stream)
+        .bind(limit.unwrap_or(&100))
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to tail logs of "{}" from [run_log]"#,
+            run_id.as_uuid()
+        ))?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::job::Job;
+    use crate::controller::entities::project::Project;
+    use crate::controller::entities::run::Run;
+    use crate::controller::entities::workflow::Workflow;
+    use crate::controller::repositories::job::JobRepository;
+    use crate::controller::repositories::job::PgJobRepository;
+    use crate::controller::repositories::project::PgProjectRepository;
+    use crate::controller::repositories::project::ProjectRepository;
+    use crate::controller::repositories::run::PgRunRepository;
+    use crate::controller::repositories::run::RunRepository;
+    use crate::controller::repositories::workflow::PgWorkflowRepository;
+    use crate::controller::repositories::workflow::WorkflowRepository;
+    use crate::messages::run::RunPriority;
+    use crate::messages::token::TokenState;
+    use sqlx::PgConnection;
+    use sqlx::PgPool;
+
+    async fn create_run(tx: &mut PgConnection) -> Result<Run> {
+        let project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )?;
+        PgProjectRepository.create(&project, &mut *tx).await?;
+        let workflow = Workflow::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            project.id().as_uuid().to_string(),
+            testutils::rand::string(10),
+            false,
+        )?;
+        PgWorkflowRepository.create(&workflow, &mut *tx).await?;
+        let job = Job::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            workflow.id().as_uuid().to_string(),
+            0,
+            testutils::rand::string(10),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        PgJobRepository.create(&job, &mut *tx).await?;
+        let run = Run::new(
+            testutils::rand::uuid(),
+            TokenState::Running,
+            RunPriority::Normal,
+            job.id().as_uuid().to_string(),
+            Utc::now(),
+        )?;
+        PgRunRepository.create(&run, &mut *tx).await?;
+        Ok(run)
+    }
+
+    fn line(seq: i64, stream: LogStream) -> LogLine {
+        LogLine {
+            seq,
+            stream,
+            line: testutils::rand::string(10),
+            emitted_at: Utc::now(),
+        }
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_create_list_and_tail(pool: PgPool) -> Result<()> {
+        let repo = PgLogRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let run = create_run(&mut tx)
+            .await
+            .expect("new run should be created");
+        let lines: Vec<LogLine> = (0..10)
+            .map(|seq| {
+                let stream = if seq % 2 == 0 {
+                    LogStream::Stdout
+                } else {
+                    LogStream::Stderr
+                };
+                line(seq, stream)
+            })
+            .collect();
+        let done = repo
+            .create(run.id(), &lines, &mut tx)
+            .await
+            .expect("logs should be inserted");
+        assert_eq!(done.rows_affected(), 10);
+        let done = repo
+            .create(run.id(), &lines[..3], &mut tx)
+            .await
+            .expect("duplicated logs should be ignored");
+        assert_eq!(done.rows_affected(), 0);
+        let rows = repo
+            .list_by_run_id(run.id(), None, Some(&4), Some(&3), &mut tx)
+            .await
+            .expect("logs should be listed");
+        assert_eq!(
+            rows.iter().map(|r| r.seq).collect::<Vec<_>>(),
+            vec![4, 5, 6]
+        );
+        let rows = repo
+            .list_by_run_id(run.id(), Some(&LogStream::Stderr), None, None, &mut tx)
+            .await
+            .expect("logs should be listed");
+        assert_eq!(rows.len(), 5);
+        let rows = repo
+            .tail_by_run_id(run.id(), None, Some(&2), &mut tx)
+            .await
+            .expect("logs should be tailed");
+        assert_eq!(rows.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![8, 9]);
+        assert_eq!(&rows[1].line, &lines[9].line);
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+}
@@ -0,0 +1,750 @@
+use crate::controller::entities::notification::NotificationEvent;
+use crate::controller::entities::notification::NotificationRule;
+use crate::controller::entities::notification::NotificationRuleId;
+use crate::controller::entities::notification::NotificationRuleName;
+use crate::controller::entities::project::ProjectId;
+use crate::infra::postgres::PgAcquire;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
+use serde_json::Value as Json;
+use sqlx::postgres::PgQueryResult;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
+pub struct NotificationRuleRow {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub name: String,
+    pub events: Vec<String>,
+    pub workflow_id: Option<Uuid>,
+    pub url: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, sqlx::FromRow)]
+pub struct SealedNotificationRuleRow {
+    pub project_id: Uuid,
+    pub name: String,
+    pub url: String,
+    pub secret: Vec<u8>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
+pub struct NotificationDeliveryRow {
+    pub id: Uuid,
+    pub rule_id: Uuid,
+    pub event: String,
+    pub subject_id: Uuid,
+    #[schema(value_type = Object)]
+    pub payload: Json,
+    pub state: String,
+    pub attempts: i32,
+    pub status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait NotificationRepository: Send + Sync + 'static {
+    async fn create(
+        &self,
+        rule: &NotificationRule,
+        sealed: &[u8],
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn delete(
+        &self,
+        project_id: &ProjectId,
+        name: &NotificationRuleName,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn get_by_name(
+        &self,
+        project_id: &ProjectId,
+        name: &NotificationRuleName,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<NotificationRuleRow>>;
+
+    async fn get_sealed_by_id(
+        &self,
+        id: &NotificationRuleId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<SealedNotificationRuleRow>>;
+
+    async fn list_by_project_id(
+        &self,
+        project_id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<NotificationRuleRow>>;
+
+    async fn list_matching(
+        &self,
+        project_id: &Uuid,
+        workflow_id: &Uuid,
+        event: &NotificationEvent,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<NotificationRuleRow>>;
+
+    async fn enqueue(
+        &self,
+        rule_id: &Uuid,
+        event: &NotificationEvent,
+        subject_id: &Uuid,
+        payload: &Json,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn claim(
+        &self,
+        limit: &i64,
+        lease_secs: &i64,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<NotificationDeliveryRow>>;
+
+    async fn mark_delivered(
+        &self,
+        id: &Uuid,
+        status_code: &i32,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn mark_retry(
+        &self,
+        id: &Uuid,
+        status_code: Option<&i32>,
+        error: &str,
+        delay_secs: &i64,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn mark_dead(
+        &self,
+        id: &Uuid,
+        status_code: Option<&i32>,
+        error: &str,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn list_deliveries_by_rule_id(
+        &self,
+        rule_id: &Uuid,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<NotificationDeliveryRow>>;
+}
+
+pub struct PgNotificationRepository;
+
+#[async_trait]
+impl NotificationRepository for PgNotificationRepository {
+    #[instrument(name = "notification_rule.create", skip_all)]
+    async fn create(
+        &self,
+        rule: &NotificationRule,
+        sealed: &[u8],
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let events: Vec<&str> = rule.events().iter().map(|event| event.as_ref()).collect();
+        sqlx::query(
+            "INSERT INTO notification_rule (
+                 id,
+                 project_id,
+                 name,
+                 events,
+                 workflow_id,
+                 url,
+                 secret
+             ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+             ON CONFLICT(project_id, name)
+             DO UPDATE
+             SET events = $4,
+                 workflow_id = $5,
+                 url = $6,
+                 secret = $7,
+                 updated_at = CURRENT_TIMESTAMP",
+        )
+        .bind(rule.id())
+        .bind(rule.project_id())
+        .bind(rule.name())
+        .bind(&events)
+        .bind(rule.workflow_id())
+        .bind(rule.url())
+        .bind(sealed)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to upsert "{}" into [notification_rule]"#,
+            rule.name().as_str()
+        ))
+    }
+
+    #[instrument(name = "notification_rule.delete", skip_all)]
+    async fn delete(
+        &self,
+        project_id: &ProjectId,
+        name: &NotificationRuleName,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "WITH this_rule AS (
+                 SELECT id
+                 FROM notification_rule
+                 WHERE project_id = $1 AND name = $2
+             ),
+             deleted_dead_letters AS (
+                 DELETE FROM notification_dead_letter
+                 WHERE rule_id IN (SELECT id FROM this_rule)
+             ),
+             deleted_deliveries AS (
+                 DELETE FROM notification_delivery
+                 WHERE rule_id IN (SELECT id FROM this_rule)
+             )
+             DELETE FROM notification_rule
+             WHERE id IN (SELECT id FROM this_rule)",
+        )
+        .bind(project_id)
+        .bind(name)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to delete "{}" from [notification_rule]"#,
+            name.as_str()
+        ))
+    }
+
+    #[instrument(name = "notification_rule.get_by_name", skip_all)]
+    async fn get_by_name(
+        &self,
+        project_id: &ProjectId,
+        name: &NotificationRuleName,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<NotificationRuleRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let row: Option<NotificationRuleRow> = sqlx::query_as::<_, NotificationRuleRow>(
+            "SELECT
+                 id,
+                 project_id,
+                 name,
+                 events,
+                 workflow_id,
+                 url,
+                 created_at,
+                 updated_at
+             FROM notification_rule
+             WHERE project_id = $1 AND name = $2",
+        )
+        .bind(project_id)
+        .bind(name)
+        .fetch_optional(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to select "{}" from [notification_rule]"#,
+            name.as_str()
+        ))?;
+        Ok(row)
+    }
+
+    #[instrument(name = "notification_rule.get_sealed_by_id", skip_all)]
+    async fn get_sealed_by_id(
+        &self,
+        id: &NotificationRuleId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<SealedNotificationRuleRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let row: Option<SealedNotificationRuleRow> =
+            sqlx::query_as::<_, SealedNotificationRuleRow>(
+                "SELECT
+                     project_id,
+                     name,
+                     url,
+                     secret
+                 FROM notification_rule
+                 WHERE id = $1",
+            )
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await
+            .context(format!(
+                r#"failed to select sealed "{}" from [notification_rule]"#,
+                id.as_uuid()
+            ))?;
+        Ok(row)
+    }
+
+    #[instrument(name = "notification_rule.list_by_project_id", skip_all)]
+    async fn list_by_project_id(
+        &self,
+        project_id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<NotificationRuleRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<NotificationRuleRow> = sqlx::query_as::<_, NotificationRuleRow>(
+            "SELECT
+                 id,
+                 project_id,
+                 name,
+                 events,
+                 workflow_id,
+                 url,
+                 created_at,
+                 updated_at
+             FROM notification_rule
+             WHERE project_id = $1
+             ORDER BY name",
+        )
+        .bind(project_id)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list notification rules of "{}" from [notification_rule]"#,
+            project_id.as_uuid()
+        ))?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "notification_rule.list_matching", skip_all)]
+    async fn list_matching(
+        &self,
+        project_id: &Uuid,
+        workflow_id: &Uuid,
+        event: &NotificationEvent,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<NotificationRuleRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<NotificationRuleRow> = sqlx::query_as::<_, NotificationRuleRow>(
+            "SELECT
+                 id,
+                 project_id,
+                 name,
+                 events,
+                 workflow_id,
+                 url,
+                 created_at,
+                 updated_at
+             FROM notification_rule
+             WHERE project_id = $1
+             AND (workflow_id IS NULL OR workflow_id = $2)
+             AND $3 = ANY(events)",
+        )
+        .bind(project_id)
+        .bind(workflow_id)
+        .bind(event.as_ref())
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list notification rules matching "{}" from [notification_rule]"#,
+            event.as_ref()
+        ))?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "notification_delivery.enqueue", skip_all)]
+    async fn enqueue(
+        &self,
+        rule_id: &Uuid,
+        event: &NotificationEvent,
+        subject_id: &Uuid,
+        payload: &Json,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "INSERT INTO notification_delivery (
+                 id,
+                 rule_id,
+                 event,
+                 subject_id,
+                 payload
+             ) VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT(rule_id, event, subject_id) DO NOTHING",
+        )
+        .bind(Uuid::new_v4())
+        .bind(rule_id)
+        .bind(event.as_ref())
+        .bind(subject_id)
+        .bind(payload)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to insert "{}" of "{}" into [notification_delivery]"#,
+            event.as_ref(),
+            subject_id
+        ))
+    }
+
+    // NOTE: Claimed deliveries are leased by pushing next_attempt_at forward, so a crashed controller only delays them.
+    #[instrument(name = "notification_delivery.claim", skip_all)]
+    async fn claim(
+        &self,
+        limit: &i64,
+        lease_secs: &i64,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<NotificationDeliveryRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<NotificationDeliveryRow> = sqlx::query_as::<_, NotificationDeliveryRow>(
+            "UPDATE notification_delivery
+             SET next_attempt_at = CURRENT_TIMESTAMP + make_interval(secs => $2),
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id IN (
+                 SELECT id
+                 FROM notification_delivery
+                 WHERE state = 'pending' AND next_attempt_at <= CURRENT_TIMESTAMP
+                 ORDER BY next_attempt_at
+                 LIMIT $1
+                 FOR UPDATE SKIP LOCKED
+             )
+             RETURNING
+                 id,
+                 rule_id,
+                 event,
+                 subject_id,
+                 payload,
+                 state,
+                 attempts,
+                 status_code,
+                 last_error,
+                 next_attempt_at,
+                 delivered_at,
+                 created_at,
+                 updated_at",
+        )
+        .bind(limit)
+        .bind(*lease_secs as f64)
+        .fetch_all(&mut *conn)
+        .await
+        .context("failed to claim due deliveries from [notification_delivery]")?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "notification_delivery.mark_delivered", skip_all)]
+    async fn mark_delivered(
+        &self,
+        id: &Uuid,
+        status_code: &i32,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "UPDATE notification_delivery
+             SET state = 'delivered',
+                 attempts = attempts + 1,
+                 status_code = $2,
+                 last_error = NULL,
+                 delivered_at = CURRENT_TIMESTAMP,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = $1",
+        )
+        .bind(id)
+        .bind(status_code)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to mark "{}" as delivered in [notification_delivery]"#,
+            id
+        ))
+    }
+
+    #[instrument(name = "notification_delivery.mark_retry", skip_all)]
+    async fn mark_retry(
+        &self,
+        id: &Uuid,
+        status_code: Option<&i32>,
+        error: &str,
+        delay_secs: &i64,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "UPDATE notification_delivery
+             SET attempts = attempts + 1,
+                 status_code = $2,
+                 last_error = $3,
+                 next_attempt_at = CURRENT_TIMESTAMP + make_interval(secs => $4),
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = $1",
+        )
+        .bind(id)
+        .bind(status_code)
+        .bind(error)
+        .bind(*delay_secs as f64)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to schedule retry of "{}" in [notification_delivery]"#,
+            id
+        ))
+    }
+
+    #[instrument(name = "notification_delivery.mark_dead", skip_all)]
+    async fn mark_dead(
+        &self,
+        id: &Uuid,
+        status_code: Option<&i32>,
+        error: &str,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "WITH dead_delivery AS (
+                 UPDATE notification_delivery
+                 SET state = 'dead',
+                     attempts = attempts + 1,
+                     status_code = $2,
+                     last_error = $3,
+                     updated_at = CURRENT_TIMESTAMP
+                 WHERE id = $1
+                 RETURNING id, rule_id, event, payload, attempts, status_code, last_error
+             )
+             INSERT INTO notification_dead_letter (
+                 id,
+                 rule_id,
+                 event,
+                 payload,
+                 attempts,
+                 status_code,
+                 last_error
+             )
+             SELECT id, rule_id, event, payload, attempts, status_code, last_error
+             FROM dead_delivery
+             ON CONFLICT(id) DO NOTHING",
+        )
+        .bind(id)
+        .bind(status_code)
+        .bind(error)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to move "{}" into [notification_dead_letter]"#,
+            id
+        ))
+    }
+
+    #[instrument(name = "notification_delivery.list_deliveries_by_rule_id", skip_all)]
+    async fn list_deliveries_by_rule_id(
+        &self,
+        rule_id: &Uuid,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<NotificationDeliveryRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<NotificationDeliveryRow> = sqlx::query_as::<_, NotificationDeliveryRow>(
+            "SELECT
+                 id,
+                 rule_id,
+                 event,
+                 subject_id,
+                 payload,
+                 state,
+                 attempts,
+                 status_code,
+                 last_error,
+                 next_attempt_at,
+                 delivered_at,
+                 created_at,
+                 updated_at
+             FROM notification_delivery
+             WHERE rule_id = $1
+             ORDER BY created_at DESC
+             LIMIT $2",
+        )
+        .bind(rule_id)
+        .bind(limit)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list deliveries of "{}" from [notification_delivery]"#,
+            rule_id
+        ))?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::project::Project;
+    use crate::controller::repositories::project::PgProjectRepository;
+    use crate::controller::repositories::project::ProjectRepository;
+    use serde_json::json;
+    use sqlx::PgConnection;
+    use sqlx::PgPool;
+
+    async fn create_project(tx: &mut PgConnection) -> Result<Project> {
+        let project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )
+        .context("failed to create project")?;
+        PgProjectRepository
+            .create(&project, tx)
+            .await
+            .context("failed to insert project")?;
+        Ok(project)
+    }
+
+    fn rule(project: &Project, workflow_id: Option<Uuid>) -> Result<NotificationRule> {
+        NotificationRule::new(
+            testutils::rand::uuid(),
+            project.id().as_uuid().to_string(),
+            testutils::rand::string(10),
+            vec![String::from("run_failure")],
+            workflow_id.map(|id| id.to_string()),
+            String::from("http://127.0.0.1/hook"),
+            testutils::rand::string(10),
+        )
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_create_and_list_matching(pool: PgPool) -> Result<()> {
+        let repo = PgNotificationRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let project = create_project(&mut tx)
+            .await
+            .expect("new project should be created");
+        let workflow_id = Uuid::new_v4();
+        let any = rule(&project, None)?;
+        let scoped = rule(&project, Some(workflow_id))?;
+        repo.create(&any, b"sealed", &mut tx).await?;
+        repo.create(&scoped, b"sealed", &mut tx).await?;
+        let matched = repo
+            .list_matching(
+                project.id().as_uuid(),
+                &workflow_id,
+                &NotificationEvent::RunFailure,
+                &mut tx,
+            )
+            .await?;
+        assert_eq!(matched.len(), 2);
+        let matched = repo
+            .list_matching(
+                project.id().as_uuid(),
+                &Uuid::new_v4(),
+                &NotificationEvent::RunFailure,
+                &mut tx,
+            )
+            .await?;
+        assert_eq!(matched.len(), 1);
+        assert_eq!(&matched[0].id, any.id().as_uuid());
+        let matched = repo
+            .list_matching(
+                project.id().as_uuid(),
+                &workflow_id,
+                &NotificationEvent::ExecutionSuccess,
+                &mut tx,
+            )
+            .await?;
+        assert!(matched.is_empty());
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_enqueue_claim_and_mark_dead(pool: PgPool) -> Result<()> {
+        let repo = PgNotificationRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let project = create_project(&mut tx)
+            .await
+            .expect("new project should be created");
+        let rule = rule(&project, None)?;
+        repo.create(&rule, b"sealed", &mut tx).await?;
+        let subject_id = Uuid::new_v4();
+        let payload = json!({ "run_id": subject_id });
+        for expected in [1, 0] {
+            let done = repo
+                .enqueue(
+                    rule.id().as_uuid(),
+                    &NotificationEvent::RunFailure,
+                    &subject_id,
+                    &payload,
+                    &mut tx,
+                )
+                .await?;
+            assert_eq!(done.rows_affected(), expected);
+        }
+        let claimed = repo.claim(&10, &60, &mut tx).await?;
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].payload, payload);
+        assert!(repo.claim(&10, &60, &mut tx).await?.is_empty());
+        repo.mark_dead(&claimed[0].id, Some(&500), "internal server error", &mut tx)
+            .await?;
+        let deliveries = repo
+            .list_deliveries_by_rule_id(rule.id().as_uuid(), None, &mut tx)
+            .await?;
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].state, "dead");
+        assert_eq!(deliveries[0].attempts, 1);
+        assert_eq!(deliveries[0].status_code, Some(500));
+        let dead: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM notification_dead_letter WHERE rule_id = $1")
+                .bind(rule.id())
+                .fetch_one(&mut tx)
+                .await?;
+        assert_eq!(dead, 1);
+        let done = repo.delete(project.id(), rule.name(), &mut tx).await?;
+        assert_eq!(done.rows_affected(), 1);
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+}
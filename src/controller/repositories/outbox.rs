@@ -0,0 +1,295 @@
+use crate::controller::entities::outbox::Outbox;
+use crate::infra::postgres::PgAcquire;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
+use serde_json::Value as Json;
+use sqlx::postgres::PgQueryResult;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+pub struct OutboxRow {
+    pub id: Uuid,
+    pub exchange: String,
+    pub routing_key: String,
+    pub payload: Json,
+    pub headers: Json,
+    pub priority: Option<i32>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub leased_until: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait OutboxRepository: Send + Sync + 'static {
+    async fn create(
+        &self,
+        outbox: &Outbox,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn claim(
+        &self,
+        limit: &i64,
+        lease_secs: &i64,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<OutboxRow>>;
+
+    async fn mark_sent(
+        &self,
+        ids: &[Uuid],
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn release(
+        &self,
+        ids: &[Uuid],
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn delete_sent(
+        &self,
+        retention_secs: &i64,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+}
+
+pub struct PgOutboxRepository;
+
+#[async_trait]
+impl OutboxRepository for PgOutboxRepository {
+    #[instrument(name = "outbox.create", skip_all)]
+    async fn create(
+        &self,
+        outbox: &Outbox,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "INSERT INTO outbox (
+                 id,
+                 exchange,
+                 routing_key,
+                 payload,
+                 headers,
+                 priority
+             ) VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(outbox.id())
+        .bind(outbox.exchange())
+        .bind(outbox.routing_key())
+        .bind(outbox.payload())
+        .bind(outbox.headers())
+        .bind(outbox.priority())
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to insert "{}" into [outbox]"#,
+            outbox.id().as_uuid()
+        ))
+    }
+
+    // NOTE: Claimed messages are leased by pushing leased_until forward, so no lock is held while publishing.
+    #[instrument(name = "outbox.claim", skip_all)]
+    async fn claim(
+        &self,
+        limit: &i64,
+        lease_secs: &i64,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<OutboxRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let mut rows: Vec<OutboxRow> = sqlx::query_as::<_, OutboxRow>(
+            "UPDATE outbox
+             SET leased_until = CURRENT_TIMESTAMP + make_interval(secs => $2),
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id IN (
+                 SELECT id
+                 FROM outbox
+                 WHERE sent_at IS NULL
+                 AND (leased_until IS NULL OR leased_until <= CURRENT_TIMESTAMP)
+                 ORDER BY created_at
+                 LIMIT $1
+                 FOR UPDATE SKIP LOCKED
+             )
+             RETURNING
+                 id,
+                 exchange,
+                 routing_key,
+                 payload,
+                 headers,
+                 priority,
+                 sent_at,
+                 leased_until,
+                 created_at,
+                 updated_at",
+        )
+        .bind(limit)
+        .bind(*lease_secs as f64)
+        .fetch_all(&mut *conn)
+        .await
+        .context("failed to claim pending messages from [outbox]")?;
+        rows.sort_by_key(|row| row.created_at);
+        Ok(rows)
+    }
+
+    #[instrument(name = "outbox.mark_sent", skip_all)]
+    async fn mark_sent(
+        &self,
+        ids: &[Uuid],
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "UPDATE outbox
+             SET sent_at = CURRENT_TIMESTAMP,
+                 leased_until = NULL,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = ANY($1)",
+        )
+        .bind(ids)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            "failed to mark {} message(s) as sent in [outbox]",
+            ids.len()
+        ))
+    }
+
+    #[instrument(name = "outbox.release", skip_all)]
+    async fn release(
+        &self,
+        ids: &[Uuid],
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "UPDATE outbox
+             SET leased_until = NULL,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = ANY($1)
+             AND sent_at IS NULL",
+        )
+        .bind(ids)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            "failed to release {} message(s) in [outbox]",
+            ids.len()
+        ))
+    }
+
+    #[instrument(name = "outbox.delete_sent", skip_all)]
+    async fn delete_sent(
+        &self,
+        retention_secs: &i64,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "DELETE FROM outbox
+             WHERE id IN (
+                 SELECT id
+                 FROM outbox
+                 WHERE sent_at < CURRENT_TIMESTAMP - make_interval(secs => $1)
+                 LIMIT $2
+                 FOR UPDATE SKIP LOCKED
+             )",
+        )
+        .bind(*retention_secs as f64)
+        .bind(limit.unwrap_or(&1000))
+        .execute(&mut *conn)
+        .await
+        .context("failed to delete sent messages from [outbox]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::config::ConfigUpdate;
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_claim_mark_sent_and_delete(pool: PgPool) -> Result<()> {
+        let repo = PgOutboxRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let outbox = Outbox::new(&ConfigUpdate::Project(Uuid::new_v4()))
+            .expect("new outbox should be created");
+        repo.create(&outbox, &mut tx)
+            .await
+            .expect("new outbox should be inserted");
+        let claimed = repo
+            .claim(&100, &60, &mut tx)
+            .await
+            .expect("pending messages should be claimed");
+        assert!(claimed.iter().any(|row| &row.id == outbox.id().as_uuid()));
+        let claimed = repo
+            .claim(&100, &60, &mut tx)
+            .await
+            .expect("pending messages should be claimed");
+        assert!(claimed.iter().all(|row| &row.id != outbox.id().as_uuid()));
+        repo.release(&[outbox.id().to_uuid()], &mut tx)
+            .await
+            .expect("outbox should be released");
+        let claimed = repo
+            .claim(&100, &60, &mut tx)
+            .await
+            .expect("pending messages should be claimed");
+        assert!(claimed.iter().any(|row| &row.id == outbox.id().as_uuid()));
+        repo.mark_sent(&[outbox.id().to_uuid()], &mut tx)
+            .await
+            .expect("outbox should be marked as sent");
+        repo.release(&[outbox.id().to_uuid()], &mut tx)
+            .await
+            .expect("outbox should be released");
+        let claimed = repo
+            .claim(&100, &0, &mut tx)
+            .await
+            .expect("pending messages should be claimed");
+        assert!(claimed.iter().all(|row| &row.id != outbox.id().as_uuid()));
+        let done = repo
+            .delete_sent(&3600, None, &mut tx)
+            .await
+            .expect("sent messages should be deleted");
+        assert_eq!(done.rows_affected(), 0);
+        sqlx::query("UPDATE outbox SET sent_at = sent_at - INTERVAL '2 hours' WHERE id = $1")
+            .bind(outbox.id())
+            .execute(&mut tx)
+            .await
+            .expect("outbox should be backdated");
+        let done = repo
+            .delete_sent(&3600, None, &mut tx)
+            .await
+            .expect("sent messages should be deleted");
+        assert_eq!(done.rows_affected(), 1);
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+}
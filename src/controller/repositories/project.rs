@@ -1,7 +1,10 @@
+use crate::controller::entities::page::Keyset;
+use crate::controller::entities::page::Page;
+use crate::controller::entities::page::Pagination;
 use crate::controller::entities::project::Project;
 use crate::controller::entities::project::ProjectId;
 use crate::controller::entities::project::ProjectName;
-use crate::controller::entities::workflow::WorkflowName;
+use crate::controller::repositories::run::RunRow;
 use crate::infra::postgres::PgAcquire;
 use anyhow::Context;
 use anyhow::Result;
@@ -10,23 +13,48 @@ use chrono::DateTime;
 use chrono::Utc;
 use serde_json::Value as Json;
 use sqlx::postgres::PgQueryResult;
+use tracing::instrument;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
 pub struct ProjectRow {
     pub id: Uuid,
     pub name: String,
     pub description: String,
+    #[schema(value_type = Option<Object>)]
     pub config: Option<Json>,
+    #[schema(value_type = Option<Object>)]
+    pub config_schema: Option<Json>,
+    pub retention_days: Option<i32>,
+    pub retention_runs: Option<i32>,
+    pub version: i64,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+impl Keyset for ProjectRow {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
 #[serde(transparent)]
 pub struct ProjectConfigRow(pub Json);
 
-#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
 pub struct ProjectSummaryRow {
     pub id: Uuid,
     pub name: String,
@@ -37,9 +65,10 @@ pub struct ProjectSummaryRow {
     pub fails_last_hour: i64,
     pub successes_last_hour: i64,
     pub errors_last_hour: i64,
+    pub version: i64,
 }
 
-#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
 pub struct WorkflowSummaryRow {
     pub id: Uuid,
     pub name: String,
@@ -50,6 +79,29 @@ pub struct WorkflowSummaryRow {
     pub failure: i64,
     pub waiting: i64,
     pub error: i64,
+    pub sla_missed: i64,
+    pub last_sla_missed_at: Option<DateTime<Utc>>,
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Keyset for WorkflowSummaryRow {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at
+    }
 }
 
 #[async_trait]
@@ -66,11 +118,29 @@ pub trait ProjectRepository: Send + Sync + 'static {
         executor: impl PgAcquire<'_> + 'async_trait,
     ) -> Result<PgQueryResult>;
 
+    async fn soft_delete(
+        &self,
+        id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn delete_cascade(
+        &self,
+        id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn list_active_runs_by_id(
+        &self,
+        id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<RunRow>>;
+
     async fn list(
         &self,
-        limit: Option<&i64>,
+        pagination: &Pagination,
         executor: impl PgAcquire<'_> + 'async_trait,
-    ) -> Result<Vec<ProjectRow>>;
+    ) -> Result<Page<ProjectRow>>;
 
     async fn get_by_id(
         &self,
@@ -84,6 +154,32 @@ pub trait ProjectRepository: Send + Sync + 'static {
         executor: impl PgAcquire<'_> + 'async_trait,
     ) -> Result<Option<ProjectRow>>;
 
+    async fn lock_by_id(
+        &self,
+        id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<ProjectRow>>;
+
+    async fn touch(
+        &self,
+        id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn update_config(
+        &self,
+        id: &ProjectId,
+        config: &Json,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn update_config_schema(
+        &self,
+        id: &ProjectId,
+        config_schema: Option<&Json>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
     async fn get_summary_by_id(
         &self,
         id: &ProjectId,
@@ -99,17 +195,16 @@ pub trait ProjectRepository: Send + Sync + 'static {
     async fn list_workflows_by_id(
         &self,
         id: &ProjectId,
-        name: Option<&WorkflowName>,
-        after: Option<&WorkflowName>,
-        limit: Option<&i64>,
+        pagination: &Pagination,
         executor: impl PgAcquire<'_> + 'async_trait,
-    ) -> Result<Vec<WorkflowSummaryRow>>;
+    ) -> Result<Page<WorkflowSummaryRow>>;
 }
 
 pub struct PgProjectRepository;
 
 #[async_trait]
 impl ProjectRepository for PgProjectRepository {
+    #[instrument(name = "project.create", skip_all)]
     async fn create(
         &self,
         project: &Project,
@@ -124,18 +219,47 @@ impl ProjectRepository for PgProjectRepository {
                  id,
                  name,
                  description,
-                 config
-             ) VALUES ($1, $2, $3, $4)
+                 config,
+                 config_schema,
+                 retention_days,
+                 retention_runs
+             ) VALUES ($1, $2, $3, $4, $5, $6, $7)
              ON CONFLICT(id)
              DO UPDATE
              SET name = $2,
                  description = $3,
-                 config = COALESCE($4, project.config)",
+                 config = COALESCE($4, project.config),
+                 config_schema = COALESCE($5, project.config_schema),
+                 retention_days = CASE WHEN $8 THEN $6 ELSE project.retention_days END,
+                 retention_runs = CASE WHEN $8 THEN $7 ELSE project.retention_runs END,
+                 deleted_at = NULL,
+                 version = project.version + 1,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE project.deleted_at IS NOT NULL
+             OR (
+                 project.name,
+                 project.description,
+                 project.config,
+                 project.config_schema,
+                 project.retention_days,
+                 project.retention_runs
+             ) IS DISTINCT FROM (
+                 EXCLUDED.name,
+                 EXCLUDED.description,
+                 COALESCE(EXCLUDED.config, project.config),
+                 COALESCE(EXCLUDED.config_schema, project.config_schema),
+                 CASE WHEN $8 THEN EXCLUDED.retention_days ELSE project.retention_days END,
+                 CASE WHEN $8 THEN EXCLUDED.retention_runs ELSE project.retention_runs END
+             )",
         )
         .bind(project.id())
         .bind(project.name())
         .bind(project.description())
         .bind(project.config())
+        .bind(project.config_schema())
+        .bind(project.retention().as_ref().and_then(|r| r.days()))
+        .bind(project.retention().as_ref().and_then(|r| r.runs()))
+        .bind(project.retention().is_some())
         .execute(&mut *conn)
         .await
         .context(format!(
@@ -144,6 +268,7 @@ impl ProjectRepository for PgProjectRepository {
         ))
     }
 
+    #[instrument(name = "project.delete", skip_all)]
     async fn delete(
         &self,
         id: &ProjectId,
@@ -166,37 +291,224 @@ impl ProjectRepository for PgProjectRepository {
         ))
     }
 
+    #[instrument(name = "project.soft_delete", skip_all)]
+    async fn soft_delete(
+        &self,
+        id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "WITH these_workflows AS (
+                 UPDATE workflow
+                 SET deleted_at = CURRENT_TIMESTAMP,
+                     updated_at = CURRENT_TIMESTAMP
+                 WHERE project_id = $1 AND deleted_at IS NULL
+                 RETURNING id
+             ),
+             these_jobs AS (
+                 UPDATE job
+                 SET deleted_at = CURRENT_TIMESTAMP,
+                     updated_at = CURRENT_TIMESTAMP
+                 WHERE workflow_id IN (SELECT id FROM these_workflows) AND deleted_at IS NULL
+             )
+             UPDATE project
+             SET deleted_at = CURRENT_TIMESTAMP,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to soft delete "{}" from [project]"#,
+            id.as_uuid()
+        ))
+    }
+
+    #[instrument(name = "project.delete_cascade", skip_all)]
+    async fn delete_cascade(
+        &self,
+        id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "WITH these_workflows AS (
+                 SELECT id
+                 FROM workflow
+                 WHERE project_id = $1
+             ),
+             these_jobs AS (
+                 SELECT id
+                 FROM job
+                 WHERE workflow_id IN (SELECT id FROM these_workflows)
+             ),
+             deleted_tokens AS (
+                 DELETE FROM token
+                 WHERE job_id IN (SELECT id FROM these_jobs)
+             ),
+             deleted_logs AS (
+                 DELETE FROM run_log
+                 WHERE run_id IN (SELECT id FROM run WHERE job_id IN (SELECT id FROM these_jobs))
+             ),
+             deleted_stashes AS (
+                 DELETE FROM stash
+                 WHERE run_id IN (SELECT id FROM run WHERE job_id IN (SELECT id FROM these_jobs))
+             ),
+             deleted_runs AS (
+                 DELETE FROM run
+                 WHERE job_id IN (SELECT id FROM these_jobs)
+             ),
+             deleted_archived_runs AS (
+                 DELETE FROM run_archive
+                 WHERE job_id IN (SELECT id FROM these_jobs)
+             ),
+             deleted_executions AS (
+                 DELETE FROM workflow_run
+                 WHERE workflow_id IN (SELECT id FROM these_workflows)
+             ),
+             deleted_sla_misses AS (
+                 DELETE FROM sla_miss
+                 WHERE workflow_id IN (SELECT id FROM these_workflows)
+             ),
+             deleted_jobs AS (
+                 DELETE FROM job
+                 WHERE id IN (SELECT id FROM these_jobs)
+             ),
+             deleted_workflows AS (
+                 DELETE FROM workflow
+                 WHERE id IN (SELECT id FROM these_workflows)
+             ),
+             deleted_secrets AS (
+                 DELETE FROM secret
+                 WHERE project_id = $1
+             ),
+             these_rules AS (
+                 SELECT id
+                 FROM notification_rule
+                 WHERE project_id = $1
+             ),
+             deleted_dead_letters AS (
+                 DELETE FROM notification_dead_letter
+                 WHERE rule_id IN (SELECT id FROM these_rules)
+             ),
+             deleted_deliveries AS (
+                 DELETE FROM notification_delivery
+                 WHERE rule_id IN (SELECT id FROM these_rules)
+             ),
+             deleted_rules AS (
+                 DELETE FROM notification_rule
+                 WHERE id IN (SELECT id FROM these_rules)
+             )
+             DELETE FROM project
+             WHERE id = $1",
+        )
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to delete "{}" and its descendants from [project]"#,
+            id.as_uuid()
+        ))
+    }
+
+    #[instrument(name = "project.list_active_runs_by_id", skip_all)]
+    async fn list_active_runs_by_id(
+        &self,
+        id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<RunRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<RunRow> = sqlx::query_as::<_, RunRow>(
+            "SELECT
+                 run.id,
+                 run.state,
+                 run.priority,
+                 run.job_id,
+                 run.triggered_at,
+                 run.started_at,
+                 run.finished_at,
+                 run.attempt,
+                 run.args,
+                 run.envs,
+                 run.execution_id,
+                 run.created_at,
+                 run.updated_at
+             FROM run
+             JOIN job ON job.id = run.job_id
+             JOIN workflow ON workflow.id = job.workflow_id
+             WHERE workflow.project_id = $1
+             AND run.state IN ('waiting', 'active', 'running')
+             ORDER BY run.triggered_at
+             FOR UPDATE OF run",
+        )
+        .bind(id)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list active runs of "{}" from [run]"#,
+            id.as_uuid()
+        ))?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "project.list", skip_all)]
     async fn list(
         &self,
-        limit: Option<&i64>,
+        pagination: &Pagination,
         executor: impl PgAcquire<'_> + 'async_trait,
-    ) -> Result<Vec<ProjectRow>> {
+    ) -> Result<Page<ProjectRow>> {
         let mut conn = executor
             .acquire()
             .await
             .context("failed to acquire postgres connection")?;
-        let rows: Vec<ProjectRow> = sqlx::query_as::<_, ProjectRow>(
+        let sql = format!(
             "SELECT
                  id,
                  name,
                  description,
-                 COALESCE(config, '{}'::jsonb) AS config,
+                 COALESCE(config, '{{}}'::jsonb) AS config,
+                 config_schema,
+                 retention_days,
+                 retention_runs,
+                 version,
                  created_at,
                  updated_at
              FROM project
-             ORDER BY name
-             LIMIT $1",
-        )
-        .bind(limit.unwrap_or(&100))
-        .fetch_all(&mut *conn)
-        .await
-        .context(format!(
-            "failed to list {} project(s) from [project]",
-            limit.unwrap_or(&100)
-        ))?;
-        Ok(rows)
+             WHERE
+                 deleted_at IS NULL
+                 AND ($1::VARCHAR IS NULL OR name LIKE $1)
+                 AND {}
+             {}
+             LIMIT $4",
+            pagination.seek("project", 2),
+            pagination.order_by("project"),
+        );
+        let rows: Vec<ProjectRow> = sqlx::query_as::<_, ProjectRow>(&sql)
+            .bind(pagination.pattern())
+            .bind(pagination.after_key())
+            .bind(pagination.after_id())
+            .bind(pagination.fetch_limit())
+            .fetch_all(&mut *conn)
+            .await
+            .context(format!(
+                "failed to list {} project(s) from [project]",
+                pagination.limit()
+            ))?;
+        Page::new(rows, pagination)
     }
 
+    #[instrument(name = "project.get_by_id", skip_all)]
     async fn get_by_id(
         &self,
         id: &ProjectId,
@@ -212,10 +524,14 @@ impl ProjectRepository for PgProjectRepository {
                  name,
                  description,
                  COALESCE(config, '{}'::jsonb) AS config,
+                 config_schema,
+                 retention_days,
+                 retention_runs,
+                 version,
                  created_at,
                  updated_at
              FROM project
-             WHERE id = $1",
+             WHERE id = $1 AND deleted_at IS NULL",
         )
         .bind(id)
         .fetch_optional(&mut *conn)
@@ -227,6 +543,7 @@ impl ProjectRepository for PgProjectRepository {
         Ok(row)
     }
 
+    #[instrument(name = "project.get_by_name", skip_all)]
     async fn get_by_name(
         &self,
         name: &ProjectName,
@@ -242,10 +559,14 @@ impl ProjectRepository for PgProjectRepository {
                  name,
                  description,
                  COALESCE(config, '{}'::jsonb) AS config,
+                 config_schema,
+                 retention_days,
+                 retention_runs,
+                 version,
                  created_at,
                  updated_at
              FROM project
-             WHERE name = $1",
+             WHERE name = $1 AND deleted_at IS NULL",
         )
         .bind(name)
         .fetch_optional(&mut *conn)
@@ -257,6 +578,121 @@ impl ProjectRepository for PgProjectRepository {
         Ok(row)
     }
 
+    #[instrument(name = "project.lock_by_id", skip_all)]
+    async fn lock_by_id(
+        &self,
+        id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<ProjectRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let row: Option<ProjectRow> = sqlx::query_as::<_, ProjectRow>(
+            "SELECT
+                 id,
+                 name,
+                 description,
+                 COALESCE(config, '{}'::jsonb) AS config,
+                 config_schema,
+                 retention_days,
+                 retention_runs,
+                 version,
+                 created_at,
+                 updated_at
+             FROM project
+             WHERE id = $1 AND deleted_at IS NULL
+             FOR UPDATE",
+        )
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .context(format!(r#"failed to lock "{}" in [project]"#, id.as_uuid()))?;
+        Ok(row)
+    }
+
+    #[instrument(name = "project.touch", skip_all)]
+    async fn touch(
+        &self,
+        id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "UPDATE project
+             SET version = version + 1,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to touch "{}" in [project]"#,
+            id.as_uuid()
+        ))
+    }
+
+    #[instrument(name = "project.update_config", skip_all)]
+    async fn update_config(
+        &self,
+        id: &ProjectId,
+        config: &Json,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "UPDATE project
+             SET config = $2,
+                 version = version + 1,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(config)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to update config of "{}" in [project]"#,
+            id.as_uuid()
+        ))
+    }
+
+    #[instrument(name = "project.update_config_schema", skip_all)]
+    async fn update_config_schema(
+        &self,
+        id: &ProjectId,
+        config_schema: Option<&Json>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "UPDATE project
+             SET config_schema = $2,
+                 version = version + 1,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(config_schema)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to update config schema of "{}" in [project]"#,
+            id.as_uuid()
+        ))
+    }
+
+    #[instrument(name = "project.get_summary_by_id", skip_all)]
     async fn get_summary_by_id(
         &self,
         id: &ProjectId,
@@ -275,7 +711,8 @@ impl ProjectRepository for PgProjectRepository {
                  JOIN job ON job.workflow_id = workflow.id
                  JOIN run ON run.job_id = job.id
                  WHERE workflow.project_id = $1
-                 AND (finished_at IS NULL OR CURRENT_TIMESTAMP - finished_at < INTERVAL '1 hour')
+                 AND workflow.deleted_at IS NULL
+                 AND (run.finished_at IS NULL OR run.finished_at > CURRENT_TIMESTAMP - INTERVAL '1 hour')
              )
              SELECT
                  id,
@@ -284,7 +721,7 @@ impl ProjectRepository for PgProjectRepository {
                  (
                      SELECT COUNT(1)
                      FROM workflow
-                     WHERE workflow.project_id = $1
+                     WHERE workflow.project_id = $1 AND workflow.deleted_at IS NULL
                  ) AS workflows,
                  (
                      SELECT COUNT(1)
@@ -310,9 +747,10 @@ impl ProjectRepository for PgProjectRepository {
                      SELECT COUNT(1)
                      FROM these_jobs
                      WHERE these_jobs.state = 'error'
-                 ) AS errors_last_hour
+                 ) AS errors_last_hour,
+                 version
              FROM project
-             WHERE id = $1",
+             WHERE id = $1 AND deleted_at IS NULL",
         )
         .bind(id)
         .fetch_optional(&mut *conn)
@@ -324,6 +762,7 @@ impl ProjectRepository for PgProjectRepository {
         Ok(row)
     }
 
+    #[instrument(name = "project.get_config_by_id", skip_all)]
     async fn get_config_by_id(
         &self,
         id: &ProjectId,
@@ -336,7 +775,7 @@ impl ProjectRepository for PgProjectRepository {
         let row: Option<ProjectConfigRow> = sqlx::query_as::<_, ProjectConfigRow>(
             "SELECT COALESCE(config, '{}'::jsonb) AS config
              FROM project
-             WHERE id = $1",
+             WHERE id = $1 AND deleted_at IS NULL",
         )
         .bind(id)
         .fetch_optional(&mut *conn)
@@ -348,19 +787,18 @@ impl ProjectRepository for PgProjectRepository {
         Ok(row)
     }
 
+    #[instrument(name = "project.list_workflows_by_id", skip_all)]
     async fn list_workflows_by_id(
         &self,
         id: &ProjectId,
-        name: Option<&WorkflowName>,
-        after: Option<&WorkflowName>,
-        limit: Option<&i64>,
+        pagination: &Pagination,
         executor: impl PgAcquire<'_> + 'async_trait,
-    ) -> Result<Vec<WorkflowSummaryRow>> {
+    ) -> Result<Page<WorkflowSummaryRow>> {
         let mut conn = executor
             .acquire()
             .await
             .context("failed to acquire postgres connection")?;
-        let rows: Vec<WorkflowSummaryRow> = sqlx::query_as::<_, WorkflowSummaryRow>(
+        let sql = format!(
             "WITH these_runs AS (
                  SELECT
                      job.workflow_id AS workflow_id,
@@ -368,9 +806,9 @@ impl ProjectRepository for PgProjectRepository {
                  FROM workflow
                  JOIN job ON workflow.id = job.workflow_id
                  LEFT OUTER JOIN run ON run.job_id = job.id
-                 WHERE workflow.project_id = $1 AND (
+                 WHERE workflow.project_id = $1 AND workflow.deleted_at IS NULL AND (
                      run.finished_at IS NULL
-                     OR CURRENT_TIMESTAMP - run.finished_at < INTERVAL '1 hour'
+                     OR run.finished_at > CURRENT_TIMESTAMP - INTERVAL '1 hour'
                  )
              ),
              summaries AS (
@@ -386,38 +824,61 @@ impl ProjectRepository for PgProjectRepository {
                      SUM(CASE WHEN state = 'error' THEN 1 ELSE 0 END) as error
                  FROM these_runs
                  GROUP BY workflow_id
+             ),
+             misses AS (
+                 SELECT
+                     sla_miss.workflow_id,
+                     COUNT(*) FILTER (
+                         WHERE sla_miss.created_at > CURRENT_TIMESTAMP - INTERVAL '1 day'
+                     ) AS sla_missed,
+                     MAX(sla_miss.deadline) AS last_sla_missed_at
+                 FROM sla_miss
+                 JOIN workflow ON workflow.id = sla_miss.workflow_id
+                 WHERE workflow.project_id = $1 AND workflow.deleted_at IS NULL
+                 GROUP BY sla_miss.workflow_id
              )
              SELECT
-                 id,
-                 name,
-                 description,
-                 paused,
+                 workflow.id,
+                 workflow.name,
+                 workflow.description,
+                 workflow.paused,
                  COALESCE(success, 0) AS success,
                  COALESCE(running, 0) AS running,
                  COALESCE(failure, 0) AS failure,
                  COALESCE(waiting, 0) AS waiting,
-                 COALESCE(error,   0) AS error
+                 COALESCE(error,   0) AS error,
+                 COALESCE(sla_missed, 0) AS sla_missed,
+                 last_sla_missed_at,
+                 workflow.version,
+                 workflow.created_at,
+                 workflow.updated_at
              FROM workflow
              LEFT OUTER JOIN summaries ON workflow.id = summaries.workflow_id
+             LEFT OUTER JOIN misses ON workflow.id = misses.workflow_id
              WHERE
-                 project_id = $1
-                 AND ($3 IS NULL OR name = $2)
-                 AND ($2 IS NULL OR name > $3)
-             ORDER BY name
-             LIMIT $4",
-        )
-        .bind(id)
-        .bind(name)
-        .bind(after)
-        .bind(limit.unwrap_or(&100))
-        .fetch_all(&mut *conn)
-        .await
-        .context(format!(
-            r#"failed to list {} workflow summary(ies) of "{}" from [project]"#,
-            limit.unwrap_or(&100),
-            id.as_uuid()
-        ))?;
-        Ok(rows)
+                 workflow.project_id = $1
+                 AND workflow.deleted_at IS NULL
+                 AND ($2::VARCHAR IS NULL OR workflow.name LIKE $2)
+                 AND {}
+             {}
+             LIMIT $5",
+            pagination.seek("workflow", 3),
+            pagination.order_by("workflow"),
+        );
+        let rows: Vec<WorkflowSummaryRow> = sqlx::query_as::<_, WorkflowSummaryRow>(&sql)
+            .bind(id)
+            .bind(pagination.pattern())
+            .bind(pagination.after_key())
+            .bind(pagination.after_id())
+            .bind(pagination.fetch_limit())
+            .fetch_all(&mut *conn)
+            .await
+            .context(format!(
+                r#"failed to list {} workflow summary(ies) of "{}" from [project]"#,
+                pagination.limit(),
+                id.as_uuid()
+            ))?;
+        Page::new(rows, pagination)
     }
 }
 
@@ -426,24 +887,40 @@ mod tests {
     use super::*;
     use crate::controller::entities::job::Job;
     use crate::controller::entities::job::JobId;
+    use crate::controller::entities::page::PageOrder;
+    use crate::controller::entities::page::PageSort;
+    use crate::controller::entities::project::ProjectDescription;
     use crate::controller::entities::run::Run;
+    use crate::controller::entities::sla::SlaKind;
+    use crate::controller::entities::sla::SlaMiss;
     use crate::controller::entities::workflow::Workflow;
     use crate::controller::entities::workflow::WorkflowId;
     use crate::controller::repositories::job::JobRepository;
     use crate::controller::repositories::job::PgJobRepository;
     use crate::controller::repositories::run::PgRunRepository;
     use crate::controller::repositories::run::RunRepository;
+    use crate::controller::repositories::sla::PgSlaRepository;
+    use crate::controller::repositories::sla::SlaRepository;
     use crate::controller::repositories::workflow::PgWorkflowRepository;
     use crate::controller::repositories::workflow::WorkflowRepository;
     use crate::messages::run::RunPriority;
     use crate::messages::token::TokenState;
     use anyhow::Context;
     use anyhow::Result;
+    use chrono::DurationRound;
     use chrono::Utc;
     use serde_json::Value as Json;
     use sqlx::PgConnection;
     use sqlx::PgPool;
     use std::cmp::min;
+    use std::collections::HashSet;
+
+    const SORTS: [PageSort; 3] = [PageSort::Name, PageSort::CreatedAt, PageSort::UpdatedAt];
+
+    fn pagination(limit: impl Into<Option<i64>>, token: Option<&str>) -> Pagination {
+        Pagination::new(limit.into(), None, None, None, token, &SORTS)
+            .expect("pagination should be created")
+    }
 
     async fn create_project(
         config: impl Into<Option<Json>>,
@@ -569,6 +1046,60 @@ mod tests {
         Ok(())
     }
 
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_create_bumps_version(pool: PgPool) -> Result<()> {
+        let repo = PgProjectRepository;
+        let mut project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )
+        .expect("project should be created");
+        repo.create(&project, &pool)
+            .await
+            .expect("new project should be inserted");
+        let created = repo
+            .get_by_id(project.id(), &pool)
+            .await?
+            .expect("inserted project should be found");
+        assert_eq!(1, created.version);
+        let done = repo
+            .create(&project, &pool)
+            .await
+            .expect("unchanged project should be upserted");
+        assert_eq!(0, done.rows_affected());
+        let fetched = repo
+            .get_by_id(project.id(), &pool)
+            .await?
+            .expect("upserted project should be found");
+        assert_eq!(1, fetched.version);
+        assert_eq!(created.updated_at, fetched.updated_at);
+        project.set_description(ProjectDescription::new(testutils::rand::string(20))?);
+        repo.create(&project, &pool)
+            .await
+            .expect("changed project should be upserted");
+        let fetched = repo
+            .get_by_id(project.id(), &pool)
+            .await?
+            .expect("upserted project should be found");
+        assert_eq!(2, fetched.version);
+        assert!(fetched.updated_at > created.updated_at);
+        repo.update_config(project.id(), &serde_json::json!({ "retries": 1 }), &pool)
+            .await
+            .expect("config should be updated");
+        repo.touch(project.id(), &pool)
+            .await
+            .expect("project should be touched");
+        let fetched = repo
+            .get_by_id(project.id(), &pool)
+            .await?
+            .expect("updated project should be found");
+        assert_eq!(4, fetched.version);
+        Ok(())
+    }
+
     #[sqlx::test]
     #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
     async fn test_create_and_get_by_name(pool: PgPool) -> Result<()> {
@@ -613,10 +1144,11 @@ mod tests {
                 .expect("new project should be created");
         }
         let fetched = repo
-            .list(None, &mut tx)
+            .list(&pagination(None, None), &mut tx)
             .await
             .expect("inserted project should be listed");
-        assert_eq!(min(records, 100) as usize, fetched.len());
+        assert_eq!(min(records, 100) as usize, fetched.items.len());
+        assert_eq!(records > 100, fetched.next_page_token.is_some());
         tx.rollback()
             .await
             .expect("rollback should be done properly");
@@ -637,12 +1169,61 @@ mod tests {
                 .await
                 .expect("new project should be created");
         }
-        let limit = testutils::rand::i64(0, 200);
+        let limit = testutils::rand::i64(1, 200);
         let fetched = repo
-            .list(Some(&limit), &mut tx)
+            .list(&pagination(limit, None), &mut tx)
             .await
             .expect("inserted project should be listed");
-        assert_eq!(min(records, limit) as usize, fetched.len());
+        assert_eq!(min(records, limit) as usize, fetched.items.len());
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_create_and_list_by_page(pool: PgPool) -> Result<()> {
+        let repo = PgProjectRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let records = testutils::rand::usize(20) + 1;
+        for _ in 0..records {
+            create_project(None, &mut tx)
+                .await
+                .expect("new project should be created");
+        }
+        let mut seen = HashSet::new();
+        let mut token: Option<String> = None;
+        loop {
+            // NOTE: Projects share created_at within a transaction, so ties are broken by ids.
+            let pagination = match &token {
+                Some(token) => pagination(3, Some(token)),
+                None => Pagination::new(
+                    Some(3),
+                    Some(PageSort::CreatedAt),
+                    Some(PageOrder::Asc),
+                    None,
+                    None,
+                    &SORTS,
+                )
+                .expect("pagination should be created"),
+            };
+            let page = repo
+                .list(&pagination, &mut tx)
+                .await
+                .expect("inserted project should be listed");
+            for row in page.items.iter() {
+                assert!(seen.insert(row.id));
+            }
+            match page.next_page_token {
+                Some(next) => token = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(records, seen.len());
         tx.rollback()
             .await
             .expect("rollback should be done properly");
@@ -763,16 +1344,251 @@ mod tests {
             }
         }
         let fetched = repo
-            .list_workflows_by_id(&project.id(), None, None, None, &mut tx)
+            .list_workflows_by_id(project.id(), &pagination(None, None), &mut tx)
             .await
             .expect("inserted project should be found");
-        let len = fetched.len();
+        let len = fetched.items.len();
+        let first = fetched.items.first().map(|s| s.name.clone());
         let sum = fetched
+            .items
             .into_iter()
             .map(|s| s.success + s.running + s.failure + s.waiting + s.error)
             .sum::<i64>();
         assert_eq!(num_workflows, len);
         assert_eq!((num_workflows * num_jobs) as i64, sum);
+        if let Some(name) = first {
+            let prefixed = Pagination::new(None, None, None, Some(name.clone()), None, &SORTS)
+                .expect("pagination should be created");
+            let fetched = repo
+                .list_workflows_by_id(project.id(), &prefixed, &mut tx)
+                .await
+                .expect("inserted project should be found");
+            assert!(fetched.items.iter().all(|s| s.name.starts_with(&name)));
+            assert!(!fetched.items.is_empty());
+        }
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_create_and_soft_delete(pool: PgPool) -> Result<()> {
+        let repo = PgProjectRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let project = create_project(None, &mut tx)
+            .await
+            .expect("new project should be created");
+        let workflow = create_workflow(project.id(), &mut tx)
+            .await
+            .expect("new workflow should be created");
+        let done = repo
+            .soft_delete(project.id(), &mut tx)
+            .await
+            .expect("project should be soft deleted");
+        assert_eq!(done.rows_affected(), 1);
+        let fetched = repo
+            .get_by_id(project.id(), &mut tx)
+            .await
+            .expect("project should be queried");
+        assert!(fetched.is_none());
+        let fetched = PgWorkflowRepository
+            .get_by_id(workflow.id(), &mut tx)
+            .await
+            .expect("workflow should be queried");
+        assert!(fetched.is_none());
+        let done = repo
+            .soft_delete(project.id(), &mut tx)
+            .await
+            .expect("soft delete should be idempotent");
+        assert_eq!(done.rows_affected(), 0);
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_create_and_delete_cascade(pool: PgPool) -> Result<()> {
+        let repo = PgProjectRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let project = create_project(None, &mut tx)
+            .await
+            .expect("new project should be created");
+        let workflow = create_workflow(project.id(), &mut tx)
+            .await
+            .expect("new workflow should be created");
+        let job = create_job(workflow.id(), &mut tx)
+            .await
+            .expect("new job should be created");
+        let run = Run::new(
+            testutils::rand::uuid(),
+            TokenState::Running,
+            RunPriority::Normal,
+            job.id().as_uuid().to_string(),
+            Utc::now(),
+        )
+        .expect("new run should be created");
+        PgRunRepository
+            .create(&run, &mut tx)
+            .await
+            .expect("new run should be inserted");
+        let active = repo
+            .list_active_runs_by_id(project.id(), &mut tx)
+            .await
+            .expect("active runs should be listed");
+        assert_eq!(active.len(), 1);
+        assert_eq!(&active[0].id, run.id().as_uuid());
+        PgRunRepository
+            .delete(run.id(), &mut tx)
+            .await
+            .expect("run should be deleted");
+        let run = Run::new(
+            testutils::rand::uuid(),
+            TokenState::Success,
+            RunPriority::Normal,
+            job.id().as_uuid().to_string(),
+            Utc::now(),
+        )
+        .expect("new run should be created");
+        PgRunRepository
+            .create(&run, &mut tx)
+            .await
+            .expect("new run should be inserted");
+        let active = repo
+            .list_active_runs_by_id(project.id(), &mut tx)
+            .await
+            .expect("active runs should be listed");
+        assert!(active.is_empty());
+        let done = repo
+            .delete_cascade(project.id(), &mut tx)
+            .await
+            .expect("project should be deleted with its descendants");
+        assert_eq!(done.rows_affected(), 1);
+        let fetched = PgRunRepository
+            .get_by_id(run.id(), &mut tx)
+            .await
+            .expect("run should be queried");
+        assert!(fetched.is_none());
+        let fetched = PgJobRepository
+            .get_by_id(job.id(), &mut tx)
+            .await
+            .expect("job should be queried");
+        assert!(fetched.is_none());
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_list_workflows_with_sla_misses_and_delete_cascade(pool: PgPool) -> Result<()> {
+        let repo = PgProjectRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let project = create_project(None, &mut tx)
+            .await
+            .expect("new project should be created");
+        let workflow = create_workflow(project.id(), &mut tx)
+            .await
+            .expect("new workflow should be created");
+        let deadline = Utc::now()
+            .duration_trunc(chrono::Duration::seconds(1))
+            .expect("deadline should be truncated");
+        for execution_id in [None, Some(testutils::rand::uuid())] {
+            let miss = SlaMiss::new(
+                testutils::rand::uuid(),
+                workflow.id().as_uuid().to_string(),
+                execution_id,
+                SlaKind::By,
+                deadline,
+            )
+            .expect("new sla miss should be created");
+            PgSlaRepository
+                .create(&miss, &mut tx)
+                .await
+                .expect("new sla miss should be inserted");
+        }
+        let fetched = repo
+            .list_workflows_by_id(project.id(), &pagination(None, None), &mut tx)
+            .await
+            .expect("workflow summaries should be listed");
+        assert_eq!(fetched.items.len(), 1);
+        assert_eq!(fetched.items[0].sla_missed, 2);
+        assert_eq!(fetched.items[0].last_sla_missed_at, Some(deadline));
+        let done = repo
+            .delete_cascade(project.id(), &mut tx)
+            .await
+            .expect("project should be deleted with its sla misses");
+        assert_eq!(done.rows_affected(), 1);
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_delete_cascade_purges_archived_runs(pool: PgPool) -> Result<()> {
+        let repo = PgProjectRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let project = create_project(None, &mut tx)
+            .await
+            .expect("new project should be created");
+        let workflow = create_workflow(project.id(), &mut tx)
+            .await
+            .expect("new workflow should be created");
+        let job = create_job(workflow.id(), &mut tx)
+            .await
+            .expect("new job should be created");
+        sqlx::query(
+            "INSERT INTO run_archive (
+                 id,
+                 state,
+                 priority,
+                 job_id,
+                 triggered_at,
+                 attempt,
+                 args,
+                 envs,
+                 created_at,
+                 updated_at
+             ) VALUES ($1, 'success', 'normal', $2, $3, 1, $4, $5, $3, $3)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(job.id())
+        .bind(Utc::now())
+        .bind(vec![String::from("--bucket=s3://kotosiro")])
+        .bind(vec![String::from("OWNER=kotosiro")])
+        .execute(&mut tx)
+        .await
+        .expect("archived run should be inserted");
+        let done = repo
+            .delete_cascade(project.id(), &mut tx)
+            .await
+            .expect("project should be deleted with its archived runs");
+        assert_eq!(done.rows_affected(), 1);
+        let archived: i64 =
+            sqlx::query_scalar("SELECT COUNT(1) FROM run_archive WHERE job_id = $1")
+                .bind(job.id())
+                .fetch_one(&mut tx)
+                .await
+                .expect("archived runs should be counted");
+        assert_eq!(archived, 0);
         tx.rollback()
             .await
             .expect("rollback should be done properly");
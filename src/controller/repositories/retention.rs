@@ -0,0 +1,198 @@
+use crate::infra::postgres::PgAcquire;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::postgres::PgQueryResult;
+use tracing::instrument;
+
+#[async_trait]
+pub trait RetentionRepository: Send + Sync + 'static {
+    async fn archive_runs(
+        &self,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn delete_tokens(
+        &self,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn delete_executions(
+        &self,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+}
+
+pub struct PgRetentionRepository;
+
+#[async_trait]
+impl RetentionRepository for PgRetentionRepository {
+    #[instrument(name = "run_archive.archive_runs", skip_all)]
+    async fn archive_runs(
+        &self,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "WITH policies AS (
+                 SELECT
+                     job.id AS job_id,
+                     project.retention_days AS days,
+                     project.retention_runs AS runs
+                 FROM project
+                 JOIN workflow ON workflow.project_id = project.id
+                 JOIN job ON job.workflow_id = workflow.id
+                 WHERE project.retention_days IS NOT NULL OR project.retention_runs IS NOT NULL
+             ),
+             ranked AS (
+                 SELECT
+                     run.id AS id,
+                     run.finished_at AS finished_at,
+                     run.execution_id AS execution_id,
+                     policies.days AS days,
+                     policies.runs AS runs,
+                     ROW_NUMBER() OVER (
+                         PARTITION BY run.job_id
+                         ORDER BY run.finished_at DESC NULLS FIRST
+                     ) AS rank
+                 FROM run
+                 JOIN policies ON policies.job_id = run.job_id
+             ),
+             expired AS (
+                 SELECT id
+                 FROM ranked
+                 WHERE finished_at IS NOT NULL
+                 AND (days IS NULL OR finished_at < CURRENT_TIMESTAMP - days * INTERVAL '1 day')
+                 AND (runs IS NULL OR rank > runs)
+                 AND NOT EXISTS (
+                     SELECT 1 FROM stash WHERE stash.run_id = ranked.id
+                 )
+                 AND NOT EXISTS (
+                     SELECT 1
+                     FROM run AS sibling
+                     WHERE sibling.execution_id = ranked.execution_id
+                     AND sibling.finished_at IS NULL
+                 )
+                 LIMIT $1
+             ),
+             these_runs AS (
+                 SELECT id
+                 FROM run
+                 WHERE id IN (SELECT id FROM expired)
+                 FOR UPDATE SKIP LOCKED
+             ),
+             deleted_logs AS (
+                 DELETE FROM run_log
+                 WHERE run_id IN (SELECT id FROM these_runs)
+             ),
+             deleted_runs AS (
+                 DELETE FROM run
+                 WHERE id IN (SELECT id FROM these_runs)
+                 RETURNING *
+             )
+             INSERT INTO run_archive (
+                 id,
+                 state,
+                 priority,
+                 job_id,
+                 execution_id,
+                 triggered_at,
+                 started_at,
+                 finished_at,
+                 attempt,
+                 args,
+                 envs,
+                 created_at,
+                 updated_at
+             )
+             SELECT
+                 id,
+                 state,
+                 priority,
+                 job_id,
+                 execution_id,
+                 triggered_at,
+                 started_at,
+                 finished_at,
+                 attempt,
+                 args,
+                 envs,
+                 created_at,
+                 updated_at
+             FROM deleted_runs
+             ON CONFLICT(id) DO NOTHING",
+        )
+        .bind(limit.unwrap_or(&1000))
+        .execute(&mut *conn)
+        .await
+        .context("failed to archive expired runs from [run]")
+    }
+
+    #[instrument(name = "token.delete_expired", skip_all)]
+    async fn delete_tokens(
+        &self,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "DELETE FROM token
+             WHERE ctid IN (
+                 SELECT token.ctid
+                 FROM token
+                 JOIN job ON job.id = token.job_id
+                 JOIN workflow ON workflow.id = job.workflow_id
+                 JOIN project ON project.id = workflow.project_id
+                 WHERE project.retention_days IS NOT NULL
+                 AND token.created_at < CURRENT_TIMESTAMP - project.retention_days * INTERVAL '1 day'
+                 LIMIT $1
+                 FOR UPDATE OF token SKIP LOCKED
+             )",
+        )
+        .bind(limit.unwrap_or(&1000))
+        .execute(&mut *conn)
+        .await
+        .context("failed to delete expired tokens from [token]")
+    }
+
+    #[instrument(name = "workflow_run.delete_archived", skip_all)]
+    async fn delete_executions(
+        &self,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "DELETE FROM workflow_run
+             WHERE id IN (
+                 SELECT id
+                 FROM workflow_run
+                 WHERE NOT EXISTS (
+                     SELECT 1 FROM run WHERE run.execution_id = workflow_run.id
+                 )
+                 AND EXISTS (
+                     SELECT 1 FROM run_archive WHERE run_archive.execution_id = workflow_run.id
+                 )
+                 LIMIT $1
+                 FOR UPDATE SKIP LOCKED
+             )",
+        )
+        .bind(limit.unwrap_or(&1000))
+        .execute(&mut *conn)
+        .await
+        .context("failed to delete archived executions from [workflow_run]")
+    }
+}
@@ -1,15 +1,22 @@
+use crate::controller::entities::execution::ExecutionId;
+use crate::controller::entities::job::JobId;
+use crate::controller::entities::page::Keyset;
+use crate::controller::entities::page::Page;
+use crate::controller::entities::page::Pagination;
 use crate::controller::entities::run::Run;
 use crate::controller::entities::run::RunId;
 use crate::infra::postgres::PgAcquire;
+use crate::messages::token::TokenState;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::DateTime;
 use chrono::Utc;
 use sqlx::postgres::PgQueryResult;
+use tracing::instrument;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
 pub struct RunRow {
     pub id: Uuid,
     pub state: String,
@@ -18,10 +25,40 @@ pub struct RunRow {
     pub triggered_at: DateTime<Utc>,
     pub started_at: Option<DateTime<Utc>>,
     pub finished_at: Option<DateTime<Utc>>,
+    pub attempt: i32,
+    pub args: Option<Vec<String>>,
+    pub envs: Option<Vec<String>>,
+    pub execution_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+impl Keyset for RunRow {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+pub struct RunCountRow {
+    pub project: String,
+    pub workflow: String,
+    pub state: String,
+    pub count: i64,
+}
+
 #[async_trait]
 pub trait RunRepository: Send + Sync + 'static {
     async fn create(
@@ -41,12 +78,56 @@ pub trait RunRepository: Send + Sync + 'static {
         id: &RunId,
         executor: impl PgAcquire<'_> + 'async_trait,
     ) -> Result<Option<RunRow>>;
+
+    async fn list_by_job_id(
+        &self,
+        job_id: &JobId,
+        pagination: &Pagination,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Page<RunRow>>;
+
+    async fn cancel(
+        &self,
+        id: &RunId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn count_by_state(
+        &self,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<RunCountRow>>;
+
+    async fn update_state(
+        &self,
+        id: &RunId,
+        state: &TokenState,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn mark_dispatched(
+        &self,
+        id: &RunId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn list_ready_by_execution_id(
+        &self,
+        execution_id: &ExecutionId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<RunRow>>;
+
+    async fn cancel_by_execution_id(
+        &self,
+        execution_id: &ExecutionId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
 }
 
 pub struct PgRunRepository;
 
 #[async_trait]
 impl RunRepository for PgRunRepository {
+    #[instrument(name = "run.create", skip_all)]
     async fn create(
         &self,
         run: &Run,
@@ -64,14 +145,22 @@ impl RunRepository for PgRunRepository {
                  job_id,
                  triggered_at,
                  started_at,
-                 finished_at
-             ) VALUES ($1, $2, $3, $4, $5, NULL, NULL)",
+                 finished_at,
+                 attempt,
+                 args,
+                 envs,
+                 execution_id
+             ) VALUES ($1, $2, $3, $4, $5, NULL, NULL, $6, $7, $8, $9)",
         )
         .bind(run.id())
         .bind(run.state())
         .bind(run.priority())
         .bind(run.job_id())
         .bind(run.triggered_at())
+        .bind(run.attempt())
+        .bind(run.args())
+        .bind(run.envs())
+        .bind(run.execution_id())
         .execute(&mut *conn)
         .await
         .context(format!(
@@ -80,6 +169,7 @@ impl RunRepository for PgRunRepository {
         ))
     }
 
+    #[instrument(name = "run.delete", skip_all)]
     async fn delete(
         &self,
         id: &RunId,
@@ -99,6 +189,7 @@ impl RunRepository for PgRunRepository {
         .context(format!(r#"failed to delete "{}" from [run]"#, id.as_uuid()))
     }
 
+    #[instrument(name = "run.get_by_id", skip_all)]
     async fn get_by_id(
         &self,
         id: &RunId,
@@ -117,6 +208,10 @@ impl RunRepository for PgRunRepository {
                  triggered_at,
                  started_at,
                  finished_at,
+                 attempt,
+                 args,
+                 envs,
+                 execution_id,
                  created_at,
                  updated_at
              FROM run
@@ -128,6 +223,248 @@ impl RunRepository for PgRunRepository {
         .context(format!(r#"failed to select "{}" from [run]"#, id.as_uuid()))?;
         Ok(row)
     }
+
+    #[instrument(name = "run.list_by_job_id", skip_all)]
+    async fn list_by_job_id(
+        &self,
+        job_id: &JobId,
+        pagination: &Pagination,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Page<RunRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let sql = format!(
+            "SELECT
+                 id,
+                 state,
+                 priority,
+                 job_id,
+                 triggered_at,
+                 started_at,
+                 finished_at,
+                 attempt,
+                 args,
+                 envs,
+                 execution_id,
+                 created_at,
+                 updated_at
+             FROM run
+             WHERE job_id = $1 AND {}
+             {}
+             LIMIT $4",
+            pagination.seek("run", 2),
+            pagination.order_by("run"),
+        );
+        let rows: Vec<RunRow> = sqlx::query_as::<_, RunRow>(&sql)
+            .bind(job_id)
+            .bind(pagination.after_key())
+            .bind(pagination.after_id())
+            .bind(pagination.fetch_limit())
+            .fetch_all(&mut *conn)
+            .await
+            .context(format!(
+                r#"failed to list runs of "{}" from [run]"#,
+                job_id.as_uuid()
+            ))?;
+        Page::new(rows, pagination)
+    }
+
+    #[instrument(name = "run.cancel", skip_all)]
+    async fn cancel(
+        &self,
+        id: &RunId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "UPDATE run
+             SET state = 'cancelled',
+                 finished_at = CURRENT_TIMESTAMP,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = $1 AND state IN ('waiting', 'active', 'running')",
+        )
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .context(format!(r#"failed to cancel "{}" in [run]"#, id.as_uuid()))
+    }
+
+    #[instrument(name = "run.count_by_state", skip_all)]
+    async fn count_by_state(
+        &self,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<RunCountRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<RunCountRow> = sqlx::query_as::<_, RunCountRow>(
+            "SELECT
+                 project.name AS project,
+                 workflow.name AS workflow,
+                 run.state AS state,
+                 COUNT(1) AS count
+             FROM run
+             JOIN job ON job.id = run.job_id
+             JOIN workflow ON workflow.id = job.workflow_id
+             JOIN project ON project.id = workflow.project_id
+             WHERE project.deleted_at IS NULL
+             GROUP BY project.name, workflow.name, run.state",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("failed to count runs by state from [run]")?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "run.update_state", skip_all)]
+    async fn update_state(
+        &self,
+        id: &RunId,
+        state: &TokenState,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "UPDATE run
+             SET state = $2,
+                 started_at = CASE
+                     WHEN $2 = 'running' THEN COALESCE(started_at, CURRENT_TIMESTAMP)
+                     ELSE started_at
+                 END,
+                 finished_at = CASE
+                     WHEN $2 IN ('success', 'failure', 'error', 'cancelled') THEN CURRENT_TIMESTAMP
+                     ELSE finished_at
+                 END,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = $1 AND state IN ('waiting', 'active', 'running')",
+        )
+        .bind(id)
+        .bind(state)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to update state of "{}" in [run]"#,
+            id.as_uuid()
+        ))
+    }
+
+    #[instrument(name = "run.mark_dispatched", skip_all)]
+    async fn mark_dispatched(
+        &self,
+        id: &RunId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "UPDATE run
+             SET dispatched_at = CURRENT_TIMESTAMP,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = $1",
+        )
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to mark "{}" as dispatched in [run]"#,
+            id.as_uuid()
+        ))
+    }
+
+    #[instrument(name = "run.list_ready_by_execution_id", skip_all)]
+    async fn list_ready_by_execution_id(
+        &self,
+        execution_id: &ExecutionId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<RunRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<RunRow> = sqlx::query_as::<_, RunRow>(
+            "WITH latest AS (
+                 SELECT DISTINCT ON (run.job_id)
+                     job.name,
+                     run.state
+                 FROM run
+                 JOIN job ON job.id = run.job_id
+                 WHERE run.execution_id = $1
+                 ORDER BY run.job_id, run.attempt DESC, run.created_at DESC
+             )
+             SELECT
+                 run.id,
+                 run.state,
+                 run.priority,
+                 run.job_id,
+                 run.triggered_at,
+                 run.started_at,
+                 run.finished_at,
+                 run.attempt,
+                 run.args,
+                 run.envs,
+                 run.execution_id,
+                 run.created_at,
+                 run.updated_at
+             FROM run
+             JOIN job ON job.id = run.job_id
+             WHERE run.execution_id = $1
+             AND run.state = 'waiting'
+             AND run.dispatched_at IS NULL
+             AND NOT EXISTS (
+                 SELECT 1
+                 FROM latest
+                 WHERE latest.name = ANY(job.depends_on)
+                 AND latest.state != 'success'
+             )
+             ORDER BY job.name
+             FOR UPDATE OF run",
+        )
+        .bind(execution_id)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list ready runs of "{}" from [run]"#,
+            execution_id.as_uuid()
+        ))?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "run.cancel_by_execution_id", skip_all)]
+    async fn cancel_by_execution_id(
+        &self,
+        execution_id: &ExecutionId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "UPDATE run
+             SET state = 'cancelled',
+                 finished_at = CURRENT_TIMESTAMP,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE execution_id = $1 AND state IN ('waiting', 'active', 'running')",
+        )
+        .bind(execution_id)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to cancel runs of "{}" in [run]"#,
+            execution_id.as_uuid()
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -135,6 +472,7 @@ mod tests {
     use super::*;
     use crate::controller::entities::job::Job;
     use crate::controller::entities::job::JobId;
+    use crate::controller::entities::page::PageSort;
     use crate::controller::entities::project::Project;
     use crate::controller::entities::project::ProjectId;
     use crate::controller::entities::workflow::Workflow;
@@ -283,4 +621,104 @@ mod tests {
             .expect("rollback should be done properly");
         Ok(())
     }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_create_and_count_by_state(pool: PgPool) -> Result<()> {
+        let repo = PgRunRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let project = create_project(&mut tx)
+            .await
+            .expect("new project should be created");
+        let workflow = create_workflow(project.id(), &mut tx)
+            .await
+            .expect("new workflow should be created");
+        let job = create_job(workflow.id(), &mut tx)
+            .await
+            .expect("new job should be created");
+        let num_runs = testutils::rand::i64(1, 20);
+        for _ in 0..num_runs {
+            create_run(job.id(), &mut tx)
+                .await
+                .expect("new run should be created");
+        }
+        let fetched = repo
+            .count_by_state(&mut tx)
+            .await
+            .expect("inserted runs should be counted");
+        let sum = fetched
+            .into_iter()
+            .filter(|c| c.project == project.name().as_str())
+            .filter(|c| c.workflow == workflow.name().as_str())
+            .map(|c| c.count)
+            .sum::<i64>();
+        assert_eq!(num_runs, sum);
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_create_list_and_cancel(pool: PgPool) -> Result<()> {
+        let repo = PgRunRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let project = create_project(&mut tx)
+            .await
+            .expect("new project should be created");
+        let workflow = create_workflow(project.id(), &mut tx)
+            .await
+            .expect("new workflow should be created");
+        let job = create_job(workflow.id(), &mut tx)
+            .await
+            .expect("new job should be created");
+        let run = Run::new(
+            testutils::rand::uuid(),
+            TokenState::Running,
+            RunPriority::Normal,
+            job.id().as_uuid().to_string(),
+            Utc::now(),
+        )
+        .expect("new run should be created");
+        repo.create(&run, &mut tx)
+            .await
+            .expect("new run should be inserted");
+        let fetched = repo
+            .list_by_job_id(
+                job.id(),
+                &Pagination::new(None, None, None, None, None, &[PageSort::CreatedAt])?,
+                &mut tx,
+            )
+            .await
+            .expect("inserted runs should be listed");
+        assert_eq!(fetched.items.len(), 1);
+        let done = repo
+            .cancel(run.id(), &mut tx)
+            .await
+            .expect("run should be cancelled");
+        assert_eq!(done.rows_affected(), 1);
+        let done = repo
+            .cancel(run.id(), &mut tx)
+            .await
+            .expect("cancelled run should be left as is");
+        assert_eq!(done.rows_affected(), 0);
+        let fetched = repo
+            .get_by_id(run.id(), &mut tx)
+            .await
+            .expect("cancelled run should be found")
+            .expect("cancelled run should exist");
+        assert_eq!(&fetched.state, TokenState::Cancelled.as_ref());
+        assert!(fetched.finished_at.is_some());
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
 }
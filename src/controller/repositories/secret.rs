@@ -0,0 +1,242 @@
+use crate::controller::entities::project::ProjectId;
+use crate::controller::entities::secret::Secret;
+use crate::controller::entities::secret::SecretName;
+use crate::infra::postgres::PgAcquire;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
+use sqlx::postgres::PgQueryResult;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
+pub struct SecretRow {
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, sqlx::FromRow)]
+pub struct SealedSecretRow {
+    pub name: String,
+    pub value: Vec<u8>,
+}
+
+#[async_trait]
+pub trait SecretRepository: Send + Sync + 'static {
+    async fn create(
+        &self,
+        secret: &Secret,
+        sealed: &[u8],
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn delete(
+        &self,
+        project_id: &ProjectId,
+        name: &SecretName,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn list_by_project_id(
+        &self,
+        project_id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<SecretRow>>;
+
+    async fn list_sealed_by_project_id(
+        &self,
+        project_id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<SealedSecretRow>>;
+}
+
+pub struct PgSecretRepository;
+
+#[async_trait]
+impl SecretRepository for PgSecretRepository {
+    #[instrument(name = "secret.create", skip_all)]
+    async fn create(
+        &self,
+        secret: &Secret,
+        sealed: &[u8],
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "INSERT INTO secret (
+                 id,
+                 project_id,
+                 name,
+                 value
+             ) VALUES ($1, $2, $3, $4)
+             ON CONFLICT(project_id, name)
+             DO UPDATE
+             SET value = $4,
+                 updated_at = CURRENT_TIMESTAMP",
+        )
+        .bind(secret.id())
+        .bind(secret.project_id())
+        .bind(secret.name())
+        .bind(sealed)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to upsert "{}" into [secret]"#,
+            secret.name().as_str()
+        ))
+    }
+
+    #[instrument(name = "secret.delete", skip_all)]
+    async fn delete(
+        &self,
+        project_id: &ProjectId,
+        name: &SecretName,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "DELETE FROM secret
+             WHERE project_id = $1 AND name = $2",
+        )
+        .bind(project_id)
+        .bind(name)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to delete "{}" from [secret]"#,
+            name.as_str()
+        ))
+    }
+
+    #[instrument(name = "secret.list_by_project_id", skip_all)]
+    async fn list_by_project_id(
+        &self,
+        project_id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<SecretRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<SecretRow> = sqlx::query_as::<_, SecretRow>(
+            "SELECT
+                 id,
+                 project_id,
+                 name,
+                 created_at,
+                 updated_at
+             FROM secret
+             WHERE project_id = $1
+             ORDER BY name",
+        )
+        .bind(project_id)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list secrets of "{}" from [secret]"#,
+            project_id.as_uuid()
+        ))?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "secret.list_sealed_by_project_id", skip_all)]
+    async fn list_sealed_by_project_id(
+        &self,
+        project_id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<SealedSecretRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<SealedSecretRow> = sqlx::query_as::<_, SealedSecretRow>(
+            "SELECT
+                 name,
+                 value
+             FROM secret
+             WHERE project_id = $1",
+        )
+        .bind(project_id)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list sealed secrets of "{}" from [secret]"#,
+            project_id.as_uuid()
+        ))?;
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::project::Project;
+    use crate::controller::repositories::project::PgProjectRepository;
+    use crate::controller::repositories::project::ProjectRepository;
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_create_list_and_delete(pool: PgPool) -> Result<()> {
+        let repo = PgSecretRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )
+        .expect("new project should be created");
+        PgProjectRepository
+            .create(&project, &mut tx)
+            .await
+            .expect("new project should be inserted");
+        let secret = Secret::new(
+            testutils::rand::uuid(),
+            project.id().as_uuid().to_string(),
+            String::from("TOKEN"),
+            testutils::rand::string(10),
+        )
+        .expect("new secret should be created");
+        repo.create(&secret, b"sealed", &mut tx)
+            .await
+            .expect("new secret should be inserted");
+        repo.create(&secret, b"resealed", &mut tx)
+            .await
+            .expect("existing secret should be updated");
+        let rows = repo
+            .list_by_project_id(project.id(), &mut tx)
+            .await
+            .expect("secrets should be listed");
+        assert_eq!(rows.len(), 1);
+        assert_eq!(&rows[0].name, "TOKEN");
+        let sealed = repo
+            .list_sealed_by_project_id(project.id(), &mut tx)
+            .await
+            .expect("sealed secrets should be listed");
+        assert_eq!(sealed[0].value, b"resealed");
+        let deleted = repo
+            .delete(project.id(), secret.name(), &mut tx)
+            .await
+            .expect("secret should be deleted");
+        assert_eq!(deleted.rows_affected(), 1);
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+}
@@ -0,0 +1,437 @@
+use crate::controller::entities::sla::SlaMiss;
+use crate::controller::entities::workflow::WorkflowId;
+use crate::infra::postgres::PgAcquire;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
+use sqlx::postgres::PgQueryResult;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+pub struct LateExecutionRow {
+    pub execution_id: Uuid,
+    pub workflow_id: Uuid,
+    pub project_id: Uuid,
+    pub deadline: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
+pub struct DailySlaRow {
+    pub workflow_id: Uuid,
+    pub project_id: Uuid,
+    pub sla_by: String,
+    pub sla_timezone: Option<String>,
+    pub sla_since: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait SlaRepository: Send + Sync + 'static {
+    async fn create(
+        &self,
+        miss: &SlaMiss,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn list_late_executions(
+        &self,
+        now: &DateTime<Utc>,
+        limit: &i64,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<LateExecutionRow>>;
+
+    async fn list_daily(
+        &self,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<DailySlaRow>>;
+
+    async fn is_met_by(
+        &self,
+        workflow_id: &WorkflowId,
+        deadline: &DateTime<Utc>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<bool>;
+}
+
+pub struct PgSlaRepository;
+
+#[async_trait]
+impl SlaRepository for PgSlaRepository {
+    #[instrument(name = "sla_miss.create", skip_all)]
+    async fn create(
+        &self,
+        miss: &SlaMiss,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "INSERT INTO sla_miss (
+                 id,
+                 workflow_id,
+                 execution_id,
+                 kind,
+                 deadline
+             ) VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(miss.id())
+        .bind(miss.workflow_id())
+        .bind(miss.execution_id())
+        .bind(miss.kind().as_ref())
+        .bind(miss.deadline())
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to insert "{}" into [sla_miss]"#,
+            miss.id().as_uuid()
+        ))
+    }
+
+    // NOTE: Deadlines older than a day are not looked back so that executions met on time are not rescanned forever.
+    // NOTE: Only the latest attempt of each job counts so that a job rerun on time does not miss the deadline.
+    // NOTE: Backfilled executions are triggered at past scheduled times, so their deadlines start when they are created.
+    #[instrument(name = "sla_miss.list_late_executions", skip_all)]
+    async fn list_late_executions(
+        &self,
+        now: &DateTime<Utc>,
+        limit: &i64,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<LateExecutionRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<LateExecutionRow> = sqlx::query_as::<_, LateExecutionRow>(
+            "WITH these_executions AS (
+                 SELECT
+                     workflow_run.id AS execution_id,
+                     workflow.id AS workflow_id,
+                     workflow.project_id AS project_id,
+                     GREATEST(workflow_run.triggered_at, workflow_run.created_at) + workflow.sla_within_secs * INTERVAL '1 second' AS deadline,
+                     workflow.sla_since AS sla_since
+                 FROM workflow_run
+                 JOIN workflow ON workflow.id = workflow_run.workflow_id
+                 WHERE workflow.sla_within_secs IS NOT NULL
+                 AND workflow.paused = false
+                 AND workflow.deleted_at IS NULL
+             )
+             SELECT
+                 execution_id,
+                 workflow_id,
+                 project_id,
+                 deadline
+             FROM these_executions
+             WHERE deadline <= $1
+             AND deadline > $1 - INTERVAL '1 day'
+             AND deadline > sla_since
+             AND NOT EXISTS (
+                 SELECT 1
+                 FROM sla_miss
+                 WHERE sla_miss.execution_id = these_executions.execution_id
+             )
+             AND EXISTS (
+                 SELECT 1
+                 FROM (
+                     SELECT DISTINCT ON (run.job_id) run.state, run.finished_at
+                     FROM run
+                     WHERE run.execution_id = these_executions.execution_id
+                     ORDER BY run.job_id, run.attempt DESC, run.created_at DESC
+                 ) AS latest
+                 WHERE latest.state <> 'success'
+                 OR latest.finished_at IS NULL
+                 OR latest.finished_at > these_executions.deadline
+             )
+             ORDER BY deadline
+             LIMIT $2",
+        )
+        .bind(now)
+        .bind(limit)
+        .fetch_all(&mut *conn)
+        .await
+        .context("failed to list late executions from [workflow_run]")?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "sla_miss.list_daily", skip_all)]
+    async fn list_daily(
+        &self,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<DailySlaRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<DailySlaRow> = sqlx::query_as::<_, DailySlaRow>(
+            "SELECT
+                 id AS workflow_id,
+                 project_id,
+                 sla_by,
+                 sla_timezone,
+                 sla_since
+             FROM workflow
+             WHERE sla_by IS NOT NULL
+             AND paused = false
+             AND deleted_at IS NULL",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("failed to list daily slas from [workflow]")?;
+        Ok(rows)
+    }
+
+    // NOTE: A daily deadline is met by any execution triggered within the preceding day and fully succeeded by then.
+    #[instrument(name = "sla_miss.is_met_by", skip_all)]
+    async fn is_met_by(
+        &self,
+        workflow_id: &WorkflowId,
+        deadline: &DateTime<Utc>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<bool> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let (met,): (bool,) = sqlx::query_as::<_, (bool,)>(
+            "SELECT EXISTS (
+                 SELECT 1
+                 FROM workflow_run
+                 WHERE workflow_run.workflow_id = $1
+                 AND workflow_run.triggered_at > $2 - INTERVAL '1 day'
+                 AND workflow_run.triggered_at <= $2
+                 AND EXISTS (
+                     SELECT 1
+                     FROM run
+                     WHERE run.execution_id = workflow_run.id
+                 )
+                 AND NOT EXISTS (
+                     SELECT 1
+                     FROM (
+                         SELECT DISTINCT ON (run.job_id) run.state, run.finished_at
+                         FROM run
+                         WHERE run.execution_id = workflow_run.id
+                         ORDER BY run.job_id, run.attempt DESC, run.created_at DESC
+                     ) AS latest
+                     WHERE latest.state <> 'success'
+                     OR latest.finished_at IS NULL
+                     OR latest.finished_at > $2
+                 )
+             )",
+        )
+        .bind(workflow_id)
+        .bind(deadline)
+        .fetch_one(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to check deadline of "{}" in [workflow_run]"#,
+            workflow_id.as_uuid()
+        ))?;
+        Ok(met)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::execution::Execution;
+    use crate::controller::entities::job::Job;
+    use crate::controller::entities::project::Project;
+    use crate::controller::entities::run::Run;
+    use crate::controller::entities::sla::SlaKind;
+    use crate::controller::entities::workflow::Workflow;
+    use crate::controller::entities::workflow::WorkflowSla;
+    use crate::controller::repositories::execution::ExecutionRepository;
+    use crate::controller::repositories::execution::PgExecutionRepository;
+    use crate::controller::repositories::job::JobRepository;
+    use crate::controller::repositories::job::PgJobRepository;
+    use crate::controller::repositories::project::PgProjectRepository;
+    use crate::controller::repositories::project::ProjectRepository;
+    use crate::controller::repositories::run::PgRunRepository;
+    use crate::controller::repositories::run::RunRepository;
+    use crate::controller::repositories::workflow::PgWorkflowRepository;
+    use crate::controller::repositories::workflow::WorkflowRepository;
+    use crate::messages::run::RunPriority;
+    use crate::messages::token::TokenState;
+    use chrono::Duration;
+    use sqlx::PgConnection;
+    use sqlx::PgPool;
+
+    async fn create_workflow(sla: WorkflowSla, tx: &mut PgConnection) -> Result<(Workflow, Job)> {
+        let project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )?;
+        PgProjectRepository.create(&project, &mut *tx).await?;
+        let mut workflow = Workflow::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            project.id().as_uuid().to_string(),
+            testutils::rand::string(10),
+            false,
+        )?;
+        workflow.set_sla(Some(sla));
+        PgWorkflowRepository.create(&workflow, &mut *tx).await?;
+        sqlx::query("UPDATE workflow SET sla_since = $2 WHERE id = $1")
+            .bind(workflow.id())
+            .bind(Utc::now() - Duration::days(2))
+            .execute(&mut *tx)
+            .await?;
+        let job = Job::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            workflow.id().as_uuid().to_string(),
+            0,
+            testutils::rand::string(10),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        PgJobRepository.create(&job, &mut *tx).await?;
+        Ok((workflow, job))
+    }
+
+    async fn create_execution(
+        job: &Job,
+        triggered_at: DateTime<Utc>,
+        state: TokenState,
+        tx: &mut PgConnection,
+    ) -> Result<Uuid> {
+        let execution = Execution::new(
+            testutils::rand::uuid(),
+            job.workflow_id().as_uuid().to_string(),
+            RunPriority::Normal,
+            triggered_at,
+        )?;
+        PgExecutionRepository.create(&execution, &mut *tx).await?;
+        sqlx::query("UPDATE workflow_run SET created_at = $2 WHERE id = $1")
+            .bind(execution.id())
+            .bind(triggered_at)
+            .execute(&mut *tx)
+            .await?;
+        let mut run = Run::new(
+            testutils::rand::uuid(),
+            TokenState::Waiting,
+            RunPriority::Normal,
+            job.id().as_uuid().to_string(),
+            triggered_at,
+        )?;
+        run.set_execution_id(Some(execution.id().clone()));
+        PgRunRepository.create(&run, &mut *tx).await?;
+        PgRunRepository
+            .update_state(run.id(), &state, &mut *tx)
+            .await?;
+        Ok(execution.id().to_uuid())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_list_late_executions_and_create(pool: PgPool) -> Result<()> {
+        let repo = PgSlaRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let now = Utc::now();
+        let (workflow, job) =
+            create_workflow(WorkflowSla::new(Some(3600), None, None)?, &mut tx).await?;
+        let failed =
+            create_execution(&job, now - Duration::hours(2), TokenState::Failure, &mut tx).await?;
+        let met =
+            create_execution(&job, now - Duration::hours(2), TokenState::Success, &mut tx).await?;
+        sqlx::query("UPDATE run SET finished_at = $2 WHERE execution_id = $1")
+            .bind(met)
+            .bind(now - Duration::minutes(90))
+            .execute(&mut *tx)
+            .await?;
+        let running = create_execution(
+            &job,
+            now - Duration::minutes(30),
+            TokenState::Running,
+            &mut tx,
+        )
+        .await?;
+        let backfilled =
+            create_execution(&job, now - Duration::hours(2), TokenState::Failure, &mut tx).await?;
+        sqlx::query("UPDATE workflow_run SET created_at = $2 WHERE id = $1")
+            .bind(backfilled)
+            .bind(now - Duration::minutes(10))
+            .execute(&mut *tx)
+            .await?;
+        let late = repo.list_late_executions(&now, &10, &mut tx).await?;
+        assert_eq!(late.len(), 1);
+        assert_eq!(late[0].execution_id, failed);
+        assert_eq!(&late[0].workflow_id, workflow.id().as_uuid());
+        let miss = SlaMiss::new(
+            testutils::rand::uuid(),
+            workflow.id().as_uuid().to_string(),
+            Some(failed.to_string()),
+            SlaKind::Within,
+            late[0].deadline,
+        )?;
+        assert_eq!(repo.create(&miss, &mut tx).await?.rows_affected(), 1);
+        let again = SlaMiss::new(
+            testutils::rand::uuid(),
+            workflow.id().as_uuid().to_string(),
+            Some(failed.to_string()),
+            SlaKind::Within,
+            late[0].deadline,
+        )?;
+        assert_eq!(repo.create(&again, &mut tx).await?.rows_affected(), 0);
+        let late = repo
+            .list_late_executions(&(now + Duration::hours(1)), &10, &mut tx)
+            .await?;
+        assert_eq!(late.len(), 2);
+        assert_eq!(late[0].execution_id, running);
+        assert_eq!(late[1].execution_id, backfilled);
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_list_daily_and_is_met_by(pool: PgPool) -> Result<()> {
+        let repo = PgSlaRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let now = Utc::now();
+        let (workflow, job) = create_workflow(
+            WorkflowSla::new(
+                None,
+                Some(String::from("06:00")),
+                Some(String::from("Asia/Tokyo")),
+            )?,
+            &mut tx,
+        )
+        .await?;
+        let daily = repo.list_daily(&mut tx).await?;
+        let row = daily
+            .iter()
+            .find(|row| &row.workflow_id == workflow.id().as_uuid())
+            .expect("daily sla should be listed");
+        assert_eq!(row.sla_by, "06:00");
+        assert_eq!(row.sla_timezone.as_deref(), Some("Asia/Tokyo"));
+        assert!(!repo.is_met_by(workflow.id(), &now, &mut tx).await?);
+        create_execution(&job, now - Duration::hours(1), TokenState::Failure, &mut tx).await?;
+        assert!(!repo.is_met_by(workflow.id(), &now, &mut tx).await?);
+        create_execution(&job, now - Duration::hours(1), TokenState::Success, &mut tx).await?;
+        assert!(repo.is_met_by(workflow.id(), &now, &mut tx).await?);
+        assert!(
+            !repo
+                .is_met_by(workflow.id(), &(now - Duration::hours(2)), &mut tx)
+                .await?
+        );
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+}
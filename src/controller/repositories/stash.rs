@@ -0,0 +1,445 @@
+use crate::controller::entities::project::ProjectId;
+use crate::controller::entities::run::RunId;
+use crate::controller::entities::stash::Stash;
+use crate::controller::entities::stash::StashName;
+use crate::infra::postgres::PgAcquire;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
+use sqlx::postgres::PgQueryResult;
+use tracing::instrument;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, serde::Serialize, sqlx::FromRow, utoipa::ToSchema)]
+pub struct StashRow {
+    pub id: Uuid,
+    pub run_id: Uuid,
+    pub name: String,
+    pub content_type: String,
+    pub size: i64,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait StashRepository: Send + Sync + 'static {
+    async fn create(
+        &self,
+        stash: &Stash,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn get_by_name(
+        &self,
+        run_id: &RunId,
+        name: &StashName,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<StashRow>>;
+
+    async fn list_by_run_id(
+        &self,
+        run_id: &RunId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<StashRow>>;
+
+    async fn list_by_project_id(
+        &self,
+        project_id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<StashRow>>;
+
+    async fn delete_expired(
+        &self,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<StashRow>>;
+
+    async fn is_shared(
+        &self,
+        owner: &RunId,
+        reader: &RunId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<bool>;
+}
+
+pub struct PgStashRepository;
+
+#[async_trait]
+impl StashRepository for PgStashRepository {
+    #[instrument(name = "stash.create", skip_all)]
+    async fn create(
+        &self,
+        stash: &Stash,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "INSERT INTO stash (
+                 id,
+                 run_id,
+                 name,
+                 content_type,
+                 size,
+                 expires_at
+             ) VALUES ($1, $2, $3, $4, $5, $6)
+             ON CONFLICT(run_id, name)
+             DO UPDATE
+             SET content_type = $4,
+                 size = $5,
+                 expires_at = $6,
+                 created_at = CURRENT_TIMESTAMP",
+        )
+        .bind(stash.id())
+        .bind(stash.run_id())
+        .bind(stash.name())
+        .bind(stash.content_type())
+        .bind(stash.size())
+        .bind(stash.expires_at())
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to upsert "{}" into [stash]"#,
+            stash.key()
+        ))
+    }
+
+    #[instrument(name = "stash.get_by_name", skip_all)]
+    async fn get_by_name(
+        &self,
+        run_id: &RunId,
+        name: &StashName,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<StashRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let row: Option<StashRow> = sqlx::query_as::<_, StashRow>(
+            "SELECT
+                 id,
+                 run_id,
+                 name,
+                 content_type,
+                 size,
+                 created_at,
+                 expires_at
+             FROM stash
+             WHERE run_id = $1 AND name = $2
+             AND expires_at > CURRENT_TIMESTAMP",
+        )
+        .bind(run_id)
+        .bind(name)
+        .fetch_optional(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to select "{}" from [stash]"#,
+            name.as_str()
+        ))?;
+        Ok(row)
+    }
+
+    #[instrument(name = "stash.list_by_run_id", skip_all)]
+    async fn list_by_run_id(
+        &self,
+        run_id: &RunId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<StashRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<StashRow> = sqlx::query_as::<_, StashRow>(
+            "SELECT
+                 id,
+                 run_id,
+                 name,
+                 content_type,
+                 size,
+                 created_at,
+                 expires_at
+             FROM stash
+             WHERE run_id = $1
+             AND expires_at > CURRENT_TIMESTAMP
+             ORDER BY name",
+        )
+        .bind(run_id)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list stashes of "{}" from [stash]"#,
+            run_id.as_uuid()
+        ))?;
+        Ok(rows)
+    }
+
+    // NOTE: Expired stashes are listed as well, their blobs remain until the rows are swept.
+    #[instrument(name = "stash.list_by_project_id", skip_all)]
+    async fn list_by_project_id(
+        &self,
+        project_id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<StashRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<StashRow> = sqlx::query_as::<_, StashRow>(
+            "SELECT
+                 stash.id,
+                 stash.run_id,
+                 stash.name,
+                 stash.content_type,
+                 stash.size,
+                 stash.created_at,
+                 stash.expires_at
+             FROM stash
+             JOIN run ON run.id = stash.run_id
+             JOIN job ON job.id = run.job_id
+             JOIN workflow ON workflow.id = job.workflow_id
+             WHERE workflow.project_id = $1
+             ORDER BY stash.run_id, stash.name",
+        )
+        .bind(project_id)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list stashes of project "{}" from [stash]"#,
+            project_id.as_uuid()
+        ))?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "stash.delete_expired", skip_all)]
+    async fn delete_expired(
+        &self,
+        limit: Option<&i64>,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<StashRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<StashRow> = sqlx::query_as::<_, StashRow>(
+            "DELETE FROM stash
+             WHERE id IN (
+                 SELECT id
+                 FROM stash
+                 WHERE expires_at <= CURRENT_TIMESTAMP
+                 ORDER BY expires_at
+                 LIMIT $1
+                 FOR UPDATE SKIP LOCKED
+             )
+             RETURNING
+                 id,
+                 run_id,
+                 name,
+                 content_type,
+                 size,
+                 created_at,
+                 expires_at",
+        )
+        .bind(limit.unwrap_or(&100))
+        .fetch_all(&mut *conn)
+        .await
+        .context("failed to delete expired stashes from [stash]")?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "stash.is_shared", skip_all)]
+    async fn is_shared(
+        &self,
+        owner: &RunId,
+        reader: &RunId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<bool> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let shared: bool = sqlx::query_scalar(
+            "SELECT EXISTS(
+                 SELECT 1
+                 FROM run AS owner_run
+                 JOIN run AS reader_run ON reader_run.execution_id = owner_run.execution_id
+                 WHERE owner_run.id = $1 AND reader_run.id = $2
+             )",
+        )
+        .bind(owner)
+        .bind(reader)
+        .fetch_one(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to check stash visibility of "{}" from [run]"#,
+            owner.as_uuid()
+        ))?;
+        Ok(shared)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::execution::Execution;
+    use crate::controller::entities::job::Job;
+    use crate::controller::entities::project::Project;
+    use crate::controller::entities::run::Run;
+    use crate::controller::entities::workflow::Workflow;
+    use crate::controller::repositories::execution::ExecutionRepository;
+    use crate::controller::repositories::execution::PgExecutionRepository;
+    use crate::controller::repositories::job::JobRepository;
+    use crate::controller::repositories::job::PgJobRepository;
+    use crate::controller::repositories::project::PgProjectRepository;
+    use crate::controller::repositories::project::ProjectRepository;
+    use crate::controller::repositories::run::PgRunRepository;
+    use crate::controller::repositories::run::RunRepository;
+    use crate::controller::repositories::workflow::PgWorkflowRepository;
+    use crate::controller::repositories::workflow::WorkflowRepository;
+    use crate::messages::run::RunPriority;
+    use crate::messages::token::TokenState;
+    use chrono::Duration;
+    use sqlx::PgConnection;
+    use sqlx::PgPool;
+
+    async fn create_workflow(tx: &mut PgConnection) -> Result<Workflow> {
+        let project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )?;
+        PgProjectRepository.create(&project, &mut *tx).await?;
+        let workflow = Workflow::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            project.id().as_uuid().to_string(),
+            testutils::rand::string(10),
+            false,
+        )?;
+        PgWorkflowRepository.create(&workflow, &mut *tx).await?;
+        Ok(workflow)
+    }
+
+    async fn create_execution(workflow: &Workflow, tx: &mut PgConnection) -> Result<Execution> {
+        let execution = Execution::new(
+            testutils::rand::uuid(),
+            workflow.id().as_uuid().to_string(),
+            RunPriority::Normal,
+            Utc::now(),
+        )?;
+        PgExecutionRepository.create(&execution, &mut *tx).await?;
+        Ok(execution)
+    }
+
+    async fn create_run(
+        workflow: &Workflow,
+        execution: &Execution,
+        tx: &mut PgConnection,
+    ) -> Result<Run> {
+        let job = Job::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            workflow.id().as_uuid().to_string(),
+            0,
+            testutils::rand::string(10),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        PgJobRepository.create(&job, &mut *tx).await?;
+        let mut run = Run::new(
+            testutils::rand::uuid(),
+            TokenState::Running,
+            RunPriority::Normal,
+            job.id().as_uuid().to_string(),
+            Utc::now(),
+        )?;
+        run.set_execution_id(Some(execution.id().clone()));
+        PgRunRepository.create(&run, &mut *tx).await?;
+        Ok(run)
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_create_get_and_expire(pool: PgPool) -> Result<()> {
+        let repo = PgStashRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let workflow = create_workflow(&mut tx)
+            .await
+            .expect("new workflow should be created");
+        let execution = create_execution(&workflow, &mut tx)
+            .await
+            .expect("new execution should be created");
+        let owner = create_run(&workflow, &execution, &mut tx)
+            .await
+            .expect("new run should be created");
+        let reader = create_run(&workflow, &execution, &mut tx)
+            .await
+            .expect("new run should be created");
+        let other = create_execution(&workflow, &mut tx)
+            .await
+            .expect("new execution should be created");
+        let stranger = create_run(&workflow, &other, &mut tx)
+            .await
+            .expect("new run should be created");
+        let stash = Stash::new(
+            testutils::rand::uuid(),
+            owner.id().as_uuid().to_string(),
+            String::from("result.json"),
+            String::from("application/json"),
+            2,
+            Utc::now() + Duration::hours(1),
+        )
+        .expect("new stash should be created");
+        repo.create(&stash, &mut tx)
+            .await
+            .expect("new stash should be inserted");
+        let row = repo
+            .get_by_name(owner.id(), stash.name(), &mut tx)
+            .await
+            .expect("stash should be selected")
+            .expect("stash should be found");
+        assert_eq!(row.size, 2);
+        assert!(repo
+            .is_shared(owner.id(), reader.id(), &mut tx)
+            .await
+            .expect("visibility should be checked"));
+        assert!(!repo
+            .is_shared(owner.id(), stranger.id(), &mut tx)
+            .await
+            .expect("visibility should be checked"));
+        let expired = Stash::new(
+            testutils::rand::uuid(),
+            owner.id().as_uuid().to_string(),
+            String::from("stale"),
+            String::from("application/octet-stream"),
+            0,
+            Utc::now() - Duration::hours(1),
+        )
+        .expect("new stash should be created");
+        repo.create(&expired, &mut tx)
+            .await
+            .expect("new stash should be inserted");
+        let rows = repo
+            .list_by_run_id(owner.id(), &mut tx)
+            .await
+            .expect("stashes should be listed");
+        assert_eq!(rows.len(), 1);
+        let deleted = repo
+            .delete_expired(None, &mut tx)
+            .await
+            .expect("expired stashes should be deleted");
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(&deleted[0].name, "stale");
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+}
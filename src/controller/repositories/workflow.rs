@@ -1,3 +1,4 @@
+use crate::controller::entities::project::ProjectId;
 use crate::controller::entities::workflow::Workflow;
 use crate::controller::entities::workflow::WorkflowId;
 use crate::infra::postgres::PgAcquire;
@@ -7,6 +8,7 @@ use async_trait::async_trait;
 use chrono::DateTime;
 use chrono::Utc;
 use sqlx::postgres::PgQueryResult;
+use tracing::instrument;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, serde::Serialize, sqlx::FromRow)]
@@ -16,6 +18,11 @@ pub struct WorkflowRow {
     pub project_id: Uuid,
     pub description: String,
     pub paused: bool,
+    pub schedule: Option<String>,
+    pub sla_within_secs: Option<i64>,
+    pub sla_by: Option<String>,
+    pub sla_timezone: Option<String>,
+    pub version: i64,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -34,12 +41,37 @@ pub trait WorkflowRepository: Send + Sync + 'static {
         executor: impl PgAcquire<'_> + 'async_trait,
     ) -> Result<PgQueryResult>;
 
+    async fn soft_delete(
+        &self,
+        id: &WorkflowId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
+    async fn set_paused(
+        &self,
+        id: &WorkflowId,
+        paused: bool,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult>;
+
     async fn get_by_id(
         &self,
         id: &WorkflowId,
         executor: impl PgAcquire<'_> + 'async_trait,
     ) -> Result<Option<WorkflowRow>>;
 
+    async fn lock_by_id(
+        &self,
+        id: &WorkflowId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<WorkflowRow>>;
+
+    async fn list_by_project_id(
+        &self,
+        project_id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<WorkflowRow>>;
+
     async fn get_project_id(
         &self,
         id: &WorkflowId,
@@ -51,6 +83,7 @@ pub struct PgWorkflowRepository;
 
 #[async_trait]
 impl WorkflowRepository for PgWorkflowRepository {
+    #[instrument(name = "workflow.create", skip_all)]
     async fn create(
         &self,
         workflow: &Workflow,
@@ -66,20 +99,72 @@ impl WorkflowRepository for PgWorkflowRepository {
                  name,
                  project_id,
                  description,
-                 paused
-             ) VALUES ($1, $2, $3, $4, $5)
+                 paused,
+                 schedule,
+                 sla_within_secs,
+                 sla_by,
+                 sla_timezone
+             ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
              ON CONFLICT(id)
              DO UPDATE
              SET name = $2,
                  project_id = $3,
                  description = $4,
-                 paused = $5",
+                 paused = $5,
+                 schedule = $6,
+                 sla_within_secs = $7,
+                 sla_by = $8,
+                 sla_timezone = $9,
+                 sla_since = CASE
+                     WHEN (
+                         workflow.sla_within_secs,
+                         workflow.sla_by,
+                         workflow.sla_timezone
+                     ) IS DISTINCT FROM (
+                         EXCLUDED.sla_within_secs,
+                         EXCLUDED.sla_by,
+                         EXCLUDED.sla_timezone
+                     ) THEN CURRENT_TIMESTAMP
+                     ELSE workflow.sla_since
+                 END,
+                 deleted_at = NULL,
+                 version = workflow.version + 1,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE workflow.deleted_at IS NOT NULL
+             OR (
+                 workflow.name,
+                 workflow.project_id,
+                 workflow.description,
+                 workflow.paused,
+                 workflow.schedule,
+                 workflow.sla_within_secs,
+                 workflow.sla_by,
+                 workflow.sla_timezone
+             ) IS DISTINCT FROM (
+                 EXCLUDED.name,
+                 EXCLUDED.project_id,
+                 EXCLUDED.description,
+                 EXCLUDED.paused,
+                 EXCLUDED.schedule,
+                 EXCLUDED.sla_within_secs,
+                 EXCLUDED.sla_by,
+                 EXCLUDED.sla_timezone
+             )",
         )
         .bind(workflow.id())
         .bind(workflow.name())
         .bind(workflow.project_id())
         .bind(workflow.description())
         .bind(workflow.paused())
+        .bind(workflow.schedule())
+        .bind(workflow.sla().as_ref().and_then(|sla| sla.within_secs()))
+        .bind(workflow.sla().as_ref().and_then(|sla| sla.by().clone()))
+        .bind(
+            workflow
+                .sla()
+                .as_ref()
+                .and_then(|sla| sla.timezone().clone()),
+        )
         .execute(&mut *conn)
         .await
         .context(format!(
@@ -88,6 +173,7 @@ impl WorkflowRepository for PgWorkflowRepository {
         ))
     }
 
+    #[instrument(name = "workflow.delete", skip_all)]
     async fn delete(
         &self,
         id: &WorkflowId,
@@ -110,6 +196,66 @@ impl WorkflowRepository for PgWorkflowRepository {
         ))
     }
 
+    #[instrument(name = "workflow.soft_delete", skip_all)]
+    async fn soft_delete(
+        &self,
+        id: &WorkflowId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "WITH these_jobs AS (
+                 UPDATE job
+                 SET deleted_at = CURRENT_TIMESTAMP,
+                     updated_at = CURRENT_TIMESTAMP
+                 WHERE workflow_id = $1 AND deleted_at IS NULL
+             )
+             UPDATE workflow
+             SET deleted_at = CURRENT_TIMESTAMP,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to soft delete "{}" from [workflow]"#,
+            id.as_uuid()
+        ))
+    }
+
+    #[instrument(name = "workflow.set_paused", skip_all)]
+    async fn set_paused(
+        &self,
+        id: &WorkflowId,
+        paused: bool,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<PgQueryResult> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        sqlx::query(
+            "UPDATE workflow
+             SET paused = $2,
+                 version = CASE WHEN paused IS DISTINCT FROM $2 THEN version + 1 ELSE version END,
+                 updated_at = CASE WHEN paused IS DISTINCT FROM $2 THEN CURRENT_TIMESTAMP ELSE updated_at END
+             WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(paused)
+        .execute(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to set paused of "{}" in [workflow]"#,
+            id.as_uuid()
+        ))
+    }
+
+    #[instrument(name = "workflow.get_by_id", skip_all)]
     async fn get_by_id(
         &self,
         id: &WorkflowId,
@@ -126,10 +272,15 @@ impl WorkflowRepository for PgWorkflowRepository {
                  project_id,
                  description,
                  paused,
+                 schedule,
+                 sla_within_secs,
+                 sla_by,
+                 sla_timezone,
+                 version,
                  created_at,
                  updated_at
              FROM workflow
-             WHERE id = $1",
+             WHERE id = $1 AND deleted_at IS NULL",
         )
         .bind(id)
         .fetch_optional(&mut *conn)
@@ -141,6 +292,83 @@ impl WorkflowRepository for PgWorkflowRepository {
         Ok(row)
     }
 
+    #[instrument(name = "workflow.lock_by_id", skip_all)]
+    async fn lock_by_id(
+        &self,
+        id: &WorkflowId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Option<WorkflowRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let row: Option<WorkflowRow> = sqlx::query_as::<_, WorkflowRow>(
+            "SELECT
+                 id,
+                 name,
+                 project_id,
+                 description,
+                 paused,
+                 schedule,
+                 sla_within_secs,
+                 sla_by,
+                 sla_timezone,
+                 version,
+                 created_at,
+                 updated_at
+             FROM workflow
+             WHERE id = $1 AND deleted_at IS NULL
+             FOR UPDATE",
+        )
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to lock "{}" in [workflow]"#,
+            id.as_uuid()
+        ))?;
+        Ok(row)
+    }
+
+    #[instrument(name = "workflow.list_by_project_id", skip_all)]
+    async fn list_by_project_id(
+        &self,
+        project_id: &ProjectId,
+        executor: impl PgAcquire<'_> + 'async_trait,
+    ) -> Result<Vec<WorkflowRow>> {
+        let mut conn = executor
+            .acquire()
+            .await
+            .context("failed to acquire postgres connection")?;
+        let rows: Vec<WorkflowRow> = sqlx::query_as::<_, WorkflowRow>(
+            "SELECT
+                 id,
+                 name,
+                 project_id,
+                 description,
+                 paused,
+                 schedule,
+                 sla_within_secs,
+                 sla_by,
+                 sla_timezone,
+                 version,
+                 created_at,
+                 updated_at
+             FROM workflow
+             WHERE project_id = $1 AND deleted_at IS NULL
+             ORDER BY name",
+        )
+        .bind(project_id)
+        .fetch_all(&mut *conn)
+        .await
+        .context(format!(
+            r#"failed to list workflows of "{}" from [workflow]"#,
+            project_id.as_uuid()
+        ))?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "workflow.get_project_id", skip_all)]
     async fn get_project_id(
         &self,
         id: &WorkflowId,
@@ -154,7 +382,7 @@ impl WorkflowRepository for PgWorkflowRepository {
             "SELECT
                  project_id
              FROM workflow
-             WHERE id = $1",
+             WHERE id = $1 AND deleted_at IS NULL",
         )
         .bind(id)
         .fetch_optional(&mut *conn)
@@ -246,6 +474,47 @@ mod tests {
         Ok(())
     }
 
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_set_paused_bumps_version(pool: PgPool) -> Result<()> {
+        let repo = PgWorkflowRepository;
+        let mut tx = pool
+            .begin()
+            .await
+            .expect("transaction should be started properly");
+        let project = create_project(&mut tx)
+            .await
+            .expect("new project should be created");
+        let workflow = create_workflow(project.id(), &mut tx)
+            .await
+            .expect("new workflow should be created");
+        let paused = workflow.paused().to_bool();
+        repo.set_paused(workflow.id(), paused, &mut tx)
+            .await
+            .expect("paused should be set");
+        let fetched = repo
+            .lock_by_id(workflow.id(), &mut tx)
+            .await?
+            .expect("inserted workflow should be found");
+        assert_eq!(1, fetched.version);
+        repo.set_paused(workflow.id(), !paused, &mut tx)
+            .await
+            .expect("paused should be flipped");
+        repo.create(&workflow, &mut tx)
+            .await
+            .expect("workflow should be upserted");
+        let fetched = repo
+            .get_by_id(workflow.id(), &mut tx)
+            .await?
+            .expect("upserted workflow should be found");
+        assert_eq!(&fetched.paused, workflow.paused().as_bool());
+        assert_eq!(3, fetched.version);
+        tx.rollback()
+            .await
+            .expect("rollback should be done properly");
+        Ok(())
+    }
+
     #[sqlx::test]
     #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
     async fn test_create_and_get_project_id(pool: PgPool) -> Result<()> {
@@ -1,3 +1,18 @@
+pub mod cluster;
 pub mod config;
+pub mod execution;
+pub mod health;
+pub mod job;
+pub mod log;
+pub mod notification;
 pub mod opa;
+pub mod outbox;
 pub mod project;
+pub mod retention;
+pub mod run;
+pub mod secret;
+pub mod sla;
+pub mod spec;
+pub mod stash;
+pub mod update;
+pub mod workflow;
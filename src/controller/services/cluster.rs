@@ -0,0 +1,118 @@
+use crate::controller::repositories::cluster::ClusterRepository;
+use crate::controller::repositories::cluster::LeaseRow;
+use crate::controller::repositories::cluster::MemberRow;
+use crate::controller::repositories::cluster::PgClusterRepository;
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::info;
+use tracing::warn;
+use uuid::Uuid;
+
+const LEADER_LEASE: &str = "leader";
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(2);
+
+const LEASE_TTL: Duration = Duration::from_secs(6);
+
+const MEMBER_TTL: Duration = Duration::from_secs(10);
+
+const PRUNE_TTL: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct Member {
+    #[serde(flatten)]
+    pub member: MemberRow,
+    pub leader: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct ClusterView {
+    pub id: Uuid,
+    pub leader: Option<LeaseRow>,
+    pub members: Vec<Member>,
+}
+
+#[async_trait]
+pub trait ClusterService {
+    async fn view(&self, id: &Uuid) -> Result<ClusterView>;
+
+    async fn campaign(&self, id: &Uuid, addr: &str) -> Result<bool>;
+
+    async fn resign(&self, id: &Uuid) -> Result<()>;
+}
+
+#[async_trait]
+impl ClusterService for PgPool {
+    async fn view(&self, id: &Uuid) -> Result<ClusterView> {
+        let repo = PgClusterRepository;
+        let leader = repo.get_lease(LEADER_LEASE, self).await?;
+        let members = repo
+            .list_members(&MEMBER_TTL, self)
+            .await?
+            .into_iter()
+            .map(|member| Member {
+                leader: leader.as_ref().map_or(false, |l| l.holder == member.id),
+                member,
+            })
+            .collect();
+        Ok(ClusterView {
+            id: *id,
+            leader,
+            members,
+        })
+    }
+
+    async fn campaign(&self, id: &Uuid, addr: &str) -> Result<bool> {
+        let repo = PgClusterRepository;
+        repo.heartbeat(id, addr, self).await?;
+        let elected = repo
+            .acquire_lease(LEADER_LEASE, id, &LEASE_TTL, self)
+            .await?;
+        if elected {
+            repo.prune_members(&PRUNE_TTL, self).await?;
+        }
+        Ok(elected)
+    }
+
+    async fn resign(&self, id: &Uuid) -> Result<()> {
+        let repo = PgClusterRepository;
+        repo.release_lease(LEADER_LEASE, id, self).await?;
+        repo.leave(id, self).await?;
+        Ok(())
+    }
+}
+
+pub async fn elect(
+    pool: PgPool,
+    id: Uuid,
+    addr: String,
+    leader: watch::Sender<bool>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        let elected = match pool.campaign(&id, &addr).await {
+            Ok(elected) => elected,
+            Err(e) => {
+                warn!("failed to renew leader lease: {}", e);
+                false
+            }
+        };
+        let previous = leader.send_replace(elected);
+        if elected && !previous {
+            info!(r#"controller id: "{}" became the leader"#, id);
+        } else if !elected && previous {
+            warn!(r#"controller id: "{}" lost the leadership"#, id);
+        }
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            _ = tokio::time::sleep(HEARTBEAT_INTERVAL) => {}
+        }
+    }
+    leader.send_replace(false);
+    if let Err(e) = pool.resign(&id).await {
+        warn!("failed to leave the cluster: {}", e);
+    }
+}
@@ -1,49 +1,491 @@
+use crate::controller::entities::job::JobId;
+use crate::controller::entities::outbox::Outbox;
+use crate::controller::entities::project::ProjectId;
+use crate::controller::entities::version::Precondition;
+use crate::controller::repositories::job::JobRepository;
+use crate::controller::repositories::job::PgJobRepository;
+use crate::controller::repositories::outbox::OutboxRepository;
+use crate::controller::repositories::outbox::PgOutboxRepository;
+use crate::controller::repositories::project::PgProjectRepository;
+use crate::controller::repositories::project::ProjectRepository;
+use crate::controller::repositories::project::ProjectRow;
 use crate::messages::config::ConfigUpdate;
-use crate::messages::config::CONFIG_UPDATES_EXCHANGE;
 use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
-use lapin::options::BasicPublishOptions;
-use lapin::options::ExchangeDeclareOptions;
-use lapin::types::FieldTable;
-use lapin::BasicProperties;
-use lapin::Channel;
-use lapin::ExchangeKind;
+use jsonschema::JSONSchema;
+use serde_json::Value as Json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, utoipa::ToSchema)]
+pub struct Violation {
+    pub path: String,
+    pub message: String,
+}
+
+pub enum Validated<T> {
+    Accepted(T),
+    Rejected(Vec<Violation>),
+}
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct JobConfig {
+    pub project_id: Uuid,
+    #[schema(value_type = Object)]
+    pub config: Json,
+    #[schema(value_type = Object)]
+    pub merged: Json,
+    #[serde(skip)]
+    pub version: i64,
+}
+
+pub fn merge(base: &Json, patch: &Json) -> Json {
+    let mut merged = base.clone();
+    json_patch::merge(&mut merged, patch);
+    merged
+}
+
+fn compile(schema: &Json) -> Result<JSONSchema, Vec<Violation>> {
+    JSONSchema::compile(schema).map_err(|e| {
+        vec![Violation {
+            path: e.schema_path.to_string(),
+            message: format!("invalid schema: {}", e),
+        }]
+    })
+}
+
+pub fn check_schema(schema: &Json) -> Vec<Violation> {
+    compile(schema).err().unwrap_or_default()
+}
+
+pub fn validate(schema: Option<&Json>, config: &Json) -> Vec<Violation> {
+    let schema = match schema.map(compile) {
+        None => return Vec::new(),
+        Some(Ok(schema)) => schema,
+        Some(Err(violations)) => return violations,
+    };
+    let violations = match schema.validate(config) {
+        Ok(_) => Vec::new(),
+        Err(errors) => errors
+            .map(|e| Violation {
+                path: e.instance_path.to_string(),
+                message: e.to_string(),
+            })
+            .collect(),
+    };
+    violations
+}
+
+async fn validate_jobs(
+    id: &ProjectId,
+    schema: Option<&Json>,
+    config: &Json,
+    tx: &mut sqlx::PgConnection,
+) -> Result<Vec<Violation>> {
+    let rows = PgJobRepository
+        .list_overrides_by_project_id(id, &mut *tx)
+        .await?;
+    let violations = rows
+        .iter()
+        .flat_map(|row| {
+            validate(schema, &merge(config, &row.config))
+                .into_iter()
+                .map(move |v| Violation {
+                    path: format!("/jobs/{}/{}{}", row.workflow_name, row.name, v.path),
+                    message: v.message,
+                })
+        })
+        .collect();
+    Ok(violations)
+}
 
 #[async_trait]
 pub trait ConfigService {
-    async fn setup(&self) -> Result<()>;
+    async fn set_schema(
+        &self,
+        id: &ProjectId,
+        schema: Option<&Json>,
+        precondition: &Precondition,
+    ) -> Result<Option<Validated<ProjectRow>>>;
+
+    async fn patch_project_config(
+        &self,
+        id: &ProjectId,
+        patch: &Json,
+        precondition: &Precondition,
+    ) -> Result<Option<Validated<ProjectRow>>>;
+
+    async fn get_job_config(&self, id: &JobId) -> Result<Option<JobConfig>>;
 
-    async fn publish(&self, update: ConfigUpdate) -> Result<()>;
+    async fn patch_job_config(
+        &self,
+        id: &JobId,
+        patch: &Json,
+        precondition: &Precondition,
+    ) -> Result<Option<Validated<JobConfig>>>;
 }
 
 #[async_trait]
-impl ConfigService for Channel {
-    async fn setup(&self) -> Result<()> {
-        self.exchange_declare(
-            CONFIG_UPDATES_EXCHANGE,
-            ExchangeKind::Fanout,
-            ExchangeDeclareOptions {
-                durable: true,
-                ..ExchangeDeclareOptions::default()
+impl ConfigService for PgPool {
+    async fn set_schema(
+        &self,
+        id: &ProjectId,
+        schema: Option<&Json>,
+        precondition: &Precondition,
+    ) -> Result<Option<Validated<ProjectRow>>> {
+        let repo = PgProjectRepository;
+        let mut tx = self
+            .begin()
+            .await
+            .context("failed to begin postgres transaction")?;
+        let row = repo.lock_by_id(id, &mut tx).await?;
+        precondition.check(row.as_ref().map(|row| row.version))?;
+        let row = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let config = row
+            .config
+            .unwrap_or_else(|| Json::Object(Default::default()));
+        let mut violations = validate(schema, &config);
+        violations.extend(validate_jobs(id, schema, &config, &mut tx).await?);
+        if !violations.is_empty() {
+            return Ok(Some(Validated::Rejected(violations)));
+        }
+        repo.update_config_schema(id, schema, &mut tx).await?;
+        let outbox = Outbox::new(&ConfigUpdate::Project(id.to_uuid()))?;
+        PgOutboxRepository.create(&outbox, &mut tx).await?;
+        let row = repo.get_by_id(id, &mut tx).await?;
+        tx.commit()
+            .await
+            .context("failed to commit postgres transaction")?;
+        Ok(row.map(Validated::Accepted))
+    }
+
+    async fn patch_project_config(
+        &self,
+        id: &ProjectId,
+        patch: &Json,
+        precondition: &Precondition,
+    ) -> Result<Option<Validated<ProjectRow>>> {
+        let repo = PgProjectRepository;
+        let mut tx = self
+            .begin()
+            .await
+            .context("failed to begin postgres transaction")?;
+        let row = repo.lock_by_id(id, &mut tx).await?;
+        precondition.check(row.as_ref().map(|row| row.version))?;
+        let row = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let config = row
+            .config
+            .unwrap_or_else(|| Json::Object(Default::default()));
+        let config = merge(&config, patch);
+        let schema = row.config_schema.as_ref();
+        let mut violations = validate(schema, &config);
+        violations.extend(validate_jobs(id, schema, &config, &mut tx).await?);
+        if !violations.is_empty() {
+            return Ok(Some(Validated::Rejected(violations)));
+        }
+        repo.update_config(id, &config, &mut tx).await?;
+        let outbox = Outbox::new(&ConfigUpdate::Project(id.to_uuid()))?;
+        PgOutboxRepository.create(&outbox, &mut tx).await?;
+        let row = repo.get_by_id(id, &mut tx).await?;
+        tx.commit()
+            .await
+            .context("failed to commit postgres transaction")?;
+        Ok(row.map(Validated::Accepted))
+    }
+
+    async fn get_job_config(&self, id: &JobId) -> Result<Option<JobConfig>> {
+        let repo = PgJobRepository;
+        let row = repo.get_config_by_id(id, self).await?;
+        Ok(row.map(|row| JobConfig {
+            project_id: row.project_id,
+            merged: merge(&row.project_config, &row.config),
+            config: row.config,
+            version: row.version,
+        }))
+    }
+
+    async fn patch_job_config(
+        &self,
+        id: &JobId,
+        patch: &Json,
+        precondition: &Precondition,
+    ) -> Result<Option<Validated<JobConfig>>> {
+        let repo = PgJobRepository;
+        let mut tx = self
+            .begin()
+            .await
+            .context("failed to begin postgres transaction")?;
+        let row = match repo.get_config_by_id(id, &mut tx).await? {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        // NOTE: Job configs are serialized on their project so that the merged
+        // view is always checked against the latest project config and schema.
+        let project_id = ProjectId::new(row.project_id);
+        if PgProjectRepository
+            .lock_by_id(&project_id, &mut tx)
+            .await?
+            .is_none()
+        {
+            return Ok(None);
+        }
+        let row = repo.get_config_by_id(id, &mut tx).await?;
+        precondition.check(row.as_ref().map(|row| row.version))?;
+        let row = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let config = merge(&row.config, patch);
+        let merged = merge(&row.project_config, &config);
+        let violations = validate(row.config_schema.as_ref(), &merged);
+        if !violations.is_empty() {
+            return Ok(Some(Validated::Rejected(violations)));
+        }
+        repo.update_config(id, &config, &mut tx).await?;
+        let outbox = Outbox::new(&ConfigUpdate::Job(id.to_uuid()))?;
+        PgOutboxRepository.create(&outbox, &mut tx).await?;
+        let row = repo.get_config_by_id(id, &mut tx).await?;
+        tx.commit()
+            .await
+            .context("failed to commit postgres transaction")?;
+        Ok(row.map(|row| {
+            Validated::Accepted(JobConfig {
+                project_id: row.project_id,
+                config,
+                merged,
+                version: row.version,
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::job::Job;
+    use crate::controller::entities::project::Project;
+    use crate::controller::entities::version::Stale;
+    use crate::controller::entities::workflow::Workflow;
+    use crate::controller::repositories::workflow::PgWorkflowRepository;
+    use crate::controller::repositories::workflow::WorkflowRepository;
+    use serde_json::json;
+
+    fn schema() -> Json {
+        json!({
+            "type": "object",
+            "properties": {
+                "retries": { "type": "integer", "minimum": 0 },
+                "owner": { "type": "string" }
             },
-            FieldTable::default(),
-        )
-        .await
-        .context("failed to declare rabbitmq exchange")?;
-        Ok(())
+            "required": ["owner"]
+        })
     }
 
-    async fn publish(&self, update: ConfigUpdate) -> Result<()> {
-        self.basic_publish(
-            CONFIG_UPDATES_EXCHANGE,
-            "",
-            BasicPublishOptions::default(),
-            &serde_json::to_vec(&update)?,
-            BasicProperties::default(),
-        )
-        .await
-        .context("failed to notify config update")?;
+    #[test]
+    fn test_merge() {
+        let base = json!({ "owner": "kotosiro", "retries": 3, "env": { "a": "1", "b": "2" } });
+        let patch = json!({ "retries": null, "env": { "b": "3" } });
+        assert_eq!(
+            merge(&base, &patch),
+            json!({ "owner": "kotosiro", "env": { "a": "1", "b": "3" } })
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(None, &json!({ "anything": true })).is_empty());
+        assert!(validate(Some(&schema()), &json!({ "owner": "kotosiro" })).is_empty());
+        let violations = validate(Some(&schema()), &json!({ "retries": -1 }));
+        assert_eq!(violations.len(), 2);
+        assert!(violations.iter().any(|v| v.path == "/retries"));
+    }
+
+    #[test]
+    fn test_check_schema() {
+        assert!(check_schema(&schema()).is_empty());
+        assert!(!check_schema(&json!({ "type": "unknown" })).is_empty());
+    }
+
+    async fn create_job(pool: &PgPool) -> Result<(Project, Job)> {
+        let mut project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            json!({ "owner": "kotosiro", "retries": 1 }),
+        )?;
+        project.set_config_schema(Some(
+            crate::controller::entities::project::ProjectConfigSchema::new(schema()),
+        ));
+        PgProjectRepository.create(&project, pool).await?;
+        let workflow = Workflow::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            project.id().as_uuid().to_string(),
+            testutils::rand::string(10),
+            false,
+        )?;
+        PgWorkflowRepository.create(&workflow, pool).await?;
+        let job = Job::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            workflow.id().as_uuid().to_string(),
+            0,
+            testutils::rand::string(10),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        PgJobRepository.create(&job, pool).await?;
+        Ok((project, job))
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_patch_project_and_job_config(pool: PgPool) -> Result<()> {
+        let (project, job) = create_job(&pool).await.expect("new job should be created");
+        match pool
+            .patch_project_config(project.id(), &json!({ "retries": -1 }), &Precondition::None)
+            .await?
+        {
+            Some(Validated::Rejected(violations)) => assert_eq!(violations.len(), 1),
+            _ => panic!("non-conforming config should be rejected"),
+        }
+        match pool
+            .patch_project_config(
+                project.id(),
+                &json!({ "retries": 2 }),
+                &Precondition::Versions(vec![1]),
+            )
+            .await?
+        {
+            Some(Validated::Accepted(row)) => {
+                assert_eq!(
+                    row.config,
+                    Some(json!({ "owner": "kotosiro", "retries": 2 }))
+                );
+                assert_eq!(2, row.version);
+            }
+            _ => panic!("conforming config should be accepted"),
+        }
+        match pool
+            .patch_job_config(job.id(), &json!({ "owner": 1 }), &Precondition::None)
+            .await?
+        {
+            Some(Validated::Rejected(violations)) => assert_eq!(violations.len(), 1),
+            _ => panic!("non-conforming job config should be rejected"),
+        }
+        match pool
+            .patch_job_config(job.id(), &json!({ "retries": 5 }), &Precondition::Any)
+            .await?
+        {
+            Some(Validated::Accepted(config)) => {
+                assert_eq!(config.config, json!({ "retries": 5 }));
+                assert_eq!(2, config.version);
+            }
+            _ => panic!("conforming job config should be accepted"),
+        }
+        let config = pool
+            .get_job_config(job.id())
+            .await?
+            .expect("job config should be found");
+        assert_eq!(config.merged, json!({ "owner": "kotosiro", "retries": 5 }));
+        match pool
+            .set_schema(
+                project.id(),
+                Some(&json!({ "type": "array" })),
+                &Precondition::None,
+            )
+            .await?
+        {
+            Some(Validated::Rejected(_)) => {}
+            _ => panic!("schema rejecting current config should be rejected"),
+        }
+        let e = pool
+            .set_schema(project.id(), None, &Precondition::Versions(vec![1]))
+            .await
+            .err()
+            .expect("stale version should be rejected");
+        assert_eq!(Some(&Stale { current: Some(2) }), e.downcast_ref::<Stale>());
+        Ok(())
+    }
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_project_changes_validate_job_overrides(pool: PgPool) -> Result<()> {
+        let (project, job) = create_job(&pool).await.expect("new job should be created");
+        match pool
+            .patch_job_config(job.id(), &json!({ "retries": 5 }), &Precondition::None)
+            .await?
+        {
+            Some(Validated::Accepted(_)) => {}
+            _ => panic!("conforming job config should be accepted"),
+        }
+        let mut strict = schema();
+        strict["properties"]["retries"]["maximum"] = json!(3);
+        match pool
+            .set_schema(project.id(), Some(&strict), &Precondition::None)
+            .await?
+        {
+            Some(Validated::Rejected(violations)) => {
+                assert_eq!(violations.len(), 1);
+                assert!(violations[0].path.starts_with("/jobs/"));
+                assert!(violations[0].path.ends_with("/retries"));
+            }
+            _ => panic!("schema rejecting a job config should be rejected"),
+        }
+        let conditional = json!({
+            "type": "object",
+            "if": { "properties": { "engine": { "const": "spark" } }, "required": ["engine"] },
+            "then": { "required": ["executors"] }
+        });
+        match pool
+            .set_schema(project.id(), Some(&conditional), &Precondition::None)
+            .await?
+        {
+            Some(Validated::Accepted(_)) => {}
+            _ => panic!("schema accepting every config should be accepted"),
+        }
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM outbox")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(2, count);
+        match pool
+            .patch_project_config(
+                project.id(),
+                &json!({ "executors": 2 }),
+                &Precondition::None,
+            )
+            .await?
+        {
+            Some(Validated::Accepted(_)) => {}
+            _ => panic!("conforming config should be accepted"),
+        }
+        match pool
+            .patch_job_config(job.id(), &json!({ "engine": "spark" }), &Precondition::None)
+            .await?
+        {
+            Some(Validated::Accepted(_)) => {}
+            _ => panic!("conforming job config should be accepted"),
+        }
+        match pool
+            .patch_project_config(
+                project.id(),
+                &json!({ "executors": null }),
+                &Precondition::None,
+            )
+            .await?
+        {
+            Some(Validated::Rejected(violations)) => {
+                assert_eq!(violations.len(), 1);
+                assert!(violations[0].path.starts_with("/jobs/"));
+            }
+            _ => panic!("config breaking a job config should be rejected"),
+        }
         Ok(())
     }
 }
@@ -0,0 +1,500 @@
+use crate::controller::entities::execution::Execution;
+use crate::controller::entities::execution::ExecutionId;
+use crate::controller::entities::job::JobId;
+use crate::controller::entities::run::Run;
+use crate::controller::entities::run::RunAttempt;
+use crate::controller::entities::workflow::WorkflowId;
+use crate::controller::repositories::execution::ExecutionNodeRow;
+use crate::controller::repositories::execution::ExecutionRepository;
+use crate::controller::repositories::execution::ExecutionRow;
+use crate::controller::repositories::execution::PgExecutionRepository;
+use crate::controller::repositories::run::PgRunRepository;
+use crate::controller::repositories::run::RunRepository;
+use crate::controller::services::run;
+use crate::messages::run::RunPriority;
+use crate::messages::token::TokenState;
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::postgres::PgQueryResult;
+use sqlx::PgConnection;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::str::FromStr;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct ExecutionSummary {
+    #[serde(flatten)]
+    pub execution: ExecutionRow,
+    pub state: TokenState,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, utoipa::ToSchema)]
+pub struct ExecutionEdge {
+    pub from: Uuid,
+    pub to: Uuid,
+}
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct ExecutionGraph {
+    #[serde(flatten)]
+    pub summary: ExecutionSummary,
+    pub nodes: Vec<ExecutionNodeRow>,
+    pub edges: Vec<ExecutionEdge>,
+}
+
+pub enum Rerun {
+    Done(Vec<Run>),
+    Blocked(Vec<ExecutionNodeRow>),
+}
+
+pub fn aggregate(states: &[TokenState]) -> TokenState {
+    let any = |state: TokenState| states.contains(&state);
+    if states.is_empty() {
+        return TokenState::Waiting;
+    }
+    if states.iter().all(|state| *state == TokenState::Success) {
+        return TokenState::Success;
+    }
+    if any(TokenState::Active) || any(TokenState::Running) {
+        return TokenState::Running;
+    }
+    if any(TokenState::Error) {
+        return TokenState::Error;
+    }
+    if any(TokenState::Failure) {
+        return TokenState::Failure;
+    }
+    if any(TokenState::Cancelled) {
+        return TokenState::Cancelled;
+    }
+    if any(TokenState::Success) {
+        return TokenState::Running;
+    }
+    TokenState::Waiting
+}
+
+fn summarize(execution: ExecutionRow) -> ExecutionSummary {
+    let states: Vec<TokenState> = execution
+        .states
+        .iter()
+        .filter_map(|state| TokenState::from_str(state).ok())
+        .collect();
+    ExecutionSummary {
+        state: aggregate(&states),
+        execution,
+    }
+}
+
+pub fn edges(nodes: &[ExecutionNodeRow]) -> Vec<ExecutionEdge> {
+    let ids: HashMap<&str, Uuid> = nodes
+        .iter()
+        .map(|node| (node.job_name.as_str(), node.job_id))
+        .collect();
+    nodes
+        .iter()
+        .flat_map(|node| {
+            node.depends_on
+                .iter()
+                .filter_map(|upstream| ids.get(upstream.as_str()))
+                .map(|from| ExecutionEdge {
+                    from: *from,
+                    to: node.job_id,
+                })
+        })
+        .collect()
+}
+
+pub async fn advance(execution_id: &ExecutionId, tx: &mut PgConnection) -> Result<usize> {
+    let ready = PgRunRepository
+        .list_ready_by_execution_id(execution_id, &mut *tx)
+        .await?;
+    for row in ready.iter() {
+        run::dispatch(row, &mut *tx).await?;
+    }
+    Ok(ready.len())
+}
+
+async fn create(
+    execution: &Execution,
+    job_ids: &[JobId],
+    tx: &mut PgConnection,
+) -> Result<Vec<Run>> {
+    PgExecutionRepository.create(execution, &mut *tx).await?;
+    let mut runs = Vec::new();
+    for job_id in job_ids.iter() {
+        let mut new = Run::new(
+            Uuid::new_v4().to_string(),
+            TokenState::Waiting,
+            *execution.priority(),
+            job_id.as_uuid().to_string(),
+            *execution.triggered_at(),
+        )?;
+        new.set_execution_id(Some(execution.id().clone()));
+        runs.push(run::prepare(&new, &mut *tx).await?);
+    }
+    advance(execution.id(), &mut *tx).await?;
+    Ok(runs)
+}
+
+#[async_trait]
+pub trait ExecutionService {
+    async fn trigger(&self, execution: &Execution, job_ids: &[JobId]) -> Result<Vec<Run>>;
+
+    async fn backfill(&self, executions: &[Execution], job_ids: &[JobId]) -> Result<Vec<Vec<Run>>>;
+
+    async fn get_by_id(&self, id: &ExecutionId) -> Result<Option<ExecutionSummary>>;
+
+    async fn get_graph_by_id(&self, id: &ExecutionId) -> Result<Option<ExecutionGraph>>;
+
+    async fn list_by_workflow_id(
+        &self,
+        workflow_id: &WorkflowId,
+        limit: Option<&i64>,
+    ) -> Result<Vec<ExecutionSummary>>;
+
+    async fn cancel(&self, id: &ExecutionId) -> Result<PgQueryResult>;
+
+    async fn rerun(&self, id: &ExecutionId, failed_only: bool) -> Result<Rerun>;
+}
+
+#[async_trait]
+impl ExecutionService for PgPool {
+    async fn trigger(&self, execution: &Execution, job_ids: &[JobId]) -> Result<Vec<Run>> {
+        let mut tx = self
+            .begin()
+            .await
+            .context("failed to begin postgres transaction")?;
+        let runs = create(execution, job_ids, &mut tx).await?;
+        tx.commit()
+            .await
+            .context("failed to commit postgres transaction")?;
+        Ok(runs)
+    }
+
+    async fn backfill(&self, executions: &[Execution], job_ids: &[JobId]) -> Result<Vec<Vec<Run>>> {
+        let mut tx = self
+            .begin()
+            .await
+            .context("failed to begin postgres transaction")?;
+        let mut runs = Vec::new();
+        for execution in executions.iter() {
+            runs.push(create(execution, job_ids, &mut tx).await?);
+        }
+        tx.commit()
+            .await
+            .context("failed to commit postgres transaction")?;
+        Ok(runs)
+    }
+
+    async fn get_by_id(&self, id: &ExecutionId) -> Result<Option<ExecutionSummary>> {
+        let repo = PgExecutionRepository;
+        Ok(repo.get_by_id(id, self).await?.map(summarize))
+    }
+
+    async fn get_graph_by_id(&self, id: &ExecutionId) -> Result<Option<ExecutionGraph>> {
+        let repo = PgExecutionRepository;
+        let summary = match repo.get_by_id(id, self).await? {
+            Some(execution) => summarize(execution),
+            None => return Ok(None),
+        };
+        let nodes = repo.list_nodes_by_id(id, self).await?;
+        Ok(Some(ExecutionGraph {
+            summary,
+            edges: edges(&nodes),
+            nodes,
+        }))
+    }
+
+    async fn list_by_workflow_id(
+        &self,
+        workflow_id: &WorkflowId,
+        limit: Option<&i64>,
+    ) -> Result<Vec<ExecutionSummary>> {
+        let repo = PgExecutionRepository;
+        let rows = repo.list_by_workflow_id(workflow_id, limit, self).await?;
+        Ok(rows.into_iter().map(summarize).collect())
+    }
+
+    async fn cancel(&self, id: &ExecutionId) -> Result<PgQueryResult> {
+        let repo = PgRunRepository;
+        repo.cancel_by_execution_id(id, self).await
+    }
+
+    async fn rerun(&self, id: &ExecutionId, failed_only: bool) -> Result<Rerun> {
+        let repo = PgExecutionRepository;
+        let mut tx = self
+            .begin()
+            .await
+            .context("failed to begin postgres transaction")?;
+        repo.lock_by_id(id, &mut tx)
+            .await?
+            .ok_or_else(|| anyhow!(r#"execution "{}" was not found"#, id.as_uuid()))?;
+        let execution = repo
+            .get_by_id(id, &mut tx)
+            .await?
+            .ok_or_else(|| anyhow!(r#"execution "{}" was not found"#, id.as_uuid()))?;
+        let priority = RunPriority::from_str(&execution.priority)
+            .map_err(|_| anyhow!(r#"invalid run priority "{}""#, execution.priority))?;
+        let nodes = repo.list_nodes_by_id(id, &mut tx).await?;
+        let (finished, unfinished): (Vec<_>, Vec<_>) = nodes.into_iter().partition(|node| {
+            TokenState::from_str(&node.state)
+                .map(|state| state.is_done())
+                .unwrap_or(false)
+        });
+        if !unfinished.is_empty() {
+            return Ok(Rerun::Blocked(unfinished));
+        }
+        let mut runs = Vec::new();
+        for node in finished.iter() {
+            let failed = matches!(
+                TokenState::from_str(&node.state),
+                Ok(TokenState::Failure | TokenState::Error | TokenState::Cancelled)
+            );
+            if failed_only && !failed {
+                continue;
+            }
+            let mut new = Run::new(
+                Uuid::new_v4().to_string(),
+                TokenState::Waiting,
+                priority,
+                node.job_id.to_string(),
+                Utc::now(),
+            )?;
+            new.set_attempt(RunAttempt::new(node.attempt + 1)?);
+            new.set_execution_id(Some(id.clone()));
+            runs.push(run::prepare(&new, &mut tx).await?);
+        }
+        advance(id, &mut tx).await?;
+        tx.commit()
+            .await
+            .context("failed to commit postgres transaction")?;
+        Ok(Rerun::Done(runs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::job::Job;
+    use crate::controller::entities::project::Project;
+    use crate::controller::entities::run::RunId;
+    use crate::controller::entities::workflow::Workflow;
+    use crate::controller::repositories::job::JobRepository;
+    use crate::controller::repositories::job::PgJobRepository;
+    use crate::controller::repositories::project::PgProjectRepository;
+    use crate::controller::repositories::project::ProjectRepository;
+    use crate::controller::repositories::workflow::PgWorkflowRepository;
+    use crate::controller::repositories::workflow::WorkflowRepository;
+    use crate::controller::services::run::RunService;
+
+    fn node(name: &str, depends_on: &[&str]) -> ExecutionNodeRow {
+        ExecutionNodeRow {
+            job_id: Uuid::new_v4(),
+            job_name: name.to_owned(),
+            depends_on: depends_on.iter().map(|name| name.to_string()).collect(),
+            run_id: Uuid::new_v4(),
+            state: String::from("waiting"),
+            attempt: 1,
+            started_at: None,
+            finished_at: None,
+        }
+    }
+
+    #[test]
+    fn test_aggregate() {
+        use TokenState::*;
+        assert_eq!(aggregate(&[]), Waiting);
+        assert_eq!(aggregate(&[Waiting, Waiting]), Waiting);
+        assert_eq!(aggregate(&[Success, Waiting]), Running);
+        assert_eq!(aggregate(&[Success, Running, Failure]), Running);
+        assert_eq!(aggregate(&[Success, Failure, Waiting]), Failure);
+        assert_eq!(aggregate(&[Failure, Error]), Error);
+        assert_eq!(aggregate(&[Success, Cancelled]), Cancelled);
+        assert_eq!(aggregate(&[Success, Success]), Success);
+    }
+
+    #[test]
+    fn test_edges() {
+        let nodes = vec![
+            node("extract", &[]),
+            node("load", &["transform"]),
+            node("transform", &["extract", "missing"]),
+        ];
+        let edges = edges(&nodes);
+        assert_eq!(
+            edges,
+            vec![
+                ExecutionEdge {
+                    from: nodes[2].job_id,
+                    to: nodes[1].job_id,
+                },
+                ExecutionEdge {
+                    from: nodes[0].job_id,
+                    to: nodes[2].job_id,
+                },
+            ]
+        );
+    }
+
+    async fn is_dispatched(pool: &PgPool, id: &RunId) -> bool {
+        sqlx::query_scalar("SELECT dispatched_at IS NOT NULL FROM run WHERE id = $1")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .expect("run should be selected")
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_trigger_advance_and_rerun(pool: PgPool) -> Result<()> {
+        let project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )?;
+        PgProjectRepository.create(&project, &pool).await?;
+        let workflow = Workflow::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            project.id().as_uuid().to_string(),
+            testutils::rand::string(10),
+            false,
+        )?;
+        PgWorkflowRepository.create(&workflow, &pool).await?;
+        let extract = Job::new(
+            testutils::rand::uuid(),
+            String::from("extract"),
+            workflow.id().as_uuid().to_string(),
+            0,
+            testutils::rand::string(10),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        PgJobRepository.create(&extract, &pool).await?;
+        let mut load = Job::new(
+            testutils::rand::uuid(),
+            String::from("load"),
+            workflow.id().as_uuid().to_string(),
+            0,
+            testutils::rand::string(10),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        load.set_depends_on(vec![extract.name().clone()]);
+        PgJobRepository.create(&load, &pool).await?;
+        let execution = Execution::new(
+            testutils::rand::uuid(),
+            workflow.id().as_uuid().to_string(),
+            RunPriority::Normal,
+            Utc::now(),
+        )?;
+        let runs = pool
+            .trigger(&execution, &[extract.id().clone(), load.id().clone()])
+            .await?;
+        assert_eq!(runs.len(), 2);
+        assert!(is_dispatched(&pool, runs[0].id()).await);
+        assert!(!is_dispatched(&pool, runs[1].id()).await);
+        let graph = pool
+            .get_graph_by_id(execution.id())
+            .await?
+            .expect("execution should be found");
+        assert_eq!(graph.summary.state, TokenState::Waiting);
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(
+            graph.edges,
+            vec![ExecutionEdge {
+                from: extract.id().to_uuid(),
+                to: load.id().to_uuid(),
+            }]
+        );
+        RunService::update_state(&pool, runs[0].id(), &TokenState::Success).await?;
+        assert!(is_dispatched(&pool, runs[1].id()).await);
+        for failed_only in [true, false] {
+            match pool.rerun(execution.id(), failed_only).await? {
+                Rerun::Blocked(nodes) => {
+                    assert_eq!(nodes.len(), 1);
+                    assert_eq!(&nodes[0].job_id, load.id().as_uuid());
+                }
+                Rerun::Done(_) => panic!("unfinished execution should not be rerun"),
+            }
+        }
+        RunService::update_state(&pool, runs[1].id(), &TokenState::Failure).await?;
+        let summary = ExecutionService::get_by_id(&pool, execution.id())
+            .await?
+            .expect("execution should be found");
+        assert_eq!(summary.state, TokenState::Failure);
+        let reruns = match pool.rerun(execution.id(), true).await? {
+            Rerun::Done(runs) => runs,
+            Rerun::Blocked(_) => panic!("finished execution should be rerun"),
+        };
+        assert_eq!(reruns.len(), 1);
+        assert_eq!(reruns[0].job_id(), load.id());
+        assert_eq!(*reruns[0].attempt().as_i32(), 2);
+        assert!(is_dispatched(&pool, reruns[0].id()).await);
+        let summary = ExecutionService::get_by_id(&pool, execution.id())
+            .await?
+            .expect("execution should be found");
+        assert_eq!(summary.state, TokenState::Running);
+        assert!(matches!(
+            pool.rerun(execution.id(), false).await?,
+            Rerun::Blocked(nodes) if nodes.len() == 1
+        ));
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_backfill(pool: PgPool) -> Result<()> {
+        let project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )?;
+        PgProjectRepository.create(&project, &pool).await?;
+        let workflow = Workflow::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            project.id().as_uuid().to_string(),
+            testutils::rand::string(10),
+            false,
+        )?;
+        PgWorkflowRepository.create(&workflow, &pool).await?;
+        let job = Job::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            workflow.id().as_uuid().to_string(),
+            0,
+            testutils::rand::string(10),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        PgJobRepository.create(&job, &pool).await?;
+        let now = Utc::now();
+        let executions = (1..=3)
+            .map(|days| {
+                Execution::new(
+                    testutils::rand::uuid(),
+                    workflow.id().as_uuid().to_string(),
+                    RunPriority::BackFill,
+                    now - chrono::Duration::days(days),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let runs = pool.backfill(&executions, &[job.id().clone()]).await?;
+        assert_eq!(runs.len(), 3);
+        for (execution, runs) in executions.iter().zip(runs.iter()) {
+            assert_eq!(runs.len(), 1);
+            assert_eq!(runs[0].triggered_at(), execution.triggered_at());
+            assert!(is_dispatched(&pool, runs[0].id()).await);
+        }
+        let listed = pool.list_by_workflow_id(workflow.id(), None).await?;
+        assert_eq!(listed.len(), 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+use crate::infra::broker::Broker;
+use crate::infra::postgres;
+use anyhow::anyhow;
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+#[async_trait]
+pub trait HealthService {
+    async fn check(&self) -> Result<()>;
+}
+
+#[async_trait]
+impl HealthService for PgPool {
+    async fn check(&self) -> Result<()> {
+        postgres::ping(self).await
+    }
+}
+
+#[async_trait]
+impl HealthService for dyn Broker {
+    async fn check(&self) -> Result<()> {
+        if self.is_connected().await {
+            Ok(())
+        } else {
+            Err(anyhow!("message broker connection is not established"))
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::controller::entities::job::JobId;
+use crate::controller::entities::page::Page;
+use crate::controller::entities::page::Pagination;
+use crate::controller::entities::workflow::WorkflowId;
+use crate::controller::repositories::job::JobRepository;
+use crate::controller::repositories::job::JobRow;
+use crate::controller::repositories::job::PgJobRepository;
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+#[async_trait]
+pub trait JobService {
+    async fn get_by_id(&self, id: &JobId) -> Result<Option<JobRow>>;
+
+    async fn list_by_workflow_id(&self, workflow_id: &WorkflowId) -> Result<Vec<JobRow>>;
+
+    async fn page_by_workflow_id(
+        &self,
+        workflow_id: &WorkflowId,
+        pagination: &Pagination,
+    ) -> Result<Page<JobRow>>;
+}
+
+#[async_trait]
+impl JobService for PgPool {
+    async fn get_by_id(&self, id: &JobId) -> Result<Option<JobRow>> {
+        let repo = PgJobRepository;
+        repo.get_by_id(id, self).await
+    }
+
+    async fn list_by_workflow_id(&self, workflow_id: &WorkflowId) -> Result<Vec<JobRow>> {
+        let repo = PgJobRepository;
+        repo.list_by_workflow_id(workflow_id, self).await
+    }
+
+    async fn page_by_workflow_id(
+        &self,
+        workflow_id: &WorkflowId,
+        pagination: &Pagination,
+    ) -> Result<Page<JobRow>> {
+        let repo = PgJobRepository;
+        repo.page_by_workflow_id(workflow_id, pagination, self)
+            .await
+    }
+}
@@ -0,0 +1,258 @@
+use crate::controller::entities::job::JobId;
+use crate::controller::entities::project::ProjectId;
+use crate::controller::entities::run::RunId;
+use crate::controller::repositories::job::JobRepository;
+use crate::controller::repositories::job::PgJobRepository;
+use crate::controller::repositories::log::LogRepository;
+use crate::controller::repositories::log::LogRow;
+use crate::controller::repositories::log::PgLogRepository;
+use crate::controller::repositories::run::PgRunRepository;
+use crate::controller::repositories::run::RunRepository;
+use crate::controller::repositories::run::RunRow;
+use crate::controller::services::secret;
+use crate::controller::services::secret::SecretService;
+use crate::infra::crypto::Cipher;
+use crate::messages::log::LogLine;
+use crate::messages::log::LogStream;
+use crate::messages::token::TokenState;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::Stream;
+use sqlx::postgres::PgQueryResult;
+use sqlx::PgPool;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::watch;
+
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+const FOLLOW_BATCH_SIZE: i64 = 1000;
+
+#[derive(Debug, Clone, Default)]
+pub struct LogQuery {
+    pub stream: Option<LogStream>,
+    pub from: Option<i64>,
+    pub tail: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+#[async_trait]
+pub trait LogService {
+    async fn append(
+        &self,
+        run: &RunRow,
+        lines: Vec<LogLine>,
+        cipher: Option<&Cipher>,
+    ) -> Result<PgQueryResult>;
+
+    async fn list(&self, run_id: &RunId, query: &LogQuery) -> Result<Vec<LogRow>>;
+}
+
+#[async_trait]
+impl LogService for PgPool {
+    async fn append(
+        &self,
+        run: &RunRow,
+        mut lines: Vec<LogLine>,
+        cipher: Option<&Cipher>,
+    ) -> Result<PgQueryResult> {
+        let repo = PgLogRepository;
+        let envs = run.envs.clone().unwrap_or_default();
+        let names = secret::references(&envs)?;
+        if let (false, Some(cipher)) = (names.is_empty(), cipher) {
+            let config = PgJobRepository
+                .get_config_by_id(&JobId::new(run.job_id), self)
+                .await?;
+            if let Some(config) = config {
+                let project_id = ProjectId::new(config.project_id);
+                let secrets = self.reveal(&project_id, &names, cipher).await?;
+                for line in lines.iter_mut() {
+                    line.line = secret::redact(&line.line, &secrets);
+                }
+            }
+        }
+        repo.create(&RunId::new(run.id), &lines, self).await
+    }
+
+    async fn list(&self, run_id: &RunId, query: &LogQuery) -> Result<Vec<LogRow>> {
+        let repo = PgLogRepository;
+        match query.tail {
+            Some(tail) => {
+                repo.tail_by_run_id(run_id, query.stream.as_ref(), Some(&tail), self)
+                    .await
+            }
+            None => {
+                repo.list_by_run_id(
+                    run_id,
+                    query.stream.as_ref(),
+                    query.from.as_ref(),
+                    query.limit.as_ref(),
+                    self,
+                )
+                .await
+            }
+        }
+    }
+}
+
+struct Follow {
+    pool: PgPool,
+    run_id: RunId,
+    stream: Option<LogStream>,
+    next: i64,
+    draining: watch::Receiver<bool>,
+}
+
+impl Follow {
+    async fn poll(&mut self) -> Result<Option<Vec<LogRow>>> {
+        let rows = PgLogRepository
+            .list_by_run_id(
+                &self.run_id,
+                self.stream.as_ref(),
+                Some(&self.next),
+                Some(&FOLLOW_BATCH_SIZE),
+                &self.pool,
+            )
+            .await?;
+        if let Some(last) = rows.last() {
+            self.next = last.seq + 1;
+            return Ok(Some(rows));
+        }
+        let finished = match PgRunRepository.get_by_id(&self.run_id, &self.pool).await? {
+            Some(run) => TokenState::from_str(&run.state)
+                .map(|state| state.is_done())
+                .unwrap_or(true),
+            None => true,
+        };
+        if finished || *self.draining.borrow() {
+            return Ok(None);
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(FOLLOW_INTERVAL) => {}
+            _ = self.draining.changed() => {}
+        }
+        Ok(Some(Vec::new()))
+    }
+}
+
+pub fn follow(
+    pool: PgPool,
+    run_id: RunId,
+    stream: Option<LogStream>,
+    from: i64,
+    draining: watch::Receiver<bool>,
+) -> impl Stream<Item = Result<Vec<LogRow>>> {
+    let follow = Follow {
+        pool,
+        run_id,
+        stream,
+        next: from,
+        draining,
+    };
+    futures::stream::unfold(Some(follow), |follow| async move {
+        let mut follow = follow?;
+        match follow.poll().await {
+            Ok(Some(rows)) => Some((Ok(rows), Some(follow))),
+            Ok(None) => None,
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::job::Job;
+    use crate::controller::entities::project::Project;
+    use crate::controller::entities::run::Run;
+    use crate::controller::entities::secret::Secret;
+    use crate::controller::entities::workflow::Workflow;
+    use crate::controller::repositories::project::PgProjectRepository;
+    use crate::controller::repositories::project::ProjectRepository;
+    use crate::controller::repositories::workflow::PgWorkflowRepository;
+    use crate::controller::repositories::workflow::WorkflowRepository;
+    use crate::messages::run::RunPriority;
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use chrono::Utc;
+    use futures::StreamExt;
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_append_and_follow(pool: PgPool) -> Result<()> {
+        let cipher = Cipher::new(&STANDARD.encode(testutils::rand::string(32)))?;
+        let project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )?;
+        PgProjectRepository.create(&project, &pool).await?;
+        let workflow = Workflow::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            project.id().as_uuid().to_string(),
+            testutils::rand::string(10),
+            false,
+        )?;
+        PgWorkflowRepository.create(&workflow, &pool).await?;
+        let job = Job::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            workflow.id().as_uuid().to_string(),
+            0,
+            testutils::rand::string(10),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        PgJobRepository.create(&job, &pool).await?;
+        let secret = Secret::new(
+            testutils::rand::uuid(),
+            project.id().as_uuid().to_string(),
+            String::from("TOKEN"),
+            String::from("s3cr3t"),
+        )?;
+        pool.put(&secret, &cipher).await?;
+        let mut run = Run::new(
+            testutils::rand::uuid(),
+            TokenState::Success,
+            RunPriority::Normal,
+            job.id().as_uuid().to_string(),
+            Utc::now(),
+        )?;
+        run.set_envs(Some(vec![String::from("TOKEN=${secret:TOKEN}")]));
+        PgRunRepository.create(&run, &pool).await?;
+        let row = PgRunRepository
+            .get_by_id(run.id(), &pool)
+            .await?
+            .expect("run should be found");
+        let lines: Vec<LogLine> = (0..3)
+            .map(|seq| LogLine {
+                seq,
+                stream: LogStream::Stdout,
+                line: format!("token is s3cr3t ({})", seq),
+                emitted_at: Utc::now(),
+            })
+            .collect();
+        pool.append(&row, lines, Some(&cipher)).await?;
+        let rows = pool
+            .list(
+                run.id(),
+                &LogQuery {
+                    tail: Some(2),
+                    ..LogQuery::default()
+                },
+            )
+            .await?;
+        assert_eq!(rows.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(&rows[0].line, "token is *** (1)");
+        let (_, draining) = watch::channel(false);
+        let followed: Vec<Vec<LogRow>> = follow(pool.clone(), run.id().clone(), None, 1, draining)
+            .map(|rows| rows.expect("logs should be followed"))
+            .collect()
+            .await;
+        assert_eq!(followed.len(), 1);
+        assert_eq!(followed[0].len(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,589 @@
+use crate::controller::entities::execution::ExecutionId;
+use crate::controller::entities::job::JobId;
+use crate::controller::entities::notification;
+use crate::controller::entities::notification::NotificationEvent;
+use crate::controller::entities::notification::NotificationRule;
+use crate::controller::entities::notification::NotificationRuleId;
+use crate::controller::entities::notification::NotificationRuleName;
+use crate::controller::entities::project::ProjectId;
+use crate::controller::repositories::execution::ExecutionRepository;
+use crate::controller::repositories::execution::PgExecutionRepository;
+use crate::controller::repositories::job::JobRepository;
+use crate::controller::repositories::job::PgJobRepository;
+use crate::controller::repositories::notification::NotificationDeliveryRow;
+use crate::controller::repositories::notification::NotificationRepository;
+use crate::controller::repositories::notification::NotificationRuleRow;
+use crate::controller::repositories::notification::PgNotificationRepository;
+use crate::controller::repositories::run::RunRow;
+use crate::controller::services::execution;
+use crate::infra::crypto::Cipher;
+use crate::infra::webhook;
+use crate::infra::webhook::Webhook;
+use crate::messages::token::TokenState;
+use crate::metrics;
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
+use serde_json::Value as Json;
+use sqlx::postgres::PgQueryResult;
+use sqlx::PgConnection;
+use sqlx::PgPool;
+use std::cmp::min;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::info;
+use tracing::warn;
+use uuid::Uuid;
+
+const BATCH_SIZE: i64 = 10;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const LEASE_SECS: i64 = 300;
+
+const MIN_RETRY_SECS: i64 = 10;
+
+const MAX_RETRY_SECS: i64 = 3600;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct Notification {
+    pub event: NotificationEvent,
+    pub project_id: Uuid,
+    pub workflow_id: Uuid,
+    pub job_id: Uuid,
+    pub run_id: Uuid,
+    pub execution_id: Option<Uuid>,
+    pub state: TokenState,
+    pub occurred_at: DateTime<Utc>,
+}
+
+pub fn retry_delay(attempts: i32) -> i64 {
+    let exponent = min(attempts.max(1) - 1, 16) as u32;
+    min(MIN_RETRY_SECS.saturating_mul(1 << exponent), MAX_RETRY_SECS)
+}
+
+fn events(state: &TokenState) -> Option<NotificationEvent> {
+    match state {
+        TokenState::Failure => Some(NotificationEvent::RunFailure),
+        TokenState::Error => Some(NotificationEvent::RunError),
+        _ => None,
+    }
+}
+
+async fn is_execution_succeeded(execution_id: &ExecutionId, tx: &mut PgConnection) -> Result<bool> {
+    let repo = PgExecutionRepository;
+    if repo.lock_by_id(execution_id, &mut *tx).await?.is_none() {
+        return Ok(false);
+    }
+    let states: Vec<TokenState> = match repo.get_by_id(execution_id, &mut *tx).await? {
+        Some(execution) => execution
+            .states
+            .iter()
+            .filter_map(|state| TokenState::from_str(state).ok())
+            .collect(),
+        None => return Ok(false),
+    };
+    Ok(execution::aggregate(&states) == TokenState::Success)
+}
+
+// NOTE: Deliveries are keyed by the run that caused them, so a repeated transition never notifies twice.
+pub async fn notify(run: &RunRow, state: &TokenState, tx: &mut PgConnection) -> Result<usize> {
+    let mut found = Vec::new();
+    if let Some(event) = events(state) {
+        found.push(event);
+    }
+    if let (TokenState::Success, Some(execution_id)) = (state, run.execution_id) {
+        if is_execution_succeeded(&ExecutionId::new(execution_id), &mut *tx).await? {
+            found.push(NotificationEvent::ExecutionSuccess);
+        }
+    }
+    if found.is_empty() {
+        return Ok(0);
+    }
+    let owner = match PgJobRepository
+        .get_owner_by_id(&JobId::new(run.job_id), &mut *tx)
+        .await?
+    {
+        Some(owner) => owner,
+        None => return Ok(0),
+    };
+    let mut enqueued = 0;
+    for event in found {
+        let notification = Notification {
+            event,
+            project_id: owner.project_id,
+            workflow_id: owner.workflow_id,
+            job_id: run.job_id,
+            run_id: run.id,
+            execution_id: run.execution_id,
+            state: *state,
+            occurred_at: Utc::now(),
+        };
+        let payload =
+            serde_json::to_value(&notification).context("failed to serialize notification")?;
+        enqueued += enqueue(
+            &owner.project_id,
+            &owner.workflow_id,
+            &event,
+            &run.id,
+            &payload,
+            &mut *tx,
+        )
+        .await?;
+    }
+    Ok(enqueued)
+}
+
+pub async fn enqueue(
+    project_id: &Uuid,
+    workflow_id: &Uuid,
+    event: &NotificationEvent,
+    subject_id: &Uuid,
+    payload: &Json,
+    tx: &mut PgConnection,
+) -> Result<usize> {
+    let repo = PgNotificationRepository;
+    let rules = repo
+        .list_matching(project_id, workflow_id, event, &mut *tx)
+        .await?;
+    let mut enqueued = 0;
+    for rule in rules.iter() {
+        let done = repo
+            .enqueue(&rule.id, event, subject_id, payload, &mut *tx)
+            .await?;
+        enqueued += done.rows_affected() as usize;
+    }
+    Ok(enqueued)
+}
+
+#[async_trait]
+pub trait NotificationService {
+    async fn put(&self, rule: &NotificationRule, cipher: &Cipher) -> Result<PgQueryResult>;
+
+    async fn delete(
+        &self,
+        project_id: &ProjectId,
+        name: &NotificationRuleName,
+    ) -> Result<PgQueryResult>;
+
+    async fn get_by_name(
+        &self,
+        project_id: &ProjectId,
+        name: &NotificationRuleName,
+    ) -> Result<Option<NotificationRuleRow>>;
+
+    async fn list_by_project_id(&self, project_id: &ProjectId) -> Result<Vec<NotificationRuleRow>>;
+
+    async fn list_deliveries(
+        &self,
+        rule_id: &NotificationRuleId,
+        limit: Option<&i64>,
+    ) -> Result<Vec<NotificationDeliveryRow>>;
+}
+
+#[async_trait]
+impl NotificationService for PgPool {
+    async fn put(&self, rule: &NotificationRule, cipher: &Cipher) -> Result<PgQueryResult> {
+        let repo = PgNotificationRepository;
+        let sealed = cipher.encrypt(&rule.aad(), rule.secret().as_str().as_bytes())?;
+        repo.create(rule, &sealed, self).await
+    }
+
+    async fn delete(
+        &self,
+        project_id: &ProjectId,
+        name: &NotificationRuleName,
+    ) -> Result<PgQueryResult> {
+        let repo = PgNotificationRepository;
+        repo.delete(project_id, name, self).await
+    }
+
+    async fn get_by_name(
+        &self,
+        project_id: &ProjectId,
+        name: &NotificationRuleName,
+    ) -> Result<Option<NotificationRuleRow>> {
+        let repo = PgNotificationRepository;
+        repo.get_by_name(project_id, name, self).await
+    }
+
+    async fn list_by_project_id(&self, project_id: &ProjectId) -> Result<Vec<NotificationRuleRow>> {
+        let repo = PgNotificationRepository;
+        repo.list_by_project_id(project_id, self).await
+    }
+
+    async fn list_deliveries(
+        &self,
+        rule_id: &NotificationRuleId,
+        limit: Option<&i64>,
+    ) -> Result<Vec<NotificationDeliveryRow>> {
+        let repo = PgNotificationRepository;
+        repo.list_deliveries_by_rule_id(rule_id.as_uuid(), limit, self)
+            .await
+    }
+}
+
+pub struct Deliverer {
+    pub pool: PgPool,
+    pub cipher: Option<Cipher>,
+    pub max_attempts: i32,
+    pub timeout: Duration,
+}
+
+impl Deliverer {
+    async fn post(&self, row: &NotificationDeliveryRow) -> Result<u16> {
+        let rule = PgNotificationRepository
+            .get_sealed_by_id(&NotificationRuleId::new(row.rule_id), &self.pool)
+            .await?
+            .ok_or_else(|| anyhow!(r#"notification rule "{}" was not found"#, row.rule_id))?;
+        let cipher = self
+            .cipher
+            .as_ref()
+            .ok_or_else(|| anyhow!("secret key is not configured"))?;
+        let name = NotificationRuleName::new(&rule.name)?;
+        let secret = cipher
+            .decrypt(
+                &notification::aad(&ProjectId::new(rule.project_id), &name),
+                &rule.secret,
+            )
+            .context(format!(
+                r#"failed to decrypt secret of notification rule "{}""#,
+                rule.name
+            ))?;
+        webhook::post(
+            Webhook {
+                url: &rule.url,
+                event: &row.event,
+                delivery_id: &row.id,
+                secret: &secret,
+                body: serde_json::to_vec(&row.payload)?,
+            },
+            Utc::now().timestamp(),
+            self.timeout,
+        )
+        .await
+    }
+
+    async fn attempt(&self, row: &NotificationDeliveryRow) -> Result<()> {
+        let repo = PgNotificationRepository;
+        let (status_code, error) = match self.post(row).await {
+            Ok(status) if (200..300).contains(&status) => {
+                metrics::NOTIFICATION_DELIVERIES_TOTAL
+                    .with_label_values(&[&row.event, "delivered"])
+                    .inc();
+                repo.mark_delivered(&row.id, &(status as i32), &self.pool)
+                    .await?;
+                return Ok(());
+            }
+            Ok(status) => (
+                Some(status as i32),
+                format!("webhook responded with status {}", status),
+            ),
+            Err(e) => (None, format!("{:#}", e)),
+        };
+        let attempts = row.attempts + 1;
+        if attempts >= self.max_attempts {
+            metrics::NOTIFICATION_DELIVERIES_TOTAL
+                .with_label_values(&[&row.event, "dead"])
+                .inc();
+            warn!(
+                r#"gave up delivering "{}" after {} attempt(s): {}"#,
+                row.id, attempts, error
+            );
+            repo.mark_dead(&row.id, status_code.as_ref(), &error, &self.pool)
+                .await?;
+        } else {
+            metrics::NOTIFICATION_DELIVERIES_TOTAL
+                .with_label_values(&[&row.event, "retried"])
+                .inc();
+            repo.mark_retry(
+                &row.id,
+                status_code.as_ref(),
+                &error,
+                &retry_delay(attempts),
+                &self.pool,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn poll(&self) -> Result<usize> {
+        let rows = PgNotificationRepository
+            .claim(&BATCH_SIZE, &LEASE_SECS, &self.pool)
+            .await?;
+        let attempted = futures::future::join_all(rows.iter().map(|row| self.attempt(row))).await;
+        for result in attempted {
+            result?;
+        }
+        Ok(rows.len())
+    }
+}
+
+pub async fn deliver(deliverer: Deliverer, mut shutdown: watch::Receiver<bool>) {
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+        }
+        match deliverer.poll().await {
+            Ok(0) => {}
+            Ok(attempted) => info!("attempted {} notification delivery(ies)", attempted),
+            Err(e) => warn!("failed to deliver notifications: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::execution::Execution;
+    use crate::controller::entities::job::Job;
+    use crate::controller::entities::project::Project;
+    use crate::controller::entities::run::RunId;
+    use crate::controller::entities::workflow::Workflow;
+    use crate::controller::repositories::project::PgProjectRepository;
+    use crate::controller::repositories::project::ProjectRepository;
+    use crate::controller::repositories::workflow::PgWorkflowRepository;
+    use crate::controller::repositories::workflow::WorkflowRepository;
+    use crate::controller::services::execution::ExecutionService;
+    use crate::controller::services::run::RunService;
+    use crate::messages::run::RunPriority;
+    use axum::body::Bytes;
+    use axum::extract::State;
+    use axum::http::HeaderMap;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::Router;
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use std::net::SocketAddr;
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, Bytes)>>>;
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), 10);
+        assert_eq!(retry_delay(2), 20);
+        assert_eq!(retry_delay(4), 80);
+        assert_eq!(retry_delay(100), MAX_RETRY_SECS);
+    }
+
+    #[test]
+    fn test_events() {
+        assert_eq!(
+            events(&TokenState::Failure),
+            Some(NotificationEvent::RunFailure)
+        );
+        assert_eq!(
+            events(&TokenState::Error),
+            Some(NotificationEvent::RunError)
+        );
+        assert_eq!(events(&TokenState::Success), None);
+        assert_eq!(events(&TokenState::Cancelled), None);
+    }
+
+    // NOTE: Stub webhook receiver which answers with the given statuses in order and then 200.
+    fn stub(statuses: Vec<StatusCode>) -> (SocketAddr, Received) {
+        let received: Received = Arc::new(Mutex::new(Vec::new()));
+        let statuses = Arc::new(Mutex::new(statuses));
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(
+                    |State((received, statuses)): State<(
+                        Received,
+                        Arc<Mutex<Vec<StatusCode>>>,
+                    )>,
+                     headers: HeaderMap,
+                     body: Bytes| async move {
+                        received
+                            .lock()
+                            .expect("lock should be acquired")
+                            .push((headers, body));
+                        let mut statuses = statuses.lock().expect("lock should be acquired");
+                        if statuses.is_empty() {
+                            StatusCode::OK
+                        } else {
+                            statuses.remove(0)
+                        }
+                    },
+                ),
+            )
+            .with_state((received.clone(), statuses));
+        let listener = TcpListener::bind("127.0.0.1:0").expect("stub should be bound");
+        let addr = listener.local_addr().expect("stub should have an address");
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .expect("stub should be served")
+                .serve(app.into_make_service()),
+        );
+        (addr, received)
+    }
+
+    async fn setup(pool: &PgPool, url: &str, cipher: &Cipher) -> Result<(Project, Job)> {
+        let project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )?;
+        PgProjectRepository.create(&project, pool).await?;
+        let workflow = Workflow::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            project.id().as_uuid().to_string(),
+            testutils::rand::string(10),
+            false,
+        )?;
+        PgWorkflowRepository.create(&workflow, pool).await?;
+        let job = Job::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            workflow.id().as_uuid().to_string(),
+            0,
+            testutils::rand::string(10),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        PgJobRepository.create(&job, pool).await?;
+        let rule = NotificationRule::new(
+            testutils::rand::uuid(),
+            project.id().as_uuid().to_string(),
+            String::from("on-call"),
+            vec![
+                String::from("run_failure"),
+                String::from("execution_success"),
+            ],
+            None,
+            url.to_owned(),
+            String::from("s3cr3t"),
+        )?;
+        pool.put(&rule, cipher).await?;
+        Ok((project, job))
+    }
+
+    async fn trigger(pool: &PgPool, job: &Job) -> Result<RunId> {
+        let execution = Execution::new(
+            Uuid::new_v4().to_string(),
+            job.workflow_id().as_uuid().to_string(),
+            RunPriority::Normal,
+            Utc::now(),
+        )?;
+        let runs = ExecutionService::trigger(pool, &execution, &[job.id().clone()]).await?;
+        Ok(runs[0].id().clone())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_notify_and_deliver(pool: PgPool) -> Result<()> {
+        let cipher = Cipher::new(&STANDARD.encode(testutils::rand::string(32)))?;
+        let (addr, received) = stub(Vec::new());
+        let (project, job) = setup(&pool, &format!("http://{}/hook", addr), &cipher).await?;
+        let failed = trigger(&pool, &job).await?;
+        RunService::update_state(&pool, &failed, &TokenState::Failure).await?;
+        RunService::update_state(&pool, &failed, &TokenState::Failure).await?;
+        let succeeded = trigger(&pool, &job).await?;
+        RunService::update_state(&pool, &succeeded, &TokenState::Success).await?;
+        let deliverer = Deliverer {
+            pool: pool.clone(),
+            cipher: Some(cipher),
+            max_attempts: 3,
+            timeout: Duration::from_secs(5),
+        };
+        assert_eq!(deliverer.poll().await?, 2);
+        assert_eq!(deliverer.poll().await?, 0);
+        let received = received.lock().expect("lock should be acquired").clone();
+        assert_eq!(received.len(), 2);
+        let mut events = Vec::new();
+        for (headers, body) in received.iter() {
+            let header = |name: &str| {
+                headers
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .expect("header should be sent")
+                    .to_owned()
+            };
+            let timestamp: i64 = header(webhook::TIMESTAMP_HEADER).parse()?;
+            assert!(webhook::verify(
+                b"s3cr3t",
+                timestamp,
+                body,
+                &header(webhook::SIGNATURE_HEADER)
+            ));
+            let notification: Notification = serde_json::from_slice(body)?;
+            assert_eq!(&notification.project_id, project.id().as_uuid());
+            assert_eq!(header(webhook::EVENT_HEADER), notification.event.as_ref());
+            events.push((notification.event, notification.run_id));
+        }
+        events.sort_by_key(|(event, _)| event.as_ref().to_owned());
+        assert_eq!(
+            events,
+            vec![
+                (NotificationEvent::ExecutionSuccess, succeeded.to_uuid()),
+                (NotificationEvent::RunFailure, failed.to_uuid()),
+            ]
+        );
+        let rule = pool
+            .get_by_name(project.id(), &NotificationRuleName::new("on-call")?)
+            .await?
+            .expect("rule should be found");
+        let deliveries = pool
+            .list_deliveries(&NotificationRuleId::new(rule.id), None)
+            .await?;
+        assert!(deliveries.iter().all(|row| row.state == "delivered"));
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_retry_and_dead_letter(pool: PgPool) -> Result<()> {
+        let cipher = Cipher::new(&STANDARD.encode(testutils::rand::string(32)))?;
+        let (addr, received) = stub(vec![
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ]);
+        let (project, job) = setup(&pool, &format!("http://{}/hook", addr), &cipher).await?;
+        let failed = trigger(&pool, &job).await?;
+        RunService::update_state(&pool, &failed, &TokenState::Failure).await?;
+        let deliverer = Deliverer {
+            pool: pool.clone(),
+            cipher: Some(cipher),
+            max_attempts: 2,
+            timeout: Duration::from_secs(5),
+        };
+        assert_eq!(deliverer.poll().await?, 1);
+        let rule = pool
+            .get_by_name(project.id(), &NotificationRuleName::new("on-call")?)
+            .await?
+            .expect("rule should be found");
+        let rule_id = NotificationRuleId::new(rule.id);
+        let deliveries = pool.list_deliveries(&rule_id, None).await?;
+        assert_eq!(deliveries[0].state, "pending");
+        assert_eq!(deliveries[0].attempts, 1);
+        assert_eq!(deliveries[0].status_code, Some(503));
+        assert!(deliveries[0].next_attempt_at > Utc::now());
+        sqlx::query("UPDATE notification_delivery SET next_attempt_at = CURRENT_TIMESTAMP")
+            .execute(&pool)
+            .await?;
+        assert_eq!(deliverer.poll().await?, 1);
+        let deliveries = pool.list_deliveries(&rule_id, None).await?;
+        assert_eq!(deliveries[0].state, "dead");
+        assert_eq!(deliveries[0].attempts, 2);
+        let dead: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM notification_dead_letter WHERE rule_id = $1")
+                .bind(rule.id)
+                .fetch_one(&pool)
+                .await?;
+        assert_eq!(dead, 1);
+        assert_eq!(received.lock().expect("lock should be acquired").len(), 2);
+        Ok(())
+    }
+}
@@ -7,6 +7,7 @@ use crate::infra::opa::Input;
 use crate::infra::opa::Query;
 use crate::infra::opa::Resource;
 use crate::infra::opa::Token;
+use crate::metrics;
 use anyhow::anyhow;
 use anyhow::Result;
 use axum::async_trait;
@@ -66,6 +67,23 @@ impl IntoResponse for TokenError {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Denial {
+    Unauthenticated,
+    Forbidden,
+}
+
+impl std::fmt::Display for Denial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Denial::Unauthenticated => write!(f, "no bearer token was presented"),
+            Denial::Forbidden => write!(f, "bearer token is not allowed to act on the resource"),
+        }
+    }
+}
+
+impl std::error::Error for Denial {}
+
 #[derive(Debug)]
 pub struct Event {
     token: Token,
@@ -134,6 +152,7 @@ impl Event {
     }
 
     async fn is_authorized_by(&self, url: Option<&String>) -> Result<bool> {
+        let timer = metrics::OPA_DECISION_DURATION_SECONDS.start_timer();
         let decision = opa::authorize(
             url,
             &Query {
@@ -145,10 +164,14 @@ impl Event {
             },
         )
         .await?;
+        timer.observe_duration();
         if decision.result.unwrap_or(false) {
             debug!(?self.token, ?self.action, ?self.resource, "authorized");
         } else {
             warn!(?self.token, ?self.action, ?self.resource, "unauthorized");
+            metrics::OPA_DENIALS_TOTAL
+                .with_label_values(&[self.action.as_ref(), &self.resource.kind])
+                .inc();
         }
         Ok(decision.result.unwrap_or(false))
     }
@@ -162,6 +185,8 @@ pub trait OPAService {
         url: impl Into<Option<&String>> + Send,
         mut event: Event,
     ) -> Result<()>;
+
+    async fn ping(&self, url: impl Into<Option<&String>> + Send) -> Result<()>;
 }
 
 #[async_trait]
@@ -186,7 +211,20 @@ impl OPAService for PgPool {
         if event.is_authorized_by(url.into()).await? {
             Ok(())
         } else {
-            Err(anyhow!(r#"failed to authorize event "{:?}""#, event))
+            let denial = match event.token {
+                Token::None => Denial::Unauthenticated,
+                Token::Bearer(_) => Denial::Forbidden,
+            };
+            Err(anyhow::Error::new(denial)
+                .context(format!(r#"failed to authorize event "{:?}""#, event)))
+        }
+    }
+
+    async fn ping(&self, url: impl Into<Option<&String>> + Send) -> Result<()> {
+        if let Some(url) = url.into() {
+            opa::health(url).await
+        } else {
+            Err(anyhow!("OPA sidecar address is unset"))
         }
     }
 }
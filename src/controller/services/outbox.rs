@@ -0,0 +1,305 @@
+use crate::controller::repositories::outbox::OutboxRepository;
+use crate::controller::repositories::outbox::OutboxRow;
+use crate::controller::repositories::outbox::PgOutboxRepository;
+use crate::infra::broker::Broker;
+use crate::infra::broker::Envelope;
+use crate::infra::jwt;
+use crate::infra::jwt::Keys;
+use crate::logging::propagation;
+use crate::messages::run::RunDispatch;
+use crate::messages::run::RUN_DISPATCH_EXCHANGE;
+use crate::metrics;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value as Json;
+use sqlx::PgPool;
+use std::cmp::min;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::info_span;
+use tracing::warn;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+
+const BATCH_SIZE: i64 = 100;
+
+const LEASE_SECS: i64 = 60;
+
+const PUBLISH_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub const SENT_RETENTION_SECS: i64 = 86400;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[async_trait]
+pub trait OutboxService {
+    async fn relay(&self, broker: &dyn Broker, keys: Option<&Keys>) -> Result<usize>;
+}
+
+#[async_trait]
+impl OutboxService for PgPool {
+    async fn relay(&self, broker: &dyn Broker, keys: Option<&Keys>) -> Result<usize> {
+        let repo = PgOutboxRepository;
+        let rows = repo.claim(&BATCH_SIZE, &LEASE_SECS, self).await?;
+        let mut sent = Vec::new();
+        let mut failure = None;
+        for row in rows.iter() {
+            if let Err(e) = publish(broker, keys, row).await {
+                failure = Some(e);
+                break;
+            }
+            sent.push(row.id);
+        }
+        let unsent: Vec<Uuid> = rows.iter().skip(sent.len()).map(|row| row.id).collect();
+        let mut tx = self
+            .begin()
+            .await
+            .context("failed to begin postgres transaction")?;
+        repo.mark_sent(&sent, &mut tx).await?;
+        repo.release(&unsent, &mut tx).await?;
+        tx.commit()
+            .await
+            .context("failed to commit postgres transaction")?;
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(sent.len()),
+        }
+    }
+}
+
+// NOTE: Run credentials are minted on publish so that they never rest in [outbox].
+fn seal(keys: Option<&Keys>, row: &OutboxRow) -> Result<Json> {
+    if row.exchange != RUN_DISPATCH_EXCHANGE {
+        return Ok(row.payload.clone());
+    }
+    let mut dispatch: RunDispatch = serde_json::from_value(row.payload.clone())
+        .context(format!(r#"failed to parse "{}" from [outbox]"#, row.id))?;
+    dispatch.config_token = keys
+        .map(|keys| jwt::config(keys, &dispatch.run_id.to_string()))
+        .transpose()?;
+    Ok(serde_json::to_value(&dispatch)?)
+}
+
+async fn publish(broker: &dyn Broker, keys: Option<&Keys>, row: &OutboxRow) -> Result<()> {
+    let carrier: HashMap<String, String> =
+        serde_json::from_value(row.headers.clone()).unwrap_or_default();
+    let span = info_span!("outbox.publish", otel.name = row.exchange.as_str());
+    span.set_parent(propagation::extract_map(&carrier));
+    async {
+        let mut headers = HashMap::new();
+        propagation::inject_map(&mut headers);
+        let envelope = Envelope {
+            exchange: row.exchange.clone(),
+            routing_key: row.routing_key.clone(),
+            payload: serde_json::to_vec(&seal(keys, row)?)?,
+            headers,
+            priority: row.priority.and_then(|p| u8::try_from(p).ok()),
+        };
+        tokio::time::timeout(PUBLISH_TIMEOUT, broker.publish(&envelope))
+            .await
+            .context("publish timed out")?
+    }
+    .instrument(span)
+    .await
+    .map_err(|e: anyhow::Error| {
+        metrics::MQ_PUBLISH_FAILURES_TOTAL
+            .with_label_values(&[&row.exchange])
+            .inc();
+        e.context(format!(r#"failed to publish "{}" from [outbox]"#, row.id))
+    })
+}
+
+async fn flush(pool: &PgPool, broker: &dyn Broker, keys: Option<&Keys>) -> Result<()> {
+    while OutboxService::relay(pool, broker, keys).await? as i64 == BATCH_SIZE {}
+    Ok(())
+}
+
+pub async fn relay(
+    pool: PgPool,
+    broker: Arc<dyn Broker>,
+    keys: Option<Keys>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut backoff: Option<Duration> = None;
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            _ = tokio::time::sleep(backoff.unwrap_or(POLL_INTERVAL)) => {}
+        }
+        backoff = match flush(&pool, broker.as_ref(), keys.as_ref()).await {
+            Ok(_) => None,
+            Err(e) => {
+                let next = backoff.map_or(MIN_BACKOFF, |b| min(b * 2, MAX_BACKOFF));
+                warn!("failed to relay outbox, retrying in {:?}: {}", next, e);
+                Some(next)
+            }
+        };
+    }
+    if let Err(e) = flush(&pool, broker.as_ref(), keys.as_ref()).await {
+        warn!(
+            "failed to relay outbox on shutdown, pending messages are kept: {}",
+            e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::outbox::Outbox;
+    use crate::infra::broker::memory::Memory;
+    use crate::messages::config::ConfigUpdate;
+    use crate::messages::config::CONFIG_UPDATES_EXCHANGE;
+    use crate::messages::run::RunPriority;
+    use crate::messages::run::RUN_DISPATCH_QUEUE;
+    use crate::messages::setup;
+    use chrono::Utc;
+    use futures::StreamExt;
+
+    fn row(outbox: &Outbox) -> OutboxRow {
+        OutboxRow {
+            id: outbox.id().to_uuid(),
+            exchange: outbox.exchange().to_string(),
+            routing_key: outbox.routing_key().to_string(),
+            payload: outbox.payload().to_json(),
+            headers: outbox.headers().to_json(),
+            priority: outbox.priority().as_ref().map(|p| p.to_i32()),
+            sent_at: None,
+            leased_until: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn dispatch(priority: RunPriority) -> RunDispatch {
+        RunDispatch {
+            run_id: Uuid::new_v4(),
+            job_id: Uuid::new_v4(),
+            priority,
+            config_token: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_config_update() -> Result<()> {
+        let broker = Memory::new();
+        setup(&broker).await?;
+        let mut first = broker.subscribe(CONFIG_UPDATES_EXCHANGE, "").await?;
+        let mut second = broker.subscribe(CONFIG_UPDATES_EXCHANGE, "").await?;
+        let uuid = Uuid::new_v4();
+        publish(
+            &broker,
+            None,
+            &row(&Outbox::new(&ConfigUpdate::Project(uuid))?),
+        )
+        .await?;
+        for subscription in [&mut first, &mut second] {
+            let envelope = subscription
+                .next()
+                .await
+                .expect("update should be broadcasted")?;
+            let update: ConfigUpdate = serde_json::from_slice(&envelope.payload)?;
+            assert!(matches!(update, ConfigUpdate::Project(id) if id == uuid));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_publish_dispatch() -> Result<()> {
+        let broker = Memory::new();
+        setup(&broker).await?;
+        let backfill = dispatch(RunPriority::BackFill);
+        let normal = dispatch(RunPriority::Normal);
+        let high = dispatch(RunPriority::High);
+        for dispatch in [&backfill, &normal, &high] {
+            publish(&broker, None, &row(&Outbox::new(dispatch)?)).await?;
+        }
+        let mut consumer = broker.consume(RUN_DISPATCH_QUEUE, 1).await?;
+        for expected in [&high, &normal, &backfill] {
+            let delivery = consumer.next().await.expect("dispatch should be queued")?;
+            let delivered: RunDispatch = serde_json::from_slice(&delivery.envelope.payload)?;
+            assert_eq!(delivered.run_id, expected.run_id);
+            assert_eq!(delivery.envelope.priority, Some(expected.priority as u8));
+            delivery.ack().await?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_publish_dispatch_with_config_token() -> Result<()> {
+        let broker = Memory::new();
+        setup(&broker).await?;
+        let keys = Keys::new(&testutils::rand::string(32));
+        let dispatch = dispatch(RunPriority::Normal);
+        let row = row(&Outbox::new(&dispatch)?);
+        assert!(row.payload.get("config_token").is_none());
+        publish(&broker, Some(&keys), &row).await?;
+        let mut consumer = broker.consume(RUN_DISPATCH_QUEUE, 1).await?;
+        let delivery = consumer.next().await.expect("dispatch should be queued")?;
+        let delivered: RunDispatch = serde_json::from_slice(&delivery.envelope.payload)?;
+        let token = delivered
+            .config_token
+            .expect("config token should be minted");
+        assert_eq!(
+            jwt::verify_config(&keys, &token)?,
+            dispatch.run_id.to_string()
+        );
+        delivery.ack().await?;
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_relay(pool: PgPool) -> Result<()> {
+        let broker = Memory::new();
+        setup(&broker).await?;
+        let mut dispatches = broker
+            .subscribe(RUN_DISPATCH_EXCHANGE, RunPriority::High.as_ref())
+            .await?;
+        let dispatch = dispatch(RunPriority::High);
+        let outbox = Outbox::new(&dispatch)?;
+        PgOutboxRepository.create(&outbox, &pool).await?;
+        assert_eq!(pool.relay(&broker, None).await?, 1);
+        let envelope = dispatches
+            .next()
+            .await
+            .expect("dispatch should be delivered")?;
+        let delivered: RunDispatch = serde_json::from_slice(&envelope.payload)?;
+        assert_eq!(delivered.run_id, dispatch.run_id);
+        assert_eq!(pool.relay(&broker, None).await?, 0);
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_relay_releases_unsent(pool: PgPool) -> Result<()> {
+        let broker = Memory::new();
+        let first = Outbox::new(&dispatch(RunPriority::Normal))?;
+        let second = Outbox::new(&dispatch(RunPriority::Normal))?;
+        PgOutboxRepository.create(&first, &pool).await?;
+        PgOutboxRepository.create(&second, &pool).await?;
+        assert!(pool.relay(&broker, None).await.is_err());
+        setup(&broker).await?;
+        let mut consumer = broker.consume(RUN_DISPATCH_QUEUE, 2).await?;
+        assert_eq!(pool.relay(&broker, None).await?, 2);
+        for _ in 0..2 {
+            let delivery = consumer.next().await.expect("dispatch should be queued")?;
+            delivery.ack().await?;
+        }
+        let sent: i64 = sqlx::query_scalar(
+            "SELECT COUNT(1) FROM outbox WHERE sent_at IS NOT NULL AND leased_until IS NULL",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(sent, 2);
+        Ok(())
+    }
+}
@@ -1,25 +1,51 @@
+use crate::controller::entities::outbox::Outbox;
+use crate::controller::entities::page::Page;
+use crate::controller::entities::page::Pagination;
 use crate::controller::entities::project::Project;
 use crate::controller::entities::project::ProjectId;
 use crate::controller::entities::project::ProjectName;
-use crate::controller::entities::workflow::WorkflowName;
+use crate::controller::entities::run::RunId;
+use crate::controller::entities::stash;
+use crate::controller::entities::stash::StashName;
+use crate::controller::entities::version::Precondition;
+use crate::controller::repositories::outbox::OutboxRepository;
+use crate::controller::repositories::outbox::PgOutboxRepository;
 use crate::controller::repositories::project::PgProjectRepository;
-use crate::controller::repositories::project::ProjectConfigRow;
 use crate::controller::repositories::project::ProjectRepository;
 use crate::controller::repositories::project::ProjectRow;
 use crate::controller::repositories::project::ProjectSummaryRow;
 use crate::controller::repositories::project::WorkflowSummaryRow;
+use crate::controller::repositories::run::RunRow;
+use crate::controller::repositories::stash::PgStashRepository;
+use crate::controller::repositories::stash::StashRepository;
+use crate::infra::stash::Backend;
+use crate::messages::config::ConfigUpdate;
+use anyhow::Context;
 use anyhow::Result;
 use async_trait::async_trait;
 use sqlx::postgres::PgQueryResult;
 use sqlx::PgPool;
+use tracing::warn;
+
+pub enum Deletion {
+    Done(PgQueryResult),
+    Blocked(Vec<RunRow>),
+}
 
 #[async_trait]
 pub trait ProjectService {
-    async fn create(&self, project: &Project) -> Result<PgQueryResult>;
+    async fn create(&self, project: &Project, precondition: &Precondition) -> Result<ProjectRow>;
+
+    async fn delete(&self, id: &ProjectId, precondition: &Precondition) -> Result<PgQueryResult>;
 
-    async fn delete(&self, id: &ProjectId) -> Result<PgQueryResult>;
+    async fn delete_cascade(
+        &self,
+        id: &ProjectId,
+        precondition: &Precondition,
+        backend: &dyn Backend,
+    ) -> Result<Deletion>;
 
-    async fn list(&self, limit: impl Into<Option<&i64>> + Send) -> Result<Vec<ProjectRow>>;
+    async fn list(&self, pagination: &Pagination) -> Result<Page<ProjectRow>>;
 
     async fn get_by_id(&self, id: &ProjectId) -> Result<Option<ProjectRow>>;
 
@@ -27,32 +53,94 @@ pub trait ProjectService {
 
     async fn get_summary_by_id(&self, id: &ProjectId) -> Result<Option<ProjectSummaryRow>>;
 
-    async fn get_config_by_id(&self, id: &ProjectId) -> Result<Option<ProjectConfigRow>>;
-
     async fn list_workflows_by_id(
         &self,
         id: &ProjectId,
-        name: impl Into<Option<&WorkflowName>> + Send,
-        after: impl Into<Option<&WorkflowName>> + Send,
-        limit: impl Into<Option<&i64>> + Send,
-    ) -> Result<Vec<WorkflowSummaryRow>>;
+        pagination: &Pagination,
+    ) -> Result<Page<WorkflowSummaryRow>>;
 }
 
 #[async_trait]
 impl ProjectService for PgPool {
-    async fn create(&self, project: &Project) -> Result<PgQueryResult> {
+    async fn create(&self, project: &Project, precondition: &Precondition) -> Result<ProjectRow> {
         let repo = PgProjectRepository;
-        repo.create(project, self).await
+        let outbox = Outbox::new(&ConfigUpdate::Project(project.id().to_uuid()))?;
+        let mut tx = self
+            .begin()
+            .await
+            .context("failed to begin postgres transaction")?;
+        let current = repo.lock_by_id(project.id(), &mut tx).await?;
+        precondition.check(current.map(|row| row.version))?;
+        repo.create(project, &mut tx).await?;
+        let row = repo
+            .get_by_id(project.id(), &mut tx)
+            .await?
+            .context("upserted project should be found")?;
+        PgOutboxRepository.create(&outbox, &mut tx).await?;
+        tx.commit()
+            .await
+            .context("failed to commit postgres transaction")?;
+        Ok(row)
+    }
+
+    async fn delete(&self, id: &ProjectId, precondition: &Precondition) -> Result<PgQueryResult> {
+        let repo = PgProjectRepository;
+        let outbox = Outbox::new(&ConfigUpdate::Project(id.to_uuid()))?;
+        let mut tx = self
+            .begin()
+            .await
+            .context("failed to begin postgres transaction")?;
+        let current = repo.lock_by_id(id, &mut tx).await?;
+        precondition.check(current.map(|row| row.version))?;
+        let result = repo.soft_delete(id, &mut tx).await?;
+        if result.rows_affected() > 0 {
+            PgOutboxRepository.create(&outbox, &mut tx).await?;
+        }
+        tx.commit()
+            .await
+            .context("failed to commit postgres transaction")?;
+        Ok(result)
     }
 
-    async fn delete(&self, id: &ProjectId) -> Result<PgQueryResult> {
+    async fn delete_cascade(
+        &self,
+        id: &ProjectId,
+        precondition: &Precondition,
+        backend: &dyn Backend,
+    ) -> Result<Deletion> {
         let repo = PgProjectRepository;
-        repo.delete(id, self).await
+        let outbox = Outbox::new(&ConfigUpdate::Project(id.to_uuid()))?;
+        let mut tx = self
+            .begin()
+            .await
+            .context("failed to begin postgres transaction")?;
+        let current = repo.lock_by_id(id, &mut tx).await?;
+        precondition.check(current.map(|row| row.version))?;
+        let runs = repo.list_active_runs_by_id(id, &mut tx).await?;
+        if !runs.is_empty() {
+            return Ok(Deletion::Blocked(runs));
+        }
+        let stashes = PgStashRepository.list_by_project_id(id, &mut tx).await?;
+        let result = repo.delete_cascade(id, &mut tx).await?;
+        if result.rows_affected() > 0 {
+            PgOutboxRepository.create(&outbox, &mut tx).await?;
+        }
+        tx.commit()
+            .await
+            .context("failed to commit postgres transaction")?;
+        // NOTE: Blobs are deleted only after the rows are gone, a failure leaves an orphan blob but never a dangling row.
+        for row in stashes.iter() {
+            let key = stash::key(&RunId::new(row.run_id), &StashName::new(&row.name)?);
+            if let Err(e) = backend.delete(&key).await {
+                warn!(r#"failed to delete stash "{}": {}"#, key, e);
+            }
+        }
+        Ok(Deletion::Done(result))
     }
 
-    async fn list(&self, limit: impl Into<Option<&i64>> + Send) -> Result<Vec<ProjectRow>> {
+    async fn list(&self, pagination: &Pagination) -> Result<Page<ProjectRow>> {
         let repo = PgProjectRepository;
-        repo.list(limit.into(), self).await
+        repo.list(pagination, self).await
     }
 
     async fn get_by_id(&self, id: &ProjectId) -> Result<Option<ProjectRow>> {
@@ -70,20 +158,96 @@ impl ProjectService for PgPool {
         repo.get_summary_by_id(id, self).await
     }
 
-    async fn get_config_by_id(&self, id: &ProjectId) -> Result<Option<ProjectConfigRow>> {
-        let repo = PgProjectRepository;
-        repo.get_config_by_id(id, self).await
-    }
-
     async fn list_workflows_by_id(
         &self,
         id: &ProjectId,
-        name: impl Into<Option<&WorkflowName>> + Send,
-        after: impl Into<Option<&WorkflowName>> + Send,
-        limit: impl Into<Option<&i64>> + Send,
-    ) -> Result<Vec<WorkflowSummaryRow>> {
+        pagination: &Pagination,
+    ) -> Result<Page<WorkflowSummaryRow>> {
         let repo = PgProjectRepository;
-        repo.list_workflows_by_id(id, name.into(), after.into(), limit.into(), self)
-            .await
+        repo.list_workflows_by_id(id, pagination, self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::job::Job;
+    use crate::controller::entities::run::Run;
+    use crate::controller::entities::stash::Stash;
+    use crate::controller::entities::workflow::Workflow;
+    use crate::controller::repositories::job::JobRepository;
+    use crate::controller::repositories::job::PgJobRepository;
+    use crate::controller::repositories::run::PgRunRepository;
+    use crate::controller::repositories::run::RunRepository;
+    use crate::controller::repositories::workflow::PgWorkflowRepository;
+    use crate::controller::repositories::workflow::WorkflowRepository;
+    use crate::controller::services::stash::StashService;
+    use crate::infra::stash::Local;
+    use crate::messages::run::RunPriority;
+    use crate::messages::token::TokenState;
+    use chrono::Utc;
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_delete_cascade_removes_stashes(pool: PgPool) -> Result<()> {
+        let root = std::env::temp_dir().join(testutils::rand::uuid());
+        let backend = Local::new(&root);
+        let project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )?;
+        PgProjectRepository.create(&project, &pool).await?;
+        let workflow = Workflow::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            project.id().as_uuid().to_string(),
+            testutils::rand::string(10),
+            false,
+        )?;
+        PgWorkflowRepository.create(&workflow, &pool).await?;
+        let job = Job::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            workflow.id().as_uuid().to_string(),
+            0,
+            testutils::rand::string(10),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        PgJobRepository.create(&job, &pool).await?;
+        let run = Run::new(
+            testutils::rand::uuid(),
+            TokenState::Success,
+            RunPriority::Normal,
+            job.id().as_uuid().to_string(),
+            Utc::now(),
+        )?;
+        PgRunRepository.create(&run, &pool).await?;
+        let stash = Stash::new(
+            testutils::rand::uuid(),
+            run.id().as_uuid().to_string(),
+            String::from("rows"),
+            String::from("application/json"),
+            2,
+            Utc::now() + chrono::Duration::hours(1),
+        )?;
+        pool.put(&stash, b"42", &backend).await?;
+        assert_eq!(backend.get(&stash.key()).await?, Some(b"42".to_vec()));
+        let listed = PgStashRepository
+            .list_by_project_id(project.id(), &pool)
+            .await?;
+        assert_eq!(listed.len(), 1);
+        match pool
+            .delete_cascade(project.id(), &Precondition::None, &backend)
+            .await?
+        {
+            Deletion::Done(done) => assert_eq!(done.rows_affected(), 1),
+            Deletion::Blocked(_) => panic!("finished runs should not block deletion"),
+        }
+        assert_eq!(backend.get(&stash.key()).await?, None);
+        std::fs::remove_dir_all(root)?;
+        Ok(())
     }
 }
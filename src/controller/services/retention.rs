@@ -0,0 +1,184 @@
+use crate::controller::repositories::outbox::OutboxRepository;
+use crate::controller::repositories::outbox::PgOutboxRepository;
+use crate::controller::repositories::retention::PgRetentionRepository;
+use crate::controller::repositories::retention::RetentionRepository;
+use crate::controller::services::outbox::SENT_RETENTION_SECS;
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::info;
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Compaction {
+    pub runs: u64,
+    pub tokens: u64,
+    pub executions: u64,
+    pub messages: u64,
+}
+
+impl Compaction {
+    pub fn is_empty(&self) -> bool {
+        self.runs == 0 && self.tokens == 0 && self.executions == 0 && self.messages == 0
+    }
+}
+
+#[async_trait]
+pub trait RetentionService {
+    async fn compact(&self, batch_size: i64) -> Result<Compaction>;
+}
+
+#[async_trait]
+impl RetentionService for PgPool {
+    async fn compact(&self, batch_size: i64) -> Result<Compaction> {
+        let repo = PgRetentionRepository;
+        let mut compaction = Compaction::default();
+        loop {
+            let done = repo.archive_runs(Some(&batch_size), self).await?;
+            compaction.runs += done.rows_affected();
+            if done.rows_affected() < batch_size as u64 {
+                break;
+            }
+        }
+        loop {
+            let done = repo.delete_tokens(Some(&batch_size), self).await?;
+            compaction.tokens += done.rows_affected();
+            if done.rows_affected() < batch_size as u64 {
+                break;
+            }
+        }
+        loop {
+            let done = repo.delete_executions(Some(&batch_size), self).await?;
+            compaction.executions += done.rows_affected();
+            if done.rows_affected() < batch_size as u64 {
+                break;
+            }
+        }
+        loop {
+            let done = PgOutboxRepository
+                .delete_sent(&SENT_RETENTION_SECS, Some(&batch_size), self)
+                .await?;
+            compaction.messages += done.rows_affected();
+            if done.rows_affected() < batch_size as u64 {
+                break;
+            }
+        }
+        Ok(compaction)
+    }
+}
+
+pub async fn compact(
+    pool: PgPool,
+    interval: Duration,
+    batch_size: i64,
+    leader: watch::Receiver<bool>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            _ = tokio::time::sleep(interval) => {}
+        }
+        if !*leader.borrow() {
+            continue;
+        }
+        match RetentionService::compact(&pool, batch_size).await {
+            Ok(compaction) if compaction.is_empty() => {}
+            Ok(compaction) => info!(
+                "archived {} run(s), deleted {} token(s), {} execution(s) and {} sent message(s)",
+                compaction.runs, compaction.tokens, compaction.executions, compaction.messages
+            ),
+            Err(e) => warn!("failed to compact run history: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::job::Job;
+    use crate::controller::entities::page::PageSort;
+    use crate::controller::entities::page::Pagination;
+    use crate::controller::entities::project::Project;
+    use crate::controller::entities::project::ProjectRetention;
+    use crate::controller::entities::run::Run;
+    use crate::controller::entities::workflow::Workflow;
+    use crate::controller::repositories::job::JobRepository;
+    use crate::controller::repositories::job::PgJobRepository;
+    use crate::controller::repositories::project::PgProjectRepository;
+    use crate::controller::repositories::project::ProjectRepository;
+    use crate::controller::repositories::run::PgRunRepository;
+    use crate::controller::repositories::run::RunRepository;
+    use crate::controller::repositories::workflow::PgWorkflowRepository;
+    use crate::controller::repositories::workflow::WorkflowRepository;
+    use crate::messages::run::RunPriority;
+    use crate::messages::token::TokenState;
+    use chrono::Utc;
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_compact(pool: PgPool) -> Result<()> {
+        let mut project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )?;
+        project.set_retention(Some(ProjectRetention::new(None, Some(1))?));
+        PgProjectRepository.create(&project, &pool).await?;
+        let workflow = Workflow::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            project.id().as_uuid().to_string(),
+            testutils::rand::string(10),
+            false,
+        )?;
+        PgWorkflowRepository.create(&workflow, &pool).await?;
+        let job = Job::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            workflow.id().as_uuid().to_string(),
+            0,
+            testutils::rand::string(10),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        PgJobRepository.create(&job, &pool).await?;
+        let mut runs = Vec::new();
+        for _ in 0..3 {
+            let run = Run::new(
+                testutils::rand::uuid(),
+                TokenState::Running,
+                RunPriority::Normal,
+                job.id().as_uuid().to_string(),
+                Utc::now(),
+            )?;
+            PgRunRepository.create(&run, &pool).await?;
+            PgRunRepository
+                .update_state(run.id(), &TokenState::Success, &pool)
+                .await?;
+            runs.push(run);
+        }
+        let compaction = pool.compact(1).await?;
+        assert_eq!(compaction.runs, 2);
+        let remaining = PgRunRepository
+            .list_by_job_id(
+                job.id(),
+                &Pagination::new(None, None, None, None, None, &[PageSort::CreatedAt])?,
+                &pool,
+            )
+            .await?;
+        assert_eq!(remaining.items.len(), 1);
+        assert_eq!(&remaining.items[0].id, runs[2].id().as_uuid());
+        let archived: i64 =
+            sqlx::query_scalar("SELECT COUNT(1) FROM run_archive WHERE job_id = $1")
+                .bind(job.id())
+                .fetch_one(&pool)
+                .await?;
+        assert_eq!(archived, 2);
+        assert!(pool.compact(1).await?.is_empty());
+        Ok(())
+    }
+}
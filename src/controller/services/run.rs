@@ -0,0 +1,273 @@
+use crate::controller::entities::execution::ExecutionId;
+use crate::controller::entities::job::JobId;
+use crate::controller::entities::outbox::Outbox;
+use crate::controller::entities::page::Page;
+use crate::controller::entities::page::Pagination;
+use crate::controller::entities::run::Run;
+use crate::controller::entities::run::RunId;
+use crate::controller::repositories::job::JobContextRow;
+use crate::controller::repositories::job::JobRepository;
+use crate::controller::repositories::job::PgJobRepository;
+use crate::controller::repositories::outbox::OutboxRepository;
+use crate::controller::repositories::outbox::PgOutboxRepository;
+use crate::controller::repositories::run::PgRunRepository;
+use crate::controller::repositories::run::RunCountRow;
+use crate::controller::repositories::run::RunRepository;
+use crate::controller::repositories::run::RunRow;
+use crate::controller::services::config;
+use crate::controller::services::execution;
+use crate::controller::services::notification;
+use crate::messages::run::RunDispatch;
+use crate::messages::run::RunPriority;
+use crate::messages::token::TokenState;
+use crate::template;
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::json;
+use sqlx::postgres::PgQueryResult;
+use sqlx::PgConnection;
+use sqlx::PgPool;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unrenderable {
+    pub path: String,
+    pub job: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Unrenderable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, r#"job "{}": {}"#, self.job, self.message)
+    }
+}
+
+impl std::error::Error for Unrenderable {}
+
+fn render_all(
+    path: &str,
+    sources: &[String],
+    context: &JobContextRow,
+    variables: &serde_json::Value,
+) -> Result<Vec<String>, Unrenderable> {
+    sources
+        .iter()
+        .enumerate()
+        .map(|(i, source)| {
+            template::render(source, variables).map_err(|e| Unrenderable {
+                path: format!("{}[{}]", path, i),
+                job: context.name.clone(),
+                message: e.to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn render(run: &Run, context: &JobContextRow) -> Result<Run> {
+    let variables = json!({
+        "run": {
+            "id": run.id().as_uuid(),
+            "triggered_at": run.triggered_at().to_rfc3339(),
+            "priority": run.priority(),
+            "attempt": run.attempt().as_i32(),
+        },
+        "execution": {
+            "id": run.execution_id().as_ref().map(|id| id.to_uuid()),
+        },
+        "job": {
+            "id": context.id,
+            "name": context.name,
+            "config": config::merge(&context.project_config, &context.config),
+        },
+        "workflow": {
+            "id": context.workflow_id,
+            "name": context.workflow_name,
+        },
+        "project": {
+            "id": context.project_id,
+            "name": context.project_name,
+            "config": context.project_config,
+        },
+    });
+    let args = render_all("args", &context.args, context, &variables)?;
+    let envs = render_all("envs", &context.envs, context, &variables)?;
+    let mut run = run.clone();
+    run.set_args(Some(args));
+    run.set_envs(Some(envs));
+    Ok(run)
+}
+
+pub async fn prepare(run: &Run, tx: &mut PgConnection) -> Result<Run> {
+    let context = PgJobRepository
+        .get_context_by_id(run.job_id(), &mut *tx)
+        .await?
+        .ok_or_else(|| anyhow!(r#"job "{}" was not found"#, run.job_id().as_uuid()))?;
+    let run = render(run, &context)?;
+    PgRunRepository.create(&run, &mut *tx).await?;
+    Ok(run)
+}
+
+pub async fn dispatch(run: &RunRow, tx: &mut PgConnection) -> Result<()> {
+    let outbox = Outbox::new(&RunDispatch {
+        run_id: run.id,
+        job_id: run.job_id,
+        priority: RunPriority::from_str(&run.priority)
+            .map_err(|_| anyhow!(r#"invalid run priority "{}""#, run.priority))?,
+        config_token: None,
+    })?;
+    PgOutboxRepository.create(&outbox, &mut *tx).await?;
+    PgRunRepository
+        .mark_dispatched(&RunId::new(run.id), &mut *tx)
+        .await?;
+    Ok(())
+}
+
+#[async_trait]
+pub trait RunService {
+    async fn update_state(&self, id: &RunId, state: &TokenState) -> Result<PgQueryResult>;
+
+    async fn cancel(&self, id: &RunId) -> Result<PgQueryResult>;
+
+    async fn get_by_id(&self, id: &RunId) -> Result<Option<RunRow>>;
+
+    async fn list_by_job_id(&self, job_id: &JobId, pagination: &Pagination)
+        -> Result<Page<RunRow>>;
+
+    async fn count_by_state(&self) -> Result<Vec<RunCountRow>>;
+}
+
+#[async_trait]
+impl RunService for PgPool {
+    async fn update_state(&self, id: &RunId, state: &TokenState) -> Result<PgQueryResult> {
+        let repo = PgRunRepository;
+        let mut tx = self
+            .begin()
+            .await
+            .context("failed to begin postgres transaction")?;
+        let done = repo.update_state(id, state, &mut tx).await?;
+        if done.rows_affected() == 1 {
+            if let Some(run) = repo.get_by_id(id, &mut tx).await? {
+                if let (TokenState::Success, Some(execution_id)) = (state, run.execution_id) {
+                    execution::advance(&ExecutionId::new(execution_id), &mut tx).await?;
+                }
+                notification::notify(&run, state, &mut tx).await?;
+            }
+        }
+        tx.commit()
+            .await
+            .context("failed to commit postgres transaction")?;
+        Ok(done)
+    }
+
+    async fn cancel(&self, id: &RunId) -> Result<PgQueryResult> {
+        let repo = PgRunRepository;
+        repo.cancel(id, self).await
+    }
+
+    async fn get_by_id(&self, id: &RunId) -> Result<Option<RunRow>> {
+        let repo = PgRunRepository;
+        repo.get_by_id(id, self).await
+    }
+
+    async fn list_by_job_id(
+        &self,
+        job_id: &JobId,
+        pagination: &Pagination,
+    ) -> Result<Page<RunRow>> {
+        let repo = PgRunRepository;
+        repo.list_by_job_id(job_id, pagination, self).await
+    }
+
+    async fn count_by_state(&self) -> Result<Vec<RunCountRow>> {
+        let repo = PgRunRepository;
+        repo.count_by_state(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_render() {
+        let context = JobContextRow {
+            id: Uuid::new_v4(),
+            name: String::from("load"),
+            args: vec![
+                String::from("--date={{ run.triggered_at | date(\"%Y-%m-%d\") }}"),
+                String::from("--bucket={{ job.config.bucket }}"),
+            ],
+            envs: vec![
+                String::from("WORKFLOW={{ workflow.name }}"),
+                String::from("TOKEN=${secret:TOKEN}"),
+            ],
+            config: json!({ "bucket": "s3://override" }),
+            workflow_id: Uuid::new_v4(),
+            workflow_name: String::from("daily"),
+            project_id: Uuid::new_v4(),
+            project_name: String::from("example"),
+            project_config: json!({ "bucket": "s3://kotosiro" }),
+        };
+        let run = Run::new(
+            testutils::rand::uuid(),
+            TokenState::Waiting,
+            RunPriority::Normal,
+            context.id.to_string(),
+            Utc.with_ymd_and_hms(2023, 3, 1, 12, 0, 0).unwrap(),
+        )
+        .expect("run should be created");
+        let run = render(&run, &context).expect("run should be rendered");
+        assert_eq!(
+            run.args(),
+            &Some(vec![
+                String::from("--date=2023-03-01"),
+                String::from("--bucket=s3://override"),
+            ])
+        );
+        assert_eq!(
+            run.envs(),
+            &Some(vec![
+                String::from("WORKFLOW=daily"),
+                String::from("TOKEN=${secret:TOKEN}"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_render_missing_config() {
+        let context = JobContextRow {
+            id: Uuid::new_v4(),
+            name: String::from("load"),
+            args: vec![
+                String::from("--verbose"),
+                String::from("--bucket={{ job.config.bucket }}"),
+            ],
+            envs: Vec::new(),
+            config: json!({}),
+            workflow_id: Uuid::new_v4(),
+            workflow_name: String::from("daily"),
+            project_id: Uuid::new_v4(),
+            project_name: String::from("example"),
+            project_config: json!({}),
+        };
+        let run = Run::new(
+            testutils::rand::uuid(),
+            TokenState::Waiting,
+            RunPriority::Normal,
+            context.id.to_string(),
+            Utc::now(),
+        )
+        .expect("run should be created");
+        let e = render(&run, &context).expect_err("missing config key should not be rendered");
+        let e = e
+            .downcast_ref::<Unrenderable>()
+            .expect("render failure should be typed");
+        assert_eq!(e.path, "args[1]");
+        assert_eq!(e.job, "load");
+    }
+}
@@ -0,0 +1,222 @@
+use crate::controller::entities::project::ProjectId;
+use crate::controller::entities::secret;
+use crate::controller::entities::secret::Secret;
+use crate::controller::entities::secret::SecretName;
+use crate::controller::repositories::secret::PgSecretRepository;
+use crate::controller::repositories::secret::SecretRepository;
+use crate::controller::repositories::secret::SecretRow;
+use crate::infra::crypto::Cipher;
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::postgres::PgQueryResult;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+const REFERENCE_PREFIX: &str = "${secret:";
+
+const REFERENCE_SUFFIX: &str = "}";
+
+fn substitute<'a>(
+    value: &'a str,
+    mut resolve: impl FnMut(&'a str) -> Result<String>,
+) -> Result<String> {
+    let mut substituted = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find(REFERENCE_PREFIX) {
+        let (head, tail) = rest.split_at(start);
+        let tail = &tail[REFERENCE_PREFIX.len()..];
+        let end = tail
+            .find(REFERENCE_SUFFIX)
+            .ok_or_else(|| anyhow!("unterminated secret reference"))?;
+        substituted.push_str(head);
+        substituted.push_str(&resolve(&tail[..end])?);
+        rest = &tail[end + REFERENCE_SUFFIX.len()..];
+    }
+    substituted.push_str(rest);
+    Ok(substituted)
+}
+
+pub fn references(envs: &[String]) -> Result<HashSet<&str>> {
+    let mut names = HashSet::new();
+    for env in envs.iter() {
+        substitute(env, |name| {
+            names.insert(name);
+            Ok(String::new())
+        })?;
+    }
+    Ok(names)
+}
+
+pub fn resolve(envs: &[String], secrets: &HashMap<String, String>) -> Result<Vec<String>> {
+    envs.iter()
+        .map(|env| {
+            substitute(env, |name| {
+                secrets
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| anyhow!(r#"unknown secret "{}""#, name))
+            })
+        })
+        .collect()
+}
+
+pub fn redact(line: &str, secrets: &HashMap<String, String>) -> String {
+    secrets
+        .values()
+        .filter(|value| !value.is_empty())
+        .fold(line.to_owned(), |line, value| {
+            line.replace(value.as_str(), "***")
+        })
+}
+
+#[async_trait]
+pub trait SecretService {
+    async fn put(&self, secret: &Secret, cipher: &Cipher) -> Result<PgQueryResult>;
+
+    async fn delete(&self, project_id: &ProjectId, name: &SecretName) -> Result<PgQueryResult>;
+
+    async fn list_by_project_id(&self, project_id: &ProjectId) -> Result<Vec<SecretRow>>;
+
+    async fn reveal(
+        &self,
+        project_id: &ProjectId,
+        names: &HashSet<&str>,
+        cipher: &Cipher,
+    ) -> Result<HashMap<String, String>>;
+
+    async fn resolve(
+        &self,
+        project_id: &ProjectId,
+        envs: &[String],
+        cipher: Option<&Cipher>,
+    ) -> Result<Vec<String>>;
+}
+
+#[async_trait]
+impl SecretService for PgPool {
+    async fn put(&self, secret: &Secret, cipher: &Cipher) -> Result<PgQueryResult> {
+        let repo = PgSecretRepository;
+        let sealed = cipher.encrypt(&secret.aad(), secret.value().as_str().as_bytes())?;
+        repo.create(secret, &sealed, self).await
+    }
+
+    async fn delete(&self, project_id: &ProjectId, name: &SecretName) -> Result<PgQueryResult> {
+        let repo = PgSecretRepository;
+        repo.delete(project_id, name, self).await
+    }
+
+    async fn list_by_project_id(&self, project_id: &ProjectId) -> Result<Vec<SecretRow>> {
+        let repo = PgSecretRepository;
+        repo.list_by_project_id(project_id, self).await
+    }
+
+    async fn reveal(
+        &self,
+        project_id: &ProjectId,
+        names: &HashSet<&str>,
+        cipher: &Cipher,
+    ) -> Result<HashMap<String, String>> {
+        let repo = PgSecretRepository;
+        let mut secrets = HashMap::new();
+        for row in repo.list_sealed_by_project_id(project_id, self).await? {
+            if !names.contains(row.name.as_str()) {
+                continue;
+            }
+            let name = SecretName::new(&row.name)?;
+            let value = cipher
+                .decrypt(&secret::aad(project_id, &name), &row.value)
+                .context(format!(r#"failed to decrypt secret "{}""#, row.name))?;
+            let value = String::from_utf8(value).context("secret must be valid UTF-8")?;
+            secrets.insert(row.name, value);
+        }
+        Ok(secrets)
+    }
+
+    async fn resolve(
+        &self,
+        project_id: &ProjectId,
+        envs: &[String],
+        cipher: Option<&Cipher>,
+    ) -> Result<Vec<String>> {
+        let names = references(envs)?;
+        if names.is_empty() {
+            return Ok(envs.to_vec());
+        }
+        let cipher = cipher.ok_or_else(|| anyhow!("secret key is not configured"))?;
+        let secrets = self.reveal(project_id, &names, cipher).await?;
+        resolve(envs, &secrets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::project::Project;
+    use crate::controller::repositories::project::PgProjectRepository;
+    use crate::controller::repositories::project::ProjectRepository;
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    #[test]
+    fn test_references() {
+        let envs = vec![
+            String::from("USER=kotosiro"),
+            String::from("URL=postgres://${secret:DB_USER}:${secret:DB_PASSWORD}@db"),
+        ];
+        let names = references(&envs).expect("references should be parsed");
+        assert_eq!(names, HashSet::from(["DB_USER", "DB_PASSWORD"]));
+        assert!(references(&[String::from("TOKEN=${secret:TOKEN")]).is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let secrets = HashMap::from([(String::from("TOKEN"), String::from("s3cr3t"))]);
+        let envs = vec![String::from("AUTH=Bearer ${secret:TOKEN}")];
+        assert_eq!(
+            resolve(&envs, &secrets).expect("secrets should be resolved"),
+            vec![String::from("AUTH=Bearer s3cr3t")]
+        );
+        let envs = vec![String::from("AUTH=${secret:UNKNOWN}")];
+        assert!(resolve(&envs, &secrets).is_err());
+    }
+
+    #[test]
+    fn test_redact() {
+        let secrets = HashMap::from([(String::from("TOKEN"), String::from("s3cr3t"))]);
+        assert_eq!(
+            redact("curl -H 'Authorization: s3cr3t'", &secrets),
+            "curl -H 'Authorization: ***'"
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_put_and_resolve(pool: PgPool) -> Result<()> {
+        let cipher = Cipher::new(&STANDARD.encode(testutils::rand::string(32)))?;
+        let project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )?;
+        PgProjectRepository.create(&project, &pool).await?;
+        let value = testutils::rand::string(10);
+        let secret = Secret::new(
+            testutils::rand::uuid(),
+            project.id().as_uuid().to_string(),
+            String::from("TOKEN"),
+            value.clone(),
+        )?;
+        pool.put(&secret, &cipher).await?;
+        let envs = vec![String::from("TOKEN=${secret:TOKEN}")];
+        let resolved = pool.resolve(project.id(), &envs, Some(&cipher)).await?;
+        assert_eq!(resolved, vec![format!("TOKEN={}", value)]);
+        assert!(pool.resolve(project.id(), &envs, None).await.is_err());
+        let plain = vec![String::from("USER=kotosiro")];
+        assert_eq!(pool.resolve(project.id(), &plain, None).await?, plain);
+        Ok(())
+    }
+}
@@ -0,0 +1,356 @@
+use crate::controller::entities::notification::NotificationEvent;
+use crate::controller::entities::sla::SlaKind;
+use crate::controller::entities::sla::SlaMiss;
+use crate::controller::entities::workflow::WorkflowId;
+use crate::controller::entities::workflow::WorkflowSla;
+use crate::controller::repositories::sla::PgSlaRepository;
+use crate::controller::repositories::sla::SlaRepository;
+use crate::controller::services::notification;
+use crate::metrics;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
+use sqlx::PgConnection;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::info;
+use tracing::warn;
+use uuid::Uuid;
+
+const BATCH_SIZE: i64 = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct SlaNotification {
+    pub event: NotificationEvent,
+    pub id: Uuid,
+    pub project_id: Uuid,
+    pub workflow_id: Uuid,
+    pub execution_id: Option<Uuid>,
+    pub kind: SlaKind,
+    pub deadline: DateTime<Utc>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+async fn record(miss: &SlaMiss, project_id: &Uuid, tx: &mut PgConnection) -> Result<bool> {
+    let done = PgSlaRepository.create(miss, &mut *tx).await?;
+    if done.rows_affected() == 0 {
+        return Ok(false);
+    }
+    let payload = serde_json::to_value(SlaNotification {
+        event: NotificationEvent::SlaMiss,
+        id: miss.id().to_uuid(),
+        project_id: *project_id,
+        workflow_id: miss.workflow_id().to_uuid(),
+        execution_id: miss.execution_id().as_ref().map(|id| id.to_uuid()),
+        kind: *miss.kind(),
+        deadline: *miss.deadline(),
+        occurred_at: Utc::now(),
+    })
+    .context("failed to serialize sla notification")?;
+    notification::enqueue(
+        project_id,
+        miss.workflow_id().as_uuid(),
+        &NotificationEvent::SlaMiss,
+        miss.id().as_uuid(),
+        &payload,
+        &mut *tx,
+    )
+    .await?;
+    metrics::SLA_MISSES_TOTAL
+        .with_label_values(&[miss.kind().as_ref()])
+        .inc();
+    Ok(true)
+}
+
+#[async_trait]
+pub trait SlaService {
+    async fn check(&self, now: DateTime<Utc>) -> Result<usize>;
+}
+
+#[async_trait]
+impl SlaService for PgPool {
+    async fn check(&self, now: DateTime<Utc>) -> Result<usize> {
+        let repo = PgSlaRepository;
+        let mut tx = self
+            .begin()
+            .await
+            .context("failed to begin postgres transaction")?;
+        let mut missed = 0;
+        for row in repo
+            .list_late_executions(&now, &BATCH_SIZE, &mut tx)
+            .await?
+        {
+            let miss = SlaMiss::new(
+                Uuid::new_v4().to_string(),
+                row.workflow_id.to_string(),
+                Some(row.execution_id.to_string()),
+                SlaKind::Within,
+                row.deadline,
+            )?;
+            if record(&miss, &row.project_id, &mut tx).await? {
+                missed += 1;
+            }
+        }
+        for row in repo.list_daily(&mut tx).await? {
+            let sla = match WorkflowSla::new(None, Some(row.sla_by), row.sla_timezone) {
+                Ok(sla) => sla,
+                Err(e) => {
+                    warn!(
+                        r#"invalid sla of workflow "{}" was ignored: {}"#,
+                        row.workflow_id, e
+                    );
+                    continue;
+                }
+            };
+            // NOTE: Deadlines due before the SLA was configured are never reported.
+            let deadline = match sla.last_deadline(now) {
+                Some(deadline) if deadline > row.sla_since => deadline,
+                _ => continue,
+            };
+            if repo
+                .is_met_by(&WorkflowId::new(row.workflow_id), &deadline, &mut tx)
+                .await?
+            {
+                continue;
+            }
+            let miss = SlaMiss::new(
+                Uuid::new_v4().to_string(),
+                row.workflow_id.to_string(),
+                None,
+                SlaKind::By,
+                deadline,
+            )?;
+            if record(&miss, &row.project_id, &mut tx).await? {
+                missed += 1;
+            }
+        }
+        tx.commit()
+            .await
+            .context("failed to commit postgres transaction")?;
+        Ok(missed)
+    }
+}
+
+pub async fn monitor(
+    pool: PgPool,
+    interval: Duration,
+    leader: watch::Receiver<bool>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            _ = tokio::time::sleep(interval) => {}
+        }
+        if !*leader.borrow() {
+            continue;
+        }
+        match SlaService::check(&pool, Utc::now()).await {
+            Ok(0) => {}
+            Ok(missed) => info!("recorded {} sla miss(es)", missed),
+            Err(e) => warn!("failed to check workflow slas: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::execution::Execution;
+    use crate::controller::entities::job::Job;
+    use crate::controller::entities::notification::NotificationRule;
+    use crate::controller::entities::project::Project;
+    use crate::controller::entities::workflow::Workflow;
+    use crate::controller::repositories::job::JobRepository;
+    use crate::controller::repositories::job::PgJobRepository;
+    use crate::controller::repositories::project::PgProjectRepository;
+    use crate::controller::repositories::project::ProjectRepository;
+    use crate::controller::repositories::workflow::PgWorkflowRepository;
+    use crate::controller::repositories::workflow::WorkflowRepository;
+    use crate::controller::services::execution::ExecutionService;
+    use crate::controller::services::execution::Rerun;
+    use crate::controller::services::notification::NotificationService;
+    use crate::controller::services::run::RunService;
+    use crate::infra::crypto::Cipher;
+    use crate::messages::run::RunPriority;
+    use crate::messages::token::TokenState;
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+
+    async fn setup(pool: &PgPool, sla: WorkflowSla) -> Result<(Project, Workflow, Job)> {
+        let project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )?;
+        PgProjectRepository.create(&project, pool).await?;
+        let mut workflow = Workflow::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            project.id().as_uuid().to_string(),
+            testutils::rand::string(10),
+            false,
+        )?;
+        workflow.set_sla(Some(sla));
+        PgWorkflowRepository.create(&workflow, pool).await?;
+        let job = Job::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            workflow.id().as_uuid().to_string(),
+            0,
+            testutils::rand::string(10),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        PgJobRepository.create(&job, pool).await?;
+        Ok((project, workflow, job))
+    }
+
+    async fn count_misses(pool: &PgPool, workflow: &Workflow) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sla_miss WHERE workflow_id = $1")
+            .bind(workflow.id())
+            .fetch_one(pool)
+            .await?;
+        Ok(count)
+    }
+
+    async fn count_deliveries(pool: &PgPool, workflow: &Workflow) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*)
+             FROM notification_delivery
+             WHERE event = 'sla_miss' AND payload->>'workflow_id' = $1::TEXT",
+        )
+        .bind(workflow.id())
+        .fetch_one(pool)
+        .await?;
+        Ok(count)
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_check_within(pool: PgPool) -> Result<()> {
+        let (project, workflow, job) =
+            setup(&pool, WorkflowSla::new(Some(60), None, None)?).await?;
+        let cipher = Cipher::new(&STANDARD.encode(testutils::rand::string(32)))?;
+        let rule = NotificationRule::new(
+            testutils::rand::uuid(),
+            project.id().as_uuid().to_string(),
+            String::from("on-call"),
+            vec![String::from("sla_miss")],
+            None,
+            String::from("http://127.0.0.1/hook"),
+            testutils::rand::string(10),
+        )?;
+        pool.put(&rule, &cipher).await?;
+        let execution = Execution::new(
+            testutils::rand::uuid(),
+            workflow.id().as_uuid().to_string(),
+            RunPriority::Normal,
+            Utc::now(),
+        )?;
+        let runs = ExecutionService::trigger(&pool, &execution, &[job.id().clone()]).await?;
+        assert_eq!(SlaService::check(&pool, Utc::now()).await?, 0);
+        let later = Utc::now() + chrono::Duration::minutes(2);
+        assert_eq!(SlaService::check(&pool, later).await?, 1);
+        assert_eq!(SlaService::check(&pool, later).await?, 0);
+        assert_eq!(count_misses(&pool, &workflow).await?, 1);
+        assert_eq!(count_deliveries(&pool, &workflow).await?, 1);
+        let execution = Execution::new(
+            testutils::rand::uuid(),
+            workflow.id().as_uuid().to_string(),
+            RunPriority::Normal,
+            Utc::now(),
+        )?;
+        let met = ExecutionService::trigger(&pool, &execution, &[job.id().clone()]).await?;
+        RunService::update_state(&pool, met[0].id(), &TokenState::Success).await?;
+        assert_eq!(SlaService::check(&pool, later).await?, 0);
+        RunService::update_state(&pool, runs[0].id(), &TokenState::Success).await?;
+        assert_eq!(count_misses(&pool, &workflow).await?, 1);
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_check_within_after_rerun(pool: PgPool) -> Result<()> {
+        let (_, workflow, job) = setup(&pool, WorkflowSla::new(Some(60), None, None)?).await?;
+        let execution = Execution::new(
+            testutils::rand::uuid(),
+            workflow.id().as_uuid().to_string(),
+            RunPriority::Normal,
+            Utc::now(),
+        )?;
+        let runs = ExecutionService::trigger(&pool, &execution, &[job.id().clone()]).await?;
+        RunService::update_state(&pool, runs[0].id(), &TokenState::Failure).await?;
+        let reruns = match ExecutionService::rerun(&pool, execution.id(), true).await? {
+            Rerun::Done(runs) => runs,
+            Rerun::Blocked(_) => panic!("failed execution should be rerun"),
+        };
+        assert_eq!(reruns.len(), 1);
+        RunService::update_state(&pool, reruns[0].id(), &TokenState::Success).await?;
+        let later = Utc::now() + chrono::Duration::minutes(2);
+        assert_eq!(SlaService::check(&pool, later).await?, 0);
+        assert_eq!(count_misses(&pool, &workflow).await?, 0);
+        Ok(())
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_check_by(pool: PgPool) -> Result<()> {
+        let sla = WorkflowSla::new(
+            None,
+            Some(String::from("06:00")),
+            Some(String::from("Asia/Tokyo")),
+        )?;
+        let (_, workflow, job) = setup(&pool, sla.clone()).await?;
+        let deadline = sla
+            .last_deadline(Utc::now() + chrono::Duration::days(2))
+            .expect("deadline should be computed");
+        assert_eq!(
+            deadline
+                .with_timezone(&chrono_tz::Asia::Tokyo)
+                .format("%H:%M")
+                .to_string(),
+            "06:00"
+        );
+        // NOTE: The deadline configured just now is not due yet.
+        assert_eq!(SlaService::check(&pool, Utc::now()).await?, 0);
+        assert_eq!(
+            SlaService::check(&pool, deadline + chrono::Duration::minutes(1)).await?,
+            1
+        );
+        assert_eq!(
+            SlaService::check(&pool, deadline + chrono::Duration::minutes(2)).await?,
+            0
+        );
+        assert_eq!(count_misses(&pool, &workflow).await?, 1);
+        let next = deadline + chrono::Duration::days(1);
+        let execution = Execution::new(
+            testutils::rand::uuid(),
+            workflow.id().as_uuid().to_string(),
+            RunPriority::Normal,
+            Utc::now(),
+        )?;
+        let runs = ExecutionService::trigger(&pool, &execution, &[job.id().clone()]).await?;
+        RunService::update_state(&pool, runs[0].id(), &TokenState::Success).await?;
+        sqlx::query("UPDATE workflow_run SET triggered_at = $2 WHERE id = $1")
+            .bind(execution.id())
+            .bind(next - chrono::Duration::hours(1))
+            .execute(&pool)
+            .await?;
+        sqlx::query("UPDATE run SET finished_at = $2 WHERE execution_id = $1")
+            .bind(execution.id())
+            .bind(next - chrono::Duration::minutes(30))
+            .execute(&pool)
+            .await?;
+        assert_eq!(
+            SlaService::check(&pool, next + chrono::Duration::minutes(1)).await?,
+            0
+        );
+        assert_eq!(count_misses(&pool, &workflow).await?, 1);
+        Ok(())
+    }
+}
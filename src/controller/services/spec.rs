@@ -0,0 +1,697 @@
+use crate::controller::entities::job::Job;
+use crate::controller::entities::job::JobId;
+use crate::controller::entities::job::JobName;
+use crate::controller::entities::outbox::Outbox;
+use crate::controller::entities::project::Project;
+use crate::controller::entities::project::ProjectId;
+use crate::controller::entities::project::ProjectName;
+use crate::controller::entities::project::ProjectRetention;
+use crate::controller::entities::version::Precondition;
+use crate::controller::entities::workflow::Workflow;
+use crate::controller::entities::workflow::WorkflowId;
+use crate::controller::entities::workflow::WorkflowSchedule;
+use crate::controller::entities::workflow::WorkflowSla;
+use crate::controller::repositories::job::JobRepository;
+use crate::controller::repositories::job::JobRow;
+use crate::controller::repositories::job::PgJobRepository;
+use crate::controller::repositories::outbox::OutboxRepository;
+use crate::controller::repositories::outbox::PgOutboxRepository;
+use crate::controller::repositories::project::PgProjectRepository;
+use crate::controller::repositories::project::ProjectRepository;
+use crate::controller::repositories::project::ProjectRow;
+use crate::controller::repositories::workflow::PgWorkflowRepository;
+use crate::controller::repositories::workflow::WorkflowRepository;
+use crate::controller::repositories::workflow::WorkflowRow;
+use crate::messages::config::ConfigUpdate;
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value as Json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub config: Option<Json>,
+    pub retention: Option<ProjectRetention>,
+    #[serde(default)]
+    pub workflows: Vec<WorkflowSpec>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub paused: bool,
+    pub schedule: Option<String>,
+    pub sla: Option<WorkflowSla>,
+    #[serde(default)]
+    pub jobs: Vec<JobSpec>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobSpec {
+    pub name: String,
+    #[serde(default)]
+    pub threshold: i32,
+    #[serde(default)]
+    pub image: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub envs: Vec<String>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+impl ProjectSpec {
+    pub fn check(&self) -> Result<()> {
+        ProjectName::new(&self.name)?;
+        if let Some(retention) = &self.retention {
+            ProjectRetention::new(retention.days(), retention.runs())?;
+        }
+        let mut workflows = HashSet::new();
+        for workflow in self.workflows.iter() {
+            if !workflows.insert(workflow.name.as_str()) {
+                return Err(anyhow!(r#"duplicated workflow "{}""#, workflow.name));
+            }
+            workflow
+                .check()
+                .context(format!(r#"invalid workflow "{}""#, workflow.name))?;
+        }
+        Ok(())
+    }
+}
+
+impl WorkflowSpec {
+    fn check(&self) -> Result<()> {
+        Workflow::new(
+            Uuid::new_v4().to_string(),
+            self.name.clone(),
+            Uuid::new_v4().to_string(),
+            self.description.clone(),
+            self.paused,
+        )?;
+        self.schedule
+            .as_ref()
+            .map(WorkflowSchedule::new)
+            .transpose()?;
+        if let Some(sla) = &self.sla {
+            WorkflowSla::new(sla.within_secs(), sla.by().clone(), sla.timezone().clone())?;
+        }
+        let mut deps = HashMap::new();
+        for job in self.jobs.iter() {
+            Job::new(
+                Uuid::new_v4().to_string(),
+                job.name.clone(),
+                Uuid::new_v4().to_string(),
+                job.threshold,
+                job.image.clone(),
+                job.args.clone(),
+                job.envs.clone(),
+            )?;
+            if deps.insert(job.name.as_str(), &job.depends_on).is_some() {
+                return Err(anyhow!(
+                    r#"duplicated job "{}" in "{}""#,
+                    job.name,
+                    self.name
+                ));
+            }
+        }
+        for (name, upstreams) in deps.iter() {
+            for upstream in upstreams.iter() {
+                if !deps.contains_key(upstream.as_str()) {
+                    return Err(anyhow!(
+                        r#"job "{}" depends on unknown job "{}" in "{}""#,
+                        name,
+                        upstream,
+                        self.name
+                    ));
+                }
+            }
+        }
+        let mut degrees: HashMap<&str, usize> = deps
+            .iter()
+            .map(|(name, upstreams)| (*name, upstreams.len()))
+            .collect();
+        let mut ready: Vec<&str> = degrees
+            .iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(name, _)| *name)
+            .collect();
+        let mut visited = 0;
+        while let Some(done) = ready.pop() {
+            visited += 1;
+            for (name, upstreams) in deps.iter() {
+                let count = upstreams.iter().filter(|u| u.as_str() == done).count();
+                if count > 0 {
+                    let degree = degrees.entry(name).or_default();
+                    *degree -= count;
+                    if *degree == 0 {
+                        ready.push(name);
+                    }
+                }
+            }
+        }
+        if visited != deps.len() {
+            return Err(anyhow!(r#"job dependencies in "{}" are cyclic"#, self.name));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Create,
+    Update,
+    Delete,
+    Unchanged,
+}
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct Change {
+    pub id: Uuid,
+    pub name: String,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct JobChange {
+    pub workflow: String,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, serde::Serialize, utoipa::ToSchema)]
+pub struct Plan {
+    pub dry_run: bool,
+    pub project: Change,
+    pub workflows: Vec<Change>,
+    pub jobs: Vec<JobChange>,
+    #[serde(skip)]
+    pub version: Option<i64>,
+    #[serde(skip)]
+    upserts: (Project, Vec<Workflow>, Vec<Job>),
+}
+
+impl Plan {
+    pub fn is_changed(&self) -> bool {
+        self.project.action != Action::Unchanged
+            || self
+                .workflows
+                .iter()
+                .any(|change| change.action != Action::Unchanged)
+            || self
+                .jobs
+                .iter()
+                .any(|job| job.change.action != Action::Unchanged)
+    }
+}
+
+pub struct Snapshot {
+    pub project: Option<ProjectRow>,
+    pub workflows: Vec<WorkflowRow>,
+    pub jobs: HashMap<Uuid, Vec<JobRow>>,
+}
+
+fn action(found: bool, changed: bool) -> Action {
+    match (found, changed) {
+        (false, _) => Action::Create,
+        (true, true) => Action::Update,
+        (true, false) => Action::Unchanged,
+    }
+}
+
+pub fn plan(id: &ProjectId, spec: &ProjectSpec, snapshot: &Snapshot) -> Result<Plan> {
+    let mut project = Project::new(
+        id.as_uuid().to_string(),
+        spec.name.clone(),
+        spec.description.clone(),
+        spec.config.clone(),
+    )?;
+    project.set_retention(spec.retention.clone());
+    let project_change = Change {
+        id: id.to_uuid(),
+        name: spec.name.clone(),
+        action: match &snapshot.project {
+            None => Action::Create,
+            Some(row) => action(
+                true,
+                row.description != spec.description
+                    || spec.config.is_some() && row.config != spec.config
+                    || spec.retention.as_ref().map_or(false, |retention| {
+                        row.retention_days != retention.days()
+                            || row.retention_runs != retention.runs()
+                    }),
+            ),
+        },
+    };
+    let mut workflow_changes = Vec::new();
+    let mut job_changes = Vec::new();
+    let mut workflows = Vec::new();
+    let mut jobs = Vec::new();
+    for workflow_spec in spec.workflows.iter() {
+        let row = snapshot
+            .workflows
+            .iter()
+            .find(|row| row.name == workflow_spec.name);
+        let workflow_id = row.map(|row| row.id).unwrap_or_else(Uuid::new_v4);
+        let mut workflow = Workflow::new(
+            workflow_id.to_string(),
+            workflow_spec.name.clone(),
+            id.as_uuid().to_string(),
+            workflow_spec.description.clone(),
+            workflow_spec.paused,
+        )?;
+        workflow.set_schedule(
+            workflow_spec
+                .schedule
+                .as_ref()
+                .map(WorkflowSchedule::new)
+                .transpose()?,
+        );
+        workflow.set_sla(workflow_spec.sla.clone());
+        workflow_changes.push(Change {
+            id: workflow_id,
+            name: workflow_spec.name.clone(),
+            action: action(
+                row.is_some(),
+                row.map_or(false, |row| {
+                    row.description != workflow_spec.description
+                        || row.paused != workflow_spec.paused
+                        || row.schedule != workflow_spec.schedule
+                        || row.sla_within_secs
+                            != workflow_spec.sla.as_ref().and_then(|sla| sla.within_secs())
+                        || row.sla_by != workflow_spec.sla.as_ref().and_then(|sla| sla.by().clone())
+                        || row.sla_timezone
+                            != workflow_spec
+                                .sla
+                                .as_ref()
+                                .and_then(|sla| sla.timezone().clone())
+                }),
+            ),
+        });
+        workflows.push(workflow);
+        let rows = snapshot
+            .jobs
+            .get(&workflow_id)
+            .map(|rows| rows.as_slice())
+            .unwrap_or_default();
+        for job_spec in workflow_spec.jobs.iter() {
+            let row = rows.iter().find(|row| row.name == job_spec.name);
+            let job_id = row.map(|row| row.id).unwrap_or_else(Uuid::new_v4);
+            let mut job = Job::new(
+                job_id.to_string(),
+                job_spec.name.clone(),
+                workflow_id.to_string(),
+                job_spec.threshold,
+                job_spec.image.clone(),
+                job_spec.args.clone(),
+                job_spec.envs.clone(),
+            )?;
+            job.set_depends_on(
+                job_spec
+                    .depends_on
+                    .iter()
+                    .map(JobName::new)
+                    .collect::<Result<Vec<_>>>()?,
+            );
+            job_changes.push(JobChange {
+                workflow: workflow_spec.name.clone(),
+                change: Change {
+                    id: job_id,
+                    name: job_spec.name.clone(),
+                    action: action(
+                        row.is_some(),
+                        row.map_or(false, |row| {
+                            row.threshold != job_spec.threshold
+                                || row.image != job_spec.image
+                                || row.args != job_spec.args
+                                || row.envs != job_spec.envs
+                                || row.depends_on != job_spec.depends_on
+                        }),
+                    ),
+                },
+            });
+            jobs.push(job);
+        }
+        for row in rows.iter() {
+            if !workflow_spec.jobs.iter().any(|job| job.name == row.name) {
+                job_changes.push(JobChange {
+                    workflow: workflow_spec.name.clone(),
+                    change: Change {
+                        id: row.id,
+                        name: row.name.clone(),
+                        action: Action::Delete,
+                    },
+                });
+            }
+        }
+    }
+    for row in snapshot.workflows.iter() {
+        if spec
+            .workflows
+            .iter()
+            .any(|workflow| workflow.name == row.name)
+        {
+            continue;
+        }
+        workflow_changes.push(Change {
+            id: row.id,
+            name: row.name.clone(),
+            action: Action::Delete,
+        });
+        for job in snapshot.jobs.get(&row.id).into_iter().flatten() {
+            job_changes.push(JobChange {
+                workflow: row.name.clone(),
+                change: Change {
+                    id: job.id,
+                    name: job.name.clone(),
+                    action: Action::Delete,
+                },
+            });
+        }
+    }
+    Ok(Plan {
+        dry_run: true,
+        project: project_change,
+        workflows: workflow_changes,
+        jobs: job_changes,
+        version: snapshot.project.as_ref().map(|row| row.version),
+        upserts: (project, workflows, jobs),
+    })
+}
+
+#[async_trait]
+pub trait SpecService {
+    async fn apply(
+        &self,
+        id: &ProjectId,
+        spec: &ProjectSpec,
+        dry_run: bool,
+        precondition: &Precondition,
+    ) -> Result<Plan>;
+}
+
+#[async_trait]
+impl SpecService for PgPool {
+    async fn apply(
+        &self,
+        id: &ProjectId,
+        spec: &ProjectSpec,
+        dry_run: bool,
+        precondition: &Precondition,
+    ) -> Result<Plan> {
+        let mut tx = self
+            .begin()
+            .await
+            .context("failed to begin postgres transaction")?;
+        let project = PgProjectRepository
+            .lock_by_id(id, &mut tx)
+            .await?
+            .filter(|row| row.name == spec.name);
+        precondition.check(project.as_ref().map(|row| row.version))?;
+        let workflows = match &project {
+            Some(_) => PgWorkflowRepository.list_by_project_id(id, &mut tx).await?,
+            None => Vec::new(),
+        };
+        let mut jobs = HashMap::new();
+        for workflow in workflows.iter() {
+            let rows = PgJobRepository
+                .list_by_workflow_id(&WorkflowId::new(workflow.id), &mut tx)
+                .await?;
+            jobs.insert(workflow.id, rows);
+        }
+        let snapshot = Snapshot {
+            project,
+            workflows,
+            jobs,
+        };
+        let mut plan = plan(id, spec, &snapshot)?;
+        plan.dry_run = dry_run;
+        if dry_run || !plan.is_changed() {
+            tx.rollback()
+                .await
+                .context("failed to rollback postgres transaction")?;
+            return Ok(plan);
+        }
+        let (project, workflows, jobs) = &plan.upserts;
+        // NOTE: The project version covers its workflows and jobs so that any change of the spec invalidates its ETag.
+        if PgProjectRepository
+            .create(project, &mut tx)
+            .await?
+            .rows_affected()
+            == 0
+        {
+            PgProjectRepository.touch(id, &mut tx).await?;
+        }
+        for workflow in workflows.iter() {
+            PgWorkflowRepository.create(workflow, &mut tx).await?;
+        }
+        for change in plan.workflows.iter() {
+            if change.action == Action::Delete {
+                PgWorkflowRepository
+                    .soft_delete(&WorkflowId::new(change.id), &mut tx)
+                    .await?;
+            }
+        }
+        for change in plan.jobs.iter().map(|job| &job.change) {
+            if change.action == Action::Delete {
+                PgJobRepository
+                    .soft_delete(&JobId::new(change.id), &mut tx)
+                    .await?;
+            }
+        }
+        for job in jobs.iter() {
+            PgJobRepository.create(job, &mut tx).await?;
+        }
+        let outbox = Outbox::new(&ConfigUpdate::Project(id.to_uuid()))?;
+        PgOutboxRepository.create(&outbox, &mut tx).await?;
+        for change in plan.jobs.iter().map(|job| &job.change) {
+            if change.action != Action::Unchanged {
+                let outbox = Outbox::new(&ConfigUpdate::Job(change.id))?;
+                PgOutboxRepository.create(&outbox, &mut tx).await?;
+            }
+        }
+        plan.version = PgProjectRepository
+            .get_by_id(id, &mut tx)
+            .await?
+            .map(|row| row.version);
+        tx.commit()
+            .await
+            .context("failed to commit postgres transaction")?;
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    const SPEC: &str = r#"
+name: example
+description: example project
+config:
+  owner: kotosiro
+workflows:
+  - name: daily
+    schedule: "0 0 * * *"
+    jobs:
+      - name: extract
+        image: busybox
+      - name: load
+        image: busybox
+        depends_on: [extract]
+"#;
+
+    fn snapshot(id: &ProjectId) -> Snapshot {
+        let workflow = Uuid::new_v4();
+        let now = Utc::now();
+        let job = |name: &str| JobRow {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            workflow_id: workflow,
+            threshold: 0,
+            image: String::from("busybox"),
+            args: Vec::new(),
+            envs: Vec::new(),
+            depends_on: Vec::new(),
+            config: None,
+            version: 1,
+            created_at: now,
+            updated_at: now,
+        };
+        Snapshot {
+            project: Some(ProjectRow {
+                id: id.to_uuid(),
+                name: String::from("example"),
+                description: String::from("example project"),
+                config: Some(serde_json::json!({ "owner": "kotosiro" })),
+                config_schema: None,
+                retention_days: None,
+                retention_runs: None,
+                version: 1,
+                created_at: now,
+                updated_at: now,
+            }),
+            workflows: vec![
+                WorkflowRow {
+                    id: workflow,
+                    name: String::from("daily"),
+                    project_id: id.to_uuid(),
+                    description: String::new(),
+                    paused: false,
+                    schedule: Some(String::from("0 0 * * *")),
+                    sla_within_secs: None,
+                    sla_by: None,
+                    sla_timezone: None,
+                    version: 1,
+                    created_at: now,
+                    updated_at: now,
+                },
+                WorkflowRow {
+                    id: Uuid::new_v4(),
+                    name: String::from("hourly"),
+                    project_id: id.to_uuid(),
+                    description: String::new(),
+                    paused: false,
+                    schedule: None,
+                    sla_within_secs: None,
+                    sla_by: None,
+                    sla_timezone: None,
+                    version: 1,
+                    created_at: now,
+                    updated_at: now,
+                },
+            ],
+            jobs: HashMap::from([(workflow, vec![job("extract"), job("load"), job("clean")])]),
+        }
+    }
+
+    #[test]
+    fn test_check_spec() {
+        let spec: ProjectSpec = serde_yaml::from_str(SPEC).expect("spec should be parsed");
+        assert!(spec.check().is_ok());
+    }
+
+    #[test]
+    fn test_check_cyclic_spec() {
+        let mut spec: ProjectSpec = serde_yaml::from_str(SPEC).expect("spec should be parsed");
+        spec.workflows[0].jobs[0].depends_on = vec![String::from("load")];
+        assert!(spec.check().is_err());
+    }
+
+    #[test]
+    fn test_check_unknown_dependency() {
+        let mut spec: ProjectSpec = serde_yaml::from_str(SPEC).expect("spec should be parsed");
+        spec.workflows[0].jobs[0].depends_on = vec![testutils::rand::string(10)];
+        assert!(spec.check().is_err());
+    }
+
+    #[test]
+    fn test_check_invalid_template() {
+        let mut spec: ProjectSpec = serde_yaml::from_str(SPEC).expect("spec should be parsed");
+        spec.workflows[0].jobs[0].args = vec![String::from("--date={{ run.triggered_at ")];
+        assert!(spec.check().is_err());
+        let mut spec: ProjectSpec = serde_yaml::from_str(SPEC).expect("spec should be parsed");
+        spec.workflows[0].jobs[1].envs =
+            vec![String::from("DATE={{ run.triggered_at | unknown }}")];
+        assert!(spec.check().is_err());
+    }
+
+    #[test]
+    fn test_check_invalid_retention() {
+        let mut spec: ProjectSpec = serde_yaml::from_str(SPEC).expect("spec should be parsed");
+        spec.retention = serde_yaml::from_str("days: 0").expect("retention should be parsed");
+        assert!(spec.check().is_err());
+    }
+
+    #[test]
+    fn test_plan_against_empty_project() {
+        let spec: ProjectSpec = serde_yaml::from_str(SPEC).expect("spec should be parsed");
+        let id = ProjectId::new(Uuid::new_v4());
+        let empty = Snapshot {
+            project: None,
+            workflows: Vec::new(),
+            jobs: HashMap::new(),
+        };
+        let plan = plan(&id, &spec, &empty).expect("plan should be computed");
+        assert_eq!(plan.project.action, Action::Create);
+        assert_eq!(plan.workflows.len(), 1);
+        assert_eq!(plan.workflows[0].action, Action::Create);
+        assert_eq!(plan.jobs.len(), 2);
+        assert!(plan
+            .jobs
+            .iter()
+            .all(|job| job.change.action == Action::Create));
+    }
+
+    #[test]
+    fn test_plan_against_existing_project() {
+        let spec: ProjectSpec = serde_yaml::from_str(SPEC).expect("spec should be parsed");
+        let id = ProjectId::new(Uuid::new_v4());
+        let plan = plan(&id, &spec, &snapshot(&id)).expect("plan should be computed");
+        let actions: HashMap<_, _> = plan
+            .jobs
+            .iter()
+            .map(|job| (job.change.name.as_str(), job.change.action))
+            .collect();
+        assert_eq!(plan.project.action, Action::Unchanged);
+        assert_eq!(plan.workflows[0].action, Action::Unchanged);
+        assert_eq!(plan.workflows[1].action, Action::Delete);
+        assert_eq!(actions["extract"], Action::Unchanged);
+        assert_eq!(actions["load"], Action::Update);
+        assert_eq!(actions["clean"], Action::Delete);
+        assert!(plan.is_changed());
+    }
+
+    #[test]
+    fn test_plan_retention_change() {
+        let mut spec: ProjectSpec = serde_yaml::from_str(SPEC).expect("spec should be parsed");
+        spec.retention =
+            serde_yaml::from_str("days: 90\nruns: 100").expect("retention should be parsed");
+        let id = ProjectId::new(Uuid::new_v4());
+        let plan = plan(&id, &spec, &snapshot(&id)).expect("plan should be computed");
+        assert_eq!(plan.project.action, Action::Update);
+    }
+
+    #[test]
+    fn test_check_invalid_sla() {
+        let mut spec: ProjectSpec = serde_yaml::from_str(SPEC).expect("spec should be parsed");
+        spec.workflows[0].sla =
+            serde_yaml::from_str("timezone: Asia/Tokyo").expect("sla should be parsed");
+        assert!(spec.check().is_err());
+        assert!(serde_yaml::from_str::<WorkflowSla>("within: 2h").is_err());
+    }
+
+    #[test]
+    fn test_plan_sla_change() {
+        let mut spec: ProjectSpec = serde_yaml::from_str(SPEC).expect("spec should be parsed");
+        spec.workflows[0].sla = serde_yaml::from_str("by: \"06:00\"\ntimezone: Asia/Tokyo")
+            .expect("sla should be parsed");
+        assert!(spec.check().is_ok());
+        let id = ProjectId::new(Uuid::new_v4());
+        let plan = plan(&id, &spec, &snapshot(&id)).expect("plan should be computed");
+        assert_eq!(plan.workflows[0].action, Action::Update);
+        assert_eq!(
+            plan.upserts.1[0]
+                .sla()
+                .as_ref()
+                .and_then(|sla| sla.by().clone()),
+            Some(String::from("06:00"))
+        );
+    }
+}
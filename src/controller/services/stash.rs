@@ -0,0 +1,215 @@
+use crate::controller::entities::run::RunId;
+use crate::controller::entities::stash;
+use crate::controller::entities::stash::Stash;
+use crate::controller::entities::stash::StashName;
+use crate::controller::repositories::stash::PgStashRepository;
+use crate::controller::repositories::stash::StashRepository;
+use crate::controller::repositories::stash::StashRow;
+use crate::infra::stash::Backend;
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::postgres::PgQueryResult;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::info;
+use tracing::warn;
+
+const BATCH_SIZE: i64 = 100;
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[async_trait]
+pub trait StashService {
+    async fn put(
+        &self,
+        stash: &Stash,
+        value: &[u8],
+        backend: &dyn Backend,
+    ) -> Result<PgQueryResult>;
+
+    async fn get(
+        &self,
+        run_id: &RunId,
+        name: &StashName,
+        backend: &dyn Backend,
+    ) -> Result<Option<(StashRow, Vec<u8>)>>;
+
+    async fn list_by_run_id(&self, run_id: &RunId) -> Result<Vec<StashRow>>;
+
+    async fn is_shared(&self, owner: &RunId, reader: &RunId) -> Result<bool>;
+
+    async fn sweep(&self, backend: &dyn Backend) -> Result<usize>;
+}
+
+#[async_trait]
+impl StashService for PgPool {
+    async fn put(
+        &self,
+        stash: &Stash,
+        value: &[u8],
+        backend: &dyn Backend,
+    ) -> Result<PgQueryResult> {
+        let repo = PgStashRepository;
+        backend.put(&stash.key(), value).await?;
+        repo.create(stash, self).await
+    }
+
+    async fn get(
+        &self,
+        run_id: &RunId,
+        name: &StashName,
+        backend: &dyn Backend,
+    ) -> Result<Option<(StashRow, Vec<u8>)>> {
+        let repo = PgStashRepository;
+        let row = match repo.get_by_name(run_id, name, self).await? {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let value = backend.get(&stash::key(run_id, name)).await?;
+        Ok(value.map(|value| (row, value)))
+    }
+
+    async fn list_by_run_id(&self, run_id: &RunId) -> Result<Vec<StashRow>> {
+        let repo = PgStashRepository;
+        repo.list_by_run_id(run_id, self).await
+    }
+
+    async fn is_shared(&self, owner: &RunId, reader: &RunId) -> Result<bool> {
+        if owner == reader {
+            return Ok(true);
+        }
+        let repo = PgStashRepository;
+        repo.is_shared(owner, reader, self).await
+    }
+
+    async fn sweep(&self, backend: &dyn Backend) -> Result<usize> {
+        let repo = PgStashRepository;
+        let mut swept = 0;
+        loop {
+            let rows = repo.delete_expired(Some(&BATCH_SIZE), self).await?;
+            for row in rows.iter() {
+                let key = stash::key(&RunId::new(row.run_id), &StashName::new(&row.name)?);
+                backend.delete(&key).await?;
+            }
+            swept += rows.len();
+            if (rows.len() as i64) < BATCH_SIZE {
+                return Ok(swept);
+            }
+        }
+    }
+}
+
+pub async fn reap(
+    pool: PgPool,
+    backend: Arc<dyn Backend>,
+    leader: watch::Receiver<bool>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            _ = tokio::time::sleep(SWEEP_INTERVAL) => {}
+        }
+        if !*leader.borrow() {
+            continue;
+        }
+        match StashService::sweep(&pool, backend.as_ref()).await {
+            Ok(0) => {}
+            Ok(swept) => info!("swept {} expired stashes", swept),
+            Err(e) => warn!("failed to sweep expired stashes: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::job::Job;
+    use crate::controller::entities::project::Project;
+    use crate::controller::entities::run::Run;
+    use crate::controller::entities::workflow::Workflow;
+    use crate::controller::repositories::job::JobRepository;
+    use crate::controller::repositories::job::PgJobRepository;
+    use crate::controller::repositories::project::PgProjectRepository;
+    use crate::controller::repositories::project::ProjectRepository;
+    use crate::controller::repositories::run::PgRunRepository;
+    use crate::controller::repositories::run::RunRepository;
+    use crate::controller::repositories::workflow::PgWorkflowRepository;
+    use crate::controller::repositories::workflow::WorkflowRepository;
+    use crate::infra::stash::Local;
+    use crate::messages::run::RunPriority;
+    use crate::messages::token::TokenState;
+    use chrono::Utc;
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_put_get_and_sweep(pool: PgPool) -> Result<()> {
+        let root = std::env::temp_dir().join(testutils::rand::uuid());
+        let backend = Local::new(&root);
+        let project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )?;
+        PgProjectRepository.create(&project, &pool).await?;
+        let workflow = Workflow::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            project.id().as_uuid().to_string(),
+            testutils::rand::string(10),
+            false,
+        )?;
+        PgWorkflowRepository.create(&workflow, &pool).await?;
+        let job = Job::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            workflow.id().as_uuid().to_string(),
+            0,
+            testutils::rand::string(10),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        PgJobRepository.create(&job, &pool).await?;
+        let run = Run::new(
+            testutils::rand::uuid(),
+            TokenState::Running,
+            RunPriority::Normal,
+            job.id().as_uuid().to_string(),
+            Utc::now(),
+        )?;
+        PgRunRepository.create(&run, &pool).await?;
+        let stash = Stash::new(
+            testutils::rand::uuid(),
+            run.id().as_uuid().to_string(),
+            String::from("rows"),
+            String::from("application/json"),
+            2,
+            Utc::now() + chrono::Duration::hours(1),
+        )?;
+        pool.put(&stash, b"42", &backend).await?;
+        let (row, value) = pool
+            .get(run.id(), stash.name(), &backend)
+            .await?
+            .expect("stash should be found");
+        assert_eq!(&row.content_type, "application/json");
+        assert_eq!(value, b"42");
+        assert!(pool.is_shared(run.id(), run.id()).await?);
+        let expired = Stash::new(
+            stash.id().as_uuid().to_string(),
+            run.id().as_uuid().to_string(),
+            String::from("rows"),
+            String::from("application/json"),
+            2,
+            Utc::now() - chrono::Duration::hours(1),
+        )?;
+        pool.put(&expired, b"42", &backend).await?;
+        assert!(pool.get(run.id(), stash.name(), &backend).await?.is_none());
+        assert_eq!(pool.sweep(&backend).await?, 1);
+        assert_eq!(backend.get(&stash.key()).await?, None);
+        std::fs::remove_dir_all(root)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,503 @@
+use crate::controller::entities::job::JobId;
+use crate::controller::entities::sla::SlaKind;
+use crate::controller::repositories::job::JobRepository;
+use crate::controller::repositories::job::PgJobRepository;
+use crate::infra::broker::Broker;
+use crate::logging::propagation;
+use crate::messages::config::ConfigUpdate;
+use crate::messages::config::CONFIG_UPDATES_EXCHANGE;
+use crate::messages::token::TokenState;
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use chrono::DateTime;
+use chrono::Utc;
+use futures::Stream;
+use futures::StreamExt;
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use std::cmp::min;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+use tracing::info_span;
+use tracing::warn;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+
+pub const RUN_UPDATES_CHANNEL: &str = "kotosiro_run_updates";
+
+pub const CAPACITY: usize = 1024;
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Update {
+    Run {
+        id: Uuid,
+        job_id: Uuid,
+        workflow_id: Uuid,
+        project_id: Uuid,
+        execution_id: Option<Uuid>,
+        state: Option<TokenState>,
+    },
+    Project {
+        project_id: Uuid,
+    },
+    Job {
+        id: Uuid,
+        workflow_id: Uuid,
+        project_id: Uuid,
+    },
+    #[serde(rename = "sla_miss")]
+    SlaMiss {
+        id: Uuid,
+        workflow_id: Uuid,
+        project_id: Uuid,
+        execution_id: Option<Uuid>,
+        kind: SlaKind,
+        deadline: DateTime<Utc>,
+    },
+    Lagged {
+        missed: u64,
+    },
+}
+
+impl Update {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Update::Run { .. } => "run",
+            Update::Project { .. } => "project",
+            Update::Job { .. } => "job",
+            Update::SlaMiss { .. } => "sla_miss",
+            Update::Lagged { .. } => "lagged",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Filter {
+    pub project_id: Uuid,
+    pub workflow_id: Option<Uuid>,
+}
+
+impl Filter {
+    // NOTE: Project updates also reach workflow subscribers since project config is merged into every job.
+    pub fn matches(&self, update: &Update) -> bool {
+        match update {
+            Update::Run {
+                project_id,
+                workflow_id,
+                ..
+            }
+            | Update::Job {
+                project_id,
+                workflow_id,
+                ..
+            }
+            | Update::SlaMiss {
+                project_id,
+                workflow_id,
+                ..
+            } => {
+                *project_id == self.project_id
+                    && self.workflow_id.map_or(true, |id| id == *workflow_id)
+            }
+            Update::Project { project_id } => *project_id == self.project_id,
+            Update::Lagged { .. } => true,
+        }
+    }
+}
+
+async fn resolve(pool: &PgPool, update: &ConfigUpdate) -> Result<Option<Update>> {
+    match update {
+        ConfigUpdate::Project(id) => Ok(Some(Update::Project { project_id: *id })),
+        ConfigUpdate::Job(id) => {
+            let owner = PgJobRepository
+                .get_owner_by_id(&JobId::new(*id), pool)
+                .await?;
+            Ok(owner.map(|owner| Update::Job {
+                id: *id,
+                workflow_id: owner.workflow_id,
+                project_id: owner.project_id,
+            }))
+        }
+    }
+}
+
+async fn forward(
+    pool: &PgPool,
+    broker: &dyn Broker,
+    updates: &broadcast::Sender<Update>,
+) -> Result<()> {
+    let mut listener = PgListener::connect_with(pool)
+        .await
+        .context("failed to connect postgres listener")?;
+    listener
+        .listen(RUN_UPDATES_CHANNEL)
+        .await
+        .context(format!(r#"failed to listen "{}""#, RUN_UPDATES_CHANNEL))?;
+    let mut configs = broker.subscribe(CONFIG_UPDATES_EXCHANGE, "").await?;
+    loop {
+        let update = tokio::select! {
+            notification = listener.recv() => {
+                let notification = notification.context("failed to receive run update")?;
+                match serde_json::from_str::<Update>(notification.payload()) {
+                    Ok(update) => Some(update),
+                    Err(e) => {
+                        warn!("malformed run update was ignored: {}", e);
+                        None
+                    }
+                }
+            }
+            envelope = configs.next() => {
+                let envelope = envelope
+                    .ok_or_else(|| anyhow!("config update subscription was closed"))??;
+                let span = info_span!("config.update", otel.name = CONFIG_UPDATES_EXCHANGE);
+                span.set_parent(propagation::extract_map(&envelope.headers));
+                async {
+                    match serde_json::from_slice::<ConfigUpdate>(&envelope.payload) {
+                        Ok(update) => resolve(pool, &update).await,
+                        Err(e) => {
+                            warn!("malformed config update was ignored: {}", e);
+                            Ok(None)
+                        }
+                    }
+                }
+                .instrument(span)
+                .await?
+            }
+        };
+        if let Some(update) = update {
+            // NOTE: Sending only fails when nobody is subscribed, which is not an error.
+            let _ = updates.send(update);
+        }
+    }
+}
+
+pub async fn listen(
+    pool: PgPool,
+    broker: Arc<dyn Broker>,
+    updates: broadcast::Sender<Update>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut backoff: Option<Duration> = None;
+    loop {
+        let started = Instant::now();
+        let e = tokio::select! {
+            _ = shutdown.changed() => break,
+            result = forward(&pool, broker.as_ref(), &updates) => match result {
+                Ok(_) => anyhow!("update forwarding stopped"),
+                Err(e) => e,
+            },
+        };
+        let next = match backoff {
+            Some(backoff) if started.elapsed() < MAX_BACKOFF => min(backoff * 2, MAX_BACKOFF),
+            _ => MIN_BACKOFF,
+        };
+        warn!("failed to forward updates, retrying in {:?}: {}", next, e);
+        backoff = Some(next);
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            _ = tokio::time::sleep(next) => {}
+        }
+    }
+}
+
+struct Subscriber {
+    receiver: broadcast::Receiver<Update>,
+    filter: Filter,
+    draining: watch::Receiver<bool>,
+}
+
+impl Subscriber {
+    async fn poll(&mut self) -> Option<Update> {
+        loop {
+            if *self.draining.borrow() {
+                return None;
+            }
+            tokio::select! {
+                received = self.receiver.recv() => match received {
+                    Ok(update) if self.filter.matches(&update) => return Some(update),
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(missed)) => return Some(Update::Lagged { missed }),
+                    Err(RecvError::Closed) => return None,
+                },
+                changed = self.draining.changed() => {
+                    if changed.is_err() {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn subscribe(
+    updates: &broadcast::Sender<Update>,
+    filter: Filter,
+    draining: watch::Receiver<bool>,
+) -> impl Stream<Item = Update> {
+    let subscriber = Subscriber {
+        receiver: updates.subscribe(),
+        filter,
+        draining,
+    };
+    futures::stream::unfold(subscriber, |mut subscriber| async move {
+        subscriber.poll().await.map(|update| (update, subscriber))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::entities::job::Job;
+    use crate::controller::entities::project::Project;
+    use crate::controller::entities::run::Run;
+    use crate::controller::entities::sla::SlaMiss;
+    use crate::controller::entities::workflow::Workflow;
+    use crate::controller::repositories::project::PgProjectRepository;
+    use crate::controller::repositories::project::ProjectRepository;
+    use crate::controller::repositories::run::PgRunRepository;
+    use crate::controller::repositories::run::RunRepository;
+    use crate::controller::repositories::sla::PgSlaRepository;
+    use crate::controller::repositories::sla::SlaRepository;
+    use crate::controller::repositories::workflow::PgWorkflowRepository;
+    use crate::controller::repositories::workflow::WorkflowRepository;
+    use crate::infra::broker::memory::Memory;
+    use crate::infra::broker::Envelope;
+    use crate::messages;
+    use crate::messages::run::RunPriority;
+    use chrono::TimeZone;
+    use std::collections::HashMap;
+
+    fn run(project_id: Uuid, workflow_id: Uuid) -> Update {
+        Update::Run {
+            id: Uuid::new_v4(),
+            job_id: Uuid::new_v4(),
+            workflow_id,
+            project_id,
+            execution_id: None,
+            state: Some(TokenState::Running),
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let project_id = Uuid::new_v4();
+        let workflow_id = Uuid::new_v4();
+        let project = Filter {
+            project_id,
+            workflow_id: None,
+        };
+        let workflow = Filter {
+            project_id,
+            workflow_id: Some(workflow_id),
+        };
+        let same = run(project_id, workflow_id);
+        let sibling = run(project_id, Uuid::new_v4());
+        let other = run(Uuid::new_v4(), workflow_id);
+        let config = Update::Project { project_id };
+        assert!(project.matches(&same));
+        assert!(project.matches(&sibling));
+        assert!(!project.matches(&other));
+        assert!(workflow.matches(&same));
+        assert!(!workflow.matches(&sibling));
+        assert!(!workflow.matches(&other));
+        assert!(workflow.matches(&config));
+        assert!(workflow.matches(&Update::Lagged { missed: 1 }));
+    }
+
+    #[test]
+    fn test_serialize() {
+        let project_id = Uuid::new_v4();
+        let value = serde_json::to_value(Update::Project { project_id })
+            .expect("update should be serialized");
+        assert_eq!(
+            value,
+            serde_json::json!({ "type": "project", "project_id": project_id })
+        );
+        let update = run(project_id, Uuid::new_v4());
+        let value = serde_json::to_value(&update).expect("update should be serialized");
+        assert_eq!(value["type"], "run");
+        assert_eq!(value["state"], "running");
+        assert_eq!(update.kind(), "run");
+        let update: Update = serde_json::from_value(serde_json::json!({
+            "type": "sla_miss",
+            "id": Uuid::new_v4(),
+            "workflow_id": Uuid::new_v4(),
+            "project_id": project_id,
+            "execution_id": null,
+            "kind": "by",
+            "deadline": "2023-03-16T06:00:00+09:00",
+        }))
+        .expect("update should be deserialized");
+        assert_eq!(update.kind(), "sla_miss");
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let (updates, _) = broadcast::channel(CAPACITY);
+        let (draining, watcher) = watch::channel(false);
+        let project_id = Uuid::new_v4();
+        let filter = Filter {
+            project_id,
+            workflow_id: None,
+        };
+        let mut subscription = Box::pin(subscribe(&updates, filter, watcher));
+        let expected = run(project_id, Uuid::new_v4());
+        updates
+            .send(run(Uuid::new_v4(), Uuid::new_v4()))
+            .expect("update should be sent");
+        updates
+            .send(expected.clone())
+            .expect("update should be sent");
+        assert_eq!(subscription.next().await, Some(expected));
+        draining.send_replace(true);
+        assert_eq!(subscription.next().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_lagged() {
+        let (updates, _) = broadcast::channel(2);
+        let (_draining, watcher) = watch::channel(false);
+        let project_id = Uuid::new_v4();
+        let filter = Filter {
+            project_id,
+            workflow_id: None,
+        };
+        let mut subscription = Box::pin(subscribe(&updates, filter, watcher));
+        for _ in 0..3 {
+            updates
+                .send(Update::Project { project_id })
+                .expect("update should be sent");
+        }
+        assert_eq!(
+            subscription.next().await,
+            Some(Update::Lagged { missed: 1 })
+        );
+        assert_eq!(
+            subscription.next().await,
+            Some(Update::Project { project_id })
+        );
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_listen(pool: PgPool) -> Result<()> {
+        let broker = Arc::new(Memory::new());
+        messages::setup(broker.as_ref()).await?;
+        let (updates, _) = broadcast::channel(CAPACITY);
+        let (shutdown, watcher) = watch::channel(false);
+        let project = Project::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            testutils::rand::string(10),
+            None,
+        )?;
+        PgProjectRepository.create(&project, &pool).await?;
+        let workflow = Workflow::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            project.id().to_uuid().to_string(),
+            testutils::rand::string(10),
+            false,
+        )?;
+        PgWorkflowRepository.create(&workflow, &pool).await?;
+        let job = Job::new(
+            testutils::rand::uuid(),
+            testutils::rand::string(10),
+            workflow.id().to_uuid().to_string(),
+            0,
+            testutils::rand::string(10),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        PgJobRepository.create(&job, &pool).await?;
+        let filter = Filter {
+            project_id: project.id().to_uuid(),
+            workflow_id: None,
+        };
+        let mut subscription = Box::pin(subscribe(&updates, filter, watcher.clone()));
+        let listener = tokio::spawn(listen(
+            pool.clone(),
+            broker.clone(),
+            updates.clone(),
+            watcher,
+        ));
+        // NOTE: The listener has to be up before anything is notified.
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let run = Run::new(
+            testutils::rand::uuid(),
+            TokenState::Waiting,
+            RunPriority::Normal,
+            job.id().to_uuid().to_string(),
+            Utc::now(),
+        )?;
+        PgRunRepository.create(&run, &pool).await?;
+        PgRunRepository
+            .update_state(run.id(), &TokenState::Running, &pool)
+            .await?;
+        for state in [TokenState::Waiting, TokenState::Running] {
+            let update = subscription.next().await.expect("run should be updated");
+            assert_eq!(
+                update,
+                Update::Run {
+                    id: run.id().to_uuid(),
+                    job_id: job.id().to_uuid(),
+                    workflow_id: workflow.id().to_uuid(),
+                    project_id: project.id().to_uuid(),
+                    execution_id: None,
+                    state: Some(state),
+                }
+            );
+        }
+        broker
+            .publish(&Envelope {
+                exchange: CONFIG_UPDATES_EXCHANGE.to_owned(),
+                routing_key: String::new(),
+                payload: serde_json::to_vec(&ConfigUpdate::Job(job.id().to_uuid()))?,
+                headers: HashMap::new(),
+                priority: None,
+            })
+            .await?;
+        assert_eq!(
+            subscription.next().await,
+            Some(Update::Job {
+                id: job.id().to_uuid(),
+                workflow_id: workflow.id().to_uuid(),
+                project_id: project.id().to_uuid(),
+            })
+        );
+        let miss = SlaMiss::new(
+            testutils::rand::uuid(),
+            workflow.id().to_uuid().to_string(),
+            None,
+            SlaKind::By,
+            Utc.with_ymd_and_hms(2023, 3, 15, 21, 0, 0).unwrap(),
+        )?;
+        PgSlaRepository.create(&miss, &pool).await?;
+        assert_eq!(
+            subscription.next().await,
+            Some(Update::SlaMiss {
+                id: miss.id().to_uuid(),
+                workflow_id: workflow.id().to_uuid(),
+                project_id: project.id().to_uuid(),
+                execution_id: None,
+                kind: SlaKind::By,
+                deadline: *miss.deadline(),
+            })
+        );
+        shutdown.send_replace(true);
+        listener.await?;
+        assert_eq!(subscription.next().await, None);
+        Ok(())
+    }
+}
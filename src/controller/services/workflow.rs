@@ -0,0 +1,60 @@
+use crate::controller::entities::outbox::Outbox;
+use crate::controller::entities::version::Precondition;
+use crate::controller::entities::workflow::WorkflowId;
+use crate::controller::repositories::outbox::OutboxRepository;
+use crate::controller::repositories::outbox::PgOutboxRepository;
+use crate::controller::repositories::workflow::PgWorkflowRepository;
+use crate::controller::repositories::workflow::WorkflowRepository;
+use crate::controller::repositories::workflow::WorkflowRow;
+use crate::messages::config::ConfigUpdate;
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+#[async_trait]
+pub trait WorkflowService {
+    async fn set_paused(
+        &self,
+        id: &WorkflowId,
+        paused: bool,
+        precondition: &Precondition,
+    ) -> Result<Option<WorkflowRow>>;
+
+    async fn get_by_id(&self, id: &WorkflowId) -> Result<Option<WorkflowRow>>;
+}
+
+#[async_trait]
+impl WorkflowService for PgPool {
+    async fn set_paused(
+        &self,
+        id: &WorkflowId,
+        paused: bool,
+        precondition: &Precondition,
+    ) -> Result<Option<WorkflowRow>> {
+        let repo = PgWorkflowRepository;
+        let mut tx = self
+            .begin()
+            .await
+            .context("failed to begin postgres transaction")?;
+        let current = repo.lock_by_id(id, &mut tx).await?;
+        precondition.check(current.as_ref().map(|row| row.version))?;
+        let current = match current {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        repo.set_paused(id, paused, &mut tx).await?;
+        let outbox = Outbox::new(&ConfigUpdate::Project(current.project_id))?;
+        PgOutboxRepository.create(&outbox, &mut tx).await?;
+        let row = repo.get_by_id(id, &mut tx).await?;
+        tx.commit()
+            .await
+            .context("failed to commit postgres transaction")?;
+        Ok(row)
+    }
+
+    async fn get_by_id(&self, id: &WorkflowId) -> Result<Option<WorkflowRow>> {
+        let repo = PgWorkflowRepository;
+        repo.get_by_id(id, self).await
+    }
+}
@@ -1,16 +1,42 @@
+pub mod broker;
+pub mod crypto;
 pub mod jwt;
 pub mod opa;
 pub mod postgres;
 pub mod rabbitmq;
+pub mod stash;
+pub mod webhook;
 use crate::config::Config;
+use crate::infra::broker::memory::Memory;
+use crate::infra::broker::Broker;
+use crate::infra::crypto::Cipher;
+use crate::infra::jwt::Keys;
+use crate::infra::rabbitmq::Session;
+use crate::infra::stash::Backend;
+use crate::infra::stash::Local;
 use anyhow::Result;
-use lapin::Connection;
 use sqlx::PgPool;
+use std::sync::Arc;
 
 pub async fn new_pg_pool(config: &Config) -> Result<PgPool> {
     postgres::connect(&config.db_url).await
 }
 
-pub async fn new_rmq_connection(config: &Config) -> Result<Connection> {
-    rabbitmq::connect(&config.mq_addr).await
+pub async fn new_broker(config: &Config) -> Result<Arc<dyn Broker>> {
+    if broker::is_memory(&config.mq_addr) {
+        return Ok(Arc::new(Memory::new()));
+    }
+    Ok(Arc::new(Session::open(&config.mq_addr).await?))
+}
+
+pub fn new_cipher(config: &Config) -> Result<Option<Cipher>> {
+    config.secret_key.as_deref().map(Cipher::new).transpose()
+}
+
+pub fn new_jwt_keys(config: &Config) -> Option<Keys> {
+    config.jwt_secret.as_deref().map(Keys::new)
+}
+
+pub fn new_stash_backend(config: &Config) -> Arc<dyn Backend> {
+    Arc::new(Local::new(&config.stash_dir))
 }
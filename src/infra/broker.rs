@@ -0,0 +1,85 @@
+pub mod memory;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use std::collections::HashMap;
+
+pub const MEMORY_SCHEME: &str = "memory";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExchangeKind {
+    Fanout,
+    Direct,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    pub exchange: String,
+    pub routing_key: String,
+    pub payload: Vec<u8>,
+    pub headers: HashMap<String, String>,
+    pub priority: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Queue {
+    pub name: String,
+    pub exchange: String,
+    pub routing_keys: Vec<String>,
+    pub max_priority: u8,
+}
+
+#[async_trait]
+pub trait Acker: Send {
+    async fn ack(self: Box<Self>) -> Result<()>;
+
+    async fn nack(self: Box<Self>, requeue: bool) -> Result<()>;
+}
+
+pub struct Delivery {
+    pub envelope: Envelope,
+    pub redelivered: bool,
+    acker: Box<dyn Acker>,
+}
+
+impl Delivery {
+    pub fn new(envelope: Envelope, redelivered: bool, acker: Box<dyn Acker>) -> Self {
+        Self {
+            envelope,
+            redelivered,
+            acker,
+        }
+    }
+
+    pub async fn ack(self) -> Result<()> {
+        self.acker.ack().await
+    }
+
+    pub async fn nack(self, requeue: bool) -> Result<()> {
+        self.acker.nack(requeue).await
+    }
+}
+
+pub type Subscription = BoxStream<'static, Result<Envelope>>;
+
+pub type Consumer = BoxStream<'static, Result<Delivery>>;
+
+#[async_trait]
+pub trait Broker: Send + Sync {
+    async fn declare(&self, exchange: &str, kind: ExchangeKind) -> Result<()>;
+
+    async fn declare_queue(&self, queue: &Queue) -> Result<()>;
+
+    async fn publish(&self, envelope: &Envelope) -> Result<()>;
+
+    async fn subscribe(&self, exchange: &str, routing_key: &str) -> Result<Subscription>;
+
+    async fn consume(&self, queue: &str, prefetch: u16) -> Result<Consumer>;
+
+    async fn is_connected(&self) -> bool;
+}
+
+pub fn is_memory(addr: &str) -> bool {
+    addr.split_once("://")
+        .map_or(false, |(scheme, _)| scheme == MEMORY_SCHEME)
+}
@@ -0,0 +1,528 @@
+use super::Acker;
+use super::Broker;
+use super::Consumer;
+use super::Delivery;
+use super::Envelope;
+use super::ExchangeKind;
+use super::Queue;
+use super::Subscription;
+use anyhow::anyhow;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::cmp::min;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+use tokio::sync::Notify;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+const CAPACITY: usize = 1024;
+
+struct Exchange {
+    kind: ExchangeKind,
+    sender: broadcast::Sender<Envelope>,
+    bindings: Vec<(String, Arc<WorkQueue>)>,
+}
+
+struct Message {
+    priority: u8,
+    sequence: u64,
+    envelope: Envelope,
+    redelivered: bool,
+}
+
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Message {}
+
+impl PartialOrd for Message {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Message {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+#[derive(Default)]
+struct Pending {
+    messages: BinaryHeap<Message>,
+    sequence: u64,
+}
+
+struct WorkQueue {
+    max_priority: u8,
+    pending: Mutex<Pending>,
+    notify: Notify,
+}
+
+impl WorkQueue {
+    fn new(max_priority: u8) -> Self {
+        Self {
+            max_priority,
+            pending: Mutex::new(Pending::default()),
+            notify: Notify::new(),
+        }
+    }
+
+    fn push(&self, envelope: Envelope) {
+        let mut pending = self.pending.lock().expect("queue should be locked");
+        let sequence = pending.sequence;
+        pending.sequence += 1;
+        pending.messages.push(Message {
+            priority: min(envelope.priority.unwrap_or(0), self.max_priority),
+            sequence,
+            envelope,
+            redelivered: false,
+        });
+        self.notify.notify_one();
+    }
+
+    fn requeue(&self, mut message: Message) {
+        // NOTE: Requeued messages keep their sequence so that they go back to their original position.
+        message.redelivered = true;
+        let mut pending = self.pending.lock().expect("queue should be locked");
+        pending.messages.push(message);
+        self.notify.notify_one();
+    }
+
+    fn pop(&self) -> Option<Message> {
+        let mut pending = self.pending.lock().expect("queue should be locked");
+        pending.messages.pop()
+    }
+}
+
+struct MemoryAcker {
+    queue: Arc<WorkQueue>,
+    message: Option<Message>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Drop for MemoryAcker {
+    fn drop(&mut self) {
+        // NOTE: Unsettled deliveries are requeued, as AMQP does when a channel is closed.
+        if let Some(message) = self.message.take() {
+            self.queue.requeue(message);
+        }
+    }
+}
+
+#[async_trait]
+impl Acker for MemoryAcker {
+    async fn ack(mut self: Box<Self>) -> Result<()> {
+        self.message.take();
+        Ok(())
+    }
+
+    async fn nack(mut self: Box<Self>, requeue: bool) -> Result<()> {
+        if let Some(message) = self.message.take() {
+            if requeue {
+                self.queue.requeue(message);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct Memory {
+    exchanges: Mutex<HashMap<String, Exchange>>,
+    queues: Mutex<HashMap<String, Arc<WorkQueue>>>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn matches(kind: ExchangeKind, binding: &str, envelope: &Envelope) -> bool {
+    match kind {
+        ExchangeKind::Fanout => true,
+        ExchangeKind::Direct => binding == envelope.routing_key,
+    }
+}
+
+#[async_trait]
+impl Broker for Memory {
+    async fn declare(&self, exchange: &str, kind: ExchangeKind) -> Result<()> {
+        let mut exchanges = self.exchanges.lock().expect("exchanges should be locked");
+        match exchanges.get(exchange) {
+            Some(declared) if declared.kind != kind => Err(anyhow!(
+                r#"exchange "{}" was already declared as {:?}"#,
+                exchange,
+                declared.kind
+            )),
+            Some(_) => Ok(()),
+            None => {
+                let (sender, _) = broadcast::channel(CAPACITY);
+                exchanges.insert(
+                    exchange.to_owned(),
+                    Exchange {
+                        kind,
+                        sender,
+                        bindings: Vec::new(),
+                    },
+                );
+                Ok(())
+            }
+        }
+    }
+
+    async fn declare_queue(&self, queue: &Queue) -> Result<()> {
+        let mut exchanges = self.exchanges.lock().expect("exchanges should be locked");
+        let exchange = exchanges
+            .get_mut(&queue.exchange)
+            .ok_or_else(|| anyhow!(r#"exchange "{}" is not declared"#, queue.exchange))?;
+        let mut queues = self.queues.lock().expect("queues should be locked");
+        let declared = queues
+            .entry(queue.name.clone())
+            .or_insert_with(|| Arc::new(WorkQueue::new(queue.max_priority)))
+            .clone();
+        if declared.max_priority != queue.max_priority {
+            return Err(anyhow!(
+                r#"queue "{}" was already declared with max priority {}"#,
+                queue.name,
+                declared.max_priority
+            ));
+        }
+        let routing_keys = if queue.routing_keys.is_empty() {
+            vec![String::new()]
+        } else {
+            queue.routing_keys.clone()
+        };
+        for routing_key in routing_keys {
+            let bound = exchange
+                .bindings
+                .iter()
+                .any(|(key, bound)| key == &routing_key && Arc::ptr_eq(bound, &declared));
+            if !bound {
+                exchange.bindings.push((routing_key, declared.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    async fn publish(&self, envelope: &Envelope) -> Result<()> {
+        let exchanges = self.exchanges.lock().expect("exchanges should be locked");
+        let exchange = exchanges
+            .get(&envelope.exchange)
+            .ok_or_else(|| anyhow!(r#"exchange "{}" is not declared"#, envelope.exchange))?;
+        let mut routed: Vec<&Arc<WorkQueue>> = Vec::new();
+        for (binding, queue) in exchange.bindings.iter() {
+            if matches(exchange.kind, binding, envelope)
+                && !routed.iter().any(|r| Arc::ptr_eq(r, queue))
+            {
+                routed.push(queue);
+            }
+        }
+        for queue in routed {
+            queue.push(envelope.clone());
+        }
+        // NOTE: Messages without any subscriber are dropped, as AMQP does for unrouted ones.
+        let _ = exchange.sender.send(envelope.clone());
+        Ok(())
+    }
+
+    async fn subscribe(&self, exchange: &str, routing_key: &str) -> Result<Subscription> {
+        let exchanges = self.exchanges.lock().expect("exchanges should be locked");
+        let declared = exchanges
+            .get(exchange)
+            .ok_or_else(|| anyhow!(r#"exchange "{}" is not declared"#, exchange))?;
+        let kind = declared.kind;
+        let binding = routing_key.to_owned();
+        let receiver = declared.sender.subscribe();
+        let stream = futures::stream::unfold(receiver, move |mut receiver| {
+            let binding = binding.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(envelope) if matches(kind, &binding, &envelope) => {
+                            return Some((Ok(envelope), receiver))
+                        }
+                        Ok(_) => continue,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!("in-memory subscriber lagged behind {} message(s)", skipped);
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            }
+        });
+        Ok(Box::pin(stream))
+    }
+
+    async fn consume(&self, queue: &str, prefetch: u16) -> Result<Consumer> {
+        let queues = self.queues.lock().expect("queues should be locked");
+        let queue = queues
+            .get(queue)
+            .ok_or_else(|| anyhow!(r#"queue "{}" is not declared"#, queue))?
+            .clone();
+        let permits = Arc::new(Semaphore::new(usize::from(prefetch.max(1))));
+        let stream = futures::stream::unfold((queue, permits), |(queue, permits)| async move {
+            let permit = match permits.clone().acquire_owned().await {
+                Ok(permit) => permit,
+                Err(_) => return None,
+            };
+            loop {
+                if let Some(message) = queue.pop() {
+                    let envelope = message.envelope.clone();
+                    let redelivered = message.redelivered;
+                    let acker = MemoryAcker {
+                        queue: queue.clone(),
+                        message: Some(message),
+                        _permit: permit,
+                    };
+                    let delivery = Delivery::new(envelope, redelivered, Box::new(acker));
+                    return Some((Ok(delivery), (queue, permits)));
+                }
+                queue.notify.notified().await;
+            }
+        });
+        Ok(Box::pin(stream))
+    }
+
+    async fn is_connected(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    async fn next(consumer: &mut Consumer) -> Delivery {
+        consumer
+            .next()
+            .await
+            .expect("delivery should be received")
+            .expect("delivery should be valid")
+    }
+
+    fn envelope(exchange: &str, routing_key: &str) -> Envelope {
+        Envelope {
+            exchange: exchange.to_owned(),
+            routing_key: routing_key.to_owned(),
+            payload: testutils::rand::bytes(20),
+            headers: HashMap::new(),
+            priority: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_and_subscribe() {
+        let broker = Memory::new();
+        let fanout = testutils::rand::string(10);
+        let direct = testutils::rand::string(10);
+        broker
+            .declare(&fanout, ExchangeKind::Fanout)
+            .await
+            .expect("exchange should be declared");
+        broker
+            .declare(&direct, ExchangeKind::Direct)
+            .await
+            .expect("exchange should be declared");
+        assert!(broker.declare(&direct, ExchangeKind::Fanout).await.is_err());
+        let mut everything = broker
+            .subscribe(&fanout, "")
+            .await
+            .expect("subscription should be created");
+        let mut high = broker
+            .subscribe(&direct, "high")
+            .await
+            .expect("subscription should be created");
+        let broadcasted = envelope(&fanout, "ignored");
+        broker
+            .publish(&broadcasted)
+            .await
+            .expect("message should be published");
+        broker
+            .publish(&envelope(&direct, "low"))
+            .await
+            .expect("message should be published");
+        let routed = envelope(&direct, "high");
+        broker
+            .publish(&routed)
+            .await
+            .expect("message should be published");
+        let received = everything
+            .next()
+            .await
+            .expect("message should be received")
+            .expect("message should be valid");
+        assert_eq!(received, broadcasted);
+        let received = high
+            .next()
+            .await
+            .expect("message should be received")
+            .expect("message should be valid");
+        assert_eq!(received, routed);
+        assert!(broker
+            .publish(&envelope(&testutils::rand::string(10), ""))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_consume_by_priority() {
+        let broker = Memory::new();
+        let exchange = testutils::rand::string(10);
+        let queue = testutils::rand::string(10);
+        broker
+            .declare(&exchange, ExchangeKind::Direct)
+            .await
+            .expect("exchange should be declared");
+        broker
+            .declare_queue(&Queue {
+                name: queue.clone(),
+                exchange: exchange.clone(),
+                routing_keys: vec!["low".into(), "high".into()],
+                max_priority: 3,
+            })
+            .await
+            .expect("queue should be declared");
+        let mut low = envelope(&exchange, "low");
+        low.priority = Some(1);
+        let mut high = envelope(&exchange, "high");
+        high.priority = Some(3);
+        let mut ignored = envelope(&exchange, "unbound");
+        ignored.priority = Some(3);
+        for envelope in [&low, &high, &ignored] {
+            broker
+                .publish(envelope)
+                .await
+                .expect("message should be published");
+        }
+        let mut consumer = broker
+            .consume(&queue, 10)
+            .await
+            .expect("consumer should be created");
+        let first = next(&mut consumer).await;
+        assert_eq!(first.envelope, high);
+        let second = next(&mut consumer).await;
+        assert_eq!(second.envelope, low);
+        first.ack().await.expect("delivery should be acked");
+        second.ack().await.expect("delivery should be acked");
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), consumer.next())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_nack_and_requeue() {
+        let broker = Memory::new();
+        let exchange = testutils::rand::string(10);
+        let queue = testutils::rand::string(10);
+        broker
+            .declare(&exchange, ExchangeKind::Fanout)
+            .await
+            .expect("exchange should be declared");
+        broker
+            .declare_queue(&Queue {
+                name: queue.clone(),
+                exchange: exchange.clone(),
+                routing_keys: Vec::new(),
+                max_priority: 0,
+            })
+            .await
+            .expect("queue should be declared");
+        assert!(broker
+            .declare_queue(&Queue {
+                name: queue.clone(),
+                exchange: exchange.clone(),
+                routing_keys: Vec::new(),
+                max_priority: 3,
+            })
+            .await
+            .is_err());
+        let published = envelope(&exchange, "");
+        broker
+            .publish(&published)
+            .await
+            .expect("message should be published");
+        let mut consumer = broker
+            .consume(&queue, 1)
+            .await
+            .expect("consumer should be created");
+        let delivery = next(&mut consumer).await;
+        assert!(!delivery.redelivered);
+        delivery
+            .nack(true)
+            .await
+            .expect("delivery should be nacked");
+        let delivery = next(&mut consumer).await;
+        assert!(delivery.redelivered);
+        assert_eq!(delivery.envelope, published);
+        drop(delivery);
+        let delivery = next(&mut consumer).await;
+        assert!(delivery.redelivered);
+        delivery
+            .nack(false)
+            .await
+            .expect("delivery should be nacked");
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), consumer.next())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prefetch() {
+        let broker = Memory::new();
+        let exchange = testutils::rand::string(10);
+        let queue = testutils::rand::string(10);
+        broker
+            .declare(&exchange, ExchangeKind::Fanout)
+            .await
+            .expect("exchange should be declared");
+        broker
+            .declare_queue(&Queue {
+                name: queue.clone(),
+                exchange: exchange.clone(),
+                routing_keys: Vec::new(),
+                max_priority: 0,
+            })
+            .await
+            .expect("queue should be declared");
+        for _ in 0..2 {
+            broker
+                .publish(&envelope(&exchange, ""))
+                .await
+                .expect("message should be published");
+        }
+        let mut consumer = broker
+            .consume(&queue, 1)
+            .await
+            .expect("consumer should be created");
+        let delivery = next(&mut consumer).await;
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), consumer.next())
+                .await
+                .is_err()
+        );
+        delivery.ack().await.expect("delivery should be acked");
+        let delivery = next(&mut consumer).await;
+        assert!(!delivery.redelivered);
+        delivery.ack().await.expect("delivery should be acked");
+    }
+}
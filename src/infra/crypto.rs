@@ -0,0 +1,97 @@
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::aead::Aad;
+use ring::aead::LessSafeKey;
+use ring::aead::Nonce;
+use ring::aead::UnboundKey;
+use ring::aead::AES_256_GCM;
+use ring::aead::NONCE_LEN;
+use ring::rand::SecureRandom;
+use ring::rand::SystemRandom;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct Cipher {
+    key: Arc<LessSafeKey>,
+    rng: SystemRandom,
+}
+
+impl Cipher {
+    pub fn new(key: &str) -> Result<Self> {
+        let key = STANDARD
+            .decode(key)
+            .context("secret key must be base64 encoded")?;
+        let key = UnboundKey::new(&AES_256_GCM, &key)
+            .map_err(|_| anyhow!("secret key must be 32 bytes long"))?;
+        Ok(Self {
+            key: Arc::new(LessSafeKey::new(key)),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    pub fn encrypt(&self, aad: &[u8], plain: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("failed to generate nonce"))?;
+        let mut sealed = plain.to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(aad),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow!("failed to encrypt secret"))?;
+        let mut value = nonce.to_vec();
+        value.append(&mut sealed);
+        Ok(value)
+    }
+
+    pub fn decrypt(&self, aad: &[u8], value: &[u8]) -> Result<Vec<u8>> {
+        if value.len() < NONCE_LEN {
+            return Err(anyhow!("encrypted secret is truncated"));
+        }
+        let (nonce, sealed) = value.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| anyhow!("encrypted secret has invalid nonce"))?;
+        let mut sealed = sealed.to_vec();
+        let plain = self
+            .key
+            .open_in_place(nonce, Aad::from(aad), &mut sealed)
+            .map_err(|_| anyhow!("failed to decrypt secret"))?;
+        Ok(plain.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> String {
+        STANDARD.encode(testutils::rand::string(32))
+    }
+
+    #[test]
+    fn test_encrypt_and_decrypt() {
+        let cipher = Cipher::new(&key()).expect("cipher should be created");
+        let plain = testutils::rand::string(20);
+        let value = cipher
+            .encrypt(b"aad", plain.as_bytes())
+            .expect("secret should be encrypted");
+        assert_ne!(&value[NONCE_LEN..], plain.as_bytes());
+        let decrypted = cipher
+            .decrypt(b"aad", &value)
+            .expect("secret should be decrypted");
+        assert_eq!(decrypted, plain.as_bytes());
+        assert!(cipher.decrypt(b"other", &value).is_err());
+    }
+
+    #[test]
+    fn test_invalid_key() {
+        assert!(Cipher::new("not base64!").is_err());
+        assert!(Cipher::new(&STANDARD.encode("short")).is_err());
+    }
+}
@@ -4,12 +4,9 @@ use jsonwebtoken::Algorithm;
 use jsonwebtoken::DecodingKey;
 use jsonwebtoken::EncodingKey;
 use jsonwebtoken::Header;
-use jsonwebtoken::TokenData;
 use jsonwebtoken::Validation;
-use std::fs;
 use std::time::Duration;
 use std::time::SystemTime;
-use tracing::debug;
 use tracing::trace;
 
 const KOTOSIRO_ISSUER: &str = "kotosiro";
@@ -18,6 +15,11 @@ const KOTOSIRO_STASH_AUDIENCE: &str = "kotosiro.stash";
 
 const KOTOSIRO_CONFIG_AUDIENCE: &str = "kotosiro.config";
 
+const STASH_TOKEN_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+
+// NOTE: Config tokens are minted when a run is dispatched and may wait in the queue behind other runs.
+const CONFIG_TOKEN_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct Claims {
     iss: String,
@@ -26,20 +28,31 @@ pub struct Claims {
     exp: u64,
 }
 
+#[derive(Clone)]
 pub struct Keys {
     algorithm: Algorithm,
     decoding: DecodingKey,
     encoding: EncodingKey,
 }
 
-fn generate(keys: &Keys, aud: String, sub: String) -> Result<String> {
+impl Keys {
+    pub fn new(secret: &str) -> Self {
+        Self {
+            algorithm: Algorithm::HS256,
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+        }
+    }
+}
+
+fn generate(keys: &Keys, aud: String, sub: String, ttl: Duration) -> Result<String> {
     trace!(r#"generating jwt for aud="{}" sub="{}""#, aud, sub);
     let header = Header::new(keys.algorithm);
     let claims = Claims {
         iss: KOTOSIRO_ISSUER.to_owned(),
         sub,
         aud,
-        exp: (SystemTime::now() + Duration::from_secs(5 * 60))
+        exp: (SystemTime::now() + ttl)
             .duration_since(SystemTime::UNIX_EPOCH)
             .context("failed to create expiration time")?
             .as_secs(),
@@ -49,12 +62,44 @@ fn generate(keys: &Keys, aud: String, sub: String) -> Result<String> {
     Ok(token)
 }
 
+fn verify(keys: &Keys, aud: &str, token: &str) -> Result<String> {
+    let mut validation = Validation::new(keys.algorithm);
+    validation.set_issuer(&[KOTOSIRO_ISSUER]);
+    validation.set_audience(&[aud]);
+    let data = jsonwebtoken::decode::<Claims>(token, &keys.decoding, &validation)
+        .context("failed to verify jwt token")?;
+    trace!(
+        r#"verified jwt for aud="{}" sub="{}""#,
+        aud,
+        data.claims.sub
+    );
+    Ok(data.claims.sub)
+}
+
 pub fn stash(keys: &Keys, id: &str) -> Result<String> {
-    generate(keys, KOTOSIRO_STASH_AUDIENCE.to_owned(), id.to_owned())
+    generate(
+        keys,
+        KOTOSIRO_STASH_AUDIENCE.to_owned(),
+        id.to_owned(),
+        STASH_TOKEN_TTL,
+    )
 }
 
 pub fn config(keys: &Keys, id: &str) -> Result<String> {
-    generate(keys, KOTOSIRO_CONFIG_AUDIENCE.to_owned(), id.to_owned())
+    generate(
+        keys,
+        KOTOSIRO_CONFIG_AUDIENCE.to_owned(),
+        id.to_owned(),
+        CONFIG_TOKEN_TTL,
+    )
+}
+
+pub fn verify_stash(keys: &Keys, token: &str) -> Result<String> {
+    verify(keys, KOTOSIRO_STASH_AUDIENCE, token)
+}
+
+pub fn verify_config(keys: &Keys, token: &str) -> Result<String> {
+    verify(keys, KOTOSIRO_CONFIG_AUDIENCE, token)
 }
 
 #[cfg(test)]
@@ -62,5 +107,16 @@ mod tests {
     use super::*;
 
     #[tokio::test]
-    async fn test_generate() {}
+    async fn test_generate() {
+        let keys = Keys::new(&testutils::rand::string(32));
+        let id = testutils::rand::uuid();
+        let token = stash(&keys, &id).expect("stash token should be generated");
+        assert_eq!(
+            verify_stash(&keys, &token).expect("stash token should be verified"),
+            id
+        );
+        assert!(verify_config(&keys, &token).is_err());
+        let other = Keys::new(&testutils::rand::string(32));
+        assert!(verify_stash(&other, &token).is_err());
+    }
 }
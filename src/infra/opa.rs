@@ -2,6 +2,7 @@ use anyhow::Context;
 use anyhow::Result;
 use reqwest::Url;
 use tracing::error;
+use tracing::instrument;
 use uuid::Uuid;
 
 #[derive(
@@ -73,6 +74,7 @@ pub struct Decision {
     pub result: Option<bool>,
 }
 
+#[instrument(name = "opa.authorize", skip_all)]
 pub async fn authorize<'a>(url: Option<&String>, query: &Query<'a>) -> Result<Decision> {
     let opa = if let Some(opa) = url {
         opa
@@ -96,6 +98,20 @@ pub async fn authorize<'a>(url: Option<&String>, query: &Query<'a>) -> Result<De
     Ok(decision)
 }
 
+#[instrument(name = "opa.health", skip_all)]
+pub async fn health(url: &str) -> Result<()> {
+    let opa = Url::parse(url).context(format!(r#"failed to parse OPA url "{}""#, &url))?;
+    let opa = opa.join("/health")?;
+    reqwest::Client::new()
+        .get(opa)
+        .send()
+        .await
+        .context(format!(r#"failed to query OPA health to "{}""#, &url))?
+        .error_for_status()
+        .context("OPA reported unhealthy status")?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -16,7 +16,7 @@ impl<'c, T> PgAcquire<'c> for T where T: Acquire<'c, Database = Postgres> + Send
 
 pub async fn connect(url: &str) -> Result<PgPool> {
     info!("connecting to database");
-    let pool = PgPool::connect(&url)
+    let pool = PgPool::connect(url)
         .await
         .context("failed to acquire postgres connection")?;
     sqlx::migrate!("./migrations")
@@ -28,6 +28,14 @@ pub async fn connect(url: &str) -> Result<PgPool> {
     Ok(pool)
 }
 
+pub async fn ping(pool: &PgPool) -> Result<()> {
+    sqlx::query("SELECT 1")
+        .execute(pool)
+        .await
+        .context("failed to ping postgres")?;
+    Ok(())
+}
+
 pub fn pg_error<T>(
     response: anyhow::Result<T>,
 ) -> Result<std::result::Result<T, Box<PgDatabaseError>>> {
@@ -35,7 +43,8 @@ pub fn pg_error<T>(
         Ok(v) => Ok(Ok(v)),
         Err(e) => match e.downcast::<sqlx::Error>() {
             Ok(sqlx::Error::Database(e)) => Ok(Err(e.downcast::<PgDatabaseError>())),
-            _ => Err(anyhow!("unknow database error")),
+            Ok(_) => Err(anyhow!("unknow database error")),
+            Err(e) => Err(e),
         },
     }
 }
@@ -71,6 +80,14 @@ mod tests {
             String::from("job"),
             String::from("run"),
             String::from("token"),
+            String::from("outbox"),
+            String::from("secret"),
+            String::from("run_log"),
+            String::from("stash"),
+            String::from("workflow_run"),
+            String::from("run_archive"),
+            String::from("controller"),
+            String::from("lease"),
         ]
         .iter()
         .cloned()
@@ -95,4 +112,10 @@ mod tests {
         );
         assert_eq!(&expected, &tables);
     }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_ping(pool: PgPool) {
+        ping(&pool).await.expect("postgres should respond to ping");
+    }
 }
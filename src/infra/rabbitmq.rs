@@ -1,8 +1,365 @@
+use crate::infra::broker::Acker;
+use crate::infra::broker::Broker;
+use crate::infra::broker::Consumer;
+use crate::infra::broker::Delivery;
+use crate::infra::broker::Envelope;
+use crate::infra::broker::ExchangeKind;
+use crate::infra::broker::Queue;
+use crate::infra::broker::Subscription;
+use anyhow::anyhow;
 use anyhow::Context;
 use anyhow::Result;
+use async_trait::async_trait;
+use futures::StreamExt;
+use lapin::options::BasicAckOptions;
+use lapin::options::BasicConsumeOptions;
+use lapin::options::BasicNackOptions;
+use lapin::options::BasicPublishOptions;
+use lapin::options::BasicQosOptions;
+use lapin::options::ConfirmSelectOptions;
+use lapin::options::ExchangeDeclareOptions;
+use lapin::options::QueueBindOptions;
+use lapin::options::QueueDeclareOptions;
+use lapin::types::AMQPValue;
+use lapin::types::FieldTable;
+use lapin::types::LongString;
+use lapin::types::ShortString;
+use lapin::BasicProperties;
+use lapin::Channel;
 use lapin::Connection;
 use lapin::ConnectionProperties;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+use tokio::sync::RwLock;
 use tracing::info;
+use tracing::warn;
+
+const PERSISTENT: u8 = 2;
+
+const MAX_PRIORITY_ARGUMENT: &str = "x-max-priority";
+
+pub struct Session {
+    addr: String,
+    state: RwLock<Option<(Connection, Channel)>>,
+    exchanges: Mutex<Vec<(String, ExchangeKind)>>,
+    queues: Mutex<Vec<Queue>>,
+}
+
+impl Session {
+    pub async fn open(addr: &str) -> Result<Self> {
+        let session = Self {
+            addr: addr.to_owned(),
+            state: RwLock::new(None),
+            exchanges: Mutex::new(Vec::new()),
+            queues: Mutex::new(Vec::new()),
+        };
+        session.reconnect().await?;
+        Ok(session)
+    }
+
+    pub async fn channel(&self) -> Option<Channel> {
+        let state = self.state.read().await;
+        state
+            .as_ref()
+            .filter(|(conn, chan)| conn.status().connected() && chan.status().connected())
+            .map(|(_, chan)| chan.clone())
+    }
+
+    pub async fn reconnect(&self) -> Result<Channel> {
+        let mut state = self.state.write().await;
+        if let Some((conn, _)) = state.take() {
+            if conn.status().connected() {
+                if let Err(e) = conn.close(0, "reconnecting").await {
+                    warn!("failed to close stale rabbitmq connection: {}", e);
+                }
+            }
+        }
+        let conn = connect(&self.addr).await?;
+        let chan = conn
+            .create_channel()
+            .await
+            .context("failed to create rabbitmq channel")?;
+        chan.confirm_select(ConfirmSelectOptions::default())
+            .await
+            .context("failed to enable rabbitmq publisher confirms")?;
+        for (exchange, kind) in self.exchanges.lock().await.iter() {
+            declare(&chan, exchange, *kind).await?;
+        }
+        for queue in self.queues.lock().await.iter() {
+            declare_queue(&chan, queue).await?;
+        }
+        *state = Some((conn, chan.clone()));
+        Ok(chan)
+    }
+
+    async fn recover(&self) -> Result<Channel> {
+        if let Some(chan) = self.channel().await {
+            return Ok(chan);
+        }
+        warn!("rabbitmq connection is lost, reconnecting");
+        let chan = self.reconnect().await?;
+        info!("rabbitmq connection is recovered");
+        Ok(chan)
+    }
+
+    async fn open_channel(&self) -> Result<Channel> {
+        self.recover().await?;
+        let state = self.state.read().await;
+        let (conn, _) = state
+            .as_ref()
+            .ok_or_else(|| anyhow!("rabbitmq connection is not established"))?;
+        conn.create_channel()
+            .await
+            .context("failed to create rabbitmq channel")
+    }
+}
+
+async fn declare(chan: &Channel, exchange: &str, kind: ExchangeKind) -> Result<()> {
+    let kind = match kind {
+        ExchangeKind::Fanout => lapin::ExchangeKind::Fanout,
+        ExchangeKind::Direct => lapin::ExchangeKind::Direct,
+    };
+    chan.exchange_declare(
+        exchange,
+        kind,
+        ExchangeDeclareOptions {
+            durable: true,
+            ..ExchangeDeclareOptions::default()
+        },
+        FieldTable::default(),
+    )
+    .await
+    .context(format!(
+        r#"failed to declare rabbitmq exchange "{}""#,
+        exchange
+    ))
+}
+
+async fn declare_queue(chan: &Channel, queue: &Queue) -> Result<()> {
+    let mut arguments = FieldTable::default();
+    arguments.insert(
+        ShortString::from(MAX_PRIORITY_ARGUMENT),
+        AMQPValue::ShortShortUInt(queue.max_priority),
+    );
+    chan.queue_declare(
+        &queue.name,
+        QueueDeclareOptions {
+            durable: true,
+            ..QueueDeclareOptions::default()
+        },
+        arguments,
+    )
+    .await
+    .context(format!(
+        r#"failed to declare rabbitmq queue "{}""#,
+        queue.name
+    ))?;
+    let routing_keys = if queue.routing_keys.is_empty() {
+        vec![String::new()]
+    } else {
+        queue.routing_keys.clone()
+    };
+    for routing_key in routing_keys.iter() {
+        chan.queue_bind(
+            &queue.name,
+            &queue.exchange,
+            routing_key,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+        .context(format!(
+            r#"failed to bind rabbitmq queue "{}" to "{}""#,
+            queue.name, queue.exchange
+        ))?;
+    }
+    Ok(())
+}
+
+struct AmqpAcker {
+    acker: lapin::acker::Acker,
+}
+
+#[async_trait]
+impl Acker for AmqpAcker {
+    async fn ack(self: Box<Self>) -> Result<()> {
+        self.acker
+            .ack(BasicAckOptions::default())
+            .await
+            .context("failed to ack rabbitmq delivery")
+    }
+
+    async fn nack(self: Box<Self>, requeue: bool) -> Result<()> {
+        self.acker
+            .nack(BasicNackOptions {
+                requeue,
+                ..BasicNackOptions::default()
+            })
+            .await
+            .context("failed to nack rabbitmq delivery")
+    }
+}
+
+fn to_envelope(delivery: &lapin::message::Delivery) -> Envelope {
+    Envelope {
+        exchange: delivery.exchange.to_string(),
+        routing_key: delivery.routing_key.to_string(),
+        headers: from_field_table(delivery.properties.headers().as_ref()),
+        priority: *delivery.properties.priority(),
+        payload: delivery.data.clone(),
+    }
+}
+
+fn to_field_table(headers: &HashMap<String, String>) -> FieldTable {
+    let mut table = FieldTable::default();
+    for (key, value) in headers.iter() {
+        table.insert(
+            ShortString::from(key.as_str()),
+            AMQPValue::LongString(LongString::from(value.as_str())),
+        );
+    }
+    table
+}
+
+fn from_field_table(table: Option<&FieldTable>) -> HashMap<String, String> {
+    table
+        .into_iter()
+        .flat_map(|table| table.inner().iter())
+        .filter_map(|(key, value)| match value {
+            AMQPValue::LongString(value) => std::str::from_utf8(value.as_bytes())
+                .ok()
+                .map(|value| (key.to_string(), value.to_owned())),
+            AMQPValue::ShortString(value) => Some((key.to_string(), value.to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Broker for Session {
+    async fn declare(&self, exchange: &str, kind: ExchangeKind) -> Result<()> {
+        let chan = self.recover().await?;
+        declare(&chan, exchange, kind).await?;
+        let mut exchanges = self.exchanges.lock().await;
+        if !exchanges.iter().any(|(declared, _)| declared == exchange) {
+            exchanges.push((exchange.to_owned(), kind));
+        }
+        Ok(())
+    }
+
+    async fn declare_queue(&self, queue: &Queue) -> Result<()> {
+        let chan = self.recover().await?;
+        declare_queue(&chan, queue).await?;
+        let mut queues = self.queues.lock().await;
+        if !queues.iter().any(|declared| declared.name == queue.name) {
+            queues.push(queue.clone());
+        }
+        Ok(())
+    }
+
+    async fn publish(&self, envelope: &Envelope) -> Result<()> {
+        let chan = self.recover().await?;
+        let mut properties = BasicProperties::default()
+            .with_delivery_mode(PERSISTENT)
+            .with_headers(to_field_table(&envelope.headers));
+        if let Some(priority) = envelope.priority {
+            properties = properties.with_priority(priority);
+        }
+        let confirmation = chan
+            .basic_publish(
+                &envelope.exchange,
+                &envelope.routing_key,
+                BasicPublishOptions::default(),
+                &envelope.payload,
+                properties,
+            )
+            .await?
+            .await?;
+        if confirmation.is_nack() {
+            return Err(anyhow!("message was rejected by rabbitmq"));
+        }
+        Ok(())
+    }
+
+    async fn subscribe(&self, exchange: &str, routing_key: &str) -> Result<Subscription> {
+        let chan = self.recover().await?;
+        let queue = chan
+            .queue_declare(
+                "",
+                QueueDeclareOptions {
+                    exclusive: true,
+                    auto_delete: true,
+                    ..QueueDeclareOptions::default()
+                },
+                FieldTable::default(),
+            )
+            .await
+            .context("failed to declare rabbitmq queue")?;
+        chan.queue_bind(
+            queue.name().as_str(),
+            exchange,
+            routing_key,
+            QueueBindOptions::default(),
+            FieldTable::default(),
+        )
+        .await
+        .context(format!(
+            r#"failed to bind rabbitmq queue to "{}""#,
+            exchange
+        ))?;
+        let consumer = chan
+            .basic_consume(
+                queue.name().as_str(),
+                "",
+                BasicConsumeOptions {
+                    no_ack: true,
+                    ..BasicConsumeOptions::default()
+                },
+                FieldTable::default(),
+            )
+            .await
+            .context("failed to consume rabbitmq queue")?;
+        let stream = consumer.map(|delivery| {
+            let delivery = delivery.context("failed to receive rabbitmq delivery")?;
+            Ok(to_envelope(&delivery))
+        });
+        Ok(Box::pin(stream))
+    }
+
+    async fn consume(&self, queue: &str, prefetch: u16) -> Result<Consumer> {
+        // NOTE: Each consumer owns its channel so that the prefetch limit is applied per consumer.
+        let chan = self.open_channel().await?;
+        chan.basic_qos(prefetch, BasicQosOptions::default())
+            .await
+            .context("failed to set rabbitmq prefetch count")?;
+        let consumer = chan
+            .basic_consume(
+                queue,
+                "",
+                BasicConsumeOptions::default(),
+                FieldTable::default(),
+            )
+            .await
+            .context(format!(r#"failed to consume rabbitmq queue "{}""#, queue))?;
+        let stream = consumer.map(|delivery| {
+            let delivery = delivery.context("failed to receive rabbitmq delivery")?;
+            let envelope = to_envelope(&delivery);
+            let acker = AmqpAcker {
+                acker: delivery.acker,
+            };
+            Ok(Delivery::new(
+                envelope,
+                delivery.redelivered,
+                Box::new(acker),
+            ))
+        });
+        Ok(Box::pin(stream))
+    }
+
+    async fn is_connected(&self) -> bool {
+        self.channel().await.is_some()
+    }
+}
 
 pub async fn connect(addr: &str) -> Result<Connection> {
     info!("connecting to message broker");
@@ -17,14 +374,6 @@ pub async fn connect(addr: &str) -> Result<Connection> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use futures::StreamExt;
-    use lapin::options::BasicConsumeOptions;
-    use lapin::options::BasicPublishOptions;
-    use lapin::options::ExchangeDeclareOptions;
-    use lapin::options::QueueBindOptions;
-    use lapin::options::QueueDeclareOptions;
-    use lapin::types::FieldTable;
-    use lapin::BasicProperties;
     use lapin::ExchangeKind;
     use std::time::Duration;
     use testcontainers::clients;
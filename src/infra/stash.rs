@@ -0,0 +1,89 @@
+use anyhow::Context;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tracing::instrument;
+
+#[async_trait]
+pub trait Backend: Send + Sync + 'static {
+    async fn put(&self, key: &str, value: &[u8]) -> Result<()>;
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    async fn delete(&self, key: &str) -> Result<()>;
+}
+
+pub struct Local {
+    root: PathBuf,
+}
+
+impl Local {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+#[async_trait]
+impl Backend for Local {
+    #[instrument(name = "stash.local.put", skip_all)]
+    async fn put(&self, key: &str, value: &[u8]) -> Result<()> {
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .context(format!(r#"failed to create "{}""#, parent.display()))?;
+        }
+        tokio::fs::write(&path, value)
+            .await
+            .context(format!(r#"failed to write "{}""#, path.display()))
+    }
+
+    #[instrument(name = "stash.local.get", skip_all)]
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let path = self.root.join(key);
+        match tokio::fs::read(&path).await {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).context(format!(r#"failed to read "{}""#, path.display())),
+        }
+    }
+
+    #[instrument(name = "stash.local.delete", skip_all)]
+    async fn delete(&self, key: &str) -> Result<()> {
+        let path = self.root.join(key);
+        match tokio::fs::remove_file(&path).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).context(format!(r#"failed to remove "{}""#, path.display())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local() {
+        let root = std::env::temp_dir().join(testutils::rand::uuid());
+        let backend = Local::new(&root);
+        let key = format!("{}/result", testutils::rand::uuid());
+        assert_eq!(backend.get(&key).await.expect("blob should be read"), None);
+        backend
+            .put(&key, b"hello")
+            .await
+            .expect("blob should be written");
+        assert_eq!(
+            backend.get(&key).await.expect("blob should be read"),
+            Some(b"hello".to_vec())
+        );
+        backend.delete(&key).await.expect("blob should be removed");
+        backend
+            .delete(&key)
+            .await
+            .expect("missing blob should be ignored");
+        assert_eq!(backend.get(&key).await.expect("blob should be read"), None);
+        std::fs::remove_dir_all(root).expect("temporary directory should be removed");
+    }
+}
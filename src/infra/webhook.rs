@@ -0,0 +1,93 @@
+use anyhow::Context;
+use anyhow::Result;
+use ring::hmac;
+use std::time::Duration;
+use tracing::instrument;
+use uuid::Uuid;
+
+pub const EVENT_HEADER: &str = "x-kotosiro-event";
+
+pub const DELIVERY_HEADER: &str = "x-kotosiro-delivery";
+
+pub const TIMESTAMP_HEADER: &str = "x-kotosiro-timestamp";
+
+pub const SIGNATURE_HEADER: &str = "x-kotosiro-signature";
+
+const SIGNATURE_PREFIX: &str = "sha256=";
+
+fn message(timestamp: i64, body: &[u8]) -> Vec<u8> {
+    let mut message = format!("{}.", timestamp).into_bytes();
+    message.extend_from_slice(body);
+    message
+}
+
+// NOTE: The timestamp is signed along with the body so that receivers can reject replayed deliveries.
+pub fn sign(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+    let tag = hmac::sign(&key, &message(timestamp, body));
+    let hex: String = tag
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}{}", SIGNATURE_PREFIX, hex)
+}
+
+pub fn verify(secret: &[u8], timestamp: i64, body: &[u8], signature: &str) -> bool {
+    let hex = match signature.strip_prefix(SIGNATURE_PREFIX) {
+        Some(hex) if hex.len() % 2 == 0 => hex,
+        _ => return false,
+    };
+    let tag: Option<Vec<u8>> = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect();
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret);
+    tag.map_or(false, |tag| {
+        hmac::verify(&key, &message(timestamp, body), &tag).is_ok()
+    })
+}
+
+pub struct Webhook<'a> {
+    pub url: &'a str,
+    pub event: &'a str,
+    pub delivery_id: &'a Uuid,
+    pub secret: &'a [u8],
+    pub body: Vec<u8>,
+}
+
+#[instrument(name = "webhook.post", skip_all)]
+pub async fn post(webhook: Webhook<'_>, timestamp: i64, timeout: Duration) -> Result<u16> {
+    let signature = sign(webhook.secret, timestamp, &webhook.body);
+    let res = reqwest::Client::new()
+        .post(webhook.url)
+        .timeout(timeout)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, webhook.event)
+        .header(DELIVERY_HEADER, webhook.delivery_id.to_string())
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(SIGNATURE_HEADER, signature)
+        .body(webhook.body)
+        .send()
+        .await
+        .context(format!(r#"failed to post webhook to "{}""#, webhook.url))?;
+    Ok(res.status().as_u16())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let secret = testutils::rand::string(16);
+        let body = br#"{"event":"run_failure"}"#;
+        let signature = sign(secret.as_bytes(), 1678838400, body);
+        assert!(signature.starts_with(SIGNATURE_PREFIX));
+        assert_eq!(signature.len(), SIGNATURE_PREFIX.len() + 64);
+        assert!(verify(secret.as_bytes(), 1678838400, body, &signature));
+        assert!(!verify(secret.as_bytes(), 1678838401, body, &signature));
+        assert!(!verify(b"other", 1678838400, body, &signature));
+        assert!(!verify(secret.as_bytes(), 1678838400, body, "sha256=zz"));
+    }
+}
@@ -1,8 +1,27 @@
 #![allow(dead_code)]
+// NOTE: Upstream tests predate these lints and are kept as written.
+#![cfg_attr(
+    test,
+    allow(
+        clippy::bool_assert_comparison,
+        clippy::clone_on_copy,
+        clippy::default_constructed_unit_structs,
+        clippy::into_iter_on_ref,
+        clippy::needless_borrow,
+        clippy::op_ref,
+        clippy::redundant_field_names,
+        clippy::redundant_pattern_matching
+    )
+)]
+pub mod client;
 pub mod config;
 pub mod controller;
-mod infra;
+pub mod infra;
 pub mod logging;
 pub mod messages;
+pub mod metrics;
+pub mod runner;
+mod shutdown;
+mod template;
 
 pub const VERSION: &str = git_version::git_version!();
@@ -1,6 +1,16 @@
+pub mod propagation;
 mod tracing;
 use crate::config::Config;
+use anyhow::Result;
 
-pub fn setup(config: &Config) {
-    tracing::init(config.use_json_log, &config.log_filter)
+pub fn setup(config: &Config) -> Result<()> {
+    tracing::init(
+        config.use_json_log,
+        &config.log_filter,
+        config.otlp_addr.as_deref(),
+    )
+}
+
+pub fn teardown() {
+    tracing::shutdown()
 }
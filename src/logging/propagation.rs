@@ -0,0 +1,87 @@
+use axum::http::HeaderMap;
+use opentelemetry::global;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::Context;
+use std::collections::HashMap;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+struct HeaderMapExtractor<'a>(&'a HeaderMap);
+
+impl<'a> Extractor for HeaderMapExtractor<'a> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+pub fn inject_map(headers: &mut HashMap<String, String>) {
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, headers));
+}
+
+pub fn extract_map(headers: &HashMap<String, String>) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(headers))
+}
+
+pub fn extract_http(headers: &HeaderMap) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(&HeaderMapExtractor(headers)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TraceContextExt;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use tracing::info_span;
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn test_inject_and_extract() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let exporter = InMemorySpanExporter::default();
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let name = testutils::rand::string(10);
+        let mut headers = HashMap::new();
+        let (published, consumed) = tracing::subscriber::with_default(subscriber, || {
+            let publish = info_span!("publish", otel.name = name.as_str());
+            let published = publish.in_scope(|| {
+                inject_map(&mut headers);
+                Span::current().context().span().span_context().trace_id()
+            });
+            let consume = info_span!("consume");
+            consume.set_parent(extract_map(&headers));
+            let consumed = consume.context().span().span_context().trace_id();
+            drop(consume);
+            drop(publish);
+            (published, consumed)
+        });
+        provider.force_flush();
+        assert!(headers.contains_key("traceparent"));
+        assert_eq!(published, consumed);
+        let spans = exporter
+            .get_finished_spans()
+            .expect("finished spans should be exported");
+        assert!(spans.iter().any(|span| span.name == name));
+        assert!(spans
+            .iter()
+            .all(|span| span.span_context.trace_id() == published));
+    }
+
+    #[test]
+    fn test_extract_without_headers() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let context = extract_map(&HashMap::new());
+        assert!(!context.span().span_context().is_valid());
+    }
+}
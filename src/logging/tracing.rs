@@ -1,5 +1,15 @@
+use anyhow::Context;
+use anyhow::Result;
 use chrono::SecondsFormat;
 use colored::Colorize;
+use opentelemetry::global;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::runtime;
+use opentelemetry_sdk::trace;
+use opentelemetry_sdk::trace::Tracer;
+use opentelemetry_sdk::Resource;
 use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Result as FmtResult;
@@ -92,18 +102,48 @@ impl Visit for LogVisitor {
     }
 }
 
-pub fn init(use_json: bool, filter: &str) {
+fn tracer(addr: &str) -> Result<Tracer> {
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(addr),
+        )
+        .with_trace_config(
+            trace::config().with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                "kotosiro",
+            )])),
+        )
+        .install_batch(runtime::Tokio)
+        .context(format!(r#"failed to install OTLP exporter for "{}""#, addr))
+}
+
+pub fn init(use_json: bool, filter: &str, otlp_addr: Option<&str>) -> Result<()> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
     let filter_layer = EnvFilter::new(filter);
+    let otlp_layer = otlp_addr
+        .map(tracer)
+        .transpose()?
+        .map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer));
     if use_json {
         tracing_subscriber::registry()
             .with(filter_layer)
+            .with(otlp_layer)
             .with(fmt::layer().json().with_file(true).with_line_number(true))
             .init();
     } else {
         let fmt_layer = fmt::layer().event_format(Log).fmt_fields(Log);
         tracing_subscriber::registry()
             .with(filter_layer)
+            .with(otlp_layer)
             .with(fmt_layer)
             .init();
     }
+    Ok(())
+}
+
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
 }
@@ -1,8 +1,12 @@
 use anyhow::Context;
 use anyhow::Result;
+use kotosiro::client;
+use kotosiro::client::Output;
 use kotosiro::config::Config;
 use kotosiro::controller::Controller;
+use kotosiro::infra;
 use kotosiro::logging;
+use kotosiro::runner::Runner;
 use tracing::debug;
 
 #[tokio::main]
@@ -19,31 +23,57 @@ async fn main() -> Result<()> {
                 .short('c')
                 .help("Provide a specific config file"),
         )
+        .arg(
+            clap::Arg::new("output")
+                .long("output")
+                .short('o')
+                .global(true)
+                .value_parser(["table", "json"])
+                .default_value("table")
+                .help("Output format of the client subcommands"),
+        )
+        .arg(
+            clap::Arg::new("token")
+                .long("token")
+                .global(true)
+                .help("Bearer token sent to the controller API"),
+        )
         .subcommand(
             clap::Command::new("controller")
                 .about("Launch the controller process")
                 .after_help("The controller has an API server embedded."),
         )
-        .subcommand(clap::Command::new("runner").about("Launch the runner process"));
+        .subcommand(clap::Command::new("runner").about("Launch the runner process"))
+        .subcommand(
+            clap::Command::new("standalone")
+                .about("Launch the controller and the runner in a single process")
+                .after_help("Messages are exchanged in memory, so only postgres is required."),
+        )
+        .subcommands(client::commands());
     let args = app.get_matches();
     let conf = args.get_one::<String>("config").map(AsRef::as_ref);
     let conf = Config::load(conf)?;
-    logging::setup(&conf);
+    logging::setup(&conf).context("failed to setup logging")?;
     debug!(
         db_url = &conf.db_url,
         controller_addr = &conf.controller_addr,
         controller_bind = &conf.controller_bind,
+        runner_bind = &conf.runner_bind,
         cluster_gossip_addr = &conf.cluster_gossip_addr,
         cluster_gossip_bind = &conf.cluster_gossip_bind,
         mq_addr = &conf.mq_addr,
         opa_addr = &conf.opa_addr,
+        otlp_addr = &conf.otlp_addr,
         no_auth = &conf.no_auth,
         use_json_log = &conf.use_json_log,
     );
-    match args.subcommand().expect("subcommand is required") {
+    let result = match args.subcommand().expect("subcommand is required") {
         ("controller", _args) => {
             debug!("controller is called");
-            let controller = Controller::new(conf)
+            let broker = infra::new_broker(&conf)
+                .await
+                .context("failed to create message broker")?;
+            let controller = Controller::new(conf, broker)
                 .await
                 .context("failed to create controller")?;
             controller
@@ -54,8 +84,49 @@ async fn main() -> Result<()> {
         }
         ("runner", _args) => {
             debug!("runner is called");
+            let broker = infra::new_broker(&conf)
+                .await
+                .context("failed to create message broker")?;
+            let runner = Runner::new(conf, broker)
+                .await
+                .context("failed to create runner")?;
+            runner.start().await.context("failed to start runner")?;
+            Ok(())
+        }
+        ("standalone", _args) => {
+            debug!("standalone is called");
+            let conf = conf.standalone();
+            let broker = infra::new_broker(&conf)
+                .await
+                .context("failed to create message broker")?;
+            let controller = Controller::new(conf.clone(), broker.clone())
+                .await
+                .context("failed to create controller")?;
+            let runner = Runner::new(conf, broker)
+                .await
+                .context("failed to create runner")?;
+            tokio::try_join!(
+                async {
+                    controller
+                        .start()
+                        .await
+                        .context("failed to start controller")
+                },
+                async { runner.start().await.context("failed to start runner") },
+            )?;
             Ok(())
         }
-        _ => unreachable!("clap should have already checked the subcommands"),
-    }
+        command => {
+            let output = args
+                .get_one::<String>("output")
+                .map(|output| output.parse::<Output>())
+                .transpose()
+                .context("failed to parse output format")?
+                .unwrap_or(Output::Table);
+            let token = args.get_one::<String>("token");
+            client::execute(&conf, token, output, command).await
+        }
+    };
+    logging::teardown();
+    result
 }
@@ -1,3 +1,51 @@
 pub mod config;
+pub mod log;
 pub mod run;
 pub mod token;
+use crate::infra::broker::Broker;
+use crate::infra::broker::ExchangeKind;
+use crate::infra::broker::Queue;
+use anyhow::Result;
+use config::CONFIG_UPDATES_EXCHANGE;
+use run::RunPriority;
+use run::RUN_DISPATCH_EXCHANGE;
+use run::RUN_DISPATCH_QUEUE;
+
+pub trait Message: serde::Serialize {
+    fn exchange(&self) -> &'static str;
+
+    fn routing_key(&self) -> String {
+        String::new()
+    }
+
+    fn priority(&self) -> Option<u8> {
+        None
+    }
+}
+
+pub async fn setup(broker: &dyn Broker) -> Result<()> {
+    broker
+        .declare(CONFIG_UPDATES_EXCHANGE, ExchangeKind::Fanout)
+        .await?;
+    broker
+        .declare(RUN_DISPATCH_EXCHANGE, ExchangeKind::Direct)
+        .await?;
+    let priorities = [
+        RunPriority::BackFill,
+        RunPriority::Low,
+        RunPriority::Normal,
+        RunPriority::High,
+    ];
+    broker
+        .declare_queue(&Queue {
+            name: RUN_DISPATCH_QUEUE.to_owned(),
+            exchange: RUN_DISPATCH_EXCHANGE.to_owned(),
+            routing_keys: priorities
+                .iter()
+                .map(|priority| priority.as_ref().to_owned())
+                .collect(),
+            max_priority: RunPriority::High as u8,
+        })
+        .await?;
+    Ok(())
+}
@@ -1,13 +1,20 @@
+use super::Message;
 use uuid::Uuid;
 
 pub const CONFIG_UPDATES_EXCHANGE: &str = "kotosiro.updates.config";
 
-#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub enum ConfigUpdate {
     Project(Uuid),
     Job(Uuid),
 }
 
+impl Message for ConfigUpdate {
+    fn exchange(&self) -> &'static str {
+        CONFIG_UPDATES_EXCHANGE
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,46 @@
+use chrono::DateTime;
+use chrono::Utc;
+
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    sqlx::Type,
+    strum_macros::EnumString,
+    strum_macros::AsRefStr,
+    utoipa::ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+#[sqlx(type_name = "VARCHAR")]
+#[strum(serialize_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, utoipa::ToSchema)]
+pub struct LogLine {
+    pub seq: i64,
+    pub stream: LogStream,
+    pub line: String,
+    pub emitted_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_log_stream() {
+        assert_eq!(LogStream::from_str("stdout").ok(), Some(LogStream::Stdout));
+        assert_eq!(LogStream::from_str("stderr").ok(), Some(LogStream::Stderr));
+        assert!(LogStream::from_str("stdin").is_err());
+        assert_eq!(LogStream::Stderr.as_ref(), "stderr");
+    }
+}
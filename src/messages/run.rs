@@ -1,3 +1,10 @@
+use super::Message;
+use uuid::Uuid;
+
+pub const RUN_DISPATCH_EXCHANGE: &str = "kotosiro.dispatch.run";
+
+pub const RUN_DISPATCH_QUEUE: &str = "kotosiro.dispatch.run";
+
 #[derive(
     Debug,
     Copy,
@@ -8,16 +15,19 @@
     serde::Deserialize,
     sqlx::Type,
     strum_macros::EnumString,
+    utoipa::ToSchema,
 )]
 #[serde(rename_all = "lowercase")]
 #[sqlx(rename_all = "lowercase")]
 #[sqlx(type_name = "VARCHAR")]
+#[derive(Default)]
 pub enum RunPriority {
     #[strum(ascii_case_insensitive)]
     BackFill = 0,
     #[strum(ascii_case_insensitive)]
     Low = 1,
     #[strum(ascii_case_insensitive)]
+    #[default]
     Normal = 2,
     #[strum(ascii_case_insensitive)]
     High = 3,
@@ -34,9 +44,26 @@ impl AsRef<str> for RunPriority {
     }
 }
 
-impl Default for RunPriority {
-    fn default() -> Self {
-        Self::Normal
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct RunDispatch {
+    pub run_id: Uuid,
+    pub job_id: Uuid,
+    pub priority: RunPriority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_token: Option<String>,
+}
+
+impl Message for RunDispatch {
+    fn exchange(&self) -> &'static str {
+        RUN_DISPATCH_EXCHANGE
+    }
+
+    fn routing_key(&self) -> String {
+        self.priority.as_ref().to_owned()
+    }
+
+    fn priority(&self) -> Option<u8> {
+        Some(self.priority as u8)
     }
 }
 
@@ -58,4 +85,17 @@ mod tests {
         let priority = testutils::rand::choice(&candidates);
         assert!(matches!(RunPriority::from_str(priority), Err(_)));
     }
+
+    #[test]
+    fn test_run_dispatch_routing_key() {
+        let dispatch = RunDispatch {
+            run_id: Uuid::new_v4(),
+            job_id: Uuid::new_v4(),
+            priority: RunPriority::High,
+            config_token: None,
+        };
+        assert_eq!(dispatch.exchange(), RUN_DISPATCH_EXCHANGE);
+        assert_eq!(dispatch.routing_key(), "high");
+        assert_eq!(Message::priority(&dispatch), Some(3));
+    }
 }
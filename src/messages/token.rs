@@ -8,6 +8,7 @@
     serde::Deserialize,
     sqlx::Type,
     strum_macros::EnumString,
+    utoipa::ToSchema,
 )]
 #[serde(rename_all = "lowercase")]
 #[sqlx(rename_all = "lowercase")]
@@ -25,13 +26,15 @@ pub enum TokenState {
     Failure,
     #[strum(ascii_case_insensitive)]
     Error,
+    #[strum(ascii_case_insensitive)]
+    Cancelled,
 }
 
 impl TokenState {
     pub fn is_done(&self) -> bool {
         matches!(
             self,
-            TokenState::Success | TokenState::Failure | TokenState::Error
+            TokenState::Success | TokenState::Failure | TokenState::Error | TokenState::Cancelled
         )
     }
 }
@@ -45,6 +48,7 @@ impl AsRef<str> for TokenState {
             TokenState::Success => "success",
             TokenState::Failure => "failure",
             TokenState::Error => "error",
+            TokenState::Cancelled => "cancelled",
         }
     }
 }
@@ -57,7 +61,13 @@ mod tests {
     #[test]
     fn test_valid_token_state() {
         let candidates = vec![
-            "Waiting", "Active", "Running", "Success", "Failure", "Error",
+            "Waiting",
+            "Active",
+            "Running",
+            "Success",
+            "Failure",
+            "Error",
+            "Cancelled",
         ];
         let state = testutils::rand::choice(&candidates);
         assert!(matches!(TokenState::from_str(state), Ok(_)));
@@ -0,0 +1,147 @@
+use anyhow::Context;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use prometheus::register_histogram;
+use prometheus::register_histogram_vec;
+use prometheus::register_int_counter_vec;
+use prometheus::register_int_gauge;
+use prometheus::register_int_gauge_vec;
+use prometheus::Encoder;
+use prometheus::Histogram;
+use prometheus::HistogramVec;
+use prometheus::IntCounterVec;
+use prometheus::IntGauge;
+use prometheus::IntGaugeVec;
+use prometheus::TextEncoder;
+
+pub const CONTENT_TYPE: &str = prometheus::TEXT_FORMAT;
+
+pub static HTTP_REQUESTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "kotosiro_http_requests_total",
+        "Number of HTTP requests handled per route",
+        &["method", "route", "status"]
+    )
+    .expect("metric should be registered")
+});
+
+pub static HTTP_REQUEST_DURATION_SECONDS: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "kotosiro_http_request_duration_seconds",
+        "HTTP request latency per route in seconds",
+        &["method", "route"]
+    )
+    .expect("metric should be registered")
+});
+
+pub static OPA_DECISION_DURATION_SECONDS: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "kotosiro_opa_decision_duration_seconds",
+        "OPA authorization decision latency in seconds"
+    )
+    .expect("metric should be registered")
+});
+
+pub static OPA_DENIALS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "kotosiro_opa_denials_total",
+        "Number of events denied by OPA",
+        &["action", "kind"]
+    )
+    .expect("metric should be registered")
+});
+
+pub static PG_POOL_CONNECTIONS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "kotosiro_postgres_pool_connections",
+        "Number of connections currently held by the postgres pool"
+    )
+    .expect("metric should be registered")
+});
+
+pub static PG_POOL_IDLE_CONNECTIONS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "kotosiro_postgres_pool_idle_connections",
+        "Number of idle connections in the postgres pool"
+    )
+    .expect("metric should be registered")
+});
+
+pub static MQ_PUBLISH_FAILURES_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "kotosiro_rabbitmq_publish_failures_total",
+        "Number of messages failed to be published to rabbitmq",
+        &["exchange"]
+    )
+    .expect("metric should be registered")
+});
+
+pub static NOTIFICATION_DELIVERIES_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "kotosiro_notification_deliveries_total",
+        "Number of webhook delivery attempts by outcome",
+        &["event", "outcome"]
+    )
+    .expect("metric should be registered")
+});
+
+pub static SLA_MISSES_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "kotosiro_sla_misses_total",
+        "Number of workflow SLA deadlines missed by kind",
+        &["kind"]
+    )
+    .expect("metric should be registered")
+});
+
+pub static RUNS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    register_int_gauge_vec!(
+        "kotosiro_runs",
+        "Number of runs per project, workflow and state",
+        &["project", "workflow", "state"]
+    )
+    .expect("metric should be registered")
+});
+
+pub static RUNNER_RUNS_EXECUTED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "kotosiro_runner_runs_executed_total",
+        "Number of runs executed by the runner per final state",
+        &["state"]
+    )
+    .expect("metric should be registered")
+});
+
+pub static RUNNER_RUN_DURATION_SECONDS: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "kotosiro_runner_run_duration_seconds",
+        "Run execution time in seconds per final state",
+        &["state"],
+        vec![1.0, 5.0, 15.0, 30.0, 60.0, 300.0, 900.0, 1800.0, 3600.0, 10800.0]
+    )
+    .expect("metric should be registered")
+});
+
+pub fn encode() -> Result<String> {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .context("failed to encode prometheus metrics")?;
+    String::from_utf8(buffer).context("failed to convert prometheus metrics into utf-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let route = testutils::rand::string(10);
+        HTTP_REQUESTS_TOTAL
+            .with_label_values(&["GET", &route, "200"])
+            .inc();
+        let encoded = encode().expect("metrics should be encoded");
+        assert!(encoded.contains("kotosiro_http_requests_total"));
+        assert!(encoded.contains(&route));
+    }
+}
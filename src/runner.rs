@@ -1 +1,179 @@
+mod dispatch;
+mod interactors;
+pub mod logs;
+use crate::client::Client;
+use crate::config::Config;
+use crate::infra::broker::Broker;
+use crate::messages;
+use crate::messages::token::TokenState;
+use crate::metrics;
+use anyhow::Context;
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::info;
+use uuid::Uuid;
 
+pub struct Runner {
+    pub id: Uuid,
+    pub broker: Arc<dyn Broker>,
+    pub client: Client,
+    pub config: Config,
+}
+
+impl Runner {
+    pub async fn new(config: Config, broker: Arc<dyn Broker>) -> Result<Arc<Self>> {
+        let client = Client::new(&config.controller_addr, config.api_token.clone())
+            .context("failed to create controller client")?;
+        Ok(Arc::new(Runner {
+            id: Uuid::new_v4(),
+            broker,
+            client,
+            config,
+        }))
+    }
+
+    pub async fn start(self: Arc<Self>) -> Result<()> {
+        messages::setup(self.broker.as_ref())
+            .await
+            .context("failed to setup message broker exchanges")?;
+        let (shutdown, watcher) = watch::channel(false);
+        let consumer = tokio::spawn(dispatch::consume(self.clone(), watcher));
+        let served = interactors::bind(self)
+            .await
+            .context("failed to start metrics server");
+        info!("draining run consumer");
+        let _ = shutdown.send(true);
+        consumer.await.context("failed to join run consumer")?;
+        served
+    }
+
+    pub fn observe(state: &TokenState, elapsed: Duration) {
+        metrics::RUNNER_RUNS_EXECUTED_TOTAL
+            .with_label_values(&[state.as_ref()])
+            .inc();
+        metrics::RUNNER_RUN_DURATION_SECONDS
+            .with_label_values(&[state.as_ref()])
+            .observe(elapsed.as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::Controller;
+    use crate::infra::broker::memory::Memory;
+    use serde_json::Value;
+    use sqlx::PgPool;
+    use std::net::TcpListener;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+
+    const SPEC: &str = r#"
+name: standalone
+config:
+  owner: kotosiro
+workflows:
+  - name: adhoc
+    jobs:
+      - name: hello
+        image: busybox
+        args: ["echo", "hello"]
+"#;
+
+    const ENGINE: &str =
+        "#!/bin/sh\necho \"$@\"\necho \"$KOTOSIRO_RUN_ID\"\necho done >&2\nexit 0\n";
+
+    fn addr() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("port should be bound");
+        listener
+            .local_addr()
+            .expect("address should be assigned")
+            .to_string()
+    }
+
+    fn engine() -> PathBuf {
+        let path = std::env::temp_dir().join(format!("kotosiro-engine-{}", Uuid::new_v4()));
+        testutils::io::persist(ENGINE, &path).expect("engine should be persisted");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .expect("engine should be executable");
+        path
+    }
+
+    async fn wait_for<F, Fut>(mut probe: F) -> Value
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Option<Value>>,
+    {
+        for _ in 0..150 {
+            if let Some(value) = probe().await {
+                return value;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        panic!("condition should be met in time");
+    }
+
+    #[sqlx::test]
+    #[ignore] // NOTE: Be sure '$ docker compose -f devops/local/docker-compose.yaml up' before running this test
+    async fn test_standalone_consumes_dispatch(pool: PgPool) -> Result<()> {
+        let engine = engine();
+        let mut config = Config::load(None)?.standalone();
+        config.controller_bind = addr();
+        config.controller_addr = format!("http://{}/", config.controller_bind);
+        config.runner_bind = addr();
+        config.runner_engine = engine.display().to_string();
+        config.opa_addr = None;
+        config.api_token = None;
+        config.no_auth = true;
+        let broker: Arc<dyn Broker> = Arc::new(Memory::new());
+        let controller = Controller::with_pool(config.clone(), pool.clone(), broker.clone())?;
+        tokio::spawn(controller.start());
+        let runner = Runner::new(config, broker).await?;
+        let client = Client::new(&runner.config.controller_addr, None)?;
+        tokio::spawn(runner.start());
+        wait_for(|| async { client.get_cluster().await.ok() }).await;
+        client.apply_project(SPEC.to_string(), false).await?;
+        let job_id: Uuid = sqlx::query_scalar("SELECT id FROM job WHERE name = 'hello'")
+            .fetch_one(&pool)
+            .await?;
+        let job_id = job_id.to_string();
+        let run = client.run_job(&job_id, None).await?;
+        let run_id = run["id"]
+            .as_str()
+            .expect("run id should be returned")
+            .to_owned();
+        let finished = wait_for(|| async {
+            let runs = client.list_runs(&job_id, &[]).await.ok()?;
+            runs["items"]
+                .as_array()?
+                .iter()
+                .find(|run| run["id"] == run_id.as_str() && run["state"] == "success")
+                .cloned()
+        })
+        .await;
+        assert_eq!(finished["attempt"], 1);
+        let logs = client.logs(&run_id, None, None).await?;
+        let lines: Vec<(&str, &str)> = logs
+            .as_array()
+            .expect("logs should be listed")
+            .iter()
+            .map(|l| (l["stream"].as_str().unwrap(), l["line"].as_str().unwrap()))
+            .collect();
+        let invoked =
+            "run --rm -e KOTOSIRO_RUN_ID -e KOTOSIRO_RUN_ATTEMPT -e KOTOSIRO_STASH_TOKEN busybox echo hello";
+        assert_eq!(lines.len(), 3);
+        assert!(lines.contains(&("stdout", invoked)));
+        assert!(lines.contains(&("stdout", run_id.as_str())));
+        assert!(lines.contains(&("stderr", "done")));
+        assert!(
+            metrics::RUNNER_RUNS_EXECUTED_TOTAL
+                .with_label_values(&["success"])
+                .get()
+                >= 1
+        );
+        testutils::io::remove(&engine)?;
+        Ok(())
+    }
+}
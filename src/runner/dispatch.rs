@@ -0,0 +1,425 @@
+use crate::infra::broker::Delivery;
+use crate::logging::propagation;
+use crate::messages::run::RunDispatch;
+use crate::messages::run::RUN_DISPATCH_QUEUE;
+use crate::messages::token::TokenState;
+use crate::runner::logs::LogSink;
+use crate::runner::Runner;
+use anyhow::anyhow;
+use anyhow::Context;
+use anyhow::Result;
+use futures::StreamExt;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::process::Child;
+use tokio::process::Command;
+use tokio::sync::watch;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::error;
+use tracing::info;
+use tracing::info_span;
+use tracing::warn;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct RunConfig {
+    pub run_id: Uuid,
+    pub attempt: i32,
+    pub job_id: Uuid,
+    pub image: String,
+    pub args: Vec<String>,
+    pub envs: Vec<String>,
+    pub stash_token: Option<String>,
+}
+
+const REQUEUE_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unstarted {
+    pub run_id: Uuid,
+}
+
+impl std::fmt::Display for Unstarted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, r#"failed to start run id: "{}""#, self.run_id)
+    }
+}
+
+impl std::error::Error for Unstarted {}
+
+pub async fn consume(runner: Arc<Runner>, mut shutdown: watch::Receiver<bool>) {
+    let mut consumer = match runner
+        .broker
+        .consume(RUN_DISPATCH_QUEUE, runner.config.runner_prefetch)
+        .await
+    {
+        Ok(consumer) => consumer,
+        Err(e) => {
+            error!("failed to consume run dispatches: {}", e);
+            return;
+        }
+    };
+    // NOTE: The broker already bounds unacked deliveries by prefetch, the semaphore
+    // keeps the number of concurrently running children in line with it.
+    let permits = Arc::new(Semaphore::new(usize::from(
+        runner.config.runner_prefetch.max(1),
+    )));
+    let mut tasks = JoinSet::new();
+    loop {
+        let permit = tokio::select! {
+            _ = shutdown.changed() => break,
+            permit = permits.clone().acquire_owned() => match permit {
+                Ok(permit) => permit,
+                Err(_) => break,
+            },
+        };
+        let delivery = tokio::select! {
+            _ = shutdown.changed() => break,
+            Some(joined) = tasks.join_next(), if !tasks.is_empty() => {
+                if let Err(e) = joined {
+                    error!("run dispatch handler panicked: {}", e);
+                }
+                continue;
+            }
+            delivery = consumer.next() => delivery,
+        };
+        let delivery = match delivery {
+            Some(Ok(delivery)) => delivery,
+            Some(Err(e)) => {
+                warn!("failed to receive run dispatch: {}", e);
+                continue;
+            }
+            None => break,
+        };
+        tasks.spawn(handle(runner.clone(), delivery, permit));
+    }
+    info!("waiting for {} running dispatch(es)", tasks.len());
+    while tasks.join_next().await.is_some() {}
+}
+
+async fn handle(runner: Arc<Runner>, delivery: Delivery, _permit: OwnedSemaphorePermit) {
+    let span = info_span!("run.dispatch", otel.name = RUN_DISPATCH_QUEUE);
+    span.set_parent(propagation::extract_map(&delivery.envelope.headers));
+    settle(runner, delivery).instrument(span).await
+}
+
+async fn settle(runner: Arc<Runner>, delivery: Delivery) {
+    let dispatch: RunDispatch = match serde_json::from_slice(&delivery.envelope.payload) {
+        Ok(dispatch) => dispatch,
+        Err(e) => {
+            warn!("malformed run dispatch was dropped: {}", e);
+            if let Err(e) = delivery.nack(false).await {
+                warn!("failed to nack run dispatch: {}", e);
+            }
+            return;
+        }
+    };
+    let settled = match execute(&runner, &dispatch).await {
+        Ok(state) => {
+            info!(
+                r#"run id: "{}" finished as "{}""#,
+                dispatch.run_id,
+                state.as_ref()
+            );
+            delivery.ack().await
+        }
+        Err(e) if e.downcast_ref::<Unstarted>().is_some() => {
+            warn!(r#"requeueing run id: "{}": {:#}"#, dispatch.run_id, e);
+            // NOTE: Backs off so that an unreachable controller does not turn into a hot loop.
+            tokio::time::sleep(REQUEUE_DELAY).await;
+            delivery.nack(true).await
+        }
+        Err(e) => {
+            // NOTE: The run was already marked running, so redelivering it would run it twice.
+            warn!(
+                r#"failed to execute run id: "{}": {:#}"#,
+                dispatch.run_id, e
+            );
+            delivery.ack().await
+        }
+    };
+    if let Err(e) = settled {
+        warn!("failed to settle run dispatch: {}", e);
+    }
+}
+
+pub async fn execute(runner: &Runner, dispatch: &RunDispatch) -> Result<TokenState> {
+    let id = dispatch.run_id.to_string();
+    let config = match resolve(runner, dispatch).await {
+        Ok(config) => config,
+        Err(e) => {
+            // NOTE: A run whose config cannot be resolved would otherwise stay active forever.
+            runner
+                .client
+                .update_run_state(&id, &TokenState::Error)
+                .await
+                .context(Unstarted {
+                    run_id: dispatch.run_id,
+                })?;
+            return Err(e);
+        }
+    };
+    runner
+        .client
+        .update_run_state(&id, &TokenState::Running)
+        .await
+        .context(Unstarted {
+            run_id: dispatch.run_id,
+        })?;
+    let started = Instant::now();
+    let state = match spawn(&runner.config.runner_engine, &config) {
+        Ok(child) => supervise(runner, &id, child).await,
+        Err(e) => {
+            warn!(r#"failed to spawn run id: "{}": {}"#, id, e);
+            TokenState::Error
+        }
+    };
+    Runner::observe(&state, started.elapsed());
+    runner
+        .client
+        .update_run_state(&id, &state)
+        .await
+        .context(format!(r#"failed to finish run id: "{}""#, id))?;
+    Ok(state)
+}
+
+async fn supervise(runner: &Runner, id: &str, mut child: Child) -> TokenState {
+    if let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) {
+        let mut sink = LogSink::new(&runner.client, id.to_owned());
+        // NOTE: Output is dropped on failure so that the run is never blocked on a full pipe.
+        if let Err(e) = sink.capture(stdout, stderr).await {
+            warn!(r#"failed to capture logs of run id: "{}": {}"#, id, e);
+        }
+    }
+    match child.wait().await {
+        Ok(status) if status.success() => TokenState::Success,
+        Ok(_) => TokenState::Failure,
+        Err(e) => {
+            warn!(r#"failed to wait run id: "{}": {}"#, id, e);
+            TokenState::Error
+        }
+    }
+}
+
+async fn resolve(runner: &Runner, dispatch: &RunDispatch) -> Result<RunConfig> {
+    let id = dispatch.run_id.to_string();
+    let token = dispatch
+        .config_token
+        .as_deref()
+        .ok_or_else(|| anyhow!(r#"run id: "{}" was dispatched without config token"#, id))?;
+    let config = runner
+        .client
+        .get_run_config(&id, token)
+        .await
+        .context(format!(r#"failed to get config of run id: "{}""#, id))?;
+    serde_json::from_value(config).context(format!(r#"failed to parse config of run id: "{}""#, id))
+}
+
+// NOTE: Values are set on the engine's environment and only their names are passed,
+// so that resolved secrets never show up in the process list.
+fn env(command: &mut Command, name: &str, value: &str) {
+    command.env(name, value).arg("-e").arg(name);
+}
+
+fn command(engine: &str, config: &RunConfig) -> Command {
+    let mut command = Command::new(engine);
+    command.arg("run").arg("--rm");
+    env(&mut command, "KOTOSIRO_RUN_ID", &config.run_id.to_string());
+    env(
+        &mut command,
+        "KOTOSIRO_RUN_ATTEMPT",
+        &config.attempt.to_string(),
+    );
+    if let Some(token) = &config.stash_token {
+        env(&mut command, "KOTOSIRO_STASH_TOKEN", token);
+    }
+    for pair in &config.envs {
+        match pair.split_once('=') {
+            Some((name, value)) => env(&mut command, name, value),
+            None => {
+                command.arg("-e").arg(pair);
+            }
+        }
+    }
+    command.arg(&config.image).args(&config.args);
+    command
+}
+
+fn spawn(engine: &str, config: &RunConfig) -> Result<Child> {
+    command(engine, config)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context(format!(r#"failed to spawn "{}""#, engine))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::infra::broker::memory::Memory;
+    use crate::infra::broker::Broker;
+    use crate::infra::broker::Consumer;
+    use crate::infra::broker::Envelope;
+    use crate::messages;
+    use crate::messages::run::RunPriority;
+    use crate::messages::run::RUN_DISPATCH_EXCHANGE;
+    use opentelemetry::global;
+    use opentelemetry::trace::TraceContextExt;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+    use opentelemetry_sdk::trace::TracerProvider;
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use tracing::Span;
+    use tracing_subscriber::prelude::*;
+
+    async fn setup() -> Result<(Arc<Runner>, Consumer)> {
+        // NOTE: Nothing listens on a port that was bound and released, so every controller call fails.
+        let addr = TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+        let mut config = Config::load(None)?;
+        config.controller_addr = format!("http://{}/", addr);
+        config.api_token = None;
+        let broker: Arc<dyn Broker> = Arc::new(Memory::new());
+        messages::setup(broker.as_ref()).await?;
+        let consumer = broker.consume(RUN_DISPATCH_QUEUE, 1).await?;
+        let runner = Runner::new(config, broker).await?;
+        Ok((runner, consumer))
+    }
+
+    async fn publish(runner: &Runner, headers: HashMap<String, String>) -> Result<Vec<u8>> {
+        let dispatch = RunDispatch {
+            run_id: Uuid::new_v4(),
+            job_id: Uuid::new_v4(),
+            priority: RunPriority::Normal,
+            config_token: Some(testutils::rand::string(10)),
+        };
+        let payload = serde_json::to_vec(&dispatch)?;
+        runner
+            .broker
+            .publish(&Envelope {
+                exchange: RUN_DISPATCH_EXCHANGE.to_owned(),
+                routing_key: dispatch.priority.as_ref().to_owned(),
+                payload: payload.clone(),
+                headers,
+                priority: Some(dispatch.priority as u8),
+            })
+            .await?;
+        Ok(payload)
+    }
+
+    async fn next(consumer: &mut Consumer) -> Result<Delivery> {
+        consumer.next().await.expect("delivery should be received")
+    }
+
+    async fn permit() -> Result<OwnedSemaphorePermit> {
+        Ok(Arc::new(Semaphore::new(1)).acquire_owned().await?)
+    }
+
+    #[test]
+    fn test_command_keeps_secrets_off_argv() {
+        let secret = testutils::rand::string(20);
+        let config = RunConfig {
+            run_id: Uuid::new_v4(),
+            attempt: 1,
+            job_id: Uuid::new_v4(),
+            image: String::from("busybox"),
+            args: vec![String::from("true")],
+            envs: vec![format!("PASSWORD={}", secret), String::from("HOME")],
+            stash_token: Some(testutils::rand::string(20)),
+        };
+        let command = command("docker", &config);
+        let args: Vec<&str> = command
+            .as_std()
+            .get_args()
+            .filter_map(|arg| arg.to_str())
+            .collect();
+        assert_eq!(
+            args,
+            vec![
+                "run",
+                "--rm",
+                "-e",
+                "KOTOSIRO_RUN_ID",
+                "-e",
+                "KOTOSIRO_RUN_ATTEMPT",
+                "-e",
+                "KOTOSIRO_STASH_TOKEN",
+                "-e",
+                "PASSWORD",
+                "-e",
+                "HOME",
+                "busybox",
+                "true",
+            ]
+        );
+        let envs: HashMap<&str, &str> = command
+            .as_std()
+            .get_envs()
+            .filter_map(|(name, value)| Some((name.to_str()?, value?.to_str()?)))
+            .collect();
+        assert_eq!(envs.get("PASSWORD"), Some(&secret.as_str()));
+        assert_eq!(
+            envs.get("KOTOSIRO_STASH_TOKEN").copied(),
+            config.stash_token.as_deref()
+        );
+        assert!(!envs.contains_key("HOME"));
+    }
+
+    #[tokio::test]
+    async fn test_requeue_unstarted_dispatch() -> Result<()> {
+        let (runner, mut consumer) = setup().await?;
+        let payload = publish(&runner, HashMap::new()).await?;
+        let delivery = next(&mut consumer).await?;
+        handle(runner, delivery, permit().await?).await;
+        let delivery = next(&mut consumer).await?;
+        assert!(delivery.redelivered);
+        assert_eq!(payload, delivery.envelope.payload);
+        delivery.ack().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_continues_publisher_trace() -> Result<()> {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let exporter = InMemorySpanExporter::default();
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let (runner, mut consumer) = setup().await?;
+        let publisher = info_span!("publish");
+        let (headers, published) = publisher.in_scope(|| {
+            let mut headers = HashMap::new();
+            propagation::inject_map(&mut headers);
+            let published = Span::current().context().span().span_context().trace_id();
+            (headers, published)
+        });
+        drop(publisher);
+        publish(&runner, headers).await?;
+        let delivery = next(&mut consumer).await?;
+        handle(runner, delivery, permit().await?).await;
+        provider.force_flush();
+        let spans = exporter
+            .get_finished_spans()
+            .expect("finished spans should be exported");
+        assert!(spans.iter().any(|span| span.name == "publish"));
+        let dispatched = spans
+            .iter()
+            .find(|span| span.name == RUN_DISPATCH_QUEUE)
+            .expect("dispatch span should be exported");
+        assert_eq!(published, dispatched.span_context.trace_id());
+        Ok(())
+    }
+}
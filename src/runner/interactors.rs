@@ -0,0 +1,50 @@
+use crate::metrics;
+use crate::runner::Runner;
+use anyhow::Context;
+use anyhow::Result;
+use axum::http::header;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use std::sync::Arc;
+use tracing::debug;
+use tracing::error;
+
+async fn get_metrics() -> Response {
+    match metrics::encode() {
+        Ok(body) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, metrics::CONTENT_TYPE)],
+            body,
+        )
+            .into_response(),
+        Err(e) => {
+            error!("failed to encode metrics: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+fn route() -> Router {
+    Router::new().route("/metrics", get(get_metrics))
+}
+
+pub async fn bind(runner: Arc<Runner>) -> Result<()> {
+    let app = route();
+    let addr = runner.config.runner_bind.as_str().parse().context(format!(
+        r#"failed to parse "{}" to SocketAddr"#,
+        runner.config.runner_bind
+    ))?;
+    debug!("kotosiro runner listening on {}", addr);
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .with_graceful_shutdown(crate::shutdown::signal())
+        .await
+        .context(format!(
+            r#"failed to bind "{}" to hyper::Server"#,
+            runner.config.runner_bind,
+        ))?;
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use crate::client::Client;
+use crate::messages::log::LogLine;
+use crate::messages::log::LogStream;
+use anyhow::Context;
+use anyhow::Result;
+use chrono::Utc;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncRead;
+use tokio::io::BufReader;
+
+const FLUSH_SIZE: usize = 100;
+
+pub struct LogSink<'a> {
+    client: &'a Client,
+    run_id: String,
+    seq: i64,
+    buffer: Vec<LogLine>,
+}
+
+impl<'a> LogSink<'a> {
+    pub fn new(client: &'a Client, run_id: String) -> Self {
+        Self {
+            client,
+            run_id,
+            seq: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub async fn push(&mut self, stream: LogStream, line: String) -> Result<()> {
+        self.buffer.push(LogLine {
+            seq: self.seq,
+            stream,
+            line,
+            emitted_at: Utc::now(),
+        });
+        self.seq += 1;
+        if self.buffer.len() >= FLUSH_SIZE {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        self.client
+            .append_logs(&self.run_id, &self.buffer)
+            .await
+            .context(format!(
+                r#"failed to ship logs of run id: "{}""#,
+                self.run_id
+            ))?;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    pub async fn capture(
+        &mut self,
+        stdout: impl AsyncRead + Unpin,
+        stderr: impl AsyncRead + Unpin,
+    ) -> Result<()> {
+        let mut stdout = BufReader::new(stdout).lines();
+        let mut stderr = BufReader::new(stderr).lines();
+        let (mut stdout_open, mut stderr_open) = (true, true);
+        while stdout_open || stderr_open {
+            let (stream, line) = tokio::select! {
+                line = stdout.next_line(), if stdout_open => (LogStream::Stdout, line),
+                line = stderr.next_line(), if stderr_open => (LogStream::Stderr, line),
+            };
+            match line.context("failed to read run output")? {
+                Some(line) => self.push(stream, line).await?,
+                None if stream == LogStream::Stdout => stdout_open = false,
+                None => stderr_open = false,
+            }
+        }
+        self.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Json;
+    use axum::extract::Path;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::Router;
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_capture() {
+        let received: Arc<Mutex<Vec<LogLine>>> = Arc::new(Mutex::new(Vec::new()));
+        let shared = received.clone();
+        let app = Router::new().route(
+            "/internal/api/run/:id/logs",
+            post(
+                move |Path(_id): Path<String>, Json(lines): Json<Vec<LogLine>>| async move {
+                    shared.lock().unwrap().extend(lines);
+                    StatusCode::NO_CONTENT
+                },
+            ),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").expect("port should be bound");
+        let addr = listener.local_addr().expect("address should be assigned");
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .expect("server should be created")
+                .serve(app.into_make_service()),
+        );
+        let client = Client::new(&addr.to_string(), None).expect("client should be created");
+        let mut sink = LogSink::new(&client, testutils::rand::uuid());
+        let stdout = (0..150).map(|i| format!("out {}\n", i)).collect::<String>();
+        sink.capture(stdout.as_bytes(), "oops\n".as_bytes())
+            .await
+            .expect("logs should be captured");
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 151);
+        assert_eq!(
+            received.iter().map(|l| l.seq).collect::<Vec<_>>(),
+            (0..151).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            received
+                .iter()
+                .filter(|l| l.stream == LogStream::Stderr)
+                .count(),
+            1
+        );
+    }
+}
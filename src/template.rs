@@ -0,0 +1,91 @@
+use anyhow::anyhow;
+use anyhow::Result;
+use chrono::DateTime;
+use minijinja::Environment;
+use minijinja::Error;
+use minijinja::ErrorKind;
+use minijinja::UndefinedBehavior;
+use once_cell::sync::Lazy;
+use serde_json::json;
+use serde_json::Value as Json;
+
+static RENDERER: Lazy<Environment<'static>> = Lazy::new(|| environment(UndefinedBehavior::Strict));
+
+static CHECKER: Lazy<Environment<'static>> =
+    Lazy::new(|| environment(UndefinedBehavior::Chainable));
+
+fn date(value: String, format: String) -> Result<String, Error> {
+    let value = DateTime::parse_from_rfc3339(&value).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidOperation,
+            format!(r#"failed to parse "{}" as date: {}"#, value, e),
+        )
+    })?;
+    Ok(value.format(&format).to_string())
+}
+
+fn environment(undefined: UndefinedBehavior) -> Environment<'static> {
+    let mut env = Environment::new();
+    env.set_undefined_behavior(undefined);
+    env.add_filter("date", date);
+    env
+}
+
+fn sample() -> Json {
+    json!({
+        "run": {
+            "id": "00000000-0000-0000-0000-000000000000",
+            "triggered_at": "1970-01-01T00:00:00Z",
+            "priority": "normal",
+            "attempt": 1,
+        },
+    })
+}
+
+pub fn check(source: &str) -> Result<()> {
+    CHECKER
+        .render_str(source, sample())
+        .map(|_| ())
+        .map_err(|e| anyhow!(r#"invalid template "{}": {}"#, source, e))
+}
+
+pub fn render(source: &str, context: &Json) -> Result<String> {
+    RENDERER
+        .render_str(source, context)
+        .map_err(|e| anyhow!(r#"failed to render template "{}": {}"#, source, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        assert!(check("--date={{ run.triggered_at | date(\"%Y-%m-%d\") }}").is_ok());
+        assert!(check("--bucket={{ project.config.bucket }}").is_ok());
+        assert!(check("--plain").is_ok());
+        assert!(check("--broken={{ run.id ").is_err());
+        assert!(check("--unknown={{ run.id | unknown }}").is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let context = json!({
+            "run": { "triggered_at": "2023-03-01T12:34:56Z", "attempt": 2 },
+            "project": { "config": { "bucket": "s3://kotosiro" } },
+        });
+        assert_eq!(
+            render(
+                "{{ run.triggered_at | date(\"%Y-%m-%d\") }}/{{ run.attempt }}",
+                &context
+            )
+            .expect("template should be rendered"),
+            "2023-03-01/2"
+        );
+        assert_eq!(
+            render("{{ project.config.bucket }}", &context).expect("template should be rendered"),
+            "s3://kotosiro"
+        );
+        assert!(render("{{ project.config.missing }}", &context).is_err());
+    }
+}